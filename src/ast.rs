@@ -0,0 +1,348 @@
+//! Syntax tree produced by the [`Parser`](crate::Parser) and consumed by
+//! [`Interpret`](crate::Interpret) and any other backend. Every node carries
+//! the [`Span`] of the source it was parsed from.
+
+use derive_more::Display;
+use serde::Serialize;
+
+use crate::{
+    diagnostic::Span,
+    parser::{Feature, LolCodeVersion},
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct LolCodeProgram {
+    version: LolCodeVersion,
+    body: Block,
+    /// From `HAI` to `KTHXBYE`.
+    span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct Block {
+    stmts: Vec<Stmt>,
+    span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct Stmt {
+    kind: StmtKind,
+    span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StmtKind {
+    /// `VISIBLE`, or `INVISIBLE` writing to the error writer instead. A
+    /// trailing `!` suppresses the newline.
+    Visible {
+        args: Vec<Expr>,
+        invisible: bool,
+        newline: bool,
+    },
+    /// `GIMMEH <name>`, reading a line of input into the variable as a
+    /// YARN.
+    Gimmeh(Ident),
+    /// `CAN HAS <module>?`
+    CanHas {
+        module: Ident,
+    },
+    /// `I HAS A <name> [ITZ <expr>]`
+    Declare {
+        name: Ident,
+        init: Option<Expr>,
+    },
+    /// `<name> R <expr>`
+    Assign {
+        name: Ident,
+        value: Expr,
+    },
+    /// `<name> IS NOW A <type>`
+    CastVar {
+        name: Ident,
+        to: Type,
+    },
+    /// A bare expression, whose value is kept in `IT`.
+    Expr(Expr),
+    /// `O RLY?` on the value of `IT`, up to `OIC`.
+    If {
+        /// `YA RLY`
+        then: Block,
+        /// `MEBBE <expr>`
+        elifs: Vec<(Expr, Block)>,
+        /// `NO WAI`
+        otherwise: Option<Block>,
+    },
+    /// `WTF?` on the value of `IT`, up to `OIC`. Cases fall through until
+    /// `GTFO`.
+    Switch {
+        /// `OMG <literal>`
+        cases: Vec<(Expr, Block)>,
+        /// `OMGWTF`
+        default: Option<Block>,
+    },
+    Loop(Loop),
+    FuncDef(FuncDef),
+    /// `FOUND YR <expr>`
+    Return(Expr),
+    /// `GTFO`
+    Break,
+}
+
+/// `IM IN YR <label> [<op> YR <var> [TIL|WILE <expr>]]` up to
+/// `IM OUTTA YR <label>`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct Loop {
+    label: Ident,
+    update: Option<LoopUpdate>,
+    condition: Option<LoopCondition>,
+    body: Block,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct LoopUpdate {
+    op: LoopOp,
+    var: Ident,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopOp {
+    #[display(fmt = "UPPIN")]
+    Uppin,
+    #[display(fmt = "NERFIN")]
+    Nerfin,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopCondition {
+    /// Loop until the expression is WIN.
+    Til(Expr),
+    /// Loop while the expression is WIN.
+    Wile(Expr),
+}
+
+/// `HOW IZ I <name> [YR <param> [AN YR <param>]...]` up to `IF U SAY SO`.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct FuncDef {
+    name: Ident,
+    params: Vec<Ident>,
+    body: Block,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[jsm::public]
+pub struct Expr {
+    kind: ExprKind,
+    span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExprKind {
+    Noob,
+    Troof(bool),
+    Numbr(i64),
+    Numbar(f64),
+    Yarn(String),
+    Var(Ident),
+    /// `<op> <left> [AN] <right>`
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// `NOT <expr>`
+    Not(Box<Expr>),
+    /// `<op> <expr> [[AN] <expr>]... [MKAY]`. Also used for YARN
+    /// interpolation, as a SMOOSH of the pieces.
+    Nary {
+        op: NaryOp,
+        args: Vec<Expr>,
+    },
+    /// `MAEK <expr> [A] <type>`
+    Cast {
+        expr: Box<Expr>,
+        to: Type,
+    },
+    /// `I IZ <name> [YR <arg> [AN YR <arg>]...] MKAY`, or a native module
+    /// function with `<module> IZ <name> ...` when `module` is set.
+    Call {
+        module: Option<Ident>,
+        name: Ident,
+        args: Vec<Expr>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOp {
+    #[display(fmt = "SUM OF")]
+    Sum,
+    #[display(fmt = "DIFF OF")]
+    Diff,
+    #[display(fmt = "PRODUKT OF")]
+    Produkt,
+    #[display(fmt = "QUOSHUNT OF")]
+    Quoshunt,
+    #[display(fmt = "MOD OF")]
+    Mod,
+    #[display(fmt = "BIGGR OF")]
+    Biggr,
+    #[display(fmt = "SMALLR OF")]
+    Smallr,
+    #[display(fmt = "BOTH OF")]
+    Both,
+    #[display(fmt = "EITHER OF")]
+    Either,
+    #[display(fmt = "WON OF")]
+    Won,
+    #[display(fmt = "BOTH SAEM")]
+    Saem,
+    #[display(fmt = "DIFFRINT")]
+    Diffrint,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NaryOp {
+    #[display(fmt = "ALL OF")]
+    All,
+    #[display(fmt = "ANY OF")]
+    Any,
+    #[display(fmt = "SMOOSH")]
+    Smoosh,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Type {
+    #[display(fmt = "NOOB")]
+    Noob,
+    #[display(fmt = "TROOF")]
+    Troof,
+    #[display(fmt = "NUMBR")]
+    Numbr,
+    #[display(fmt = "NUMBAR")]
+    Numbar,
+    #[display(fmt = "YARN")]
+    Yarn,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[jsm::public]
+pub struct Ident {
+    name: String,
+    span: Span,
+}
+
+impl Stmt {
+    /// Version-gated feature the statement itself relies on, if any.
+    pub fn feature(&self) -> Option<Feature> {
+        match self.kind {
+            StmtKind::Visible {
+                invisible: true, ..
+            } => Some(Feature::Invisible),
+            _ => None,
+        }
+    }
+
+    /// Expressions of the statement itself, not of its nested blocks.
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match &self.kind {
+            StmtKind::Visible { args, .. } => args.iter().collect(),
+            StmtKind::Declare { init, .. } => init.iter().collect(),
+            StmtKind::Assign { value: expr, .. }
+            | StmtKind::Expr(expr)
+            | StmtKind::Return(expr) => {
+                vec![expr]
+            }
+            StmtKind::If { elifs, .. } => elifs.iter().map(|(cond, _)| cond).collect(),
+            StmtKind::Switch { cases, .. } => cases.iter().map(|(case, _)| case).collect(),
+            StmtKind::Loop(lp) => match &lp.condition {
+                Some(LoopCondition::Til(cond) | LoopCondition::Wile(cond)) => vec![cond],
+                None => Vec::new(),
+            },
+            StmtKind::Gimmeh(_)
+            | StmtKind::CanHas { .. }
+            | StmtKind::CastVar { .. }
+            | StmtKind::FuncDef(_)
+            | StmtKind::Break => Vec::new(),
+        }
+    }
+
+    /// Blocks nested directly in the statement.
+    pub fn blocks(&self) -> Vec<&Block> {
+        match &self.kind {
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => std::iter::once(then)
+                .chain(elifs.iter().map(|(_, block)| block))
+                .chain(otherwise)
+                .collect(),
+            StmtKind::Switch { cases, default } => cases
+                .iter()
+                .map(|(_, block)| block)
+                .chain(default)
+                .collect(),
+            StmtKind::Loop(lp) => vec![&lp.body],
+            StmtKind::FuncDef(func) => vec![&func.body],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Expr {
+    /// Version-gated feature the expression itself relies on, if any.
+    pub fn feature(&self) -> Option<Feature> {
+        match self.kind {
+            ExprKind::Call {
+                module: Some(_), ..
+            } => Some(Feature::ModuleCall),
+            _ => None,
+        }
+    }
+
+    /// The expression and every expression nested in it.
+    pub fn walk(&self) -> Vec<&Expr> {
+        let nested: Vec<&Expr> = match &self.kind {
+            ExprKind::Binary { left, right, .. } => vec![left, right],
+            ExprKind::Not(expr) | ExprKind::Cast { expr, .. } => vec![expr],
+            ExprKind::Nary { args, .. } | ExprKind::Call { args, .. } => args.iter().collect(),
+            ExprKind::Noob
+            | ExprKind::Troof(_)
+            | ExprKind::Numbr(_)
+            | ExprKind::Numbar(_)
+            | ExprKind::Yarn(_)
+            | ExprKind::Var(_) => Vec::new(),
+        };
+        let mut exprs = vec![self];
+        for expr in nested {
+            exprs.extend(expr.walk());
+        }
+        exprs
+    }
+}
+
+impl Block {
+    /// Every statement in the block, including those in nested blocks.
+    pub fn walk(&self) -> Vec<&Stmt> {
+        let mut stmts = Vec::new();
+        for stmt in &self.stmts {
+            stmts.push(stmt);
+            for block in stmt.blocks() {
+                stmts.extend(block.walk());
+            }
+        }
+        stmts
+    }
+}
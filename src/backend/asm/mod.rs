@@ -0,0 +1,832 @@
+//! Lowering to x86-64 assembly for the GNU assembler, linked into a static
+//! Linux executable without a C library. The runtime in `runtime.s` is
+//! pasted into every program.
+//!
+//! Each frame becomes a function keeping `IT` and its variables on the
+//! stack below `%rbp`, null until their declaration has run. Expressions
+//! leave their value in `%rax`, and the stack holds operands still waiting
+//! for theirs, so that operands are evaluated in the same order as by the
+//! interpreter. Constants are cells in `.rodata`, built by the assembler.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    env,
+    ffi::OsString,
+    fmt::Write as _,
+    fs,
+    io::Write as _,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    interpreter::{
+        break_outside, check_features, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+};
+
+use super::{escape, function_defs, includable, mangle, Escape};
+
+const RUNTIME: &str = include_str!("runtime.s");
+
+/// Cells the runtime keeps for these values.
+const NOOB: &str = "lol_noob_cell";
+const WIN: &str = "lol_win_cell";
+const FAIL: &str = "lol_fail_cell";
+
+/// Where a frame keeps `IT`, below the saved `%rbp`.
+const IT: i64 = -8;
+
+pub trait EmitAsm {
+    /// Lowers `prog` to x86-64 assembly, naming the source `source` in its
+    /// runtime errors.
+    fn emit_asm(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String>;
+}
+
+impl<T> EmitAsm for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn emit_asm(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String> {
+        check_features(self, prog)?;
+        let modules = includable(self, prog, "x86-64 assembly")?;
+        let functions = function_defs(prog);
+
+        let mut names = BTreeSet::new();
+        for func in &functions {
+            names.insert(func.name.name.as_str());
+        }
+        let mut writer = Writer {
+            functions: &functions,
+            defined: names,
+            modules,
+            data: Data::default(),
+            lines: String::new(),
+            labels: 0,
+            slots: Vec::new(),
+            vars: HashMap::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        let mut code = String::new();
+        for (index, func) in functions.iter().enumerate() {
+            writer.in_function = true;
+            // IT is never a variable
+            let params: Vec<Option<i64>> = func
+                .params
+                .iter()
+                .map(|param| (param.name != "IT").then(|| writer.var(param)))
+                .collect();
+            writer.block(&func.body);
+            writeln!(code, "\n# HOW IZ I {}", func.name.name).unwrap();
+            code.push_str(&writer.finish(&format!("f_{index}"), &params, true));
+        }
+        writer.in_function = false;
+        writer.block(&prog.body);
+        code.push('\n');
+        code.push_str(&writer.finish("lol_main", &[], false));
+
+        let mut program = RUNTIME.to_string();
+        program.push_str("\n    .text\n");
+        program.push_str(&code);
+
+        program.push_str("\n    .data\n    .balign 8\n");
+        for module_name in &writer.modules {
+            writeln!(program, "{}:\n    .quad 0", mangle("loaded_", module_name)).unwrap();
+        }
+        // Functions are called through these, so that a name refers to
+        // whichever definition ran last
+        for name in &writer.defined {
+            writeln!(program, "{}:\n    .quad 0", mangle("fn_", name)).unwrap();
+            writeln!(program, "{}:\n    .quad 0", mangle("arity_", name)).unwrap();
+        }
+
+        writer.data.cell("lol_source", source);
+        program.push_str("\n    .section .rodata\n    .balign 8\n");
+        program.push_str(&writer.data.cells);
+        program.push_str(&writer.data.texts);
+        Ok(program)
+    }
+}
+
+/// Builds the assembly `source` into an executable at `output` with `$AS`
+/// and `$LD`, or `as` and `ld` if they are unset.
+pub(crate) fn assemble(source: &str, output: &Path) -> anyhow::Result<()> {
+    let mut object = output.as_os_str().to_owned();
+    object.push(".o");
+
+    let assembler = env::var_os("AS").unwrap_or_else(|| OsString::from("as"));
+    let mut child = Command::new(&assembler)
+        .arg("--64")
+        .arg("-o")
+        .arg(&object)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {}", assembler.to_string_lossy()))?;
+    child
+        .stdin
+        .take()
+        .context("stdin of the assembler is piped")?
+        .write_all(source.as_bytes())
+        .context("writing to the assembler")?;
+    let result = child
+        .wait_with_output()
+        .context("waiting for the assembler")?;
+    if !result.status.success() {
+        bail!(
+            "{} failed with {}:\n{}",
+            assembler.to_string_lossy(),
+            result.status,
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+
+    let linker = env::var_os("LD").unwrap_or_else(|| OsString::from("ld"));
+    let result = Command::new(&linker)
+        .arg("-o")
+        .arg(output)
+        .arg(&object)
+        .output()
+        .with_context(|| format!("running {}", linker.to_string_lossy()));
+    fs::remove_file(&object).context("removing the object file")?;
+    let result = result?;
+    if !result.status.success() {
+        bail!(
+            "{} failed with {}:\n{}",
+            linker.to_string_lossy(),
+            result.status,
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// The program's constants, as cells in `.rodata` with the bytes of their
+/// YARNs after them.
+#[derive(Default)]
+struct Data {
+    cells: String,
+    texts: String,
+    count: usize,
+    constants: HashMap<(u32, u64), String>,
+    yarns: HashMap<String, String>,
+}
+
+impl Data {
+    fn label(&mut self, kind: &str) -> String {
+        self.count += 1;
+        format!(".L{kind}_{}", self.count)
+    }
+
+    /// Label of a cell with the tag `tag` and the payload `payload`, added
+    /// unless it already is.
+    fn constant(&mut self, tag: u32, payload: u64) -> String {
+        if let Some(label) = self.constants.get(&(tag, payload)) {
+            return label.clone();
+        }
+        let label = self.label("const");
+        writeln!(
+            self.cells,
+            "{label}:\n    .long {}, 0\n    .quad {payload:#x}",
+            tag_name(tag)
+        )
+        .unwrap();
+        self.constants.insert((tag, payload), label.clone());
+        label
+    }
+
+    /// Label of a YARN cell of `text`, added unless it already is.
+    fn yarn(&mut self, text: &str) -> String {
+        if let Some(label) = self.yarns.get(text) {
+            return label.clone();
+        }
+        let label = self.label("const");
+        self.cell(&label, text);
+        self.yarns.insert(text.to_string(), label.clone());
+        label
+    }
+
+    /// Adds a YARN cell of `text` called `label`.
+    fn cell(&mut self, label: &str, text: &str) {
+        let bytes = self.label("text");
+        writeln!(
+            self.cells,
+            "{label}:\n    .long TAG_YARN, {}\n    .quad {bytes}",
+            text.len()
+        )
+        .unwrap();
+        writeln!(
+            self.texts,
+            "{bytes}:\n    .ascii {}",
+            string(text.as_bytes())
+        )
+        .unwrap();
+    }
+}
+
+/// String literal with the bytes `bytes`.
+fn string(bytes: &[u8]) -> String {
+    format!("\"{}\"", escape(bytes, Escape::Octal))
+}
+
+/// Instructions passing the position of `span` to the runtime as the
+/// arguments `line` and `column`.
+fn position(line: &str, column: &str, span: Span) -> [String; 2] {
+    [
+        format!("movl ${}, {line}", span.start.line),
+        format!("movl ${}, {column}", span.start.column),
+    ]
+}
+
+/// Operand selecting `op` in `lol_binary`.
+fn binary_op(op: BinaryOp) -> u32 {
+    match op {
+        BinaryOp::Sum => 0,
+        BinaryOp::Diff => 1,
+        BinaryOp::Produkt => 2,
+        BinaryOp::Quoshunt => 3,
+        BinaryOp::Mod => 4,
+        BinaryOp::Biggr => 5,
+        BinaryOp::Smallr => 6,
+        BinaryOp::Both => 7,
+        BinaryOp::Either => 8,
+        BinaryOp::Won => 9,
+        BinaryOp::Saem => 10,
+        BinaryOp::Diffrint => 11,
+    }
+}
+
+/// Tag of the cells of type `to`.
+fn type_tag(to: Type) -> u32 {
+    match to {
+        Type::Noob => 1,
+        Type::Troof => 2,
+        Type::Numbr => 3,
+        Type::Numbar => 4,
+        Type::Yarn => 5,
+    }
+}
+
+/// Name the runtime gives the tag `tag`.
+fn tag_name(tag: u32) -> &'static str {
+    match tag {
+        1 => "TAG_NOOB",
+        2 => "TAG_TROOF",
+        3 => "TAG_NUMBR",
+        4 => "TAG_NUMBAR",
+        _ => "TAG_YARN",
+    }
+}
+
+/// Writes the body of one function at a time.
+struct Writer<'p, 'f> {
+    /// Every function definition, in the order of the `f_<index>`
+    /// functions.
+    functions: &'f [&'p FuncDef],
+    /// Names of the functions the program defines.
+    defined: BTreeSet<&'p str>,
+    /// Modules the program can include.
+    modules: BTreeSet<&'p str>,
+    data: Data,
+    lines: String,
+    labels: usize,
+    /// What each slot of the current frame below `IT` holds.
+    slots: Vec<String>,
+    /// Offsets from `%rbp` of the variables of the current frame.
+    vars: HashMap<&'p str, i64>,
+    /// Labels just after each enclosing loop or `WTF?`, innermost last.
+    breaks: Vec<String>,
+    in_function: bool,
+}
+
+impl<'p> Writer<'p, '_> {
+    fn line<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        self.lines.push_str("    ");
+        self.lines.push_str(line.as_ref());
+        self.lines.push('\n');
+    }
+
+    fn lines<I>(&mut self, lines: I)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        for line in lines {
+            self.line(line);
+        }
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!(".L{kind}_{}", self.labels)
+    }
+
+    fn place(&mut self, label: &str) {
+        writeln!(self.lines, "{label}:").unwrap();
+    }
+
+    /// Offset from `%rbp` of a new slot of the current frame holding
+    /// `what`.
+    fn slot(&mut self, what: String) -> i64 {
+        self.slots.push(what);
+        IT - 8 * self.slots.len() as i64
+    }
+
+    /// Wraps what was written since the last call in a function called
+    /// `name`, with the parameters in the slots `params`, and starts
+    /// afresh. Functions return `IT`, and the main program nothing.
+    fn finish(&mut self, name: &str, params: &[Option<i64>], returns: bool) -> String {
+        let mut function = format!("{name}:\n");
+        function.push_str("    pushq %rbp\n    movq %rsp, %rbp\n");
+        writeln!(function, "    leaq {NOOB}(%rip), %rax").unwrap();
+        writeln!(function, "    pushq %rax\n    # IT at {IT}(%rbp)").unwrap();
+        for (index, what) in self.slots.iter().enumerate() {
+            let offset = IT - 8 * (index as i64 + 1);
+            writeln!(function, "    pushq $0\n    # {what} at {offset}(%rbp)").unwrap();
+        }
+        // The caller pushed the arguments in order, so the last one is at
+        // %rdi, and a later parameter with the same name wins
+        for (index, offset) in params.iter().enumerate() {
+            let Some(offset) = offset else {
+                continue;
+            };
+            let at = 8 * (params.len() - 1 - index);
+            writeln!(function, "    movq {at}(%rdi), %rax").unwrap();
+            writeln!(function, "    movq %rax, {offset}(%rbp)").unwrap();
+        }
+        function.push_str(&std::mem::take(&mut self.lines));
+        if returns {
+            writeln!(function, "    movq {IT}(%rbp), %rax").unwrap();
+        }
+        function.push_str("    leave\n    ret\n");
+
+        self.slots.clear();
+        self.vars.clear();
+        function
+    }
+
+    /// Offset from `%rbp` of the variable `name`, or `IT`.
+    fn var(&mut self, name: &'p Ident) -> i64 {
+        if name.name == "IT" {
+            return IT;
+        }
+        if let Some(offset) = self.vars.get(name.name.as_str()) {
+            return *offset;
+        }
+        let offset = self.slot(name.name.clone());
+        self.vars.insert(&name.name, offset);
+        offset
+    }
+
+    /// Fails at runtime if `name` has not been declared, and returns its
+    /// offset.
+    fn declared(&mut self, name: &'p Ident) -> i64 {
+        let var = self.var(name);
+        if var != IT {
+            let declared = self.label("declared");
+            self.line(format!("cmpq $0, {var}(%rbp)"));
+            self.line(format!("jne {declared}"));
+            self.fail(&undefined_variable(name));
+            self.place(&declared);
+        }
+        var
+    }
+
+    /// Writes a call reporting `diagnostic` at runtime.
+    fn fail(&mut self, diagnostic: &Diagnostic) {
+        let span = diagnostic.span.expect("runtime errors have a location");
+        let code = self.data.yarn(&diagnostic.code.to_string());
+        let message = self.data.yarn(&diagnostic.message);
+        self.line(format!("leaq {code}(%rip), %rdi"));
+        self.line(format!("leaq {message}(%rip), %rsi"));
+        match &diagnostic.help {
+            Some(help) => {
+                let help = self.data.yarn(help);
+                self.line(format!("leaq {help}(%rip), %rdx"));
+            }
+            None => self.line("xorl %edx, %edx"),
+        }
+        self.lines(position("%ecx", "%r8d", span));
+        self.line("call lol_fail");
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        writeln!(self.lines, "    # line {}", stmt.span.start.line).unwrap();
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                match args.split_first() {
+                    Some((first, rest)) => {
+                        self.expr(first);
+                        self.line("movq %rax, %rdi");
+                        self.line("call lol_show");
+                        for arg in rest {
+                            self.line("pushq %rax");
+                            self.expr(arg);
+                            self.line("movq %rax, %rdi");
+                            self.line("call lol_show");
+                            self.line("movq %rax, %rsi");
+                            self.line("popq %rdi");
+                            self.line("call lol_concat");
+                        }
+                    }
+                    None => self.line("leaq lol_empty(%rip), %rax"),
+                }
+                let stream = match invisible {
+                    true => 2,
+                    false => 1,
+                };
+                self.line("movq %rax, %rdi");
+                self.line(format!("movl ${stream}, %esi"));
+                self.line(format!("movl ${}, %edx", i32::from(*newline)));
+                self.line("call lol_visible");
+            }
+            StmtKind::CanHas { module } => {
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    self.line(format!("movq $1, {loaded}(%rip)"));
+                } else {
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span);
+                    self.fail(&diagnostic);
+                }
+            }
+            StmtKind::Declare { name, init } => {
+                match init {
+                    Some(init) => self.expr(init),
+                    None => self.line(format!("leaq {NOOB}(%rip), %rax")),
+                }
+                // Declaring IT has no effect
+                let var = self.var(name);
+                if var != IT {
+                    self.line(format!("movq %rax, {var}(%rbp)"));
+                }
+            }
+            StmtKind::Assign { name, value } => {
+                self.expr(value);
+                let var = self.declared(name);
+                self.line(format!("movq %rax, {var}(%rbp)"));
+            }
+            StmtKind::Gimmeh(name) => {
+                self.line("call lol_gimmeh");
+                let var = self.declared(name);
+                self.line(format!("movq %rax, {var}(%rbp)"));
+            }
+            StmtKind::CastVar { name, to } => {
+                let var = self.declared(name);
+                self.line(format!("movq {var}(%rbp), %rdi"));
+                self.line(format!("movl ${}, %esi", type_tag(*to)));
+                self.lines(position("%edx", "%ecx", stmt.span));
+                self.line("call lol_cast");
+                self.line(format!("movq %rax, {var}(%rbp)"));
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(expr);
+                self.line(format!("movq %rax, {IT}(%rbp)"));
+            }
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                let done = self.label("oic");
+                let mut next = self.label("else");
+                self.line(format!("movq {IT}(%rbp), %rdi"));
+                self.line("call lol_to_troof");
+                self.line("testl %eax, %eax");
+                self.line(format!("jz {next}"));
+                self.block(then);
+                self.line(format!("jmp {done}"));
+                for (condition, block) in elifs {
+                    self.place(&next);
+                    next = self.label("else");
+                    self.expr(condition);
+                    self.line("movq %rax, %rdi");
+                    self.line("call lol_to_troof");
+                    self.line("testl %eax, %eax");
+                    self.line(format!("jz {next}"));
+                    self.block(block);
+                    self.line(format!("jmp {done}"));
+                }
+                self.place(&next);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+                self.place(&done);
+            }
+            StmtKind::Switch { cases, default } => {
+                // Each case falls through into the ones after, OMGWTF
+                // included
+                let done = self.label("done");
+                let unmatched = self.label("omgwtf");
+                let labels: Vec<String> = cases.iter().map(|_| self.label("omg")).collect();
+                for ((literal, _), label) in cases.iter().zip(&labels) {
+                    self.expr(literal);
+                    self.line("movq %rax, %rdi");
+                    self.line(format!("movq {IT}(%rbp), %rsi"));
+                    self.line("call lol_saem");
+                    self.line("testl %eax, %eax");
+                    self.line(format!("jnz {label}"));
+                }
+                self.line(format!("jmp {unmatched}"));
+
+                self.breaks.push(done.clone());
+                for ((_, block), label) in cases.iter().zip(&labels) {
+                    self.place(label);
+                    self.block(block);
+                }
+                self.place(&unmatched);
+                if let Some(default) = default {
+                    self.block(default);
+                }
+                self.breaks.pop();
+                self.place(&done);
+            }
+            StmtKind::Loop(lp) => {
+                // The variable is only declared for the duration of the loop
+                // if it does not exist yet
+                let temporary = lp.update.as_ref().map(|update| {
+                    let var = self.var(&update.var);
+                    let temporary = self.slot(format!("whether {} is new", update.var.name));
+                    let counted = self.label("counted");
+                    self.line("xorl %eax, %eax");
+                    self.line(format!("cmpq $0, {var}(%rbp)"));
+                    self.line("sete %al");
+                    self.line(format!("movq %rax, {temporary}(%rbp)"));
+                    self.line(format!("jne {counted}"));
+                    self.line("leaq lol_zero_cell(%rip), %rax");
+                    self.line(format!("movq %rax, {var}(%rbp)"));
+                    self.place(&counted);
+                    (var, temporary)
+                });
+
+                let done = self.label("done");
+                let top = self.label("top");
+                self.place(&top);
+                if let Some(condition) = &lp.condition {
+                    let (condition, leave) = match condition {
+                        LoopCondition::Til(condition) => (condition, "jnz"),
+                        LoopCondition::Wile(condition) => (condition, "jz"),
+                    };
+                    self.expr(condition);
+                    self.line("movq %rax, %rdi");
+                    self.line("call lol_to_troof");
+                    self.line("testl %eax, %eax");
+                    self.line(format!("{leave} {done}"));
+                }
+                self.breaks.push(done.clone());
+                self.block(&lp.body);
+                self.breaks.pop();
+                if let Some(update) = &lp.update {
+                    let op = match update.op {
+                        LoopOp::Uppin => BinaryOp::Sum,
+                        LoopOp::Nerfin => BinaryOp::Diff,
+                    };
+                    let var = self.declared(&update.var);
+                    self.line(format!("movq {var}(%rbp), %rdi"));
+                    self.line("leaq lol_one_cell(%rip), %rsi");
+                    self.line(format!("movl ${}, %edx", binary_op(op)));
+                    self.lines(position("%ecx", "%r8d", update.var.span));
+                    self.line("call lol_binary");
+                    self.line(format!("movq %rax, {var}(%rbp)"));
+                }
+                self.line(format!("jmp {top}"));
+                self.place(&done);
+
+                if let Some((var, temporary)) = temporary {
+                    let kept = self.label("kept");
+                    self.line(format!("cmpq $0, {temporary}(%rbp)"));
+                    self.line(format!("je {kept}"));
+                    self.line(format!("movq $0, {var}(%rbp)"));
+                    self.place(&kept);
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let index = self
+                    .functions
+                    .iter()
+                    .position(|other| std::ptr::eq(*other, func))
+                    .expect("every function definition is collected");
+                let function = mangle("fn_", &func.name.name);
+                let arity = mangle("arity_", &func.name.name);
+                let count = func.params.len();
+                self.line(format!("leaq f_{index}(%rip), %rax"));
+                self.line(format!("movq %rax, {function}(%rip)"));
+                self.line(format!("movq ${count}, {arity}(%rip)"));
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.fail(&return_outside(stmt.span));
+                    return;
+                }
+                self.expr(expr);
+                self.line("leave");
+                self.line("ret");
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(done) => {
+                    let done = done.clone();
+                    self.line(format!("jmp {done}"));
+                }
+                // GTFO in a function returns NOOB
+                None if self.in_function => {
+                    self.line(format!("leaq {NOOB}(%rip), %rax"));
+                    self.line("leave");
+                    self.line("ret");
+                }
+                None => self.fail(&break_outside(stmt.span)),
+            },
+        }
+    }
+
+    /// Writes the evaluation of `expr`, leaving its value in `%rax`.
+    fn expr(&mut self, expr: &'p Expr) {
+        match &expr.kind {
+            ExprKind::Noob => self.line(format!("leaq {NOOB}(%rip), %rax")),
+            ExprKind::Troof(true) => self.line(format!("leaq {WIN}(%rip), %rax")),
+            ExprKind::Troof(false) => self.line(format!("leaq {FAIL}(%rip), %rax")),
+            ExprKind::Numbr(numbr) => {
+                let constant = self.data.constant(3, *numbr as u64);
+                self.line(format!("leaq {constant}(%rip), %rax"));
+            }
+            ExprKind::Numbar(numbar) => {
+                let constant = self.data.constant(4, numbar.to_bits());
+                self.line(format!("leaq {constant}(%rip), %rax"));
+            }
+            ExprKind::Yarn(yarn) => {
+                let constant = self.data.yarn(yarn);
+                self.line(format!("leaq {constant}(%rip), %rax"));
+            }
+            ExprKind::Var(var) => {
+                let var = self.declared(var);
+                self.line(format!("movq {var}(%rbp), %rax"));
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.expr(left);
+                self.line("pushq %rax");
+                self.expr(right);
+                self.line("movq %rax, %rsi");
+                self.line("popq %rdi");
+                self.line(format!("movl ${}, %edx", binary_op(*op)));
+                self.lines(position("%ecx", "%r8d", expr.span));
+                self.line("call lol_binary");
+            }
+            ExprKind::Not(operand) => {
+                self.expr(operand);
+                self.line("movq %rax, %rdi");
+                self.line("call lol_to_troof");
+                self.line("xorl $1, %eax");
+                self.line("movl %eax, %edi");
+                self.line("call lol_troof");
+            }
+            ExprKind::Nary { op, args } => match op {
+                NaryOp::All | NaryOp::Any => {
+                    let (empty, function) = match op {
+                        NaryOp::All => (WIN, "lol_all"),
+                        _ => (FAIL, "lol_any"),
+                    };
+                    if args.is_empty() {
+                        self.line(format!("leaq {empty}(%rip), %rax"));
+                        return;
+                    }
+                    for arg in args {
+                        self.expr(arg);
+                        self.line("pushq %rax");
+                    }
+                    self.line("movq %rsp, %rdi");
+                    self.line(format!("movl ${}, %esi", args.len()));
+                    self.line(format!("call {function}"));
+                    self.line(format!("addq ${}, %rsp", 8 * args.len()));
+                }
+                // Every piece is evaluated before any is cast, and the YARN
+                // so far is kept on top of them
+                NaryOp::Smoosh => {
+                    for arg in args {
+                        self.expr(arg);
+                        self.line("pushq %rax");
+                    }
+                    self.line("leaq lol_empty(%rip), %rax");
+                    self.line("pushq %rax");
+                    for (index, arg) in args.iter().enumerate() {
+                        let at = 8 * (args.len() - index);
+                        self.line(format!("movq {at}(%rsp), %rdi"));
+                        self.lines(position("%esi", "%edx", arg.span));
+                        self.line("call lol_to_yarn");
+                        self.line("movq %rax, %rsi");
+                        self.line("movq (%rsp), %rdi");
+                        self.line("call lol_concat");
+                        self.line("movq %rax, (%rsp)");
+                    }
+                    self.line("popq %rax");
+                    self.line(format!("addq ${}, %rsp", 8 * args.len()));
+                }
+            },
+            ExprKind::Cast { expr: operand, to } => {
+                self.expr(operand);
+                self.line("movq %rax, %rdi");
+                self.line(format!("movl ${}, %esi", type_tag(*to)));
+                self.lines(position("%edx", "%ecx", expr.span));
+                self.line("call lol_cast");
+            }
+            ExprKind::Call {
+                module: None,
+                name,
+                args,
+            } => {
+                if !self.defined.contains(name.name.as_str()) {
+                    for arg in args {
+                        self.expr(arg);
+                    }
+                    self.fail(&undefined_function(name));
+                    return;
+                }
+
+                for arg in args {
+                    self.expr(arg);
+                    self.line("pushq %rax");
+                }
+                let function = mangle("fn_", &name.name);
+                let arity = mangle("arity_", &name.name);
+                let count = args.len();
+                let found = self.label("found");
+                self.line(format!("cmpq $0, {function}(%rip)"));
+                self.line(format!("jne {found}"));
+                self.fail(&undefined_function(name));
+                self.place(&found);
+                let matches = self.label("arity");
+                self.line(format!("cmpq ${count}, {arity}(%rip)"));
+                self.line(format!("je {matches}"));
+                let yarn = self.data.yarn(&name.name);
+                self.line(format!("leaq {yarn}(%rip), %rdi"));
+                self.line(format!("movq {arity}(%rip), %rsi"));
+                self.line(format!("movl ${count}, %edx"));
+                self.lines(position("%ecx", "%r8d", expr.span));
+                self.line("call lol_arity_error");
+                self.place(&matches);
+                self.line("movq %rsp, %rdi");
+                self.line(format!("call *{function}(%rip)"));
+                if count > 0 {
+                    self.line(format!("addq ${}, %rsp", 8 * count));
+                }
+            }
+            // Modules that can be included have no functions
+            ExprKind::Call {
+                module: Some(module),
+                name,
+                args,
+            } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let not_loaded = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!(
+                        "Module {0} has not been loaded. Are you missing CAN HAS {0}?",
+                        module.name
+                    ),
+                )
+                .with_span(expr.span);
+                let no_function = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has no function {}", module.name, name.name),
+                )
+                .with_span(expr.span);
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    let found = self.label("loaded");
+                    self.line(format!("cmpq $0, {loaded}(%rip)"));
+                    self.line(format!("jne {found}"));
+                    self.fail(&not_loaded);
+                    self.place(&found);
+                    self.fail(&no_function);
+                } else {
+                    self.fail(&not_loaded);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1428 @@
+# Runtime of LOLCODE programs compiled to x86-64 assembly by rlcc, for Linux
+# without a C library. It mirrors the semantics of rlcc's interpreter,
+# including the text of its diagnostics, which are written to stderr before
+# exiting with status 1.
+#
+# Values are pointers to immutable 16 byte cells: a tag at offset 0, then
+# the TROOF, NUMBR or NUMBAR at offset 8, or for a YARN its length at offset
+# 4 and the address of its bytes at 8. Null is an undeclared variable.
+# Memory comes from the program break and is never freed, so allocations
+# start zeroed. The program defines lol_main, and lol_source with the name
+# of its source.
+#
+# Functions follow the System V calling convention, except that the stack
+# is not kept aligned since nothing here needs it.
+
+    .equ TAG_NOOB, 1
+    .equ TAG_TROOF, 2
+    .equ TAG_NUMBR, 3
+    .equ TAG_NUMBAR, 4
+    .equ TAG_YARN, 5
+
+    .equ SYS_READ, 0
+    .equ SYS_WRITE, 1
+    .equ SYS_BRK, 12
+    .equ SYS_EXIT, 60
+
+    .equ MIN_NUMBR, 0x8000000000000000
+
+# Defines a YARN cell called \name holding \text.
+    .macro yarn name, text
+    .balign 8
+\name:
+    .long TAG_YARN, 2f - 1f
+    .quad 1f
+1:  .ascii "\text"
+2:
+    .endm
+
+    .section .rodata
+
+# NOOB, WIN and FAIL, shared by every use
+    .balign 8
+lol_noob_cell:
+    .long TAG_NOOB, 0
+    .quad 0
+lol_win_cell:
+    .long TAG_TROOF, 0
+    .quad 1
+lol_fail_cell:
+    .long TAG_TROOF, 0
+    .quad 0
+lol_zero_cell:
+    .long TAG_NUMBR, 0
+    .quad 0
+lol_one_cell:
+    .long TAG_NUMBR, 0
+    .quad 1
+
+    yarn lol_empty, ""
+    yarn lol_text_noob, "NOOB"
+    yarn lol_text_win, "WIN"
+    yarn lol_text_fail, "FAIL"
+    yarn lol_text_nan, "NaN"
+    yarn lol_text_inf, "inf"
+    yarn lol_text_neg_inf, "-inf"
+    yarn lol_newline, "\n"
+    yarn lol_error, "error["
+    yarn lol_after_code, "]: "
+    yarn lol_colon, ":"
+    yarn lol_arrow, "--> "
+    yarn lol_help, " = help: "
+    yarn lol_spaces, "                    "
+    yarn lol_out_of_memory_text, "error: out of memory\n"
+    yarn lol_e0015, "E0015"
+    yarn lol_e0019, "E0019"
+    yarn lol_e0020, "E0020"
+    yarn lol_cast_noob_number, "Cannot implicitly cast NOOB to a number"
+    yarn lol_cast_yarn, "Cannot cast YARN "
+    yarn lol_to_number_text, " to a number"
+    yarn lol_cast_noob_yarn, "Cannot implicitly cast NOOB to a YARN"
+    yarn lol_division, "Division by zero"
+    yarn lol_takes, " takes "
+    yarn lol_arguments, " argument(s) but "
+    yarn lol_given, " were given"
+
+    .balign 8
+lol_ten:
+    .double 10
+lol_1e22:
+    .double 1e22
+
+    .bss
+    .balign 8
+# Next free byte of the heap, or 0 before the first allocation
+lol_heap:
+    .zero 8
+# Program break, where the heap ends
+lol_heap_end:
+    .zero 8
+
+    .text
+
+    .globl _start
+_start:
+    call lol_main
+    xorl %edi, %edi
+    jmp lol_exit
+
+# Exits with the status in %edi.
+lol_exit:
+    movl $SYS_EXIT, %eax
+    syscall
+
+# Writes %rdx bytes at %rsi to the file descriptor %edi.
+lol_write:
+    testq %rdx, %rdx
+    jz 1f
+    movl $SYS_WRITE, %eax
+    syscall
+    testq %rax, %rax
+    jle 1f
+    addq %rax, %rsi
+    subq %rax, %rdx
+    jmp lol_write
+1:  ret
+
+# Writes the YARN %rsi to the file descriptor %edi.
+lol_write_yarn:
+    movl 4(%rsi), %edx
+    movq 8(%rsi), %rsi
+    jmp lol_write
+
+lol_out_of_memory:
+    movl $2, %edi
+    leaq lol_out_of_memory_text(%rip), %rsi
+    call lol_write_yarn
+    movl $1, %edi
+    jmp lol_exit
+
+# Returns %rdi bytes of memory, rounded up to a multiple of 8.
+lol_alloc:
+    movq lol_heap(%rip), %rax
+    testq %rax, %rax
+    jnz 1f
+    # The heap starts at the initial program break
+    pushq %rdi
+    movl $SYS_BRK, %eax
+    xorl %edi, %edi
+    syscall
+    popq %rdi
+    movq %rax, lol_heap_end(%rip)
+    addq $7, %rax
+    andq $-8, %rax
+1:  addq $7, %rdi
+    andq $-8, %rdi
+    leaq (%rax,%rdi), %rdx
+    cmpq lol_heap_end(%rip), %rdx
+    jbe 2f
+    # Moves the break 1 MiB further than needed, which fails by leaving it
+    # where it was
+    pushq %rax
+    pushq %rdx
+    leaq 0x100000(%rdx), %rdi
+    movl $SYS_BRK, %eax
+    syscall
+    popq %rdx
+    cmpq %rdx, %rax
+    jb lol_out_of_memory
+    movq %rax, lol_heap_end(%rip)
+    popq %rax
+2:  movq %rdx, lol_heap(%rip)
+    ret
+
+# A cell with the tag %edi and the payload %rsi.
+lol_cell:
+    pushq %rdi
+    pushq %rsi
+    movl $16, %edi
+    call lol_alloc
+    popq 8(%rax)
+    popq %rdi
+    movl %edi, (%rax)
+    ret
+
+# The cell of the TROOF %edi.
+lol_troof:
+    leaq lol_win_cell(%rip), %rax
+    leaq lol_fail_cell(%rip), %rdx
+    testl %edi, %edi
+    cmovzq %rdx, %rax
+    ret
+
+lol_numbr:
+    movq %rdi, %rsi
+    movl $TAG_NUMBR, %edi
+    jmp lol_cell
+
+# The cell of the NUMBAR %xmm0.
+lol_numbar:
+    movq %xmm0, %rsi
+    movl $TAG_NUMBAR, %edi
+    jmp lol_cell
+
+# The YARN of the %rsi bytes at %rdi.
+lol_yarn:
+    pushq %rsi
+    movq %rdi, %rsi
+    movl $TAG_YARN, %edi
+    call lol_cell
+    popq %rdx
+    movl %edx, 4(%rax)
+    ret
+
+# The YARNs %rdi and %rsi joined together.
+lol_concat:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    movl 4(%rbx), %edi
+    addl 4(%r12), %edi
+    call lol_alloc
+    movq %rax, %r13
+    movq %rax, %rdi
+    movq 8(%rbx), %rsi
+    movl 4(%rbx), %ecx
+    rep movsb
+    movq 8(%r12), %rsi
+    movl 4(%r12), %ecx
+    rep movsb
+    movq %r13, %rdi
+    movl 4(%rbx), %esi
+    addl 4(%r12), %esi
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_yarn
+
+# Whether the value %rdi is WIN once cast to a TROOF, as 0 or 1 in %eax.
+lol_to_troof:
+    movl (%rdi), %ecx
+    xorl %eax, %eax
+    cmpl $TAG_TROOF, %ecx
+    jne 1f
+    movl 8(%rdi), %eax
+    ret
+1:  cmpl $TAG_NUMBR, %ecx
+    jne 2f
+    cmpq $0, 8(%rdi)
+    setne %al
+    ret
+2:  cmpl $TAG_NUMBAR, %ecx
+    jne 3f
+    # NaN is unordered, and WIN
+    xorpd %xmm1, %xmm1
+    ucomisd 8(%rdi), %xmm1
+    setne %al
+    setp %cl
+    orb %cl, %al
+    ret
+3:  cmpl $TAG_YARN, %ecx
+    jne 4f
+    cmpl $0, 4(%rdi)
+    setne %al
+4:  ret
+
+# Writes the digits of the unsigned %rdi backwards, ending before %rsi, and
+# returns where they start.
+lol_digits:
+    movq %rdi, %rax
+    movl $10, %ecx
+1:  xorl %edx, %edx
+    divq %rcx
+    addb $'0', %dl
+    decq %rsi
+    movb %dl, (%rsi)
+    testq %rax, %rax
+    jnz 1b
+    movq %rsi, %rax
+    ret
+
+# Like lol_digits, padded with zeros to %rdx digits.
+lol_digits_padded:
+    pushq %rsi
+    pushq %rdx
+    call lol_digits
+    popq %rdx
+    popq %rsi
+    subq %rdx, %rsi
+1:  cmpq %rsi, %rax
+    jbe 2f
+    decq %rax
+    movb $'0', (%rax)
+    jmp 1b
+2:  ret
+
+lol_show_numbr:
+    pushq %rbx
+    pushq %r12
+    movq %rdi, %rbx
+    movl $20, %edi
+    call lol_alloc
+    leaq 20(%rax), %r12
+    # Negating MIN_NUMBR wraps to its magnitude as an unsigned number
+    movq %rbx, %rdi
+    negq %rdi
+    testq %rbx, %rbx
+    cmovnsq %rbx, %rdi
+    movq %r12, %rsi
+    call lol_digits
+    testq %rbx, %rbx
+    jns 1f
+    decq %rax
+    movb $'-', (%rax)
+1:  movq %rax, %rdi
+    movq %r12, %rsi
+    subq %rax, %rsi
+    popq %r12
+    popq %rbx
+    jmp lol_yarn
+
+# Writes the digits of the mantissa %rdi times 2 to the power %rsi
+# backwards, ending before %rdx, for powers too large for 64 bits.
+lol_digits_big:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    pushq %r14
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    movq %rdx, %r13
+    # Little-endian 64 bit limbs, enough for any finite NUMBAR
+    movl $144, %edi
+    call lol_alloc
+    movq %rax, %r14
+    movl %r12d, %ecx
+    shrq $6, %r12
+    andl $63, %ecx
+    movq %rbx, %rax
+    shlq %cl, %rax
+    movq %rax, (%r14,%r12,8)
+    testl %ecx, %ecx
+    jz 1f
+    # Shifting by 64 minus the bit, modulo 64
+    negl %ecx
+    shrq %cl, %rbx
+    movq %rbx, 8(%r14,%r12,8)
+1:  incq %r12
+    movl $1000000000, %ebx
+
+    # Divides by 10^9, writing the remainder as the next nine digits
+2:  xorl %edx, %edx
+    movq %r12, %rcx
+3:  movq (%r14,%rcx,8), %rax
+    divq %rbx
+    movq %rax, (%r14,%rcx,8)
+    decq %rcx
+    jns 3b
+4:  cmpq $0, (%r14,%r12,8)
+    jne 5f
+    decq %r12
+    jns 4b
+    movq %rdx, %rdi
+    movq %r13, %rsi
+    call lol_digits
+    popq %r14
+    popq %r13
+    popq %r12
+    popq %rbx
+    ret
+5:  movq %rdx, %rdi
+    movq %r13, %rsi
+    movl $9, %edx
+    call lol_digits_padded
+    movq %rax, %r13
+    jmp 2b
+
+# Exact decimal expansion of the NUMBAR %xmm0 rounded half to even to two
+# places, as Rust's {:.2} formats it.
+lol_show_numbar:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    pushq %r14
+    movq %xmm0, %rbx
+    leaq lol_text_nan(%rip), %rax
+    ucomisd %xmm0, %xmm0
+    jp 9f
+    movq %rbx, %rax
+    btrq $63, %rax
+    movabsq $0x7ff0000000000000, %rcx
+    cmpq %rcx, %rax
+    jne 1f
+    leaq lol_text_inf(%rip), %rax
+    leaq lol_text_neg_inf(%rip), %rcx
+    testq %rbx, %rbx
+    cmovsq %rcx, %rax
+    jmp 9f
+
+    # The value is the mantissa %r13 times 2 to the power %r12
+1:  movq %rbx, %r12
+    shrq $52, %r12
+    andl $0x7ff, %r12d
+    movabsq $0xfffffffffffff, %r13
+    andq %rbx, %r13
+    testl %r12d, %r12d
+    jnz 2f
+    movq $-1074, %r12
+    jmp 3f
+2:  btsq $52, %r13
+    subq $1075, %r12
+3:  movl $400, %edi
+    call lol_alloc
+    leaq 400(%rax), %r14
+    testq %r12, %r12
+    js 4f
+
+    leaq -3(%r14), %rsi
+    movb $'.', (%rsi)
+    movw $0x3030, 1(%rsi)
+    cmpq $10, %r12
+    jg 3f
+    movq %r13, %rdi
+    movl %r12d, %ecx
+    shlq %cl, %rdi
+    call lol_digits
+    jmp 8f
+3:  movq %rsi, %rdx
+    movq %r13, %rdi
+    movq %r12, %rsi
+    call lol_digits_big
+    jmp 8f
+
+    # The value in hundredths is the mantissa times 100 shifted right by
+    # %rcx, which rounds to 0 for shifts of 62 and more
+4:  movq %r12, %rcx
+    negq %rcx
+    xorl %eax, %eax
+    cmpq $62, %rcx
+    jae 7f
+    imulq $100, %r13, %rax
+    movq %rax, %rdx
+    shrq %cl, %rax
+    movl $1, %edi
+    shlq %cl, %rdi
+    leaq -1(%rdi), %rsi
+    andq %rsi, %rdx
+    shrq $1, %rdi
+    cmpq %rdi, %rdx
+    ja 6f
+    jb 7f
+    testb $1, %al
+    jz 7f
+6:  incq %rax
+7:  xorl %edx, %edx
+    movl $100, %ecx
+    divq %rcx
+    movq %rax, %r12
+    movq %rdx, %rdi
+    movq %r14, %rsi
+    movl $2, %edx
+    call lol_digits_padded
+    decq %rax
+    movb $'.', (%rax)
+    movq %r12, %rdi
+    movq %rax, %rsi
+    call lol_digits
+
+8:  testq %rbx, %rbx
+    jns 1f
+    decq %rax
+    movb $'-', (%rax)
+1:  movq %rax, %rdi
+    movq %r14, %rsi
+    subq %rax, %rsi
+    call lol_yarn
+9:  popq %r14
+    popq %r13
+    popq %r12
+    popq %rbx
+    ret
+
+# Text of the value %rdi as VISIBLE writes it.
+lol_show:
+    movl (%rdi), %eax
+    cmpl $TAG_TROOF, %eax
+    jne 1f
+    leaq lol_text_win(%rip), %rax
+    leaq lol_text_fail(%rip), %rcx
+    cmpq $0, 8(%rdi)
+    cmoveq %rcx, %rax
+    ret
+1:  cmpl $TAG_NUMBR, %eax
+    jne 2f
+    movq 8(%rdi), %rdi
+    jmp lol_show_numbr
+2:  cmpl $TAG_NUMBAR, %eax
+    jne 3f
+    movsd 8(%rdi), %xmm0
+    jmp lol_show_numbar
+3:  cmpl $TAG_YARN, %eax
+    jne 4f
+    movq %rdi, %rax
+    ret
+4:  leaq lol_text_noob(%rip), %rax
+    ret
+
+# Reports an error the way rlcc does without the source at hand, and exits.
+# Takes the YARNs of the code %rdi, the message %rsi and the help %rdx, or
+# null for none, then the line %rcx and the column %r8.
+lol_fail:
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    movq %rdx, %r13
+    movq %rcx, %rdi
+    movq %r8, %r14
+    call lol_show_numbr
+    movq %rax, %r15
+
+    movl $2, %edi
+    leaq lol_error(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    movq %rbx, %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_after_code(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    movq %r12, %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_newline(%rip), %rsi
+    call lol_write_yarn
+    # The gutter is as wide as the line number
+    movl $2, %edi
+    movq lol_spaces+8(%rip), %rsi
+    movl 4(%r15), %edx
+    call lol_write
+    movl $2, %edi
+    leaq lol_arrow(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_source(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_colon(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    movq %r15, %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_colon(%rip), %rsi
+    call lol_write_yarn
+    movq %r14, %rdi
+    call lol_show_numbr
+    movl $2, %edi
+    movq %rax, %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_newline(%rip), %rsi
+    call lol_write_yarn
+    testq %r13, %r13
+    jz 1f
+    movl $2, %edi
+    movq lol_spaces+8(%rip), %rsi
+    movl 4(%r15), %edx
+    call lol_write
+    movl $2, %edi
+    leaq lol_help(%rip), %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    movq %r13, %rsi
+    call lol_write_yarn
+    movl $2, %edi
+    leaq lol_newline(%rip), %rsi
+    call lol_write_yarn
+1:  movl $1, %edi
+    jmp lol_exit
+
+# Fails for a call of the function named by the YARN %rdi, taking %rsi
+# arguments, with %rdx, at the line %rcx and the column %r8.
+lol_arity_error:
+    movq %rsi, %rbx
+    movq %rdx, %r12
+    movq %rcx, %r13
+    movq %r8, %r14
+    leaq lol_takes(%rip), %rsi
+    call lol_concat
+    movq %rax, %r15
+    movq %rbx, %rdi
+    call lol_show_numbr
+    movq %r15, %rdi
+    movq %rax, %rsi
+    call lol_concat
+    movq %rax, %rdi
+    leaq lol_arguments(%rip), %rsi
+    call lol_concat
+    movq %rax, %r15
+    movq %r12, %rdi
+    call lol_show_numbr
+    movq %r15, %rdi
+    movq %rax, %rsi
+    call lol_concat
+    movq %rax, %rdi
+    leaq lol_given(%rip), %rsi
+    call lol_concat
+    leaq lol_e0015(%rip), %rdi
+    movq %rax, %rsi
+    xorl %edx, %edx
+    movq %r13, %rcx
+    movq %r14, %r8
+    jmp lol_fail
+
+# Fails at the line %rdi and the column %rsi.
+lol_division_by_zero:
+    movq %rdi, %rcx
+    movq %rsi, %r8
+    leaq lol_e0020(%rip), %rdi
+    leaq lol_division(%rip), %rsi
+    xorl %edx, %edx
+    jmp lol_fail
+
+# The hexadecimal digit of %edi.
+lol_hex:
+    leal '0'(%rdi), %eax
+    leal 'a' - 10(%rdi), %ecx
+    cmpl $10, %edi
+    cmovael %ecx, %eax
+    ret
+
+# The YARN %rdi in quotes with special characters escaped, as Rust debug
+# formatting writes it.
+lol_quote:
+    pushq %rbx
+    pushq %r12
+    movq %rdi, %rbx
+    movl 4(%rbx), %edi
+    leaq (%rdi,%rdi,2), %rdi
+    leaq 2(,%rdi,2), %rdi
+    call lol_alloc
+    movq %rax, %r12
+    movb $'"', (%rax)
+    leaq 1(%rax), %rdi
+    movq 8(%rbx), %rsi
+    movl 4(%rbx), %r8d
+    addq %rsi, %r8
+
+1:  cmpq %r8, %rsi
+    jae 5f
+    movzbl (%rsi), %eax
+    incq %rsi
+    cmpb $'"', %al
+    je 3f
+    cmpb $'\\', %al
+    je 3f
+    movb $'n', %dl
+    cmpb $'\n', %al
+    je 2f
+    movb $'r', %dl
+    cmpb $'\r', %al
+    je 2f
+    movb $'t', %dl
+    cmpb $'\t', %al
+    je 2f
+    movb $'0', %dl
+    testb %al, %al
+    je 2f
+    cmpb $32, %al
+    jb 4f
+    cmpb $127, %al
+    je 4f
+    movb %al, (%rdi)
+    incq %rdi
+    jmp 1b
+2:  movl %edx, %eax
+3:  movb $'\\', (%rdi)
+    movb %al, 1(%rdi)
+    addq $2, %rdi
+    jmp 1b
+
+    # \u{..} with as few hexadecimal digits as needed
+4:  movl $0x7b755c, (%rdi)
+    addq $3, %rdi
+    movl %eax, %edx
+    cmpb $16, %dl
+    jb 4f
+    movq %rdi, %r9
+    movl %edx, %edi
+    shrl $4, %edi
+    call lol_hex
+    movq %r9, %rdi
+    movb %al, (%rdi)
+    incq %rdi
+4:  movq %rdi, %r9
+    movl %edx, %edi
+    andl $15, %edi
+    call lol_hex
+    movq %r9, %rdi
+    movb %al, (%rdi)
+    movb $'}', 1(%rdi)
+    addq $2, %rdi
+    jmp 1b
+
+5:  movb $'"', (%rdi)
+    incq %rdi
+    movq %rdi, %rsi
+    subq %r12, %rsi
+    movq %r12, %rdi
+    popq %r12
+    popq %rbx
+    jmp lol_yarn
+
+# A NUMBR as Rust's i64 parsing accepts the YARN %rdi, or null.
+lol_parse_numbr:
+    movq 8(%rdi), %rsi
+    movl 4(%rdi), %r10d
+    addq %rsi, %r10
+    xorl %r8d, %r8d
+    cmpq %r10, %rsi
+    jae 3f
+    movzbl (%rsi), %eax
+    cmpb $'+', %al
+    je 1f
+    cmpb $'-', %al
+    jne 2f
+    movl $1, %r8d
+1:  incq %rsi
+2:  cmpq %r10, %rsi
+    jae 3f
+
+    # Accumulates the magnitude, which may be up to 2^63 if negative
+    xorl %eax, %eax
+    movl $10, %r11d
+    movabsq $MIN_NUMBR, %r9
+4:  movzbl (%rsi), %ecx
+    subl $'0', %ecx
+    cmpl $10, %ecx
+    jae 3f
+    mulq %r11
+    jc 3f
+    addq %rcx, %rax
+    jc 3f
+    cmpq %r9, %rax
+    ja 3f
+    incq %rsi
+    cmpq %r10, %rsi
+    jb 4b
+
+    testl %r8d, %r8d
+    jz 5f
+    negq %rax
+    movq %rax, %rdi
+    jmp lol_numbr
+5:  cmpq %r9, %rax
+    je 3f
+    movq %rax, %rdi
+    jmp lol_numbr
+3:  xorl %eax, %eax
+    ret
+
+# A NUMBAR as Rust's f64 parsing accepts the YARN %rdi, or null. Up to 19
+# significant digits are kept, and the result is exact unless the digits
+# exceed 2^53 or the power of ten is beyond 10^22.
+lol_parse_numbar:
+    pushq %rbx
+    movq 8(%rdi), %rsi
+    movl 4(%rdi), %r10d
+    addq %rsi, %r10
+    xorl %r8d, %r8d
+    # The digits %r9, of which %ecx are significant, times 10 to the power
+    # %r11d, and whether there are any in %edi
+    xorl %r9d, %r9d
+    xorl %ecx, %ecx
+    xorl %r11d, %r11d
+    xorl %edi, %edi
+    cmpq %r10, %rsi
+    jae 1f
+    movzbl (%rsi), %eax
+    cmpb $'+', %al
+    je 2f
+    cmpb $'-', %al
+    jne 1f
+    movl $1, %r8d
+2:  incq %rsi
+
+    # Integer part
+1:  cmpq %r10, %rsi
+    jae 4f
+    movzbl (%rsi), %edx
+    subl $'0', %edx
+    cmpl $10, %edx
+    jae 4f
+    movl $1, %edi
+    cmpl $19, %ecx
+    jae 2f
+    imulq $10, %r9
+    addq %rdx, %r9
+    jz 3f
+    incl %ecx
+    jmp 3f
+2:  incl %r11d
+3:  incq %rsi
+    jmp 1b
+
+    # Fraction
+4:  cmpq %r10, %rsi
+    jae 7f
+    cmpb $'.', (%rsi)
+    jne 7f
+    incq %rsi
+5:  cmpq %r10, %rsi
+    jae 7f
+    movzbl (%rsi), %edx
+    subl $'0', %edx
+    cmpl $10, %edx
+    jae 7f
+    movl $1, %edi
+    cmpl $19, %ecx
+    jae 6f
+    imulq $10, %r9
+    addq %rdx, %r9
+    jz 2f
+    incl %ecx
+2:  decl %r11d
+6:  incq %rsi
+    jmp 5b
+7:  testl %edi, %edi
+    jz 9f
+
+    # Exponent, whose sign is in %ebx
+    cmpq %r10, %rsi
+    jae 4f
+    movzbl (%rsi), %eax
+    orb $32, %al
+    cmpb $'e', %al
+    jne 9f
+    incq %rsi
+    xorl %ebx, %ebx
+    cmpq %r10, %rsi
+    jae 9f
+    movzbl (%rsi), %eax
+    cmpb $'+', %al
+    je 1f
+    cmpb $'-', %al
+    jne 2f
+    movl $1, %ebx
+1:  incq %rsi
+2:  cmpq %r10, %rsi
+    jae 9f
+    xorl %eax, %eax
+3:  movzbl (%rsi), %edx
+    subl $'0', %edx
+    cmpl $10, %edx
+    jae 9f
+    # Anything this large overflows or underflows anyway
+    cmpl $100000, %eax
+    jae 1f
+    imull $10, %eax
+    addl %edx, %eax
+1:  incq %rsi
+    cmpq %r10, %rsi
+    jb 3b
+    movl %eax, %edx
+    negl %edx
+    testl %ebx, %ebx
+    cmovnzl %edx, %eax
+    addl %eax, %r11d
+
+    # The digits are unsigned, so those past 2^63 are halved, keeping the
+    # lowest bit for rounding, then doubled
+4:  testq %r9, %r9
+    js 1f
+    cvtsi2sdq %r9, %xmm0
+    jmp 2f
+1:  movq %r9, %rax
+    shrq $1, %rax
+    andl $1, %r9d
+    orq %r9, %rax
+    cvtsi2sdq %rax, %xmm0
+    addsd %xmm0, %xmm0
+    # Zero stays zero whatever the power of ten
+    jmp 3f
+2:  testq %r9, %r9
+    jz 8f
+3:  movsd lol_1e22(%rip), %xmm1
+4:  cmpl $22, %r11d
+    jle 5f
+    mulsd %xmm1, %xmm0
+    subl $22, %r11d
+    jmp 4b
+5:  cmpl $-22, %r11d
+    jge 6f
+    divsd %xmm1, %xmm0
+    addl $22, %r11d
+    jmp 5b
+    # The power of ten is exact up to 10^22
+6:  movl %r11d, %eax
+    negl %eax
+    cmovsl %r11d, %eax
+    movsd lol_ten(%rip), %xmm2
+    movl $1, %edx
+    cvtsi2sdl %edx, %xmm1
+7:  testl %eax, %eax
+    jz 1f
+    mulsd %xmm2, %xmm1
+    decl %eax
+    jmp 7b
+1:  testl %r11d, %r11d
+    js 2f
+    mulsd %xmm1, %xmm0
+    jmp 8f
+2:  divsd %xmm1, %xmm0
+
+8:  testl %r8d, %r8d
+    jz 1f
+    movq %xmm0, %rax
+    btcq $63, %rax
+    movq %rax, %xmm0
+1:  popq %rbx
+    jmp lol_numbar
+9:  popq %rbx
+    xorl %eax, %eax
+    ret
+
+# Whether the YARN %rdi contains a dot, as 0 or 1 in %eax.
+lol_contains_dot:
+    movl 4(%rdi), %ecx
+    movq 8(%rdi), %rdi
+    movb $'.', %al
+    # An empty YARN leaves the flags of this comparison alone
+    cmpl $1, %ecx
+    repne scasb
+    sete %al
+    movzbl %al, %eax
+    ret
+
+# Implicit cast of the value %rdi for arithmetic at the line %rsi and the
+# column %rdx. NOOB may only be cast explicitly.
+lol_to_number:
+    movl (%rdi), %eax
+    cmpl $TAG_NOOB, %eax
+    jne 1f
+    movq %rsi, %rcx
+    movq %rdx, %r8
+    leaq lol_e0019(%rip), %rdi
+    leaq lol_cast_noob_number(%rip), %rsi
+    xorl %edx, %edx
+    jmp lol_fail
+1:  cmpl $TAG_TROOF, %eax
+    jne 2f
+    movq 8(%rdi), %rdi
+    jmp lol_numbr
+2:  cmpl $TAG_YARN, %eax
+    je 3f
+    movq %rdi, %rax
+    ret
+
+3:  pushq %rbx
+    pushq %r12
+    pushq %r13
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    movq %rdx, %r13
+    call lol_contains_dot
+    movq %rbx, %rdi
+    testl %eax, %eax
+    jz 4f
+    call lol_parse_numbar
+    jmp 5f
+4:  call lol_parse_numbr
+5:  testq %rax, %rax
+    jz 6f
+    popq %r13
+    popq %r12
+    popq %rbx
+    ret
+6:  movq %rbx, %rdi
+    call lol_quote
+    leaq lol_cast_yarn(%rip), %rdi
+    movq %rax, %rsi
+    call lol_concat
+    movq %rax, %rdi
+    leaq lol_to_number_text(%rip), %rsi
+    call lol_concat
+    leaq lol_e0019(%rip), %rdi
+    movq %rax, %rsi
+    xorl %edx, %edx
+    movq %r12, %rcx
+    movq %r13, %r8
+    jmp lol_fail
+
+# Implicit cast of the value %rdi for SMOOSH at the line %rsi and the column
+# %rdx.
+lol_to_yarn:
+    cmpl $TAG_NOOB, (%rdi)
+    jne lol_show
+    movq %rsi, %rcx
+    movq %rdx, %r8
+    leaq lol_e0019(%rip), %rdi
+    leaq lol_cast_noob_yarn(%rip), %rsi
+    xorl %edx, %edx
+    jmp lol_fail
+
+# NUMBAR %xmm0 to NUMBR, saturating like Rust's `as`.
+lol_truncate:
+    xorl %eax, %eax
+    ucomisd %xmm0, %xmm0
+    jp 1f
+    # Out of range values convert to MIN_NUMBR
+    cvttsd2siq %xmm0, %rax
+    movabsq $MIN_NUMBR, %rcx
+    cmpq %rcx, %rax
+    jne 1f
+    xorpd %xmm1, %xmm1
+    ucomisd %xmm1, %xmm0
+    jbe 1f
+    decq %rax
+1:  ret
+
+# Explicit cast with MAEK or IS NOW A of the value %rdi to the type with
+# the tag %esi, at the line %rdx and the column %rcx.
+lol_cast:
+    cmpl $TAG_NOOB, %esi
+    jne 1f
+    leaq lol_noob_cell(%rip), %rax
+    ret
+1:  cmpl $TAG_TROOF, %esi
+    jne 2f
+    call lol_to_troof
+    movl %eax, %edi
+    jmp lol_troof
+2:  cmpl $TAG_NUMBR, %esi
+    jne 3f
+    cmpl $TAG_NOOB, (%rdi)
+    je 1f
+    movq %rdx, %rsi
+    movq %rcx, %rdx
+    call lol_to_number
+    cmpl $TAG_NUMBAR, (%rax)
+    jne 5f
+    movsd 8(%rax), %xmm0
+    call lol_truncate
+    movq %rax, %rdi
+    jmp lol_numbr
+1:  leaq lol_zero_cell(%rip), %rax
+    ret
+3:  cmpl $TAG_NUMBAR, %esi
+    jne 4f
+    xorpd %xmm0, %xmm0
+    cmpl $TAG_NOOB, (%rdi)
+    je lol_numbar
+    movq %rdx, %rsi
+    movq %rcx, %rdx
+    call lol_to_number
+    cmpl $TAG_NUMBR, (%rax)
+    jne 5f
+    cvtsi2sdq 8(%rax), %xmm0
+    jmp lol_numbar
+4:  cmpl $TAG_NOOB, (%rdi)
+    jne lol_show
+    leaq lol_empty(%rip), %rax
+5:  ret
+
+# Remainder of the NUMBARs %xmm0 and %xmm1 truncated towards zero, which
+# the x87 computes exactly.
+lol_fmod:
+    subq $16, %rsp
+    movsd %xmm1, 8(%rsp)
+    movsd %xmm0, (%rsp)
+    fldl 8(%rsp)
+    fldl (%rsp)
+    # Each step reduces the exponent by at most 63, until C2 is clear
+1:  fprem
+    fnstsw %ax
+    testw $0x400, %ax
+    jnz 1b
+    fstp %st(1)
+    fstpl (%rsp)
+    movsd (%rsp), %xmm0
+    addq $16, %rsp
+    ret
+
+# Larger or smaller of the NUMBARs %xmm0 and %xmm1, ignoring NaN as Rust's
+# max and min do.
+lol_fmax:
+    ucomisd %xmm0, %xmm0
+    jp 1f
+    ucomisd %xmm1, %xmm1
+    jp 2f
+    maxsd %xmm1, %xmm0
+    ret
+1:  movapd %xmm1, %xmm0
+2:  ret
+
+lol_fmin:
+    ucomisd %xmm0, %xmm0
+    jp 1f
+    ucomisd %xmm1, %xmm1
+    jp 2f
+    minsd %xmm1, %xmm0
+    ret
+1:  movapd %xmm1, %xmm0
+2:  ret
+
+# The number %rdi as a NUMBAR in %xmm0, leaving the other registers alone.
+lol_as_numbar:
+    cmpl $TAG_NUMBR, (%rdi)
+    jne 1f
+    cvtsi2sdq 8(%rdi), %xmm0
+    ret
+1:  movsd 8(%rdi), %xmm0
+    ret
+
+# SUM OF through SMALLR OF, numbered 0 to 6 in %edx, of the values %rdi and
+# %rsi at the line %rcx and the column %r8. NUMBRs stay NUMBRs unless either
+# side is a NUMBAR.
+lol_arithmetic:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    pushq %r14
+    pushq %r15
+    movq %rsi, %r12
+    movl %edx, %r13d
+    movq %rcx, %r14
+    movq %r8, %r15
+    movq %r14, %rsi
+    movq %r15, %rdx
+    call lol_to_number
+    movq %rax, %rbx
+    movq %r12, %rdi
+    movq %r14, %rsi
+    movq %r15, %rdx
+    call lol_to_number
+    movq %rax, %r12
+    cmpl $TAG_NUMBR, (%rbx)
+    jne 8f
+    cmpl $TAG_NUMBR, (%r12)
+    jne 8f
+
+    movq 8(%rbx), %rax
+    movq 8(%r12), %rcx
+    cmpl $0, %r13d
+    jne 1f
+    addq %rcx, %rax
+    jmp 7f
+1:  cmpl $1, %r13d
+    jne 2f
+    subq %rcx, %rax
+    jmp 7f
+2:  cmpl $2, %r13d
+    jne 3f
+    imulq %rcx, %rax
+    jmp 7f
+3:  cmpl $4, %r13d
+    ja 5f
+    # Fails like Rust's checked division and remainder
+    testq %rcx, %rcx
+    jz 4f
+    cmpq $-1, %rcx
+    jne 3f
+    movabsq $MIN_NUMBR, %rdx
+    cmpq %rdx, %rax
+    je 4f
+3:  cqto
+    idivq %rcx
+    cmpl $4, %r13d
+    cmoveq %rdx, %rax
+    jmp 7f
+4:  movq %r14, %rdi
+    movq %r15, %rsi
+    jmp lol_division_by_zero
+5:  cmpl $5, %r13d
+    jne 6f
+    cmpq %rcx, %rax
+    cmovlq %rcx, %rax
+    jmp 7f
+6:  cmpq %rcx, %rax
+    cmovgq %rcx, %rax
+7:  movq %rax, %rdi
+    popq %r15
+    popq %r14
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_numbr
+
+8:  movq %r12, %rdi
+    call lol_as_numbar
+    movapd %xmm0, %xmm1
+    movq %rbx, %rdi
+    call lol_as_numbar
+    cmpl $0, %r13d
+    jne 1f
+    addsd %xmm1, %xmm0
+    jmp 7f
+1:  cmpl $1, %r13d
+    jne 2f
+    subsd %xmm1, %xmm0
+    jmp 7f
+2:  cmpl $2, %r13d
+    jne 3f
+    mulsd %xmm1, %xmm0
+    jmp 7f
+3:  cmpl $4, %r13d
+    ja 5f
+    xorpd %xmm2, %xmm2
+    ucomisd %xmm2, %xmm1
+    jp 3f
+    je 4f
+3:  cmpl $4, %r13d
+    je 3f
+    divsd %xmm1, %xmm0
+    jmp 7f
+3:  call lol_fmod
+    jmp 7f
+4:  movq %r14, %rdi
+    movq %r15, %rsi
+    jmp lol_division_by_zero
+5:  cmpl $5, %r13d
+    jne 6f
+    call lol_fmax
+    jmp 7f
+6:  call lol_fmin
+7:  popq %r15
+    popq %r14
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_numbar
+
+# BOTH SAEM of the values %rdi and %rsi, as 0 or 1 in %eax. Only NUMBRs and
+# NUMBARs are compared across types.
+lol_saem:
+    movl (%rdi), %eax
+    movl (%rsi), %ecx
+    cmpl %ecx, %eax
+    je 2f
+    subl $TAG_NUMBR, %eax
+    cmpl $1, %eax
+    ja 1f
+    subl $TAG_NUMBR, %ecx
+    cmpl $1, %ecx
+    ja 1f
+    pushq %rsi
+    call lol_as_numbar
+    popq %rdi
+    movapd %xmm0, %xmm1
+    call lol_as_numbar
+    jmp 5f
+1:  xorl %eax, %eax
+    ret
+
+2:  cmpl $TAG_NOOB, %eax
+    jne 3f
+    movl $1, %eax
+    ret
+3:  cmpl $TAG_NUMBAR, %eax
+    jne 4f
+    movsd 8(%rdi), %xmm0
+    movsd 8(%rsi), %xmm1
+5:  ucomisd %xmm1, %xmm0
+    sete %al
+    setnp %cl
+    andb %cl, %al
+    movzbl %al, %eax
+    ret
+4:  cmpl $TAG_YARN, %eax
+    je 6f
+    movq 8(%rdi), %rax
+    cmpq 8(%rsi), %rax
+    sete %al
+    movzbl %al, %eax
+    ret
+6:  movl 4(%rdi), %ecx
+    cmpl 4(%rsi), %ecx
+    jne 1b
+    movq 8(%rdi), %rdi
+    movq 8(%rsi), %rsi
+    # An empty YARN leaves the flags of this comparison alone
+    cmpl %ecx, %ecx
+    repe cmpsb
+    sete %al
+    movzbl %al, %eax
+    ret
+
+# Any operator with two operands, numbered as for lol_arithmetic then BOTH
+# OF through DIFFRINT as 7 to 11. Both are evaluated whatever the first one
+# is.
+lol_binary:
+    cmpl $7, %edx
+    jb lol_arithmetic
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    movq %rsi, %r12
+    movl %edx, %r13d
+    cmpl $10, %r13d
+    jae 4f
+    call lol_to_troof
+    movl %eax, %ebx
+    movq %r12, %rdi
+    call lol_to_troof
+    cmpl $7, %r13d
+    jne 1f
+    andl %ebx, %eax
+    jmp 5f
+1:  cmpl $8, %r13d
+    jne 2f
+    orl %ebx, %eax
+    jmp 5f
+2:  xorl %ebx, %eax
+    jmp 5f
+4:  call lol_saem
+    cmpl $11, %r13d
+    jne 5f
+    xorl $1, %eax
+5:  movl %eax, %edi
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_troof
+
+# ALL OF the %rsi values at %rdi.
+lol_all:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    movl $1, %r13d
+1:  testq %r12, %r12
+    jz 2f
+    decq %r12
+    movq (%rbx,%r12,8), %rdi
+    call lol_to_troof
+    andl %eax, %r13d
+    jmp 1b
+2:  movl %r13d, %edi
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_troof
+
+# ANY OF the %rsi values at %rdi.
+lol_any:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    movq %rdi, %rbx
+    movq %rsi, %r12
+    xorl %r13d, %r13d
+1:  testq %r12, %r12
+    jz 2f
+    decq %r12
+    movq (%rbx,%r12,8), %rdi
+    call lol_to_troof
+    orl %eax, %r13d
+    jmp 1b
+2:  movl %r13d, %edi
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_troof
+
+# Writes the YARN %rdi to stdout for VISIBLE, or stderr for INVISIBLE if %esi
+# is 2, followed by a newline unless %edx is 0.
+# A line of standard input as a YARN, without its line ending. At the end
+# of the input it is whatever is left.
+lol_gimmeh:
+    pushq %rbx
+    pushq %r12
+    pushq %r13
+    xorl %ebx, %ebx
+    movl $64, %r13d
+    movq %r13, %rdi
+    call lol_alloc
+    movq %rax, %r12
+1:  cmpq %r13, %rbx
+    jb 2f
+    # The buffer is full, so its bytes move to one twice the size
+    leaq (%r13,%r13), %rdi
+    call lol_alloc
+    movq %rax, %rdi
+    movq %r12, %rsi
+    movq %rbx, %rcx
+    rep movsb
+    movq %rax, %r12
+    addq %r13, %r13
+2:  xorl %edi, %edi
+    leaq (%r12,%rbx), %rsi
+    movl $1, %edx
+    movl $SYS_READ, %eax
+    syscall
+    # Reads again when interrupted by a signal (EINTR)
+    cmpq $-4, %rax
+    je 2b
+    testq %rax, %rax
+    jle 4f
+    cmpb $10, (%r12,%rbx)
+    je 3f
+    incq %rbx
+    jmp 1b
+    # Drops a carriage return before the newline
+3:  testq %rbx, %rbx
+    jz 4f
+    cmpb $13, -1(%r12,%rbx)
+    jne 4f
+    decq %rbx
+4:  movq %r12, %rdi
+    movq %rbx, %rsi
+    popq %r13
+    popq %r12
+    popq %rbx
+    jmp lol_yarn
+
+lol_visible:
+    pushq %rsi
+    testl %edx, %edx
+    jz 1f
+    leaq lol_newline(%rip), %rsi
+    call lol_concat
+    movq %rax, %rdi
+1:  movq %rdi, %rsi
+    popq %rdi
+    jmp lol_write_yarn
@@ -0,0 +1,628 @@
+//! Lowering to C99, built into a native executable with the system C
+//! compiler. The runtime in `runtime.c` is pasted into every program. It has
+//! NOOB, TROOF, NUMBR, NUMBAR and YARN values but no BUKKITs, which no
+//! program accepted by the parser can have yet.
+//!
+//! Each frame becomes a C function with a local per variable, starting out
+//! undeclared and checked on use, since variables only exist once their
+//! declaration has run. Expressions are evaluated into temporaries one
+//! operand at a time so that errors are reported in the same order as by
+//! the interpreter.
+
+use std::{
+    collections::BTreeSet,
+    env,
+    ffi::OsString,
+    fmt::Write as _,
+    io::Write as _,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    interpreter::{
+        break_outside, check_features, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+};
+
+use super::{escape, function_defs, includable, mangle, Escape};
+
+pub(super) const RUNTIME: &str = include_str!("runtime.c");
+
+pub trait EmitC {
+    /// Lowers `prog` to a standalone C program, naming the source `source`
+    /// in its runtime errors.
+    fn emit_c(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String>;
+}
+
+impl<T> EmitC for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn emit_c(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String> {
+        check_features(self, prog)?;
+
+        let modules = includable(self, prog, "C")?;
+        let functions = function_defs(prog);
+
+        let mut program = format!("#define LOL_SOURCE {}\n", c_string(source));
+        program.push_str(RUNTIME);
+        program.push('\n');
+
+        for module in &modules {
+            writeln!(program, "static int {};", mangle("loaded_", module)).unwrap();
+        }
+        let mut names = BTreeSet::new();
+        for func in &functions {
+            if names.insert(func.name.name.as_str()) {
+                let name = mangle("fn_", &func.name.name);
+                writeln!(program, "static lol_function {name};").unwrap();
+            }
+        }
+        for index in 0..functions.len() {
+            writeln!(program, "static lol_value f_{index}(lol_value *args);").unwrap();
+        }
+
+        let mut writer = Writer {
+            functions: &functions,
+            defined: names,
+            modules,
+            lines: String::new(),
+            indent: 1,
+            temps: 0,
+            labels: 0,
+            vars: BTreeSet::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        for (index, func) in functions.iter().enumerate() {
+            writer.in_function = true;
+            writer.block(&func.body);
+            let params = func.params.iter().map(|param| param.name.as_str());
+            let head = format!("static lol_value f_{index}(lol_value *args)");
+            program.push_str(&writer.finish(&head, params, "return it;"));
+        }
+        writer.in_function = false;
+        writer.block(&prog.body);
+        program.push_str(&writer.finish("int main(void)", [].into_iter(), "return 0;"));
+        Ok(program)
+    }
+}
+
+/// Builds the C program `source` into an executable at `output` with `$CC`,
+/// or `cc` if it is unset.
+pub(crate) fn build(source: &str, output: &Path) -> anyhow::Result<()> {
+    let cc = env::var_os("CC").unwrap_or_else(|| OsString::from("cc"));
+    let mut child = Command::new(&cc)
+        .args(["-std=c99", "-O2", "-x", "c", "-", "-o"])
+        .arg(output)
+        .arg("-lm")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("running {}", cc.to_string_lossy()))?;
+    child
+        .stdin
+        .take()
+        .context("stdin of the C compiler is piped")?
+        .write_all(source.as_bytes())
+        .context("writing to the C compiler")?;
+
+    let result = child
+        .wait_with_output()
+        .context("waiting for the C compiler")?;
+    if !result.status.success() {
+        bail!(
+            "{} failed with {}:\n{}",
+            cc.to_string_lossy(),
+            result.status,
+            String::from_utf8_lossy(&result.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// C string literal with the bytes of `text`.
+fn c_string(text: &str) -> String {
+    format!("\"{}\"", escape(text.as_bytes(), Escape::Octal))
+}
+
+/// Compound literal for an array of `items`, or `NULL` if there are none
+/// since C has no empty arrays.
+fn array(element: &str, items: &[String]) -> String {
+    match items {
+        [] => String::from("NULL"),
+        items => format!("({element}[]){{{}}}", items.join(", ")),
+    }
+}
+
+fn position(span: Span) -> String {
+    format!("{}, {}", span.start.line, span.start.column)
+}
+
+/// Statement reporting `diagnostic` at runtime.
+fn fail(diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.span.expect("runtime errors have a location");
+    let help = match &diagnostic.help {
+        Some(help) => c_string(help),
+        None => String::from("NULL"),
+    };
+    format!(
+        "lol_fail(\"{}\", {}, {help}, {});",
+        diagnostic.code,
+        c_string(&diagnostic.message),
+        position(span)
+    )
+}
+
+fn binary_op(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Sum => "LOL_SUM",
+        BinaryOp::Diff => "LOL_DIFF",
+        BinaryOp::Produkt => "LOL_PRODUKT",
+        BinaryOp::Quoshunt => "LOL_QUOSHUNT",
+        BinaryOp::Mod => "LOL_MOD",
+        BinaryOp::Biggr => "LOL_BIGGR",
+        BinaryOp::Smallr => "LOL_SMALLR",
+        BinaryOp::Both => "LOL_BOTH",
+        BinaryOp::Either => "LOL_EITHER",
+        BinaryOp::Won => "LOL_WON",
+        BinaryOp::Saem => "LOL_SAEM",
+        BinaryOp::Diffrint => "LOL_DIFFRINT",
+    }
+}
+
+fn type_tag(to: Type) -> &'static str {
+    match to {
+        Type::Noob => "LOL_NOOB",
+        Type::Troof => "LOL_TROOF",
+        Type::Numbr => "LOL_NUMBR",
+        Type::Numbar => "LOL_NUMBAR",
+        Type::Yarn => "LOL_YARN",
+    }
+}
+
+/// Writes the body of one C function at a time.
+struct Writer<'p, 'f> {
+    /// Every function definition, in the order of the `f_<index>` functions.
+    functions: &'f [&'p FuncDef],
+    /// Names of the functions the program defines.
+    defined: BTreeSet<&'p str>,
+    /// Modules the program can include.
+    modules: BTreeSet<&'p str>,
+    lines: String,
+    indent: usize,
+    temps: usize,
+    labels: usize,
+    /// Variables used in the current frame.
+    vars: BTreeSet<&'p str>,
+    /// Labels just past each enclosing loop or `WTF?`, innermost last.
+    breaks: Vec<String>,
+    in_function: bool,
+}
+
+impl<'p> Writer<'p, '_> {
+    fn line<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        for _ in 0..self.indent {
+            self.lines.push_str("    ");
+        }
+        self.lines.push_str(line.as_ref());
+        self.lines.push('\n');
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("t{}", self.temps)
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("{kind}_{}", self.labels)
+    }
+
+    /// Wraps what was written since the last call in a C function, declaring
+    /// its variables, and starts afresh.
+    fn finish<'a, I>(&mut self, head: &str, params: I, end: &str) -> String
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut function = format!("\n{head} {{\n    lol_value it = lol_noob();\n");
+        // IT is never a variable
+        let params: Vec<(usize, &str)> = params
+            .enumerate()
+            .filter(|(_, param)| *param != "IT")
+            .collect();
+        let vars: BTreeSet<&str> = self
+            .vars
+            .iter()
+            .copied()
+            .chain(params.iter().map(|(_, param)| *param))
+            .collect();
+        for var in vars {
+            let var = mangle("v_", var);
+            writeln!(function, "    lol_value {var} = LOL_UNDECLARED;").unwrap();
+        }
+        // A later parameter with the same name wins
+        for (index, param) in params {
+            let var = mangle("v_", param);
+            writeln!(function, "    {var} = args[{index}];").unwrap();
+        }
+        function.push_str(&std::mem::take(&mut self.lines));
+        writeln!(function, "    {end}\n}}").unwrap();
+
+        self.vars.clear();
+        self.temps = 0;
+        function
+    }
+
+    /// C lvalue holding the variable `name`, or `IT`.
+    fn var(&mut self, name: &'p Ident) -> String {
+        match name.name.as_str() {
+            "IT" => String::from("it"),
+            var => {
+                self.vars.insert(var);
+                mangle("v_", var)
+            }
+        }
+    }
+
+    /// Fails at runtime if `name` has not been declared, and returns its
+    /// lvalue.
+    fn declared(&mut self, name: &'p Ident) -> String {
+        let var = self.var(name);
+        if var != "it" {
+            let fail = fail(&undefined_variable(name));
+            self.line(format!("if ({var}.type == LOL_UNDEFINED) {fail}"));
+        }
+        var
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// Writes a `{ ... }` scope for the temporaries of `body`.
+    fn scope<F>(&mut self, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.line("{");
+        self.indent += 1;
+        body(self);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => self.scope(|writer| {
+                let args: Vec<String> = args.iter().map(|arg| writer.expr(arg)).collect();
+                let stream = match invisible {
+                    true => "stderr",
+                    false => "stdout",
+                };
+                let count = args.len();
+                let args = array("lol_value", &args);
+                let newline = i32::from(*newline);
+                writer.line(format!(
+                    "lol_visible({stream}, {count}, {args}, {newline});"
+                ));
+            }),
+            StmtKind::Gimmeh(name) => self.scope(|writer| {
+                let line = writer.temp();
+                writer.line(format!("lol_value {line} = lol_gimmeh();"));
+                let var = writer.declared(name);
+                writer.line(format!("{var} = {line};"));
+            }),
+            StmtKind::CanHas { module } => {
+                if self.modules.contains(module.name.as_str()) {
+                    self.line(format!("{} = 1;", mangle("loaded_", &module.name)));
+                } else {
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span);
+                    self.line(fail(&diagnostic));
+                }
+            }
+            StmtKind::Declare { name, init } => self.scope(|writer| {
+                let value = match init {
+                    Some(init) => writer.expr(init),
+                    None => String::from("lol_noob()"),
+                };
+                // Declaring IT has no effect
+                let var = writer.var(name);
+                if var != "it" {
+                    writer.line(format!("{var} = {value};"));
+                }
+            }),
+            StmtKind::Assign { name, value } => self.scope(|writer| {
+                let value = writer.expr(value);
+                let var = writer.declared(name);
+                writer.line(format!("{var} = {value};"));
+            }),
+            StmtKind::CastVar { name, to } => {
+                let var = self.declared(name);
+                let (to, at) = (type_tag(*to), position(stmt.span));
+                self.line(format!("{var} = lol_cast({var}, {to}, {at});"));
+            }
+            StmtKind::Expr(expr) => self.scope(|writer| {
+                let value = writer.expr(expr);
+                writer.line(format!("it = {value};"));
+            }),
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                self.line("if (lol_to_troof(it)) {");
+                self.indent += 1;
+                self.block(then);
+                self.indent -= 1;
+                for (condition, block) in elifs {
+                    self.line("} else {");
+                    self.indent += 1;
+                    let condition = self.expr(condition);
+                    self.line(format!("if (lol_to_troof({condition})) {{"));
+                    self.indent += 1;
+                    self.block(block);
+                    self.indent -= 1;
+                }
+                if let Some(otherwise) = otherwise {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.block(otherwise);
+                    self.indent -= 1;
+                }
+                for _ in elifs {
+                    self.line("}");
+                    self.indent -= 1;
+                }
+                self.line("}");
+            }
+            StmtKind::Switch { cases, default } => self.scope(|writer| {
+                let cases: Vec<_> = cases
+                    .iter()
+                    .map(|(literal, block)| (literal, block, writer.label("omg")))
+                    .collect();
+                for (literal, _, label) in &cases {
+                    writer.scope(|writer| {
+                        let literal = writer.expr(literal);
+                        writer.line(format!("if (lol_saem({literal}, it)) goto {label};"));
+                    });
+                }
+                let done = writer.label("done");
+                let unmatched = writer.label("omgwtf");
+                writer.line(format!("goto {unmatched};"));
+
+                // Cases fall through into the ones after, OMGWTF included
+                writer.breaks.push(done.clone());
+                for (_, block, label) in cases {
+                    writer.line(format!("{label}:;"));
+                    writer.block(block);
+                }
+                writer.line(format!("{unmatched}:;"));
+                if let Some(default) = default {
+                    writer.block(default);
+                }
+                writer.breaks.pop();
+                writer.line(format!("{done}:;"));
+            }),
+            StmtKind::Loop(lp) => self.scope(|writer| {
+                let temporary = lp.update.as_ref().map(|update| {
+                    let var = writer.var(&update.var);
+                    let temporary = writer.temp();
+                    writer.line(format!("int {temporary} = lol_enter_loop(&{var});"));
+                    (var, temporary)
+                });
+
+                let done = writer.label("done");
+                writer.line("for (;;) {");
+                writer.indent += 1;
+                if let Some(condition) = &lp.condition {
+                    writer.scope(|writer| {
+                        let (condition, negate) = match condition {
+                            LoopCondition::Til(condition) => (condition, ""),
+                            LoopCondition::Wile(condition) => (condition, "!"),
+                        };
+                        let condition = writer.expr(condition);
+                        writer.line(format!(
+                            "if ({negate}lol_to_troof({condition})) goto {done};"
+                        ));
+                    });
+                }
+                writer.breaks.push(done.clone());
+                writer.block(&lp.body);
+                writer.breaks.pop();
+                if let Some(update) = &lp.update {
+                    let op = match update.op {
+                        LoopOp::Uppin => BinaryOp::Sum,
+                        LoopOp::Nerfin => BinaryOp::Diff,
+                    };
+                    let var = writer.declared(&update.var);
+                    let (op, at) = (binary_op(op), position(update.var.span));
+                    writer.line(format!(
+                        "{var} = lol_binary({op}, {var}, lol_numbr(1), {at});"
+                    ));
+                }
+                writer.indent -= 1;
+                writer.line("}");
+                writer.line(format!("{done}:;"));
+
+                if let Some((var, temporary)) = temporary {
+                    writer.line(format!("if ({temporary}) {var} = LOL_UNDECLARED;"));
+                }
+            }),
+            StmtKind::FuncDef(func) => {
+                let index = self
+                    .functions
+                    .iter()
+                    .position(|other| std::ptr::eq(*other, func))
+                    .expect("every function definition is collected");
+                let name = mangle("fn_", &func.name.name);
+                let arity = func.params.len();
+                self.line(format!("{name}.fn = f_{index};"));
+                self.line(format!("{name}.arity = {arity};"));
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.line(fail(&return_outside(stmt.span)));
+                    return;
+                }
+                self.scope(|writer| {
+                    let value = writer.expr(expr);
+                    writer.line(format!("return {value};"));
+                });
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(done) => {
+                    let done = done.clone();
+                    self.line(format!("goto {done};"));
+                }
+                // GTFO in a function returns NOOB
+                None if self.in_function => self.line("return lol_noob();"),
+                None => self.line(fail(&break_outside(stmt.span))),
+            },
+        }
+    }
+
+    /// Writes the evaluation of `expr` into a new temporary and returns it.
+    fn expr(&mut self, expr: &'p Expr) -> String {
+        let at = position(expr.span);
+        let value = match &expr.kind {
+            ExprKind::Noob => String::from("lol_noob()"),
+            ExprKind::Troof(troof) => format!("lol_troof({})", i32::from(*troof)),
+            ExprKind::Numbr(i64::MIN) => String::from("lol_numbr(INT64_MIN)"),
+            ExprKind::Numbr(numbr) => format!("lol_numbr(INT64_C({numbr}))"),
+            ExprKind::Numbar(numbar) => {
+                format!("lol_numbar_bits(UINT64_C({:#x}))", numbar.to_bits())
+            }
+            ExprKind::Yarn(yarn) => format!("lol_yarn({})", c_string(yarn)),
+            ExprKind::Var(var) => self.declared(var),
+            ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.expr(left), self.expr(right));
+                format!("lol_binary({}, {left}, {right}, {at})", binary_op(*op))
+            }
+            ExprKind::Not(operand) => {
+                let operand = self.expr(operand);
+                format!("lol_troof(!lol_to_troof({operand}))")
+            }
+            ExprKind::Nary { op, args } => {
+                let values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                match op {
+                    NaryOp::All | NaryOp::Any => {
+                        let (empty, join) = match op {
+                            NaryOp::All => ("1", " && "),
+                            _ => ("0", " || "),
+                        };
+                        let troofs: Vec<String> = values
+                            .iter()
+                            .map(|value| format!("lol_to_troof({value})"))
+                            .collect();
+                        match troofs.is_empty() {
+                            true => format!("lol_troof({empty})"),
+                            false => format!("lol_troof({})", troofs.join(join)),
+                        }
+                    }
+                    // Every piece is evaluated before any is cast
+                    NaryOp::Smoosh => {
+                        let pieces: Vec<String> = values
+                            .iter()
+                            .zip(args)
+                            .map(|(value, arg)| {
+                                let piece = self.temp();
+                                let at = position(arg.span);
+                                self.line(format!(
+                                    "const char *{piece} = lol_to_yarn({value}, {at});"
+                                ));
+                                piece
+                            })
+                            .collect();
+                        let count = pieces.len();
+                        let pieces = array("const char *", &pieces);
+                        format!("lol_concat({count}, {pieces})")
+                    }
+                }
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                let operand = self.expr(operand);
+                format!("lol_cast({operand}, {}, {at})", type_tag(*to))
+            }
+            ExprKind::Call {
+                module: None,
+                name,
+                args,
+            } => {
+                let values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                if !self.defined.contains(name.name.as_str()) {
+                    self.line(fail(&undefined_function(name)));
+                    String::from("lol_noob()")
+                } else {
+                    let function = mangle("fn_", &name.name);
+                    let count = values.len();
+                    let args = array("lol_value", &values);
+                    format!(
+                        "lol_call(&{function}, {}, {count}, {args}, {at}, {})",
+                        c_string(&name.name),
+                        position(name.span)
+                    )
+                }
+            }
+            // Modules that can be included have no functions
+            ExprKind::Call {
+                module: Some(module),
+                name,
+                args,
+            } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let not_loaded = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!(
+                        "Module {0} has not been loaded. Are you missing CAN HAS {0}?",
+                        module.name
+                    ),
+                )
+                .with_span(expr.span);
+                let not_loaded = fail(&not_loaded);
+                let no_function = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has no function {}", module.name, name.name),
+                )
+                .with_span(expr.span);
+                let no_function = fail(&no_function);
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    self.line(format!("if (!{loaded}) {not_loaded}"));
+                    self.line(no_function);
+                } else {
+                    self.line(not_loaded);
+                }
+                String::from("lol_noob()")
+            }
+        };
+        let temp = self.temp();
+        self.line(format!("lol_value {temp} = {value};"));
+        temp
+    }
+}
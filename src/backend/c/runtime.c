@@ -0,0 +1,499 @@
+/*
+ * Runtime of LOLCODE programs compiled to C by rlcc. It mirrors the
+ * semantics of rlcc's interpreter, including the text of its diagnostics.
+ * YARNs are never freed since programs are short-lived.
+ *
+ * There are no BUKKIT values: the front end reports BUKKITs as unsupported,
+ * so nothing would create them. They are to come with front end support.
+ *
+ * The generated program defines LOL_SOURCE, the name of the source file,
+ * before this runtime.
+ */
+#include <errno.h>
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum {
+    LOL_UNDEFINED,
+    LOL_NOOB,
+    LOL_TROOF,
+    LOL_NUMBR,
+    LOL_NUMBAR,
+    LOL_YARN
+} lol_type;
+
+typedef struct {
+    lol_type type;
+    union {
+        int troof;
+        int64_t numbr;
+        double numbar;
+        const char *yarn;
+    } as;
+} lol_value;
+
+/* Value of a variable that has not been declared (yet). */
+static const lol_value LOL_UNDECLARED = {LOL_UNDEFINED, {0}};
+
+enum {
+    LOL_SUM,
+    LOL_DIFF,
+    LOL_PRODUKT,
+    LOL_QUOSHUNT,
+    LOL_MOD,
+    LOL_BIGGR,
+    LOL_SMALLR,
+    LOL_BOTH,
+    LOL_EITHER,
+    LOL_WON,
+    LOL_SAEM,
+    LOL_DIFFRINT
+};
+
+typedef lol_value (*lol_fn)(lol_value *args);
+
+/* A function defined with HOW IZ I, once its definition has run. */
+typedef struct {
+    lol_fn fn;
+    int arity;
+} lol_function;
+
+/* Reports an error the way rlcc does without the source at hand, and exits. */
+static void lol_fail(const char *code, const char *message, const char *help, int line,
+                     int column) {
+    int width = snprintf(NULL, 0, "%d", line);
+    fflush(stdout);
+    fprintf(stderr, "error[%s]: %s\n", code, message);
+    fprintf(stderr, "%*s--> %s:%d:%d\n", width, "", LOL_SOURCE, line, column);
+    if (help) {
+        fprintf(stderr, "%*s = help: %s\n", width, "", help);
+    }
+    exit(1);
+}
+
+static void *lol_alloc(size_t size) {
+    void *memory = malloc(size);
+    if (!memory) {
+        fputs("out of memory\n", stderr);
+        exit(1);
+    }
+    return memory;
+}
+
+static char *lol_format(const char *format, ...) {
+    va_list args;
+    int len;
+    char *text;
+
+    va_start(args, format);
+    len = vsnprintf(NULL, 0, format, args);
+    va_end(args);
+
+    text = lol_alloc((size_t)len + 1);
+    va_start(args, format);
+    vsnprintf(text, (size_t)len + 1, format, args);
+    va_end(args);
+    return text;
+}
+
+static lol_value lol_noob(void) {
+    lol_value value = {LOL_NOOB, {0}};
+    return value;
+}
+
+static lol_value lol_troof(int troof) {
+    lol_value value;
+    value.type = LOL_TROOF;
+    value.as.troof = troof != 0;
+    return value;
+}
+
+static lol_value lol_numbr(int64_t numbr) {
+    lol_value value;
+    value.type = LOL_NUMBR;
+    value.as.numbr = numbr;
+    return value;
+}
+
+static lol_value lol_numbar(double numbar) {
+    lol_value value;
+    value.type = LOL_NUMBAR;
+    value.as.numbar = numbar;
+    return value;
+}
+
+/* A NUMBAR given by its bits, so literals round-trip exactly. */
+static lol_value lol_numbar_bits(uint64_t bits) {
+    double numbar;
+    memcpy(&numbar, &bits, sizeof numbar);
+    return lol_numbar(numbar);
+}
+
+static lol_value lol_yarn(const char *yarn) {
+    lol_value value;
+    value.type = LOL_YARN;
+    value.as.yarn = yarn;
+    return value;
+}
+
+static int lol_to_troof(lol_value value) {
+    switch (value.type) {
+    case LOL_TROOF:
+        return value.as.troof;
+    case LOL_NUMBR:
+        return value.as.numbr != 0;
+    case LOL_NUMBAR:
+        return value.as.numbar != 0.0;
+    case LOL_YARN:
+        return value.as.yarn[0] != '\0';
+    default:
+        return 0;
+    }
+}
+
+/* Text of a value as VISIBLE writes it. */
+static const char *lol_show(lol_value value) {
+    switch (value.type) {
+    case LOL_TROOF:
+        return value.as.troof ? "WIN" : "FAIL";
+    case LOL_NUMBR:
+        return lol_format("%" PRId64, value.as.numbr);
+    case LOL_NUMBAR:
+        if (isnan(value.as.numbar)) {
+            return "NaN";
+        }
+        if (isinf(value.as.numbar)) {
+            return value.as.numbar > 0 ? "inf" : "-inf";
+        }
+        return lol_format("%.2f", value.as.numbar);
+    case LOL_YARN:
+        return value.as.yarn;
+    default:
+        return "NOOB";
+    }
+}
+
+/* A YARN in quotes with special characters escaped, for diagnostics. */
+static const char *lol_quote(const char *yarn) {
+    char *quoted = lol_alloc(strlen(yarn) * 8 + 3);
+    char *end = quoted;
+
+    *end++ = '"';
+    for (; *yarn; yarn++) {
+        unsigned char c = (unsigned char)*yarn;
+        switch (c) {
+        case '"':
+            end += sprintf(end, "\\\"");
+            break;
+        case '\\':
+            end += sprintf(end, "\\\\");
+            break;
+        case '\n':
+            end += sprintf(end, "\\n");
+            break;
+        case '\r':
+            end += sprintf(end, "\\r");
+            break;
+        case '\t':
+            end += sprintf(end, "\\t");
+            break;
+        default:
+            if (c < 0x20 || c == 0x7f) {
+                end += sprintf(end, "\\u{%x}", c);
+            } else {
+                *end++ = (char)c;
+            }
+        }
+    }
+    *end++ = '"';
+    *end = '\0';
+    return quoted;
+}
+
+static int lol_parse_numbr(const char *yarn, int64_t *numbr) {
+    const char *digit = yarn;
+    long long parsed;
+
+    if (*digit == '+' || *digit == '-') {
+        digit++;
+    }
+    if (*digit == '\0') {
+        return 0;
+    }
+    for (; *digit; digit++) {
+        if (*digit < '0' || *digit > '9') {
+            return 0;
+        }
+    }
+    errno = 0;
+    parsed = strtoll(yarn, NULL, 10);
+    if (errno == ERANGE) {
+        return 0;
+    }
+    *numbr = parsed;
+    return 1;
+}
+
+static int lol_parse_numbar(const char *yarn, double *numbar) {
+    char *end;
+
+    /* strtod also skips whitespace and reads hexadecimal, which rlcc does not */
+    if (*yarn == '\0' || strpbrk(yarn, " \t\n\v\f\rxX")) {
+        return 0;
+    }
+    *numbar = strtod(yarn, &end);
+    return *end == '\0';
+}
+
+/* Implicit cast for arithmetic. NOOB may only be cast explicitly. */
+static lol_value lol_to_number(lol_value value, int line, int column) {
+    int64_t numbr;
+    double numbar;
+
+    switch (value.type) {
+    case LOL_NOOB:
+        lol_fail("E0019", "Cannot implicitly cast NOOB to a number", NULL, line, column);
+        return value;
+    case LOL_TROOF:
+        return lol_numbr(value.as.troof);
+    case LOL_YARN:
+        if (strchr(value.as.yarn, '.')) {
+            if (lol_parse_numbar(value.as.yarn, &numbar)) {
+                return lol_numbar(numbar);
+            }
+        } else if (lol_parse_numbr(value.as.yarn, &numbr)) {
+            return lol_numbr(numbr);
+        }
+        lol_fail("E0019", lol_format("Cannot cast YARN %s to a number", lol_quote(value.as.yarn)),
+                 NULL, line, column);
+        return value;
+    default:
+        return value;
+    }
+}
+
+/* Implicit cast for SMOOSH and interpolation. */
+static const char *lol_to_yarn(lol_value value, int line, int column) {
+    if (value.type == LOL_NOOB) {
+        lol_fail("E0019", "Cannot implicitly cast NOOB to a YARN", NULL, line, column);
+    }
+    return lol_show(value);
+}
+
+/* NUMBAR to NUMBR, saturating and with NaN as 0. */
+static int64_t lol_truncate(double numbar) {
+    if (isnan(numbar)) {
+        return 0;
+    }
+    if (numbar >= 9223372036854775808.0) {
+        return INT64_MAX;
+    }
+    if (numbar < -9223372036854775808.0) {
+        return INT64_MIN;
+    }
+    return (int64_t)numbar;
+}
+
+/* Explicit cast with MAEK or IS NOW A. */
+static lol_value lol_cast(lol_value value, lol_type to, int line, int column) {
+    switch (to) {
+    case LOL_NOOB:
+        return lol_noob();
+    case LOL_TROOF:
+        return lol_troof(lol_to_troof(value));
+    case LOL_NUMBR:
+        if (value.type == LOL_NOOB) {
+            return lol_numbr(0);
+        }
+        value = lol_to_number(value, line, column);
+        return value.type == LOL_NUMBAR ? lol_numbr(lol_truncate(value.as.numbar)) : value;
+    case LOL_NUMBAR:
+        if (value.type == LOL_NOOB) {
+            return lol_numbar(0.0);
+        }
+        value = lol_to_number(value, line, column);
+        return value.type == LOL_NUMBR ? lol_numbar((double)value.as.numbr) : value;
+    default:
+        return lol_yarn(value.type == LOL_NOOB ? "" : lol_show(value));
+    }
+}
+
+static void lol_division_by_zero(int line, int column) {
+    lol_fail("E0020", "Division by zero", NULL, line, column);
+}
+
+/* SUM OF through SMALLR OF. NUMBRs stay NUMBRs unless either side is a NUMBAR. */
+static lol_value lol_arithmetic(int op, lol_value left, lol_value right, int line, int column) {
+    double a, b;
+
+    left = lol_to_number(left, line, column);
+    right = lol_to_number(right, line, column);
+    if (left.type == LOL_NUMBR && right.type == LOL_NUMBR) {
+        int64_t x = left.as.numbr, y = right.as.numbr;
+        switch (op) {
+        case LOL_SUM:
+            return lol_numbr((int64_t)((uint64_t)x + (uint64_t)y));
+        case LOL_DIFF:
+            return lol_numbr((int64_t)((uint64_t)x - (uint64_t)y));
+        case LOL_PRODUKT:
+            return lol_numbr((int64_t)((uint64_t)x * (uint64_t)y));
+        case LOL_QUOSHUNT:
+        case LOL_MOD:
+            if (y == 0 || (x == INT64_MIN && y == -1)) {
+                lol_division_by_zero(line, column);
+            }
+            return lol_numbr(op == LOL_QUOSHUNT ? x / y : x % y);
+        case LOL_BIGGR:
+            return lol_numbr(x > y ? x : y);
+        default:
+            return lol_numbr(x < y ? x : y);
+        }
+    }
+
+    a = left.type == LOL_NUMBR ? (double)left.as.numbr : left.as.numbar;
+    b = right.type == LOL_NUMBR ? (double)right.as.numbr : right.as.numbar;
+    switch (op) {
+    case LOL_SUM:
+        return lol_numbar(a + b);
+    case LOL_DIFF:
+        return lol_numbar(a - b);
+    case LOL_PRODUKT:
+        return lol_numbar(a * b);
+    case LOL_QUOSHUNT:
+    case LOL_MOD:
+        if (b == 0.0) {
+            lol_division_by_zero(line, column);
+        }
+        return lol_numbar(op == LOL_QUOSHUNT ? a / b : fmod(a, b));
+    case LOL_BIGGR:
+        return lol_numbar(fmax(a, b));
+    default:
+        return lol_numbar(fmin(a, b));
+    }
+}
+
+/* BOTH SAEM. Only NUMBRs and NUMBARs are compared across types. */
+static int lol_saem(lol_value left, lol_value right) {
+    if (left.type == LOL_NUMBR && right.type == LOL_NUMBAR) {
+        return (double)left.as.numbr == right.as.numbar;
+    }
+    if (left.type == LOL_NUMBAR && right.type == LOL_NUMBR) {
+        return left.as.numbar == (double)right.as.numbr;
+    }
+    if (left.type != right.type) {
+        return 0;
+    }
+    switch (left.type) {
+    case LOL_TROOF:
+        return left.as.troof == right.as.troof;
+    case LOL_NUMBR:
+        return left.as.numbr == right.as.numbr;
+    case LOL_NUMBAR:
+        return left.as.numbar == right.as.numbar;
+    case LOL_YARN:
+        return strcmp(left.as.yarn, right.as.yarn) == 0;
+    default:
+        return 1;
+    }
+}
+
+/* Any operator with two operands. Both are evaluated whatever the first one is. */
+static lol_value lol_binary(int op, lol_value left, lol_value right, int line, int column) {
+    switch (op) {
+    case LOL_BOTH:
+        return lol_troof(lol_to_troof(left) && lol_to_troof(right));
+    case LOL_EITHER:
+        return lol_troof(lol_to_troof(left) || lol_to_troof(right));
+    case LOL_WON:
+        return lol_troof(lol_to_troof(left) != lol_to_troof(right));
+    case LOL_SAEM:
+        return lol_troof(lol_saem(left, right));
+    case LOL_DIFFRINT:
+        return lol_troof(!lol_saem(left, right));
+    default:
+        return lol_arithmetic(op, left, right, line, column);
+    }
+}
+
+static lol_value lol_concat(int count, const char *const *pieces) {
+    size_t len = 0;
+    char *yarn;
+    int i;
+
+    for (i = 0; i < count; i++) {
+        len += strlen(pieces[i]);
+    }
+    yarn = lol_alloc(len + 1);
+    yarn[0] = '\0';
+    for (i = 0; i < count; i++) {
+        strcat(yarn, pieces[i]);
+    }
+    return lol_yarn(yarn);
+}
+
+static void lol_visible(FILE *stream, int count, const lol_value *args, int newline) {
+    int i;
+
+    for (i = 0; i < count; i++) {
+        fputs(lol_show(args[i]), stream);
+    }
+    if (newline) {
+        fputc('\n', stream);
+    }
+}
+
+/* GIMMEH: a line of standard input without its line ending. At the end of the
+ * input this is whatever is left, so an empty YARN once nothing is. */
+static lol_value lol_gimmeh(void) {
+    size_t len = 0;
+    size_t size = 64;
+    char *yarn = lol_alloc(size);
+    int c;
+
+    /* Prompts written with VISIBLE ...! show before waiting on input */
+    fflush(stdout);
+    while ((c = getchar()) != EOF && c != '\n') {
+        if (len + 1 == size) {
+            char *grown = lol_alloc(size * 2);
+            memcpy(grown, yarn, len);
+            yarn = grown;
+            size *= 2;
+        }
+        yarn[len++] = (char)c;
+    }
+    if (c == '\n' && len > 0 && yarn[len - 1] == '\r') {
+        len--;
+    }
+    yarn[len] = '\0';
+    return lol_yarn(yarn);
+}
+
+/* Declares the variable of a loop if it does not exist yet, returning whether
+ * it did so and the loop should remove it again. */
+static int lol_enter_loop(lol_value *var) {
+    if (var->type != LOL_UNDEFINED) {
+        return 0;
+    }
+    *var = lol_numbr(0);
+    return 1;
+}
+
+static lol_value lol_call(const lol_function *function, const char *name, int count,
+                          lol_value *args, int line, int column, int name_line,
+                          int name_column) {
+    if (!function->fn) {
+        lol_fail("E0014", lol_format("Unknown function %s", name), NULL, name_line, name_column);
+    }
+    if (function->arity != count) {
+        lol_fail("E0015",
+                 lol_format("%s takes %d argument(s) but %d were given", name, function->arity,
+                            count),
+                 NULL, line, column);
+    }
+    return function->fn(args);
+}
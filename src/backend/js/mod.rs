@@ -0,0 +1,534 @@
+//! Transpiling to ES2020 JavaScript for Node.js, with the runtime in
+//! `runtime.js` pasted into every program.
+//!
+//! Statements become the matching JavaScript statements, preceded by a
+//! comment with their line in the source, and expressions become calls into
+//! the runtime with their operands as arguments, so that they are evaluated
+//! in the same order as by the interpreter. `VISIBLE` writes with
+//! `console.log`, or `process.stdout.write` when it ends with `!`, and
+//! `INVISIBLE` likewise to stderr.
+
+use std::{collections::BTreeSet, fmt::Write as _};
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    interpreter::{break_outside, check_features, return_outside},
+    modules::Modules,
+};
+
+use super::includable;
+
+const RUNTIME: &str = include_str!("runtime.js");
+
+pub trait EmitJs {
+    /// Transpiles `prog` to a JavaScript program, naming the source `source`
+    /// in its runtime errors.
+    fn emit_js(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String>;
+}
+
+impl<T> EmitJs for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn emit_js(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String> {
+        check_features(self, prog)?;
+        let modules = includable(self, prog, "JavaScript")?;
+
+        let mut program = format!("// Transpiled from {source} by rlcc\n\"use strict\";\n\n");
+        writeln!(program, "const SOURCE = {};\n", string(source)).unwrap();
+        program.push_str(RUNTIME);
+        program.push_str("\n// Functions by name, as last defined\nconst functions = new Map();\n");
+        program.push_str("// Native modules included so far\nconst modules = new Set();\n");
+
+        let mut writer = Writer {
+            modules,
+            lines: String::new(),
+            indent: 1,
+            last_line: None,
+            loops: 0,
+            vars: BTreeSet::new(),
+            breakable: 0,
+            in_function: false,
+        };
+        writer.block(&prog.body);
+        let body = writer.frame(&[]);
+        writeln!(program, "\nlol.run(() => {{\n{body}}});").unwrap();
+        Ok(program)
+    }
+}
+
+/// JavaScript string literal of `text`.
+fn string(text: &str) -> String {
+    serde_json::to_string(text).expect("strings serialize")
+}
+
+/// Arguments giving the position of `span` to the runtime.
+fn position(span: Span) -> String {
+    format!("{}, {}", span.start.line, span.start.column)
+}
+
+/// JavaScript variable holding the LOLCODE variable `name`, which cannot
+/// clash with anything else since LOLCODE names have no `$`.
+fn var(name: &str) -> String {
+    match name {
+        "IT" => String::from("it"),
+        name => format!("${name}"),
+    }
+}
+
+/// Statement reporting `diagnostic` at runtime.
+fn fail(diagnostic: &Diagnostic) -> String {
+    let span = diagnostic.span.expect("runtime errors have a location");
+    let help = match &diagnostic.help {
+        Some(help) => string(help),
+        None => String::from("null"),
+    };
+    format!(
+        "lol.fail(\"{}\", {}, {help}, {});",
+        diagnostic.code,
+        string(&diagnostic.message),
+        position(span)
+    )
+}
+
+/// Runtime function applying `op`.
+fn binary_op(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Sum => "sum",
+        BinaryOp::Diff => "diff",
+        BinaryOp::Produkt => "produkt",
+        BinaryOp::Quoshunt => "quoshunt",
+        BinaryOp::Mod => "mod",
+        BinaryOp::Biggr => "biggr",
+        BinaryOp::Smallr => "smallr",
+        BinaryOp::Both => "both",
+        BinaryOp::Either => "either",
+        BinaryOp::Won => "won",
+        BinaryOp::Saem => "saem",
+        BinaryOp::Diffrint => "diffrint",
+    }
+}
+
+fn type_name(to: Type) -> &'static str {
+    match to {
+        Type::Noob => "NOOB",
+        Type::Troof => "TROOF",
+        Type::Numbr => "NUMBR",
+        Type::Numbar => "NUMBAR",
+        Type::Yarn => "YARN",
+    }
+}
+
+/// Writes the body of one function at a time.
+struct Writer<'p> {
+    /// Modules the program can include.
+    modules: BTreeSet<&'p str>,
+    lines: String,
+    indent: usize,
+    /// Source line of the last statement written in the current frame.
+    last_line: Option<usize>,
+    loops: usize,
+    /// Variables used in the current frame.
+    vars: BTreeSet<&'p str>,
+    /// Loops and `WTF?`s around the current statement, which `GTFO` leaves
+    /// with `break` as in JavaScript.
+    breakable: usize,
+    in_function: bool,
+}
+
+impl<'p> Writer<'p> {
+    fn line<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        for _ in 0..self.indent {
+            self.lines.push_str("  ");
+        }
+        self.lines.push_str(line.as_ref());
+        self.lines.push('\n');
+    }
+
+    /// Writes `head`, `body` one level deeper, and a closing brace.
+    fn nest<F>(&mut self, head: &str, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.line(head);
+        self.indent += 1;
+        body(self);
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    /// What was written since the last call, after a declaration of the
+    /// variables of the frame other than `params`.
+    fn frame(&mut self, params: &[&str]) -> String {
+        let mut body = String::new();
+        for _ in 0..self.indent {
+            body.push_str("  ");
+        }
+        body.push_str("let it = null;\n");
+        let vars: Vec<String> = self
+            .vars
+            .iter()
+            .filter(|name| !params.contains(name))
+            .map(|name| var(name))
+            .collect();
+        if !vars.is_empty() {
+            for _ in 0..self.indent {
+                body.push_str("  ");
+            }
+            writeln!(body, "let {};", vars.join(", ")).unwrap();
+        }
+        body.push_str(&std::mem::take(&mut self.lines));
+        self.vars.clear();
+        self.last_line = None;
+        body
+    }
+
+    /// Expression reading the variable `name`, which must have been
+    /// declared.
+    fn get(&mut self, name: &'p Ident) -> String {
+        if name.name == "IT" {
+            return String::from("it");
+        }
+        self.vars.insert(&name.name);
+        format!(
+            "lol.get({}, {}, {})",
+            var(&name.name),
+            string(&name.name),
+            position(name.span)
+        )
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// Assigns `value` to the existing variable `name`.
+    fn assign(&mut self, name: &'p Ident, value: String) {
+        if name.name == "IT" {
+            self.line(format!("it = {value};"));
+        } else {
+            self.vars.insert(&name.name);
+            let (var, at) = (var(&name.name), position(name.span));
+            let name = string(&name.name);
+            self.line(format!("{var} = lol.set({var}, {value}, {name}, {at});"));
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        let line = stmt.span.start.line;
+        if self.last_line != Some(line) {
+            self.last_line = Some(line);
+            self.line(format!("// line {line}"));
+        }
+
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                let text = match args.is_empty() {
+                    true => String::from("\"\""),
+                    false => args
+                        .iter()
+                        .map(|arg| format!("lol.show({})", self.expr(arg)))
+                        .collect::<Vec<_>>()
+                        .join(" + "),
+                };
+                let write = match (invisible, newline) {
+                    (false, true) => "console.log",
+                    (false, false) => "process.stdout.write",
+                    (true, true) => "console.error",
+                    (true, false) => "process.stderr.write",
+                };
+                self.line(format!("{write}({text});"));
+            }
+            StmtKind::CanHas { module } => {
+                if self.modules.contains(module.name.as_str()) {
+                    self.line(format!("modules.add({});", string(&module.name)));
+                } else {
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span);
+                    self.line(fail(&diagnostic));
+                }
+            }
+            StmtKind::Declare { name, init } => {
+                let value = match init {
+                    Some(init) => self.expr(init),
+                    None => String::from("null"),
+                };
+                // Declaring IT has no effect
+                if name.name == "IT" {
+                    self.line(format!("{value};"));
+                } else {
+                    self.vars.insert(&name.name);
+                    self.line(format!("{} = {value};", var(&name.name)));
+                }
+            }
+            StmtKind::Gimmeh(name) => self.assign(name, String::from("lol.gimmeh()")),
+            StmtKind::Assign { name, value } => {
+                let value = self.expr(value);
+                self.assign(name, value);
+            }
+            StmtKind::CastVar { name, to } => {
+                let value = self.get(name);
+                let (to, at) = (type_name(*to), position(stmt.span));
+                let var = var(&name.name);
+                self.line(format!("{var} = lol.cast({value}, \"{to}\", {at});"));
+            }
+            StmtKind::Expr(expr) => {
+                let value = self.expr(expr);
+                self.line(format!("it = {value};"));
+            }
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                self.line("if (lol.troof(it)) {");
+                self.indent += 1;
+                self.block(then);
+                self.indent -= 1;
+                for (condition, block) in elifs {
+                    let condition = self.expr(condition);
+                    self.line(format!("}} else if (lol.troof({condition})) {{"));
+                    self.indent += 1;
+                    self.block(block);
+                    self.indent -= 1;
+                }
+                if let Some(otherwise) = otherwise {
+                    self.line("} else {");
+                    self.indent += 1;
+                    self.block(otherwise);
+                    self.indent -= 1;
+                }
+                self.line("}");
+            }
+            // Cases are compared in order and fall through into the ones
+            // after, OMGWTF included, as in JavaScript
+            StmtKind::Switch { cases, default } => {
+                self.breakable += 1;
+                self.nest("switch (true) {", |writer| {
+                    for (literal, block) in cases {
+                        let literal = writer.expr(literal);
+                        writer.line(format!("case lol.saem({literal}, it):"));
+                        writer.indent += 1;
+                        writer.block(block);
+                        writer.indent -= 1;
+                    }
+                    if let Some(default) = default {
+                        writer.line("default:");
+                        writer.indent += 1;
+                        writer.block(default);
+                        writer.indent -= 1;
+                    }
+                });
+                self.breakable -= 1;
+            }
+            StmtKind::Loop(lp) => {
+                // The variable is only declared for the duration of the loop
+                // if it does not exist yet
+                let fresh = lp.update.as_ref().map(|update| {
+                    self.loops += 1;
+                    let fresh = format!("fresh{}", self.loops);
+                    let var = var(&update.var.name);
+                    if update.var.name != "IT" {
+                        self.vars.insert(&update.var.name);
+                    }
+                    self.line(format!("const {fresh} = {var} === undefined;"));
+                    self.line(format!("if ({fresh}) {var} = 0n;"));
+                    (fresh, var)
+                });
+
+                let head = match &lp.condition {
+                    Some(LoopCondition::Til(condition)) => {
+                        format!("while (!lol.troof({})) {{", self.expr(condition))
+                    }
+                    Some(LoopCondition::Wile(condition)) => {
+                        format!("while (lol.troof({})) {{", self.expr(condition))
+                    }
+                    None => String::from("for (;;) {"),
+                };
+                self.breakable += 1;
+                self.nest(&head, |writer| {
+                    writer.block(&lp.body);
+                    if let Some(update) = &lp.update {
+                        let op = match update.op {
+                            LoopOp::Uppin => BinaryOp::Sum,
+                            LoopOp::Nerfin => BinaryOp::Diff,
+                        };
+                        let value = writer.get(&update.var);
+                        let (op, at) = (binary_op(op), position(update.var.span));
+                        let var = var(&update.var.name);
+                        writer.line(format!("{var} = lol.{op}({value}, 1n, {at});"));
+                    }
+                });
+                self.breakable -= 1;
+
+                if let Some((fresh, var)) = fresh {
+                    self.line(format!("if ({fresh}) {var} = undefined;"));
+                }
+            }
+            StmtKind::FuncDef(func) => self.function(func),
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.line(fail(&return_outside(stmt.span)));
+                    return;
+                }
+                let value = self.expr(expr);
+                self.line(format!("return {value};"));
+            }
+            StmtKind::Break => match self.breakable {
+                0 if self.in_function => self.line("return null;"),
+                0 => self.line(fail(&break_outside(stmt.span))),
+                _ => self.line("break;"),
+            },
+        }
+    }
+
+    /// Writes a statement defining `func`, with its body as a frame of its
+    /// own.
+    fn function(&mut self, func: &'p FuncDef) {
+        // Parameters that a later one shadows, and IT, which is never a
+        // variable, are only counted towards the arity
+        let names: Vec<&str> = func
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect();
+        let params: Vec<String> = names
+            .iter()
+            .enumerate()
+            .map(
+                |(index, name)| match *name == "IT" || names[index + 1..].contains(name) {
+                    true => format!("_{index}"),
+                    false => var(name),
+                },
+            )
+            .collect();
+
+        // The enclosing frame is resumed once the body is written
+        let outer = (
+            std::mem::take(&mut self.lines),
+            std::mem::take(&mut self.vars),
+            self.last_line,
+            self.breakable,
+            self.in_function,
+        );
+        self.indent += 1;
+        self.breakable = 0;
+        self.in_function = true;
+        self.block(&func.body);
+        let body = self.frame(&names);
+        self.indent -= 1;
+        (
+            self.lines,
+            self.vars,
+            self.last_line,
+            self.breakable,
+            self.in_function,
+        ) = outer;
+
+        let name = string(&func.name.name);
+        self.line(format!(
+            "functions.set({name}, function ({}) {{",
+            params.join(", ")
+        ));
+        self.lines.push_str(&body);
+        self.indent += 1;
+        self.line("return it;");
+        self.indent -= 1;
+        self.line("});");
+    }
+
+    /// JavaScript expression evaluating `expr`.
+    fn expr(&mut self, expr: &'p Expr) -> String {
+        let at = position(expr.span);
+        match &expr.kind {
+            ExprKind::Noob => String::from("null"),
+            ExprKind::Troof(troof) => troof.to_string(),
+            ExprKind::Numbr(numbr) => format!("{numbr}n"),
+            ExprKind::Numbar(numbar) if numbar.is_nan() => String::from("NaN"),
+            ExprKind::Numbar(numbar) if numbar.is_infinite() => match numbar.is_sign_positive() {
+                true => String::from("Infinity"),
+                false => String::from("-Infinity"),
+            },
+            // Debug formatting round-trips
+            ExprKind::Numbar(numbar) => format!("{numbar:?}"),
+            ExprKind::Yarn(yarn) => string(yarn),
+            ExprKind::Var(var) => self.get(var),
+            ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.expr(left), self.expr(right));
+                match op {
+                    BinaryOp::Both
+                    | BinaryOp::Either
+                    | BinaryOp::Won
+                    | BinaryOp::Saem
+                    | BinaryOp::Diffrint => {
+                        format!("lol.{}({left}, {right})", binary_op(*op))
+                    }
+                    op => format!("lol.{}({left}, {right}, {at})", binary_op(*op)),
+                }
+            }
+            ExprKind::Not(operand) => format!("!lol.troof({})", self.expr(operand)),
+            ExprKind::Nary { op, args } => {
+                let values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                let values = values.join(", ");
+                match op {
+                    NaryOp::All => format!("lol.all([{values}])"),
+                    NaryOp::Any => format!("lol.any([{values}])"),
+                    NaryOp::Smoosh => {
+                        let positions: Vec<String> = args
+                            .iter()
+                            .map(|arg| format!("[{}]", position(arg.span)))
+                            .collect();
+                        format!("lol.smoosh([{values}], [{}])", positions.join(", "))
+                    }
+                }
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                let operand = self.expr(operand);
+                format!("lol.cast({operand}, \"{}\", {at})", type_name(*to))
+            }
+            ExprKind::Call {
+                module: None,
+                name,
+                args,
+            } => {
+                let values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                format!(
+                    "lol.call(functions, {}, [{}], {at}, {})",
+                    string(&name.name),
+                    values.join(", "),
+                    position(name.span)
+                )
+            }
+            ExprKind::Call {
+                module: Some(module),
+                name,
+                args,
+            } => {
+                let values: Vec<String> = args.iter().map(|arg| self.expr(arg)).collect();
+                format!(
+                    "lol.callNative(modules, {}, {}, [{}], {at})",
+                    string(&module.name),
+                    string(&name.name),
+                    values.join(", ")
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,417 @@
+// Runtime of LOLCODE programs transpiled to JavaScript by rlcc, following
+// the semantics of its interpreter. NOOB is null, TROOFs are booleans,
+// NUMBRs are BigInts wrapped to 64 bits, NUMBARs are numbers and YARNs are
+// strings. A variable is undefined until its declaration has run.
+
+class LolError extends Error {}
+
+const lol = {
+  MIN: -(1n << 63n),
+  MAX: (1n << 63n) - 1n,
+
+  /** Reports an error as rlcc does without the source at hand. */
+  fail(code, message, help, line, column) {
+    const gutter = " ".repeat(String(line).length);
+    let report = `error[${code}]: ${message}\n${gutter}--> ${SOURCE}:${line}:${column}\n`;
+    if (help !== null) {
+      report += `${gutter} = help: ${help}\n`;
+    }
+    process.stderr.write(report);
+    throw new LolError(message);
+  },
+
+  /** Runs the main program, exiting with 1 if it fails. */
+  run(main) {
+    try {
+      main();
+    } catch (error) {
+      if (!(error instanceof LolError)) {
+        throw error;
+      }
+      process.exitCode = 1;
+    }
+  },
+
+  /** The value of a variable, which must have been declared. */
+  get(value, name, line, column) {
+    if (value === undefined) {
+      const help = `Declare it first with I HAS A ${name}`;
+      lol.fail("E0013", `Unknown variable ${name}`, help, line, column);
+    }
+    return value;
+  },
+
+  /** GIMMEH: a line of standard input without its line ending. At the end of
+   * the input this is whatever is left, so "" once nothing is. */
+  gimmeh() {
+    const fs = require("fs");
+    const bytes = [];
+    const byte = Buffer.alloc(1);
+    let newline = false;
+    for (;;) {
+      let read;
+      try {
+        read = fs.readSync(0, byte, 0, 1, null);
+      } catch (error) {
+        if (error.code === "EAGAIN") {
+          continue;
+        }
+        if (error.code === "EOF") {
+          break;
+        }
+        throw error;
+      }
+      if (read === 0) {
+        break;
+      }
+      if (byte[0] === 10) {
+        newline = true;
+        break;
+      }
+      bytes.push(byte[0]);
+    }
+    if (newline && bytes[bytes.length - 1] === 13) {
+      bytes.pop();
+    }
+    return Buffer.from(bytes).toString("utf8");
+  },
+
+  /** `value`, to be assigned to a variable holding `old`. */
+  set(old, value, name, line, column) {
+    lol.get(old, name, line, column);
+    return value;
+  },
+
+  troof(value) {
+    switch (typeof value) {
+      case "boolean":
+        return value;
+      case "bigint":
+        return value !== 0n;
+      case "number":
+        return value !== 0;
+      case "string":
+        return value !== "";
+      default:
+        return false;
+    }
+  },
+
+  /** Text of a value as VISIBLE writes it. */
+  show(value) {
+    switch (typeof value) {
+      case "boolean":
+        return value ? "WIN" : "FAIL";
+      case "bigint":
+        return value.toString();
+      case "number":
+        return lol.showNumbar(value);
+      case "string":
+        return value;
+      default:
+        return "NOOB";
+    }
+  },
+
+  /** Exact decimal expansion rounded half to even to two places. */
+  showNumbar(numbar) {
+    if (Number.isNaN(numbar)) {
+      return "NaN";
+    }
+    if (!Number.isFinite(numbar)) {
+      return numbar > 0 ? "inf" : "-inf";
+    }
+    const view = new DataView(new ArrayBuffer(8));
+    view.setFloat64(0, numbar);
+    const bits = view.getBigUint64(0);
+    const sign = bits >> 63n === 1n ? "-" : "";
+    const biased = (bits >> 52n) & 0x7ffn;
+    let mantissa = bits & 0xfffffffffffffn;
+    let exponent = -1074n;
+    if (biased !== 0n) {
+      mantissa |= 1n << 52n;
+      exponent = biased - 1075n;
+    }
+
+    // The value in hundredths is mantissa * 100 * 2^exponent
+    let cents;
+    if (exponent >= 0n) {
+      cents = (mantissa << exponent) * 100n;
+    } else {
+      const scaled = mantissa * 100n;
+      cents = scaled >> -exponent;
+      const rest = scaled - (cents << -exponent);
+      const half = 1n << (-exponent - 1n);
+      if (rest > half || (rest === half && cents % 2n === 1n)) {
+        cents += 1n;
+      }
+    }
+    const digits = cents.toString().padStart(3, "0");
+    return `${sign}${digits.slice(0, -2)}.${digits.slice(-2)}`;
+  },
+
+  /** A YARN in quotes with special characters escaped, as Rust does. */
+  quote(yarn) {
+    const escaped = {
+      "\t": "\\t",
+      "\r": "\\r",
+      "\n": "\\n",
+      "\\": "\\\\",
+      '"': '\\"',
+      "\0": "\\0",
+    };
+    let quoted = '"';
+    for (const c of yarn) {
+      const code = c.codePointAt(0);
+      if (c in escaped) {
+        quoted += escaped[c];
+      } else if (code < 0x20 || (code >= 0x7f && code < 0xa0)) {
+        quoted += `\\u{${code.toString(16)}}`;
+      } else {
+        quoted += c;
+      }
+    }
+    return quoted + '"';
+  },
+
+  /** A YARN as a NUMBAR if it has a dot and a NUMBR otherwise, or null. */
+  parseNumber(yarn) {
+    if (yarn.includes(".")) {
+      return /^[+-]?(\d+\.?\d*|\.\d+)([eE][+-]?\d+)?$/.test(yarn) ? Number(yarn) : null;
+    }
+    if (!/^[+-]?\d+$/.test(yarn)) {
+      return null;
+    }
+    const numbr = BigInt(yarn.replace(/^\+/, ""));
+    return numbr >= lol.MIN && numbr <= lol.MAX ? numbr : null;
+  },
+
+  /** Implicit cast for arithmetic. NOOB may only be cast explicitly. */
+  toNumber(value, line, column) {
+    switch (typeof value) {
+      case "boolean":
+        return value ? 1n : 0n;
+      case "bigint":
+      case "number":
+        return value;
+      case "string": {
+        const number = lol.parseNumber(value);
+        if (number === null) {
+          lol.fail("E0019", `Cannot cast YARN ${lol.quote(value)} to a number`, null, line, column);
+        }
+        return number;
+      }
+      default:
+        return lol.fail("E0019", "Cannot implicitly cast NOOB to a number", null, line, column);
+    }
+  },
+
+  /** Implicit cast for SMOOSH. */
+  toYarn(value, line, column) {
+    if (value === null) {
+      lol.fail("E0019", "Cannot implicitly cast NOOB to a YARN", null, line, column);
+    }
+    return lol.show(value);
+  },
+
+  /** NUMBAR to NUMBR, saturating like Rust's `as`. */
+  truncate(numbar) {
+    if (Number.isNaN(numbar)) {
+      return 0n;
+    }
+    if (numbar >= 2 ** 63) {
+      return lol.MAX;
+    }
+    if (numbar <= -(2 ** 63)) {
+      return lol.MIN;
+    }
+    return BigInt(Math.trunc(numbar));
+  },
+
+  /** Explicit cast with MAEK or IS NOW A. */
+  cast(value, to, line, column) {
+    switch (to) {
+      case "NOOB":
+        return null;
+      case "TROOF":
+        return lol.troof(value);
+      case "NUMBR": {
+        if (value === null) {
+          return 0n;
+        }
+        const number = lol.toNumber(value, line, column);
+        return typeof number === "number" ? lol.truncate(number) : number;
+      }
+      case "NUMBAR":
+        return value === null ? 0 : Number(lol.toNumber(value, line, column));
+      default:
+        return value === null ? "" : lol.show(value);
+    }
+  },
+
+  /** Applies `numbr` to two NUMBRs or `numbar` to two NUMBARs. */
+  arithmetic(left, right, line, column, numbr, numbar) {
+    left = lol.toNumber(left, line, column);
+    right = lol.toNumber(right, line, column);
+    if (typeof left === "bigint" && typeof right === "bigint") {
+      return BigInt.asIntN(64, numbr(left, right));
+    }
+    return numbar(Number(left), Number(right));
+  },
+
+  divisionByZero(line, column) {
+    lol.fail("E0020", "Division by zero", null, line, column);
+  },
+
+  /** Fails like Rust's checked division and remainder. */
+  checkDivision(x, y, line, column) {
+    if (y === 0n || (x === lol.MIN && y === -1n)) {
+      lol.divisionByZero(line, column);
+    }
+  },
+
+  sum(left, right, line, column) {
+    return lol.arithmetic(left, right, line, column, (x, y) => x + y, (a, b) => a + b);
+  },
+
+  diff(left, right, line, column) {
+    return lol.arithmetic(left, right, line, column, (x, y) => x - y, (a, b) => a - b);
+  },
+
+  produkt(left, right, line, column) {
+    return lol.arithmetic(left, right, line, column, (x, y) => x * y, (a, b) => a * b);
+  },
+
+  quoshunt(left, right, line, column) {
+    return lol.arithmetic(
+      left,
+      right,
+      line,
+      column,
+      (x, y) => {
+        lol.checkDivision(x, y, line, column);
+        return x / y;
+      },
+      (a, b) => {
+        if (b === 0) {
+          lol.divisionByZero(line, column);
+        }
+        return a / b;
+      },
+    );
+  },
+
+  mod(left, right, line, column) {
+    return lol.arithmetic(
+      left,
+      right,
+      line,
+      column,
+      (x, y) => {
+        lol.checkDivision(x, y, line, column);
+        return x % y;
+      },
+      (a, b) => {
+        if (b === 0) {
+          lol.divisionByZero(line, column);
+        }
+        return a % b;
+      },
+    );
+  },
+
+  // Rust's max and min ignore NaN, where Math's propagate it
+  biggr(left, right, line, column) {
+    return lol.arithmetic(
+      left,
+      right,
+      line,
+      column,
+      (x, y) => (x > y ? x : y),
+      (a, b) => (Number.isNaN(a) ? b : Number.isNaN(b) ? a : Math.max(a, b)),
+    );
+  },
+
+  smallr(left, right, line, column) {
+    return lol.arithmetic(
+      left,
+      right,
+      line,
+      column,
+      (x, y) => (x < y ? x : y),
+      (a, b) => (Number.isNaN(a) ? b : Number.isNaN(b) ? a : Math.min(a, b)),
+    );
+  },
+
+  // Both operands are evaluated whatever the first one is
+  both(left, right) {
+    return lol.troof(left) && lol.troof(right);
+  },
+
+  either(left, right) {
+    return lol.troof(left) || lol.troof(right);
+  },
+
+  won(left, right) {
+    return lol.troof(left) !== lol.troof(right);
+  },
+
+  /** BOTH SAEM. Only NUMBRs and NUMBARs are compared across types. */
+  saem(left, right) {
+    const numeric = (value) => typeof value === "bigint" || typeof value === "number";
+    if (typeof left !== typeof right && numeric(left) && numeric(right)) {
+      return Number(left) === Number(right);
+    }
+    return left === right;
+  },
+
+  diffrint(left, right) {
+    return !lol.saem(left, right);
+  },
+
+  all(values) {
+    return values.every(lol.troof);
+  },
+
+  any(values) {
+    return values.some(lol.troof);
+  },
+
+  /** Joins values evaluated beforehand, casting each at its position. */
+  smoosh(values, positions) {
+    return values.map((value, index) => lol.toYarn(value, ...positions[index])).join("");
+  },
+
+  /** Calls the function the program last defined as `name`. */
+  call(functions, name, args, line, column, nameLine, nameColumn) {
+    const fn = functions.get(name);
+    if (fn === undefined) {
+      lol.fail("E0014", `Unknown function ${name}`, null, nameLine, nameColumn);
+    }
+    if (fn.length !== args.length) {
+      lol.fail(
+        "E0015",
+        `${name} takes ${fn.length} argument(s) but ${args.length} were given`,
+        null,
+        line,
+        column,
+      );
+    }
+    return fn(...args);
+  },
+
+  /** Native modules that can be included have no functions, so this only
+   * fails once the arguments have been evaluated. */
+  callNative(modules, module, name, args, line, column) {
+    if (!modules.has(module)) {
+      lol.fail(
+        "E0016",
+        `Module ${module} has not been loaded. Are you missing CAN HAS ${module}?`,
+        null,
+        line,
+        column,
+      );
+    }
+    lol.fail("E0016", `Module ${module} has no function ${name}`, null, line, column);
+  },
+};
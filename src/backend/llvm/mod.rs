@@ -0,0 +1,787 @@
+//! Lowering to textual LLVM IR, generated as plain text so that no LLVM
+//! library is needed. The IR calls external functions for everything that
+//! touches values, which the bundled C runtime from [`runtime`] defines, so
+//! a program can be built with e.g. `clang prog.ll prog.runtime.c -lm`.
+//!
+//! Values are never looked at in the IR: each one lives in an `alloca` and
+//! is passed to the runtime by pointer. Each frame becomes a function with
+//! an `alloca` per variable, undeclared until its declaration has run. The
+//! IR uses opaque pointers, so it needs LLVM 15 or later.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write as _,
+};
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    interpreter::{
+        break_outside, check_features, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+};
+
+use super::{c, escape, function_defs, includable, mangle, Escape};
+
+const RUNTIME: &str = include_str!("runtime.c");
+
+/// Types and runtime functions every program declares.
+const DECLARATIONS: &str = "\
+%lol_value = type { i32, i64 }
+%lol_function = type { ptr, i32 }
+
+declare void @lolrt_fail(ptr, ptr, ptr, i32, i32) noreturn
+declare void @lolrt_noob(ptr)
+declare void @lolrt_troof(ptr, i32)
+declare void @lolrt_numbr(ptr, i64)
+declare void @lolrt_numbar(ptr, i64)
+declare void @lolrt_yarn(ptr, ptr)
+declare i32 @lolrt_to_troof(ptr)
+declare void @lolrt_cast(ptr, ptr, i32, i32, i32)
+declare void @lolrt_binary(ptr, i32, ptr, ptr, i32, i32)
+declare i32 @lolrt_saem(ptr, ptr)
+declare ptr @lolrt_to_yarn(ptr, i32, i32)
+declare void @lolrt_concat(ptr, i32, ptr)
+declare void @lolrt_visible(i32, i32, ptr, i32)
+declare void @lolrt_gimmeh(ptr)
+declare i32 @lolrt_enter_loop(ptr)
+declare void @lolrt_call(ptr, ptr, ptr, i32, ptr, i32, i32, i32, i32)
+";
+
+pub trait EmitLlvm {
+    /// Lowers `prog` to an LLVM IR module calling the C runtime from
+    /// [`runtime`], naming the source `source` in its runtime errors.
+    fn emit_llvm(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String>;
+}
+
+impl<T> EmitLlvm for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn emit_llvm(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String> {
+        check_features(self, prog)?;
+        let modules = includable(self, prog, "LLVM IR")?;
+        let functions = function_defs(prog);
+
+        let mut names = BTreeSet::new();
+        for func in &functions {
+            names.insert(func.name.name.as_str());
+        }
+        let mut writer = Writer {
+            functions: &functions,
+            defined: names,
+            modules,
+            strings: Strings::default(),
+            allocas: String::new(),
+            lines: String::new(),
+            open: true,
+            temps: 0,
+            labels: 0,
+            vars: BTreeSet::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        let source_name = writer.strings.get(source);
+
+        let mut code = String::new();
+        for (index, func) in functions.iter().enumerate() {
+            writer.in_function = true;
+            writer.block(&func.body);
+            writeln!(code, "\n; HOW IZ I {}", func.name.name).unwrap();
+            let head = format!("define internal void @f_{index}(ptr %out, ptr %args)");
+            let params: Vec<&str> = func
+                .params
+                .iter()
+                .map(|param| param.name.as_str())
+                .collect();
+            code.push_str(&writer.finish(&head, &params, true));
+        }
+        writer.in_function = false;
+        writer.block(&prog.body);
+        code.push('\n');
+        code.push_str(&writer.finish("define i32 @main()", &[], false));
+
+        // Control characters would end the comment
+        let title: String = source.chars().filter(|c| !c.is_control()).collect();
+        let mut program = format!("; {title} lowered to LLVM IR by rlcc\n\n");
+        program.push_str(DECLARATIONS);
+        writeln!(program, "\n@lol_source = constant ptr {source_name}").unwrap();
+        for module_name in &writer.modules {
+            writeln!(
+                program,
+                "@{} = internal global i32 0",
+                mangle("loaded_", module_name)
+            )
+            .unwrap();
+        }
+        // Functions are called through these, so that a name refers to
+        // whichever definition ran last
+        for name in &writer.defined {
+            let function = mangle("fn_", name);
+            writeln!(
+                program,
+                "@{function} = internal global %lol_function zeroinitializer"
+            )
+            .unwrap();
+        }
+        program.push_str(&code);
+        program.push('\n');
+        program.push_str(&writer.strings.defs);
+        Ok(program)
+    }
+}
+
+/// The C runtime programs lowered by [`EmitLlvm`] are built with: the
+/// runtime of the C backend, and the functions the IR calls on top of it.
+pub(crate) fn runtime() -> String {
+    format!(
+        "/* Runtime of LOLCODE programs lowered to LLVM IR by rlcc. */\n\
+         extern const char *const lol_source;\n\
+         #define LOL_SOURCE lol_source\n\n\
+         {}\n{RUNTIME}",
+        c::RUNTIME
+    )
+}
+
+/// Null-terminated string constants, each defined once.
+#[derive(Default)]
+struct Strings {
+    ids: HashMap<String, usize>,
+    defs: String,
+}
+
+impl Strings {
+    /// Pointer to a constant holding `text`.
+    fn get(&mut self, text: &str) -> String {
+        if let Some(id) = self.ids.get(text) {
+            return format!("@.str.{id}");
+        }
+        let id = self.ids.len();
+        writeln!(
+            self.defs,
+            "@.str.{id} = private unnamed_addr constant [{} x i8] c\"{}\\00\"",
+            text.len() + 1,
+            escape(text.as_bytes(), Escape::Hex)
+        )
+        .unwrap();
+        self.ids.insert(text.to_string(), id);
+        format!("@.str.{id}")
+    }
+}
+
+fn position(span: Span) -> String {
+    format!("i32 {}, i32 {}", span.start.line, span.start.column)
+}
+
+/// Number of the operator in the C runtime's enum.
+fn binary_op(op: BinaryOp) -> u32 {
+    match op {
+        BinaryOp::Sum => 0,
+        BinaryOp::Diff => 1,
+        BinaryOp::Produkt => 2,
+        BinaryOp::Quoshunt => 3,
+        BinaryOp::Mod => 4,
+        BinaryOp::Biggr => 5,
+        BinaryOp::Smallr => 6,
+        BinaryOp::Both => 7,
+        BinaryOp::Either => 8,
+        BinaryOp::Won => 9,
+        BinaryOp::Saem => 10,
+        BinaryOp::Diffrint => 11,
+    }
+}
+
+/// Number of the type in the C runtime's `lol_type`.
+fn type_tag(to: Type) -> u32 {
+    match to {
+        Type::Noob => 1,
+        Type::Troof => 2,
+        Type::Numbr => 3,
+        Type::Numbar => 4,
+        Type::Yarn => 5,
+    }
+}
+
+/// Writes the body of one LLVM function at a time.
+struct Writer<'p, 'f> {
+    /// Every function definition, in the order of the `f_<index>` functions.
+    functions: &'f [&'p FuncDef],
+    /// Names of the functions the program defines.
+    defined: BTreeSet<&'p str>,
+    /// Modules the program can include.
+    modules: BTreeSet<&'p str>,
+    strings: Strings,
+    /// Allocations for the entry block of the current function.
+    allocas: String,
+    lines: String,
+    /// Whether the current basic block still needs a terminator.
+    open: bool,
+    temps: usize,
+    labels: usize,
+    /// Variables used in the current frame.
+    vars: BTreeSet<&'p str>,
+    /// Labels just past each enclosing loop or `WTF?`, innermost last.
+    breaks: Vec<String>,
+    in_function: bool,
+}
+
+impl<'p> Writer<'p, '_> {
+    /// Writes an instruction, in a block of its own if the last one was
+    /// terminated, as after `GTFO`.
+    fn line<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        if !self.open {
+            let dead = self.label("dead");
+            writeln!(self.lines, "{dead}:").unwrap();
+            self.open = true;
+        }
+        self.lines.push_str("  ");
+        self.lines.push_str(line.as_ref());
+        self.lines.push('\n');
+    }
+
+    /// Writes the terminator of the current block.
+    fn terminate<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        self.line(line);
+        self.open = false;
+    }
+
+    /// Branches to `label` unless the current block is already terminated.
+    fn jump(&mut self, label: &str) {
+        if self.open {
+            self.terminate(format!("br label %{label}"));
+        }
+    }
+
+    /// Starts the block `label`, which the current one falls through to.
+    fn start(&mut self, label: &str) {
+        self.jump(label);
+        writeln!(self.lines, "{label}:").unwrap();
+        self.open = true;
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("%t{}", self.temps)
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("{kind}_{}", self.labels)
+    }
+
+    /// A new value slot in the entry block.
+    fn alloca(&mut self, ty: &str) -> String {
+        let temp = self.temp();
+        writeln!(self.allocas, "  {temp} = alloca {ty}").unwrap();
+        temp
+    }
+
+    fn copy(&mut self, from: &str, to: &str) {
+        let value = self.temp();
+        self.line(format!("{value} = load %lol_value, ptr {from}"));
+        self.line(format!("store %lol_value {value}, ptr {to}"));
+    }
+
+    /// Writes the report of `diagnostic`, which ends the block.
+    fn fail(&mut self, diagnostic: &Diagnostic) {
+        let span = diagnostic.span.expect("runtime errors have a location");
+        let code = self.strings.get(&diagnostic.code.to_string());
+        let message = self.strings.get(&diagnostic.message);
+        let help = match &diagnostic.help {
+            Some(help) => self.strings.get(help),
+            None => String::from("null"),
+        };
+        self.line(format!(
+            "call void @lolrt_fail(ptr {code}, ptr {message}, ptr {help}, {})",
+            position(span)
+        ));
+        self.terminate("unreachable");
+    }
+
+    /// Wraps what was written since the last call in an LLVM function,
+    /// allocating its variables, and starts afresh.
+    fn finish(&mut self, head: &str, params: &[&'p str], returns: bool) -> String {
+        let mut function = format!("{head} {{\nentry:\n  %it = alloca %lol_value\n");
+        // IT is never a variable
+        let params: Vec<(usize, &str)> = params
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, param)| *param != "IT")
+            .collect();
+        let vars: BTreeSet<&str> = self
+            .vars
+            .iter()
+            .copied()
+            .chain(params.iter().map(|(_, param)| *param))
+            .collect();
+        for var in &vars {
+            writeln!(function, "  %{} = alloca %lol_value", mangle("v_", var)).unwrap();
+        }
+        function.push_str(&std::mem::take(&mut self.allocas));
+        function.push_str("  call void @lolrt_noob(ptr %it)\n");
+        for var in &vars {
+            let var = mangle("v_", var);
+            writeln!(function, "  store %lol_value zeroinitializer, ptr %{var}").unwrap();
+        }
+        // A later parameter with the same name wins
+        for (index, param) in params {
+            let var = mangle("v_", param);
+            writeln!(
+                function,
+                "  %arg_{index} = getelementptr %lol_value, ptr %args, i64 {index}\n  \
+                 %arg_{index}.value = load %lol_value, ptr %arg_{index}\n  \
+                 store %lol_value %arg_{index}.value, ptr %{var}"
+            )
+            .unwrap();
+        }
+
+        if self.open {
+            match returns {
+                true => {
+                    self.copy("%it", "%out");
+                    self.terminate("ret void");
+                }
+                false => self.terminate("ret i32 0"),
+            }
+        }
+        function.push_str(&std::mem::take(&mut self.lines));
+        function.push_str("}\n");
+
+        self.vars.clear();
+        self.temps = 0;
+        self.labels = 0;
+        self.open = true;
+        function
+    }
+
+    /// Pointer to the variable `name`, or `IT`.
+    fn var(&mut self, name: &'p Ident) -> String {
+        match name.name.as_str() {
+            "IT" => String::from("%it"),
+            var => {
+                self.vars.insert(var);
+                format!("%{}", mangle("v_", var))
+            }
+        }
+    }
+
+    /// Fails at runtime if `name` has not been declared, and returns its
+    /// pointer.
+    fn declared(&mut self, name: &'p Ident) -> String {
+        let var = self.var(name);
+        if var != "%it" {
+            let (tag, undeclared) = (self.temp(), self.temp());
+            self.line(format!("{tag} = load i32, ptr {var}"));
+            self.line(format!("{undeclared} = icmp eq i32 {tag}, 0"));
+            let (fail, ok) = (self.label("undeclared"), self.label("declared"));
+            self.terminate(format!("br i1 {undeclared}, label %{fail}, label %{ok}"));
+            self.start(&fail);
+            self.fail(&undefined_variable(name));
+            self.start(&ok);
+        }
+        var
+    }
+
+    /// Branches to `then` if `value` is WIN and to `otherwise` if not.
+    fn branch(&mut self, value: &str, then: &str, otherwise: &str) {
+        let (troof, win) = (self.temp(), self.temp());
+        self.line(format!("{troof} = call i32 @lolrt_to_troof(ptr {value})"));
+        self.line(format!("{win} = icmp ne i32 {troof}, 0"));
+        self.terminate(format!("br i1 {win}, label %{then}, label %{otherwise}"));
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                let values = self.values(args);
+                let stream = match invisible {
+                    true => 2,
+                    false => 1,
+                };
+                let newline = i32::from(*newline);
+                self.line(format!(
+                    "call void @lolrt_visible(i32 {stream}, i32 {}, ptr {values}, i32 {newline})",
+                    args.len()
+                ));
+            }
+            StmtKind::Gimmeh(name) => {
+                let line = self.alloca("%lol_value");
+                self.line(format!("call void @lolrt_gimmeh(ptr {line})"));
+                let var = self.declared(name);
+                self.copy(&line, &var);
+            }
+            StmtKind::CanHas { module } => {
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    self.line(format!("store i32 1, ptr @{loaded}"));
+                } else {
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span);
+                    self.fail(&diagnostic);
+                }
+            }
+            StmtKind::Declare { name, init } => {
+                let value = self.alloca("%lol_value");
+                match init {
+                    Some(init) => self.expr(init, &value),
+                    None => self.line(format!("call void @lolrt_noob(ptr {value})")),
+                }
+                // Declaring IT has no effect
+                let var = self.var(name);
+                if var != "%it" {
+                    self.copy(&value, &var);
+                }
+            }
+            StmtKind::Assign { name, value } => {
+                let value = self.value(value);
+                let var = self.declared(name);
+                self.copy(&value, &var);
+            }
+            StmtKind::CastVar { name, to } => {
+                let var = self.declared(name);
+                let (to, at) = (type_tag(*to), position(stmt.span));
+                self.line(format!(
+                    "call void @lolrt_cast(ptr {var}, ptr {var}, i32 {to}, {at})"
+                ));
+            }
+            StmtKind::Expr(expr) => self.expr(expr, "%it"),
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                let done = self.label("oic");
+                let mut label = self.label("ya_rly");
+                let mut next = self.label("no_wai");
+                self.branch("%it", &label, &next);
+                self.start(&label);
+                self.block(then);
+                self.jump(&done);
+                for (condition, block) in elifs {
+                    self.start(&next);
+                    let condition = self.value(condition);
+                    label = self.label("mebbe");
+                    next = self.label("no_wai");
+                    self.branch(&condition, &label, &next);
+                    self.start(&label);
+                    self.block(block);
+                    self.jump(&done);
+                }
+                self.start(&next);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+                self.start(&done);
+            }
+            StmtKind::Switch { cases, default } => {
+                let labels: Vec<String> = cases.iter().map(|_| self.label("omg")).collect();
+                for ((literal, _), label) in cases.iter().zip(&labels) {
+                    let literal = self.value(literal);
+                    let (saem, matched) = (self.temp(), self.temp());
+                    self.line(format!(
+                        "{saem} = call i32 @lolrt_saem(ptr {literal}, ptr %it)"
+                    ));
+                    self.line(format!("{matched} = icmp ne i32 {saem}, 0"));
+                    let next = self.label("next");
+                    self.terminate(format!("br i1 {matched}, label %{label}, label %{next}"));
+                    self.start(&next);
+                }
+                let done = self.label("done");
+                let unmatched = self.label("omgwtf");
+                self.jump(&unmatched);
+
+                // Cases fall through into the ones after, OMGWTF included
+                self.breaks.push(done.clone());
+                for ((_, block), label) in cases.iter().zip(&labels) {
+                    self.start(label);
+                    self.block(block);
+                }
+                self.start(&unmatched);
+                if let Some(default) = default {
+                    self.block(default);
+                }
+                self.breaks.pop();
+                self.start(&done);
+            }
+            StmtKind::Loop(lp) => {
+                let entered = lp.update.as_ref().map(|update| {
+                    let var = self.var(&update.var);
+                    let entered = self.temp();
+                    self.line(format!("{entered} = call i32 @lolrt_enter_loop(ptr {var})"));
+                    (var, entered)
+                });
+
+                let (top, body, done) =
+                    (self.label("loop"), self.label("body"), self.label("done"));
+                self.start(&top);
+                if let Some(condition) = &lp.condition {
+                    match condition {
+                        LoopCondition::Til(condition) => {
+                            let condition = self.value(condition);
+                            self.branch(&condition, &done, &body);
+                        }
+                        LoopCondition::Wile(condition) => {
+                            let condition = self.value(condition);
+                            self.branch(&condition, &body, &done);
+                        }
+                    }
+                }
+                self.start(&body);
+                self.breaks.push(done.clone());
+                self.block(&lp.body);
+                self.breaks.pop();
+                if let Some(update) = &lp.update {
+                    let op = match update.op {
+                        LoopOp::Uppin => BinaryOp::Sum,
+                        LoopOp::Nerfin => BinaryOp::Diff,
+                    };
+                    let var = self.declared(&update.var);
+                    let one = self.alloca("%lol_value");
+                    self.line(format!("call void @lolrt_numbr(ptr {one}, i64 1)"));
+                    let (op, at) = (binary_op(op), position(update.var.span));
+                    self.line(format!(
+                        "call void @lolrt_binary(ptr {var}, i32 {op}, ptr {var}, ptr {one}, {at})"
+                    ));
+                }
+                self.jump(&top);
+                self.start(&done);
+
+                if let Some((var, entered)) = entered {
+                    let (leave, after) = (self.label("leave"), self.label("left"));
+                    let remove = self.temp();
+                    self.line(format!("{remove} = icmp ne i32 {entered}, 0"));
+                    self.terminate(format!("br i1 {remove}, label %{leave}, label %{after}"));
+                    self.start(&leave);
+                    self.line(format!("store %lol_value zeroinitializer, ptr {var}"));
+                    self.start(&after);
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let index = self
+                    .functions
+                    .iter()
+                    .position(|other| std::ptr::eq(*other, func))
+                    .expect("every function definition is collected");
+                let name = mangle("fn_", &func.name.name);
+                let arity = func.params.len();
+                self.line(format!(
+                    "store %lol_function {{ ptr @f_{index}, i32 {arity} }}, ptr @{name}"
+                ));
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.fail(&return_outside(stmt.span));
+                    return;
+                }
+                self.expr(expr, "%out");
+                self.terminate("ret void");
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(done) => {
+                    let done = done.clone();
+                    self.jump(&done);
+                }
+                // GTFO in a function returns NOOB
+                None if self.in_function => {
+                    self.line("call void @lolrt_noob(ptr %out)");
+                    self.terminate("ret void");
+                }
+                None => self.fail(&break_outside(stmt.span)),
+            },
+        }
+    }
+
+    /// Evaluates `expr` into a new slot and returns it.
+    fn value(&mut self, expr: &'p Expr) -> String {
+        let value = self.alloca("%lol_value");
+        self.expr(expr, &value);
+        value
+    }
+
+    /// Evaluates `exprs` in order into a new array, returning it or `null`
+    /// if there are none.
+    fn values(&mut self, exprs: &'p [Expr]) -> String {
+        if exprs.is_empty() {
+            return String::from("null");
+        }
+        let array = self.alloca(&format!("[{} x %lol_value]", exprs.len()));
+        for (index, expr) in exprs.iter().enumerate() {
+            let element = self.temp();
+            self.line(format!(
+                "{element} = getelementptr %lol_value, ptr {array}, i64 {index}"
+            ));
+            self.expr(expr, &element);
+        }
+        array
+    }
+
+    /// Writes the evaluation of `expr`, storing its value at `out` once its
+    /// operands are done.
+    fn expr(&mut self, expr: &'p Expr, out: &str) {
+        let at = position(expr.span);
+        match &expr.kind {
+            ExprKind::Noob => self.line(format!("call void @lolrt_noob(ptr {out})")),
+            ExprKind::Troof(troof) => {
+                let troof = i32::from(*troof);
+                self.line(format!("call void @lolrt_troof(ptr {out}, i32 {troof})"));
+            }
+            ExprKind::Numbr(numbr) => {
+                self.line(format!("call void @lolrt_numbr(ptr {out}, i64 {numbr})"));
+            }
+            ExprKind::Numbar(numbar) => {
+                let bits = numbar.to_bits() as i64;
+                self.line(format!("call void @lolrt_numbar(ptr {out}, i64 {bits})"));
+            }
+            ExprKind::Yarn(yarn) => {
+                let yarn = self.strings.get(yarn);
+                self.line(format!("call void @lolrt_yarn(ptr {out}, ptr {yarn})"));
+            }
+            ExprKind::Var(var) => {
+                let var = self.declared(var);
+                self.copy(&var, out);
+            }
+            ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.value(left), self.value(right));
+                let op = binary_op(*op);
+                self.line(format!(
+                    "call void @lolrt_binary(ptr {out}, i32 {op}, ptr {left}, ptr {right}, {at})"
+                ));
+            }
+            ExprKind::Not(operand) => {
+                let operand = self.value(operand);
+                let (troof, not) = (self.temp(), self.temp());
+                self.line(format!("{troof} = call i32 @lolrt_to_troof(ptr {operand})"));
+                self.line(format!("{not} = xor i32 {troof}, 1"));
+                self.line(format!("call void @lolrt_troof(ptr {out}, i32 {not})"));
+            }
+            ExprKind::Nary { op, args } => {
+                let values: Vec<String> = args.iter().map(|arg| self.value(arg)).collect();
+                match op {
+                    NaryOp::All | NaryOp::Any => {
+                        let (mut result, join) = match op {
+                            NaryOp::All => (String::from("1"), "and"),
+                            _ => (String::from("0"), "or"),
+                        };
+                        for value in values {
+                            let (troof, joined) = (self.temp(), self.temp());
+                            self.line(format!("{troof} = call i32 @lolrt_to_troof(ptr {value})"));
+                            self.line(format!("{joined} = {join} i32 {result}, {troof}"));
+                            result = joined;
+                        }
+                        self.line(format!("call void @lolrt_troof(ptr {out}, i32 {result})"));
+                    }
+                    // Every piece is evaluated before any is cast
+                    NaryOp::Smoosh => {
+                        let pieces = match values.len() {
+                            0 => String::from("null"),
+                            count => self.alloca(&format!("[{count} x ptr]")),
+                        };
+                        for (index, (value, arg)) in values.iter().zip(args).enumerate() {
+                            let (yarn, piece) = (self.temp(), self.temp());
+                            let at = position(arg.span);
+                            self.line(format!(
+                                "{yarn} = call ptr @lolrt_to_yarn(ptr {value}, {at})"
+                            ));
+                            self.line(format!(
+                                "{piece} = getelementptr ptr, ptr {pieces}, i64 {index}"
+                            ));
+                            self.line(format!("store ptr {yarn}, ptr {piece}"));
+                        }
+                        self.line(format!(
+                            "call void @lolrt_concat(ptr {out}, i32 {}, ptr {pieces})",
+                            values.len()
+                        ));
+                    }
+                }
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                let operand = self.value(operand);
+                let to = type_tag(*to);
+                self.line(format!(
+                    "call void @lolrt_cast(ptr {out}, ptr {operand}, i32 {to}, {at})"
+                ));
+            }
+            ExprKind::Call {
+                module: None,
+                name,
+                args,
+            } => {
+                let values = self.values(args);
+                if !self.defined.contains(name.name.as_str()) {
+                    self.fail(&undefined_function(name));
+                    return;
+                }
+                let function = mangle("fn_", &name.name);
+                let text = self.strings.get(&name.name);
+                self.line(format!(
+                    "call void @lolrt_call(ptr {out}, ptr @{function}, ptr {text}, i32 {}, \
+                     ptr {values}, {at}, {})",
+                    args.len(),
+                    position(name.span)
+                ));
+            }
+            // Modules that can be included have no functions
+            ExprKind::Call {
+                module: Some(module),
+                name,
+                args,
+            } => {
+                for arg in args {
+                    self.value(arg);
+                }
+                let not_loaded = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!(
+                        "Module {0} has not been loaded. Are you missing CAN HAS {0}?",
+                        module.name
+                    ),
+                )
+                .with_span(expr.span);
+                let no_function = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has no function {}", module.name, name.name),
+                )
+                .with_span(expr.span);
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    let (flag, missing) = (self.temp(), self.temp());
+                    self.line(format!("{flag} = load i32, ptr @{loaded}"));
+                    self.line(format!("{missing} = icmp eq i32 {flag}, 0"));
+                    let (fail, ok) = (self.label("not_loaded"), self.label("loaded"));
+                    self.terminate(format!("br i1 {missing}, label %{fail}, label %{ok}"));
+                    self.start(&fail);
+                    self.fail(&not_loaded);
+                    self.start(&ok);
+                    self.fail(&no_function);
+                } else {
+                    self.fail(&not_loaded);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ * Functions that LOLCODE programs lowered to LLVM IR by rlcc call, built on
+ * the runtime of the C backend above. Values are only ever handled through
+ * pointers, so the IR does not depend on how the C ABI passes structs; the
+ * IR declares lol_value as { i32, i64 } to allocate them.
+ */
+
+/* A function defined with HOW IZ I. It stores its result in *it. */
+typedef void (*lolrt_fn)(lol_value *it, lol_value *args);
+
+/* A function once its definition has run, null until then. */
+typedef struct {
+    lolrt_fn fn;
+    int arity;
+} lolrt_function;
+
+/* Reports a runtime error at line and column of the source, and exits. help
+ * may be null. */
+void lolrt_fail(const char *code, const char *message, const char *help, int line, int column) {
+    lol_fail(code, message, help, line, column);
+}
+
+void lolrt_noob(lol_value *out) {
+    *out = lol_noob();
+}
+
+void lolrt_troof(lol_value *out, int troof) {
+    *out = lol_troof(troof);
+}
+
+void lolrt_numbr(lol_value *out, int64_t numbr) {
+    *out = lol_numbr(numbr);
+}
+
+/* A NUMBAR given by its bits, so literals round-trip exactly. */
+void lolrt_numbar(lol_value *out, uint64_t bits) {
+    *out = lol_numbar_bits(bits);
+}
+
+/* A YARN of the null-terminated text, which must outlive the program. */
+void lolrt_yarn(lol_value *out, const char *yarn) {
+    *out = lol_yarn(yarn);
+}
+
+/* Whether a value is WIN when cast to a TROOF, as 1 or 0. */
+int lolrt_to_troof(const lol_value *value) {
+    return lol_to_troof(*value);
+}
+
+/* Explicit cast with MAEK or IS NOW A to the type numbered as lol_type. */
+void lolrt_cast(lol_value *out, const lol_value *value, int to, int line, int column) {
+    *out = lol_cast(*value, (lol_type)to, line, column);
+}
+
+/* Operator numbered like LOL_SUM through LOL_DIFFRINT applied to left and
+ * right. */
+void lolrt_binary(lol_value *out, int op, const lol_value *left, const lol_value *right, int line,
+                  int column) {
+    *out = lol_binary(op, *left, *right, line, column);
+}
+
+/* BOTH SAEM as 1 or 0, for the cases of WTF?. */
+int lolrt_saem(const lol_value *left, const lol_value *right) {
+    return lol_saem(*left, *right);
+}
+
+/* Implicit cast of a piece of SMOOSH to a YARN. */
+const char *lolrt_to_yarn(const lol_value *value, int line, int column) {
+    return lol_to_yarn(*value, line, column);
+}
+
+/* SMOOSH of count pieces, each already cast to a YARN. */
+void lolrt_concat(lol_value *out, int count, const char *const *pieces) {
+    *out = lol_concat(count, pieces);
+}
+
+/* VISIBLE of count values to stdout, or to stderr if stream is 2. */
+void lolrt_visible(int stream, int count, const lol_value *args, int newline) {
+    lol_visible(stream == 2 ? stderr : stdout, count, args, newline);
+}
+
+/* GIMMEH, storing a line of standard input in *out. */
+void lolrt_gimmeh(lol_value *out) {
+    *out = lol_gimmeh();
+}
+
+/* Declares the variable of a loop if it does not exist yet, returning whether
+ * it did so and the loop should remove it again. */
+int lolrt_enter_loop(lol_value *var) {
+    return lol_enter_loop(var);
+}
+
+/* Calls function with count args, storing its result in *out. An unknown
+ * function is reported at the position of its name, and a wrong number of
+ * arguments at that of the call. */
+void lolrt_call(lol_value *out, const lolrt_function *function, const char *name, int count,
+                lol_value *args, int line, int column, int name_line, int name_column) {
+    if (!function->fn) {
+        lol_fail("E0014", lol_format("Unknown function %s", name), NULL, name_line, name_column);
+    }
+    if (function->arity != count) {
+        lol_fail("E0015",
+                 lol_format("%s takes %d argument(s) but %d were given", name, function->arity,
+                            count),
+                 NULL, line, column);
+    }
+    function->fn(out, args);
+}
@@ -0,0 +1,117 @@
+//! Ahead-of-time backends, turning a [`LolCodeProgram`] into a program for
+//! another platform rather than running it.
+//!
+//! [`LolCodeProgram`]: crate::LolCodeProgram
+
+mod asm;
+mod c;
+mod js;
+mod llvm;
+mod wat;
+
+use std::{collections::BTreeSet, fmt::Write as _};
+
+use crate::{
+    ast::{FuncDef, LolCodeProgram, StmtKind},
+    diagnostic::{CompileError, Diagnostic, ErrorCode},
+    framework::HandleTokenProcessingError,
+    modules::Modules,
+};
+
+pub(crate) use asm::assemble;
+pub use asm::EmitAsm;
+pub(crate) use c::build;
+pub use c::EmitC;
+pub use js::EmitJs;
+pub(crate) use llvm::runtime as llvm_runtime;
+pub use llvm::EmitLlvm;
+pub use wat::EmitWat;
+
+/// Names of the modules `prog` can include on `platform`. Native modules are
+/// Rust code, so only those without functions are available, and including
+/// any other is an error.
+fn includable<'p, T>(
+    app: &mut T,
+    prog: &'p LolCodeProgram,
+    platform: &str,
+) -> anyhow::Result<BTreeSet<&'p str>>
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    let mut modules = BTreeSet::new();
+    let mut unsupported = false;
+    for stmt in prog.body.walk() {
+        let StmtKind::CanHas { module } = &stmt.kind else {
+            continue;
+        };
+        match app.modules().functions(&module.name) {
+            Some([]) => {
+                modules.insert(module.name.as_str());
+            }
+            Some(_) => {
+                let message = format!("Module {} is not available in {platform}", module.name);
+                let diagnostic = Diagnostic::error(ErrorCode::UnsupportedByBackend, message)
+                    .with_span(module.span);
+                app.emit(diagnostic)?;
+                unsupported = true;
+            }
+            None => {}
+        }
+    }
+    match unsupported {
+        true => Err(CompileError.into()),
+        false => Ok(modules),
+    }
+}
+
+/// Every function definition in `prog`, including those in function bodies.
+fn function_defs(prog: &LolCodeProgram) -> Vec<&FuncDef> {
+    prog.body
+        .walk()
+        .into_iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::FuncDef(func) => Some(func),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Identifier for the LOLCODE name `name`, valid in C, WebAssembly text,
+/// assembly and LLVM IR.
+fn mangle(prefix: &str, name: &str) -> String {
+    let mut mangled = prefix.to_string();
+    for c in name.chars() {
+        match c {
+            '_' => mangled.push_str("__"),
+            c if c.is_ascii_alphanumeric() => mangled.push(c),
+            c => write!(mangled, "_{:x}_", c as u32).unwrap(),
+        }
+    }
+    mangled
+}
+
+/// How a backend writes a byte in a string literal as digits.
+#[derive(Clone, Copy)]
+enum Escape {
+    /// `\ooo`, for C and assembly. Octal escapes end after three digits,
+    /// unlike hexadecimal ones in C.
+    Octal,
+    /// `\hh`, for WebAssembly text and LLVM IR.
+    Hex,
+}
+
+/// Contents of a string literal holding `bytes`, without the quotes around
+/// it. Printable ASCII is written as is except for the quote, the backslash
+/// and `?`, since C reads `??` as the start of a trigraph under -std=c99.
+fn escape(bytes: &[u8], style: Escape) -> String {
+    let mut literal = String::new();
+    for &byte in bytes {
+        let plain = (byte.is_ascii_graphic() || byte == b' ') && !b"\"\\?".contains(&byte);
+        match (plain, style) {
+            (true, _) => literal.push(byte as char),
+            (false, Escape::Octal) => write!(literal, "\\{byte:03o}").unwrap(),
+            (false, Escape::Hex) => write!(literal, "\\{byte:02x}").unwrap(),
+        }
+    }
+    literal
+}
@@ -0,0 +1,753 @@
+//! Lowering to a module in the WebAssembly text format. The runtime in
+//! `runtime.wat` is pasted into every module.
+//!
+//! A module imports two functions from `lolcode`, which the host implements.
+//! `visible` writes output: it takes 1 for `VISIBLE` or 2 for `INVISIBLE`,
+//! then the address and length of UTF-8 text in the exported `memory`.
+//! Runtime errors are written to 2 the same way before trapping. `gimmeh`
+//! reads input for `GIMMEH`: it takes the address and capacity of a buffer
+//! and returns the length of the next line without its line ending, having
+//! copied the line into the buffer only if it fits. A line that does not is
+//! returned again by the next call, which the runtime makes with a buffer of
+//! that length. At the end of the input the line is whatever is left.
+//! The program runs when its `run` export is called.
+//!
+//! Each frame becomes a function with a local per variable, null until its
+//! declaration has run. Expressions leave their value on the stack, so that
+//! operands are evaluated in the same order as by the interpreter.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write as _,
+};
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    interpreter::{
+        break_outside, check_features, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+};
+
+use super::{escape, function_defs, includable, mangle, Escape};
+
+const RUNTIME: &str = include_str!("runtime.wat");
+
+/// Address of the program's own data, past that of the runtime.
+const DATA_START: usize = 1024;
+const PAGE_SIZE: usize = 65536;
+
+/// Instructions pushing the cells the runtime keeps for these values.
+const NOOB: &str = "i32.const 128";
+const WIN: &str = "i32.const 144";
+const FAIL: &str = "i32.const 160";
+
+pub trait EmitWat {
+    /// Lowers `prog` to a WebAssembly text module, naming the source
+    /// `source` in its runtime errors.
+    fn emit_wat(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String>;
+}
+
+impl<T> EmitWat for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn emit_wat(&mut self, prog: &LolCodeProgram, source: &str) -> anyhow::Result<String> {
+        check_features(self, prog)?;
+        let modules = includable(self, prog, "WebAssembly")?;
+        let functions = function_defs(prog);
+
+        let mut data = Data::default();
+        let (source_start, source_len) = data.intern(source);
+
+        let mut names = BTreeSet::new();
+        for func in &functions {
+            names.insert(func.name.name.as_str());
+        }
+        let mut writer = Writer {
+            functions: &functions,
+            defined: names,
+            modules,
+            data,
+            lines: String::new(),
+            indent: 2,
+            temps: 0,
+            labels: 0,
+            vars: BTreeSet::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        let mut code = String::new();
+        for (index, func) in functions.iter().enumerate() {
+            writer.in_function = true;
+            writer.block(&func.body);
+            let params = func.params.iter().map(|param| param.name.as_str());
+            let head = format!("$f_{index} (type $lol_function) (param $args i32) (result i32)");
+            writeln!(code, "\n  ;; HOW IZ I {}", func.name.name).unwrap();
+            code.push_str(&writer.finish(&head, params, Some("local.get $it")));
+        }
+        writer.in_function = false;
+        writer.block(&prog.body);
+        code.push('\n');
+        code.push_str(&writer.finish("(export \"run\")", [].into_iter(), None));
+
+        let data = writer.data.bytes;
+        let heap = (DATA_START + data.len()).next_multiple_of(8);
+        let pages = heap.div_ceil(PAGE_SIZE);
+
+        let mut module = String::from("(module\n");
+        module.push_str(
+            "  (import \"lolcode\" \"visible\" (func $host_visible (param i32 i32 i32)))\n",
+        );
+        module.push_str(
+            "  (import \"lolcode\" \"gimmeh\" (func $host_gimmeh (param i32 i32) (result i32)))\n\n",
+        );
+        module.push_str(RUNTIME);
+        module.push('\n');
+        writeln!(module, "  (memory (export \"memory\") {pages})").unwrap();
+        writeln!(module, "  (global $heap (mut i32) (i32.const {heap}))").unwrap();
+        writeln!(
+            module,
+            "  (global $source_start i32 (i32.const {source_start}))"
+        )
+        .unwrap();
+        writeln!(
+            module,
+            "  (global $source_len i32 (i32.const {source_len}))"
+        )
+        .unwrap();
+        for module_name in &writer.modules {
+            let loaded = mangle("loaded_", module_name);
+            writeln!(module, "  (global ${loaded} (mut i32) (i32.const 0))").unwrap();
+        }
+        // Functions are called through the table, so that a name refers to
+        // whichever definition ran last
+        for name in &writer.defined {
+            let (function, arity) = (mangle("fn_", name), mangle("arity_", name));
+            writeln!(module, "  (global ${function} (mut i32) (i32.const -1))").unwrap();
+            writeln!(module, "  (global ${arity} (mut i32) (i32.const 0))").unwrap();
+        }
+        writeln!(module, "  (table {} funcref)", functions.len()).unwrap();
+        if !functions.is_empty() {
+            let elements: Vec<String> = (0..functions.len())
+                .map(|index| format!("$f_{index}"))
+                .collect();
+            writeln!(module, "  (elem (i32.const 0) func {})", elements.join(" ")).unwrap();
+        }
+        module.push_str(&code);
+        writeln!(
+            module,
+            "\n  (data (i32.const {DATA_START}) {})",
+            string(&data)
+        )
+        .unwrap();
+        module.push_str(")\n");
+        Ok(module)
+    }
+}
+
+/// The program's YARN literals and messages, laid out from [`DATA_START`].
+#[derive(Default)]
+struct Data {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, usize>,
+}
+
+impl Data {
+    /// Address and length of `text`, added unless it already is.
+    fn intern(&mut self, text: &str) -> (usize, usize) {
+        let offset = match self.offsets.get(text) {
+            Some(offset) => *offset,
+            None => {
+                let offset = DATA_START + self.bytes.len();
+                self.bytes.extend_from_slice(text.as_bytes());
+                self.offsets.insert(text.to_string(), offset);
+                offset
+            }
+        };
+        (offset, text.len())
+    }
+
+    /// Instruction pushing a new YARN of `text`.
+    fn yarn(&mut self, text: &str) -> String {
+        let (offset, len) = self.intern(text);
+        format!("(call $lol_yarn (i32.const {offset}) (i32.const {len}))")
+    }
+}
+
+/// String literal with the bytes `bytes`.
+fn string(bytes: &[u8]) -> String {
+    format!("\"{}\"", escape(bytes, Escape::Hex))
+}
+
+/// Operands giving the position of `span` to the runtime.
+fn position(span: Span) -> String {
+    format!(
+        "(i32.const {}) (i32.const {})",
+        span.start.line, span.start.column
+    )
+}
+
+/// Operand selecting `op` in `$lol_binary`.
+fn binary_op(op: BinaryOp) -> u32 {
+    match op {
+        BinaryOp::Sum => 0,
+        BinaryOp::Diff => 1,
+        BinaryOp::Produkt => 2,
+        BinaryOp::Quoshunt => 3,
+        BinaryOp::Mod => 4,
+        BinaryOp::Biggr => 5,
+        BinaryOp::Smallr => 6,
+        BinaryOp::Both => 7,
+        BinaryOp::Either => 8,
+        BinaryOp::Won => 9,
+        BinaryOp::Saem => 10,
+        BinaryOp::Diffrint => 11,
+    }
+}
+
+/// Tag of the cells of type `to`.
+fn type_tag(to: Type) -> u32 {
+    match to {
+        Type::Noob => 1,
+        Type::Troof => 2,
+        Type::Numbr => 3,
+        Type::Numbar => 4,
+        Type::Yarn => 5,
+    }
+}
+
+/// Writes the body of one function at a time.
+struct Writer<'p, 'f> {
+    /// Every function definition, in the order of the `$f_<index>`
+    /// functions and the table.
+    functions: &'f [&'p FuncDef],
+    /// Names of the functions the program defines.
+    defined: BTreeSet<&'p str>,
+    /// Modules the program can include.
+    modules: BTreeSet<&'p str>,
+    data: Data,
+    lines: String,
+    indent: usize,
+    temps: usize,
+    labels: usize,
+    /// Variables used in the current frame.
+    vars: BTreeSet<&'p str>,
+    /// Labels of the blocks just around each enclosing loop or `WTF?`,
+    /// innermost last.
+    breaks: Vec<String>,
+    in_function: bool,
+}
+
+impl<'p> Writer<'p, '_> {
+    fn line<S>(&mut self, line: S)
+    where
+        S: AsRef<str>,
+    {
+        for _ in 0..self.indent {
+            self.lines.push_str("  ");
+        }
+        self.lines.push_str(line.as_ref());
+        self.lines.push('\n');
+    }
+
+    /// Writes `head`, then `body` one level deeper, then `end`.
+    fn nest<F>(&mut self, head: &str, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.line(head);
+        self.indent += 1;
+        body(self);
+        self.indent -= 1;
+        self.line("end");
+    }
+
+    fn temp(&mut self) -> String {
+        self.temps += 1;
+        format!("$t{}", self.temps)
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("${kind}_{}", self.labels)
+    }
+
+    /// Wraps what was written since the last call in a function, declaring
+    /// its variables, and starts afresh.
+    fn finish<'a, I>(&mut self, head: &str, params: I, end: Option<&str>) -> String
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut function = format!("  (func {head}\n    (local $it i32)\n");
+        // IT is never a variable
+        let params: Vec<(usize, &str)> = params
+            .enumerate()
+            .filter(|(_, param)| *param != "IT")
+            .collect();
+        let vars: BTreeSet<&str> = self
+            .vars
+            .iter()
+            .copied()
+            .chain(params.iter().map(|(_, param)| *param))
+            .collect();
+        for var in vars {
+            writeln!(function, "    (local ${} i32)", mangle("v_", var)).unwrap();
+        }
+        for temp in 1..=self.temps {
+            writeln!(function, "    (local $t{temp} i32)").unwrap();
+        }
+        writeln!(function, "    (local.set $it ({NOOB}))").unwrap();
+        // A later parameter with the same name wins
+        for (index, param) in params {
+            let var = mangle("v_", param);
+            let offset = index * 4;
+            writeln!(
+                function,
+                "    (local.set ${var} (i32.load offset={offset} (local.get $args)))"
+            )
+            .unwrap();
+        }
+        function.push_str(&std::mem::take(&mut self.lines));
+        if let Some(end) = end {
+            writeln!(function, "    {end}").unwrap();
+        }
+        function.push_str("  )\n");
+
+        self.vars.clear();
+        self.temps = 0;
+        function
+    }
+
+    /// Local holding the variable `name`, or `IT`.
+    fn var(&mut self, name: &'p Ident) -> String {
+        match name.name.as_str() {
+            "IT" => String::from("$it"),
+            var => {
+                self.vars.insert(var);
+                format!("${}", mangle("v_", var))
+            }
+        }
+    }
+
+    /// Fails at runtime if `name` has not been declared, and returns its
+    /// local.
+    fn declared(&mut self, name: &'p Ident) -> String {
+        let var = self.var(name);
+        if var != "$it" {
+            self.line(format!("(i32.eqz (local.get {var}))"));
+            self.nest("if", |writer| writer.fail(&undefined_variable(name)));
+        }
+        var
+    }
+
+    /// Writes a call reporting `diagnostic` at runtime.
+    fn fail(&mut self, diagnostic: &Diagnostic) {
+        let span = diagnostic.span.expect("runtime errors have a location");
+        let code = self.data.yarn(&diagnostic.code.to_string());
+        let message = self.data.yarn(&diagnostic.message);
+        let help = match &diagnostic.help {
+            Some(help) => self.data.yarn(help),
+            None => String::from("(i32.const 0)"),
+        };
+        self.line(format!(
+            "(call $lol_fail {code} {message} {help} {})",
+            position(span)
+        ));
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                match args.split_first() {
+                    Some((first, rest)) => {
+                        self.expr(first);
+                        self.line("call $lol_show");
+                        for arg in rest {
+                            self.expr(arg);
+                            self.line("call $lol_show");
+                            self.line("call $lol_concat");
+                        }
+                    }
+                    None => self.line("(call $lol_yarn (i32.const 0) (i32.const 0))"),
+                }
+                let stream = match invisible {
+                    true => 2,
+                    false => 1,
+                };
+                let newline = i32::from(*newline);
+                self.line(format!(
+                    "(call $lol_visible (i32.const {stream}) (i32.const {newline}))"
+                ));
+            }
+            StmtKind::CanHas { module } => {
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    self.line(format!("(global.set ${loaded} (i32.const 1))"));
+                } else {
+                    let diagnostic = Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span);
+                    self.fail(&diagnostic);
+                }
+            }
+            StmtKind::Declare { name, init } => {
+                match init {
+                    Some(init) => self.expr(init),
+                    None => self.line(NOOB),
+                }
+                // Declaring IT has no effect
+                match self.var(name).as_str() {
+                    "$it" => self.line("drop"),
+                    var => self.line(format!("local.set {var}")),
+                }
+            }
+            StmtKind::Assign { name, value } => {
+                self.expr(value);
+                let var = self.declared(name);
+                self.line(format!("local.set {var}"));
+            }
+            StmtKind::Gimmeh(name) => {
+                self.line("call $lol_gimmeh");
+                let var = self.declared(name);
+                self.line(format!("local.set {var}"));
+            }
+            StmtKind::CastVar { name, to } => {
+                let var = self.declared(name);
+                let (to, at) = (type_tag(*to), position(stmt.span));
+                self.line(format!(
+                    "(local.set {var} (call $lol_cast (local.get {var}) (i32.const {to}) {at}))"
+                ));
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(expr);
+                self.line("local.set $it");
+            }
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                self.line("(call $lol_to_troof (local.get $it))");
+                self.line("if");
+                self.indent += 1;
+                self.block(then);
+                self.indent -= 1;
+                for (condition, block) in elifs {
+                    self.line("else");
+                    self.indent += 1;
+                    self.expr(condition);
+                    self.line("call $lol_to_troof");
+                    self.line("if");
+                    self.indent += 1;
+                    self.block(block);
+                    self.indent -= 1;
+                }
+                if let Some(otherwise) = otherwise {
+                    self.line("else");
+                    self.indent += 1;
+                    self.block(otherwise);
+                    self.indent -= 1;
+                }
+                for _ in elifs {
+                    self.line("end");
+                    self.indent -= 1;
+                }
+                self.line("end");
+            }
+            StmtKind::Switch { cases, default } => {
+                // Each case is entered by leaving the block around it, and
+                // falls through into the ones after, OMGWTF included
+                let done = self.label("done");
+                let unmatched = self.label("omgwtf");
+                let labels: Vec<String> = cases.iter().map(|_| self.label("omg")).collect();
+                self.line(format!("block {done}"));
+                self.indent += 1;
+                self.line(format!("block {unmatched}"));
+                self.indent += 1;
+                for label in labels.iter().rev() {
+                    self.line(format!("block {label}"));
+                    self.indent += 1;
+                }
+                for ((literal, _), label) in cases.iter().zip(&labels) {
+                    self.expr(literal);
+                    self.line("(call $lol_saem (local.get $it))");
+                    self.line(format!("br_if {label}"));
+                }
+                self.line(format!("br {unmatched}"));
+
+                self.breaks.push(done);
+                for (_, block) in cases {
+                    self.indent -= 1;
+                    self.line("end");
+                    self.block(block);
+                }
+                self.indent -= 1;
+                self.line("end");
+                if let Some(default) = default {
+                    self.block(default);
+                }
+                self.breaks.pop();
+                self.indent -= 1;
+                self.line("end");
+            }
+            StmtKind::Loop(lp) => {
+                // The variable is only declared for the duration of the loop
+                // if it does not exist yet
+                let temporary = lp.update.as_ref().map(|update| {
+                    let var = self.var(&update.var);
+                    let temporary = self.temp();
+                    self.line(format!(
+                        "(local.tee {temporary} (i32.eqz (local.get {var})))"
+                    ));
+                    self.nest("if", |writer| {
+                        writer.line(format!("(local.set {var} (call $lol_numbr (i64.const 0)))"));
+                    });
+                    (var, temporary)
+                });
+
+                let done = self.label("done");
+                let top = self.label("top");
+                self.nest(&format!("block {done}"), |writer| {
+                    writer.nest(&format!("loop {top}"), |writer| {
+                        if let Some(condition) = &lp.condition {
+                            let (condition, negate) = match condition {
+                                LoopCondition::Til(condition) => (condition, false),
+                                LoopCondition::Wile(condition) => (condition, true),
+                            };
+                            writer.expr(condition);
+                            writer.line("call $lol_to_troof");
+                            if negate {
+                                writer.line("i32.eqz");
+                            }
+                            writer.line(format!("br_if {done}"));
+                        }
+                        writer.breaks.push(done.clone());
+                        writer.block(&lp.body);
+                        writer.breaks.pop();
+                        if let Some(update) = &lp.update {
+                            let op = match update.op {
+                                LoopOp::Uppin => BinaryOp::Sum,
+                                LoopOp::Nerfin => BinaryOp::Diff,
+                            };
+                            let var = writer.declared(&update.var);
+                            let (op, at) = (binary_op(op), position(update.var.span));
+                            writer.line(format!("local.get {var}"));
+                            writer.line("(call $lol_numbr (i64.const 1))");
+                            writer.line(format!("(call $lol_binary (i32.const {op}) {at})"));
+                            writer.line(format!("local.set {var}"));
+                        }
+                        writer.line(format!("br {top}"));
+                    });
+                });
+
+                if let Some((var, temporary)) = temporary {
+                    self.line(format!("local.get {temporary}"));
+                    self.nest("if", |writer| {
+                        writer.line(format!("(local.set {var} (i32.const 0))"));
+                    });
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let index = self
+                    .functions
+                    .iter()
+                    .position(|other| std::ptr::eq(*other, func))
+                    .expect("every function definition is collected");
+                let function = mangle("fn_", &func.name.name);
+                let arity = mangle("arity_", &func.name.name);
+                let count = func.params.len();
+                self.line(format!("(global.set ${function} (i32.const {index}))"));
+                self.line(format!("(global.set ${arity} (i32.const {count}))"));
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.fail(&return_outside(stmt.span));
+                    return;
+                }
+                self.expr(expr);
+                self.line("return");
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(done) => {
+                    let done = done.clone();
+                    self.line(format!("br {done}"));
+                }
+                // GTFO in a function returns NOOB
+                None if self.in_function => self.line(format!("({NOOB}) return")),
+                None => self.fail(&break_outside(stmt.span)),
+            },
+        }
+    }
+
+    /// Writes the evaluation of `expr`, leaving its value on the stack.
+    fn expr(&mut self, expr: &'p Expr) {
+        let at = position(expr.span);
+        match &expr.kind {
+            ExprKind::Noob => self.line(NOOB),
+            ExprKind::Troof(true) => self.line(WIN),
+            ExprKind::Troof(false) => self.line(FAIL),
+            ExprKind::Numbr(numbr) => self.line(format!("(call $lol_numbr (i64.const {numbr}))")),
+            ExprKind::Numbar(numbar) => self.line(format!(
+                "(call $lol_numbar (f64.reinterpret_i64 (i64.const {:#x})))",
+                numbar.to_bits()
+            )),
+            ExprKind::Yarn(yarn) => {
+                let yarn = self.data.yarn(yarn);
+                self.line(yarn);
+            }
+            ExprKind::Var(var) => {
+                let var = self.declared(var);
+                self.line(format!("local.get {var}"));
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.expr(left);
+                self.expr(right);
+                let op = binary_op(*op);
+                self.line(format!("(call $lol_binary (i32.const {op}) {at})"));
+            }
+            ExprKind::Not(operand) => {
+                self.expr(operand);
+                self.line("call $lol_to_troof");
+                self.line("(call $lol_troof (i32.eqz))");
+            }
+            ExprKind::Nary { op, args } => match op {
+                NaryOp::All | NaryOp::Any => {
+                    let (empty, join) = match op {
+                        NaryOp::All => (WIN, "i32.and"),
+                        _ => (FAIL, "i32.or"),
+                    };
+                    let Some((first, rest)) = args.split_first() else {
+                        self.line(empty);
+                        return;
+                    };
+                    self.expr(first);
+                    self.line("call $lol_to_troof");
+                    for arg in rest {
+                        self.expr(arg);
+                        self.line("call $lol_to_troof");
+                        self.line(join);
+                    }
+                    self.line("call $lol_troof");
+                }
+                // Every piece is evaluated before any is cast
+                NaryOp::Smoosh => {
+                    let pieces: Vec<(String, Span)> = args
+                        .iter()
+                        .map(|arg| {
+                            self.expr(arg);
+                            let piece = self.temp();
+                            self.line(format!("local.set {piece}"));
+                            (piece, arg.span)
+                        })
+                        .collect();
+                    self.line("(call $lol_yarn (i32.const 0) (i32.const 0))");
+                    for (piece, span) in pieces {
+                        let at = position(span);
+                        self.line(format!("(call $lol_to_yarn (local.get {piece}) {at})"));
+                        self.line("call $lol_concat");
+                    }
+                }
+            },
+            ExprKind::Cast { expr: operand, to } => {
+                self.expr(operand);
+                let to = type_tag(*to);
+                self.line(format!("(call $lol_cast (i32.const {to}) {at})"));
+            }
+            ExprKind::Call {
+                module: None,
+                name,
+                args,
+            } => {
+                if !self.defined.contains(name.name.as_str()) {
+                    for arg in args {
+                        self.expr(arg);
+                        self.line("drop");
+                    }
+                    self.fail(&undefined_function(name));
+                    self.line(NOOB);
+                    return;
+                }
+
+                let array = self.temp();
+                let size = args.len() * 4;
+                self.line(format!(
+                    "(local.set {array} (call $lol_alloc (i32.const {size})))"
+                ));
+                for (index, arg) in args.iter().enumerate() {
+                    self.line(format!("local.get {array}"));
+                    self.expr(arg);
+                    self.line(format!("i32.store offset={}", index * 4));
+                }
+
+                let function = mangle("fn_", &name.name);
+                let arity = mangle("arity_", &name.name);
+                self.line(format!("(i32.eq (global.get ${function}) (i32.const -1))"));
+                self.nest("if", |writer| writer.fail(&undefined_function(name)));
+                let count = args.len();
+                self.line(format!(
+                    "(i32.ne (global.get ${arity}) (i32.const {count}))"
+                ));
+                self.nest("if", |writer| {
+                    let name = writer.data.yarn(&name.name);
+                    writer.line(format!(
+                        "(call $lol_arity_error {name} (global.get ${arity}) (i32.const {count}) {at})"
+                    ));
+                });
+                self.line(format!(
+                    "(call_indirect (type $lol_function) (local.get {array}) (global.get ${function}))"
+                ));
+            }
+            // Modules that can be included have no functions
+            ExprKind::Call {
+                module: Some(module),
+                name,
+                args,
+            } => {
+                for arg in args {
+                    self.expr(arg);
+                    self.line("drop");
+                }
+                let not_loaded = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!(
+                        "Module {0} has not been loaded. Are you missing CAN HAS {0}?",
+                        module.name
+                    ),
+                )
+                .with_span(expr.span);
+                let no_function = Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has no function {}", module.name, name.name),
+                )
+                .with_span(expr.span);
+                if self.modules.contains(module.name.as_str()) {
+                    let loaded = mangle("loaded_", &module.name);
+                    self.line(format!("(i32.eqz (global.get ${loaded}))"));
+                    self.nest("if", |writer| writer.fail(&not_loaded));
+                    self.fail(&no_function);
+                } else {
+                    self.fail(&not_loaded);
+                }
+                self.line(NOOB);
+            }
+        }
+    }
+}
@@ -0,0 +1,950 @@
+  ;; Runtime of LOLCODE programs compiled to WebAssembly by rlcc. It mirrors
+  ;; the semantics of rlcc's interpreter, including the text of its
+  ;; diagnostics, which are written to stream 2 before trapping.
+  ;;
+  ;; Values are pointers to immutable 16 byte cells: a tag at offset 0, then
+  ;; the TROOF, NUMBR or NUMBAR at offset 8, or for a YARN its bytes' address
+  ;; at 8 and length at 12. Null is an undeclared variable. Memory is never
+  ;; freed. The program imports $host_visible and $host_gimmeh, and defines
+  ;; the memory along with $heap, $source_start and $source_len.
+
+  (type $lol_function (func (param i32) (result i32)))
+
+  ;; NOOB, WIN and FAIL, shared by every use
+  (data (i32.const 128) "\01\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00")
+  (data (i32.const 144) "\02\00\00\00\00\00\00\00\01\00\00\00\00\00\00\00")
+  (data (i32.const 160) "\02\00\00\00\00\00\00\00\00\00\00\00\00\00\00\00")
+
+  ;; Text of the runtime, at
+  ;;  176 NOOB
+  ;;  180 WIN
+  ;;  183 FAIL
+  ;;  187 NAN
+  ;;  190 NEG_INF
+  ;;  194 ERROR
+  ;;  200 AFTER_CODE
+  ;;  203 NEWLINE
+  ;;  204 COLON
+  ;;  205 ARROW
+  ;;  209 HELP
+  ;;  218 E0015
+  ;;  223 E0019
+  ;;  228 E0020
+  ;;  233 CAST_NOOB_NUMBER
+  ;;  272 CAST_YARN
+  ;;  289 TO_NUMBER
+  ;;  301 CAST_NOOB_YARN
+  ;;  338 DIVISION
+  ;;  354 TAKES
+  ;;  361 ARGUMENTS
+  ;;  378 GIVEN
+  (data (i32.const 176) "NOOBWINFAILNaN-inferror[]: \0a:-->  = help: E0015E0019E0020Cannot implicitly cast NOOB to a numberCannot cast YARN  to a numberCannot implicitly cast NOOB to a YARNDivision by zero takes  argument(s) but  were given")
+
+  (func $lol_alloc (param $size i32) (result i32)
+    (local $ptr i32) (local $end i32) (local $available i32)
+    (local.set $ptr (global.get $heap))
+    (local.set $end
+      (i32.add (local.get $ptr) (i32.and (i32.add (local.get $size) (i32.const 7)) (i32.const -8))))
+    (local.set $available (i32.shl (memory.size) (i32.const 16)))
+    (if (i32.gt_u (local.get $end) (local.get $available))
+      (then
+        (if (i32.eq
+              (memory.grow
+                (i32.shr_u
+                  (i32.add (i32.sub (local.get $end) (local.get $available)) (i32.const 65535))
+                  (i32.const 16)))
+              (i32.const -1))
+          (then (unreachable)))))
+    (global.set $heap (local.get $end))
+    (local.get $ptr))
+
+  (func $lol_cell (param $tag i32) (result i32)
+    (local $cell i32)
+    (local.set $cell (call $lol_alloc (i32.const 16)))
+    (i32.store (local.get $cell) (local.get $tag))
+    (local.get $cell))
+
+  (func $lol_noob (result i32)
+    (i32.const 128))
+
+  (func $lol_troof (param $troof i32) (result i32)
+    (select (i32.const 144) (i32.const 160) (local.get $troof)))
+
+  (func $lol_numbr (param $numbr i64) (result i32)
+    (local $cell i32)
+    (local.set $cell (call $lol_cell (i32.const 3)))
+    (i64.store offset=8 (local.get $cell) (local.get $numbr))
+    (local.get $cell))
+
+  (func $lol_numbar (param $numbar f64) (result i32)
+    (local $cell i32)
+    (local.set $cell (call $lol_cell (i32.const 4)))
+    (f64.store offset=8 (local.get $cell) (local.get $numbar))
+    (local.get $cell))
+
+  (func $lol_yarn (param $start i32) (param $len i32) (result i32)
+    (local $cell i32)
+    (local.set $cell (call $lol_cell (i32.const 5)))
+    (i32.store offset=8 (local.get $cell) (local.get $start))
+    (i32.store offset=12 (local.get $cell) (local.get $len))
+    (local.get $cell))
+
+  (func $lol_tag (param $value i32) (result i32)
+    (i32.load (local.get $value)))
+
+  (func $lol_start (param $yarn i32) (result i32)
+    (i32.load offset=8 (local.get $yarn)))
+
+  (func $lol_len (param $yarn i32) (result i32)
+    (i32.load offset=12 (local.get $yarn)))
+
+  (func $lol_concat (param $left i32) (param $right i32) (result i32)
+    (local $start i32) (local $left_len i32) (local $right_len i32)
+    (local.set $left_len (call $lol_len (local.get $left)))
+    (local.set $right_len (call $lol_len (local.get $right)))
+    (local.set $start (call $lol_alloc (i32.add (local.get $left_len) (local.get $right_len))))
+    (memory.copy (local.get $start) (call $lol_start (local.get $left)) (local.get $left_len))
+    (memory.copy
+      (i32.add (local.get $start) (local.get $left_len))
+      (call $lol_start (local.get $right))
+      (local.get $right_len))
+    (call $lol_yarn (local.get $start) (i32.add (local.get $left_len) (local.get $right_len))))
+
+  (func $lol_to_troof (param $value i32) (result i32)
+    (block $noob
+      (block $yarn
+        (block $numbar
+          (block $numbr
+            (block $troof
+              (br_table $noob $noob $troof $numbr $numbar $yarn (call $lol_tag (local.get $value))))
+            (return (i32.load offset=8 (local.get $value))))
+          (return (i64.ne (i64.load offset=8 (local.get $value)) (i64.const 0))))
+        (return (f64.ne (f64.load offset=8 (local.get $value)) (f64.const 0))))
+      (return (i32.ne (call $lol_len (local.get $value)) (i32.const 0))))
+    (i32.const 0))
+
+  ;; Writes the digits of the unsigned $n backwards, ending before $end, and
+  ;; returns where they start.
+  (func $lol_digits (param $n i64) (param $end i32) (result i32)
+    (loop $digit
+      (local.set $end (i32.sub (local.get $end) (i32.const 1)))
+      (i64.store8 (local.get $end) (i64.add (i64.rem_u (local.get $n) (i64.const 10)) (i64.const 48)))
+      (local.set $n (i64.div_u (local.get $n) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $n) (i64.const 0))))
+    (local.get $end))
+
+  ;; Like $lol_digits, padded with zeros to $count digits.
+  (func $lol_digits_padded (param $n i64) (param $end i32) (param $count i32) (result i32)
+    (local $start i32)
+    (local.set $start (call $lol_digits (local.get $n) (local.get $end)))
+    (block $done
+      (loop $pad
+        (br_if $done (i32.ge_s (i32.sub (local.get $end) (local.get $start)) (local.get $count)))
+        (local.set $start (i32.sub (local.get $start) (i32.const 1)))
+        (i32.store8 (local.get $start) (i32.const 48))
+        (br $pad)))
+    (local.get $start))
+
+  (func $lol_show_numbr (param $numbr i64) (result i32)
+    (local $end i32) (local $start i32)
+    (local.set $end (i32.add (call $lol_alloc (i32.const 20)) (i32.const 20)))
+    (if (i64.lt_s (local.get $numbr) (i64.const 0))
+      (then
+        ;; Negating i64::MIN wraps to its magnitude as an unsigned number
+        (local.set $start
+          (call $lol_digits (i64.sub (i64.const 0) (local.get $numbr)) (local.get $end)))
+        (local.set $start (i32.sub (local.get $start) (i32.const 1)))
+        (i32.store8 (local.get $start) (i32.const 45)))
+      (else
+        (local.set $start (call $lol_digits (local.get $numbr) (local.get $end)))))
+    (call $lol_yarn (local.get $start) (i32.sub (local.get $end) (local.get $start))))
+
+  ;; Writes the digits of $mantissa * 2^$exponent backwards, ending before
+  ;; $end, for exponents too large for an i64.
+  (func $lol_digits_big (param $mantissa i64) (param $exponent i32) (param $end i32) (result i32)
+    (local $limbs i32) (local $top i32) (local $i i32) (local $word i32) (local $bit i64)
+    (local $low i64) (local $remainder i64) (local $current i64)
+    ;; Little-endian 32 bit limbs, enough for any finite NUMBAR
+    (local.set $limbs (call $lol_alloc (i32.const 144)))
+    (local.set $word (i32.shl (i32.shr_u (local.get $exponent) (i32.const 5)) (i32.const 2)))
+    (local.set $bit (i64.extend_i32_u (i32.and (local.get $exponent) (i32.const 31))))
+    (local.set $low (i64.shl (local.get $mantissa) (local.get $bit)))
+    (i64.store32 (i32.add (local.get $limbs) (local.get $word)) (local.get $low))
+    (i64.store32 offset=4
+      (i32.add (local.get $limbs) (local.get $word))
+      (i64.shr_u (local.get $low) (i64.const 32)))
+    (if (i64.ne (local.get $bit) (i64.const 0))
+      (then
+        (i64.store32 offset=8
+          (i32.add (local.get $limbs) (local.get $word))
+          (i64.shr_u (local.get $mantissa) (i64.sub (i64.const 64) (local.get $bit))))))
+    (local.set $top (i32.add (local.get $word) (i32.const 8)))
+
+    (loop $chunk
+      ;; Divides by 10^9, writing the remainder as the next nine digits
+      (local.set $remainder (i64.const 0))
+      (local.set $i (local.get $top))
+      (loop $limb
+        (local.set $current
+          (i64.or
+            (i64.shl (local.get $remainder) (i64.const 32))
+            (i64.load32_u (i32.add (local.get $limbs) (local.get $i)))))
+        (i64.store32
+          (i32.add (local.get $limbs) (local.get $i))
+          (i64.div_u (local.get $current) (i64.const 1000000000)))
+        (local.set $remainder (i64.rem_u (local.get $current) (i64.const 1000000000)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+        (br_if $limb (i32.ge_s (local.get $i) (i32.const 0))))
+
+      (block $trimmed
+        (loop $trim
+          (br_if $trimmed (i32.lt_s (local.get $top) (i32.const 0)))
+          (br_if $trimmed (i32.load (i32.add (local.get $limbs) (local.get $top))))
+          (local.set $top (i32.sub (local.get $top) (i32.const 4)))
+          (br $trim)))
+      (if (i32.lt_s (local.get $top) (i32.const 0))
+        (then (return (call $lol_digits (local.get $remainder) (local.get $end)))))
+      (local.set $end (call $lol_digits_padded (local.get $remainder) (local.get $end) (i32.const 9)))
+      (br $chunk))
+    (unreachable))
+
+  ;; Exact decimal expansion rounded half to even to two places, as Rust's
+  ;; {:.2} formats it.
+  (func $lol_show_numbar (param $numbar f64) (result i32)
+    (local $bits i64) (local $biased i32) (local $mantissa i64) (local $exponent i32)
+    (local $end i32) (local $start i32) (local $shift i64) (local $scaled i64)
+    (local $cents i64) (local $rest i64) (local $half i64)
+    (if (f64.ne (local.get $numbar) (local.get $numbar))
+      (then (return (call $lol_yarn (i32.const 187) (i32.const 3)))))
+    (local.set $bits (i64.reinterpret_f64 (local.get $numbar)))
+    (if (f64.eq (f64.abs (local.get $numbar)) (f64.const inf))
+      (then
+        (return
+          (select
+            (call $lol_yarn (i32.const 190) (i32.const 4))
+            (call $lol_yarn (i32.add (i32.const 190) (i32.const 1)) (i32.const 3))
+            (i64.lt_s (local.get $bits) (i64.const 0))))))
+
+    (local.set $biased
+      (i32.wrap_i64 (i64.and (i64.shr_u (local.get $bits) (i64.const 52)) (i64.const 0x7ff))))
+    (local.set $mantissa (i64.and (local.get $bits) (i64.const 0xfffffffffffff)))
+    (if (i32.eqz (local.get $biased))
+      (then (local.set $exponent (i32.const -1074)))
+      (else
+        (local.set $mantissa (i64.or (local.get $mantissa) (i64.const 0x10000000000000)))
+        (local.set $exponent (i32.sub (local.get $biased) (i32.const 1075)))))
+
+    (local.set $end (i32.add (call $lol_alloc (i32.const 400)) (i32.const 400)))
+    (if (i32.ge_s (local.get $exponent) (i32.const 0))
+      (then
+        (local.set $start (i32.sub (local.get $end) (i32.const 3)))
+        (i32.store8 (local.get $start) (i32.const 46))
+        (i32.store16 offset=1 (local.get $start) (i32.const 0x3030))
+        (if (i32.le_s (local.get $exponent) (i32.const 10))
+          (then
+            (local.set $start
+              (call $lol_digits
+                (i64.shl (local.get $mantissa) (i64.extend_i32_u (local.get $exponent)))
+                (local.get $start))))
+          (else
+            (local.set $start
+              (call $lol_digits_big
+                (local.get $mantissa) (local.get $exponent) (local.get $start))))))
+      (else
+        ;; The value in hundredths is $mantissa * 100 / 2^$shift, which
+        ;; rounds to 0 for shifts of 62 and more
+        (local.set $shift (i64.extend_i32_u (i32.sub (i32.const 0) (local.get $exponent))))
+        (if (i64.lt_u (local.get $shift) (i64.const 62))
+          (then
+            (local.set $scaled (i64.mul (local.get $mantissa) (i64.const 100)))
+            (local.set $cents (i64.shr_u (local.get $scaled) (local.get $shift)))
+            (local.set $rest
+              (i64.and
+                (local.get $scaled)
+                (i64.sub (i64.shl (i64.const 1) (local.get $shift)) (i64.const 1))))
+            (local.set $half (i64.shl (i64.const 1) (i64.sub (local.get $shift) (i64.const 1))))
+            (if (i32.or
+                  (i64.gt_u (local.get $rest) (local.get $half))
+                  (i32.and
+                    (i64.eq (local.get $rest) (local.get $half))
+                    (i32.wrap_i64 (i64.and (local.get $cents) (i64.const 1)))))
+              (then (local.set $cents (i64.add (local.get $cents) (i64.const 1)))))))
+        (local.set $start
+          (call $lol_digits_padded
+            (i64.rem_u (local.get $cents) (i64.const 100)) (local.get $end) (i32.const 2)))
+        (local.set $start (i32.sub (local.get $start) (i32.const 1)))
+        (i32.store8 (local.get $start) (i32.const 46))
+        (local.set $start
+          (call $lol_digits (i64.div_u (local.get $cents) (i64.const 100)) (local.get $start)))))
+
+    (if (i64.lt_s (local.get $bits) (i64.const 0))
+      (then
+        (local.set $start (i32.sub (local.get $start) (i32.const 1)))
+        (i32.store8 (local.get $start) (i32.const 45))))
+    (call $lol_yarn (local.get $start) (i32.sub (local.get $end) (local.get $start))))
+
+  ;; Text of a value as VISIBLE writes it.
+  (func $lol_show (param $value i32) (result i32)
+    (block $noob
+      (block $yarn
+        (block $numbar
+          (block $numbr
+            (block $troof
+              (br_table $noob $noob $troof $numbr $numbar $yarn (call $lol_tag (local.get $value))))
+            (return
+              (select
+                (call $lol_yarn (i32.const 180) (i32.const 3))
+                (call $lol_yarn (i32.const 183) (i32.const 4))
+                (i32.load offset=8 (local.get $value)))))
+          (return (call $lol_show_numbr (i64.load offset=8 (local.get $value)))))
+        (return (call $lol_show_numbar (f64.load offset=8 (local.get $value)))))
+      (return (local.get $value)))
+    (call $lol_yarn (i32.const 176) (i32.const 4)))
+
+  ;; Reports an error the way rlcc does without the source at hand, and traps.
+  (func $lol_fail (param $code i32) (param $message i32) (param $help i32) (param $line i32)
+    (param $column i32)
+    (local $text i32) (local $line_text i32) (local $gutter i32) (local $width i32)
+    (local.set $line_text (call $lol_show_numbr (i64.extend_i32_u (local.get $line))))
+    (local.set $width (call $lol_len (local.get $line_text)))
+    (local.set $gutter (call $lol_alloc (local.get $width)))
+    (memory.fill (local.get $gutter) (i32.const 32) (local.get $width))
+    (local.set $gutter (call $lol_yarn (local.get $gutter) (local.get $width)))
+
+    (local.set $text (call $lol_concat (call $lol_yarn (i32.const 194) (i32.const 6)) (local.get $code)))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 200) (i32.const 3))))
+    (local.set $text (call $lol_concat (local.get $text) (local.get $message)))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 203) (i32.const 1))))
+    (local.set $text (call $lol_concat (local.get $text) (local.get $gutter)))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 205) (i32.const 4))))
+    (local.set $text
+      (call $lol_concat
+        (local.get $text)
+        (call $lol_yarn (global.get $source_start) (global.get $source_len))))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 204) (i32.const 1))))
+    (local.set $text (call $lol_concat (local.get $text) (local.get $line_text)))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 204) (i32.const 1))))
+    (local.set $text
+      (call $lol_concat
+        (local.get $text)
+        (call $lol_show_numbr (i64.extend_i32_u (local.get $column)))))
+    (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 203) (i32.const 1))))
+    (if (local.get $help)
+      (then
+        (local.set $text (call $lol_concat (local.get $text) (local.get $gutter)))
+        (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 209) (i32.const 9))))
+        (local.set $text (call $lol_concat (local.get $text) (local.get $help)))
+        (local.set $text (call $lol_concat (local.get $text) (call $lol_yarn (i32.const 203) (i32.const 1))))))
+    (call $host_visible
+      (i32.const 2) (call $lol_start (local.get $text)) (call $lol_len (local.get $text)))
+    (unreachable))
+
+  (func $lol_arity_error (param $name i32) (param $arity i32) (param $given i32) (param $line i32)
+    (param $column i32)
+    (local $message i32)
+    (local.set $message
+      (call $lol_concat
+        (local.get $name)
+        (call $lol_yarn (i32.const 354) (i32.const 7))))
+    (local.set $message
+      (call $lol_concat
+        (local.get $message)
+        (call $lol_show_numbr (i64.extend_i32_u (local.get $arity)))))
+    (local.set $message (call $lol_concat (local.get $message) (call $lol_yarn (i32.const 361) (i32.const 17))))
+    (local.set $message
+      (call $lol_concat
+        (local.get $message)
+        (call $lol_show_numbr (i64.extend_i32_u (local.get $given)))))
+    (local.set $message (call $lol_concat (local.get $message) (call $lol_yarn (i32.const 378) (i32.const 11))))
+    (call $lol_fail
+      (call $lol_yarn (i32.const 218) (i32.const 5)) (local.get $message) (i32.const 0) (local.get $line) (local.get $column)))
+
+  (func $lol_division_by_zero (param $line i32) (param $column i32)
+    (call $lol_fail
+      (call $lol_yarn (i32.const 228) (i32.const 5)) (call $lol_yarn (i32.const 338) (i32.const 16)) (i32.const 0) (local.get $line) (local.get $column)))
+
+  ;; A YARN in quotes with special characters escaped, as Rust debug
+  ;; formatting writes it.
+  (func $lol_quote (param $yarn i32) (result i32)
+    (local $from i32) (local $end i32) (local $buffer i32) (local $to i32) (local $byte i32)
+    (local.set $from (call $lol_start (local.get $yarn)))
+    (local.set $end (i32.add (local.get $from) (call $lol_len (local.get $yarn))))
+    (local.set $buffer
+      (call $lol_alloc (i32.add (i32.mul (call $lol_len (local.get $yarn)) (i32.const 6)) (i32.const 2))))
+    (local.set $to (i32.add (local.get $buffer) (i32.const 1)))
+    (i32.store8 (local.get $buffer) (i32.const 34))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $from) (local.get $end)))
+        (local.set $byte (i32.load8_u (local.get $from)))
+        (local.set $from (i32.add (local.get $from) (i32.const 1)))
+        (block $plain
+          (block $escape
+            (block $control
+              (br_if $escape (i32.eq (local.get $byte) (i32.const 34)))
+              (br_if $escape (i32.eq (local.get $byte) (i32.const 92)))
+              (if (i32.eq (local.get $byte) (i32.const 10))
+                (then (local.set $byte (i32.const 110)) (br $escape)))
+              (if (i32.eq (local.get $byte) (i32.const 13))
+                (then (local.set $byte (i32.const 114)) (br $escape)))
+              (if (i32.eq (local.get $byte) (i32.const 9))
+                (then (local.set $byte (i32.const 116)) (br $escape)))
+              (if (i32.eqz (local.get $byte))
+                (then (local.set $byte (i32.const 48)) (br $escape)))
+              (br_if $control (i32.lt_u (local.get $byte) (i32.const 32)))
+              (br_if $control (i32.eq (local.get $byte) (i32.const 127)))
+              (br $plain))
+            ;; \u{..} with as few hex digits as needed
+            (i32.store (local.get $to) (i32.const 0x7b755c))
+            (local.set $to (i32.add (local.get $to) (i32.const 3)))
+            (if (i32.ge_u (local.get $byte) (i32.const 16))
+              (then
+                (i32.store8 (local.get $to) (call $lol_hex (i32.shr_u (local.get $byte) (i32.const 4))))
+                (local.set $to (i32.add (local.get $to) (i32.const 1)))))
+            (i32.store8 (local.get $to) (call $lol_hex (i32.and (local.get $byte) (i32.const 15))))
+            (i32.store8 offset=1 (local.get $to) (i32.const 125))
+            (local.set $to (i32.add (local.get $to) (i32.const 2)))
+            (br $next))
+          (i32.store8 (local.get $to) (i32.const 92))
+          (local.set $to (i32.add (local.get $to) (i32.const 1))))
+        (i32.store8 (local.get $to) (local.get $byte))
+        (local.set $to (i32.add (local.get $to) (i32.const 1)))
+        (br $next)))
+    (i32.store8 (local.get $to) (i32.const 34))
+    (call $lol_yarn
+      (local.get $buffer) (i32.sub (i32.add (local.get $to) (i32.const 1)) (local.get $buffer))))
+
+  (func $lol_hex (param $digit i32) (result i32)
+    (i32.add
+      (local.get $digit)
+      (select (i32.const 48) (i32.const 87) (i32.lt_u (local.get $digit) (i32.const 10)))))
+
+  (func $lol_is_digit (param $byte i32) (result i32)
+    (i32.lt_u (i32.sub (local.get $byte) (i32.const 48)) (i32.const 10)))
+
+  ;; A NUMBR as Rust's i64 parsing accepts it, or null.
+  (func $lol_parse_numbr (param $yarn i32) (result i32)
+    (local $at i32) (local $end i32) (local $negative i32) (local $byte i32) (local $n i64)
+    (local.set $at (call $lol_start (local.get $yarn)))
+    (local.set $end (i32.add (local.get $at) (call $lol_len (local.get $yarn))))
+    (if (i32.lt_u (local.get $at) (local.get $end))
+      (then
+        (local.set $byte (i32.load8_u (local.get $at)))
+        (if (i32.or (i32.eq (local.get $byte) (i32.const 43)) (i32.eq (local.get $byte) (i32.const 45)))
+          (then
+            (local.set $negative (i32.eq (local.get $byte) (i32.const 45)))
+            (local.set $at (i32.add (local.get $at) (i32.const 1)))))))
+    (if (i32.ge_u (local.get $at) (local.get $end))
+      (then (return (i32.const 0))))
+    (loop $digit
+      (local.set $byte (i32.sub (i32.load8_u (local.get $at)) (i32.const 48)))
+      (if (i32.ge_u (local.get $byte) (i32.const 10))
+        (then (return (i32.const 0))))
+      ;; Accumulates the magnitude, which may be up to 2^63 if negative
+      (if (i64.gt_u
+            (local.get $n)
+            (i64.div_u
+              (i64.sub (i64.const 0x8000000000000000) (i64.extend_i32_u (local.get $byte)))
+              (i64.const 10)))
+        (then (return (i32.const 0))))
+      (local.set $n
+        (i64.add (i64.mul (local.get $n) (i64.const 10)) (i64.extend_i32_u (local.get $byte))))
+      (local.set $at (i32.add (local.get $at) (i32.const 1)))
+      (br_if $digit (i32.lt_u (local.get $at) (local.get $end))))
+    (if (local.get $negative)
+      (then (return (call $lol_numbr (i64.sub (i64.const 0) (local.get $n))))))
+    (if (i64.eq (local.get $n) (i64.const 0x8000000000000000))
+      (then (return (i32.const 0))))
+    (call $lol_numbr (local.get $n)))
+
+  (func $lol_pow10 (param $n i32) (result f64)
+    (local $power f64)
+    (local.set $power (f64.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $n)))
+        (local.set $power (f64.mul (local.get $power) (f64.const 10)))
+        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+        (br $next)))
+    (local.get $power))
+
+  ;; A NUMBAR as Rust's f64 parsing accepts it, or null. Up to 19
+  ;; significant digits are kept, and the result is exact unless the digits
+  ;; exceed 2^53 or the power of ten is beyond 10^22.
+  (func $lol_parse_numbar (param $yarn i32) (result i32)
+    (local $at i32) (local $end i32) (local $negative i32) (local $byte i32)
+    (local $digits i64) (local $count i32) (local $exponent i32) (local $any i32)
+    (local $exponent_negative i32) (local $written i32) (local $numbar f64)
+    (local.set $at (call $lol_start (local.get $yarn)))
+    (local.set $end (i32.add (local.get $at) (call $lol_len (local.get $yarn))))
+    (if (i32.lt_u (local.get $at) (local.get $end))
+      (then
+        (local.set $byte (i32.load8_u (local.get $at)))
+        (if (i32.or (i32.eq (local.get $byte) (i32.const 43)) (i32.eq (local.get $byte) (i32.const 45)))
+          (then
+            (local.set $negative (i32.eq (local.get $byte) (i32.const 45)))
+            (local.set $at (i32.add (local.get $at) (i32.const 1)))))))
+
+    ;; Integer part
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $at) (local.get $end)))
+        (local.set $byte (i32.sub (i32.load8_u (local.get $at)) (i32.const 48)))
+        (br_if $done (i32.ge_u (local.get $byte) (i32.const 10)))
+        (local.set $any (i32.const 1))
+        (if (i32.lt_u (local.get $count) (i32.const 19))
+          (then
+            (local.set $digits
+              (i64.add (i64.mul (local.get $digits) (i64.const 10)) (i64.extend_i32_u (local.get $byte))))
+            (if (i64.ne (local.get $digits) (i64.const 0))
+              (then (local.set $count (i32.add (local.get $count) (i32.const 1))))))
+          (else (local.set $exponent (i32.add (local.get $exponent) (i32.const 1)))))
+        (local.set $at (i32.add (local.get $at) (i32.const 1)))
+        (br $next)))
+
+    ;; Fraction
+    (if (i32.lt_u (local.get $at) (local.get $end))
+      (then
+        (if (i32.eq (i32.load8_u (local.get $at)) (i32.const 46))
+          (then
+            (local.set $at (i32.add (local.get $at) (i32.const 1)))
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_u (local.get $at) (local.get $end)))
+                (local.set $byte (i32.sub (i32.load8_u (local.get $at)) (i32.const 48)))
+                (br_if $done (i32.ge_u (local.get $byte) (i32.const 10)))
+                (local.set $any (i32.const 1))
+                (if (i32.lt_u (local.get $count) (i32.const 19))
+                  (then
+                    (local.set $digits
+                      (i64.add
+                        (i64.mul (local.get $digits) (i64.const 10))
+                        (i64.extend_i32_u (local.get $byte))))
+                    (if (i64.ne (local.get $digits) (i64.const 0))
+                      (then (local.set $count (i32.add (local.get $count) (i32.const 1)))))
+                    (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1)))))
+                (local.set $at (i32.add (local.get $at) (i32.const 1)))
+                (br $next)))))))
+    (if (i32.eqz (local.get $any))
+      (then (return (i32.const 0))))
+
+    ;; Exponent
+    (if (i32.lt_u (local.get $at) (local.get $end))
+      (then
+        (if (i32.ne (i32.or (i32.load8_u (local.get $at)) (i32.const 32)) (i32.const 101))
+          (then (return (i32.const 0))))
+        (local.set $at (i32.add (local.get $at) (i32.const 1)))
+        (if (i32.lt_u (local.get $at) (local.get $end))
+          (then
+            (local.set $byte (i32.load8_u (local.get $at)))
+            (if (i32.or (i32.eq (local.get $byte) (i32.const 43)) (i32.eq (local.get $byte) (i32.const 45)))
+              (then
+                (local.set $exponent_negative (i32.eq (local.get $byte) (i32.const 45)))
+                (local.set $at (i32.add (local.get $at) (i32.const 1)))))))
+        (if (i32.ge_u (local.get $at) (local.get $end))
+          (then (return (i32.const 0))))
+        (loop $next
+          (local.set $byte (i32.sub (i32.load8_u (local.get $at)) (i32.const 48)))
+          (if (i32.ge_u (local.get $byte) (i32.const 10))
+            (then (return (i32.const 0))))
+          ;; Anything this large overflows or underflows anyway
+          (if (i32.lt_u (local.get $written) (i32.const 100000))
+            (then
+              (local.set $written
+                (i32.add (i32.mul (local.get $written) (i32.const 10)) (local.get $byte)))))
+          (local.set $at (i32.add (local.get $at) (i32.const 1)))
+          (br_if $next (i32.lt_u (local.get $at) (local.get $end))))
+        (local.set $exponent
+          (select
+            (i32.sub (local.get $exponent) (local.get $written))
+            (i32.add (local.get $exponent) (local.get $written))
+            (local.get $exponent_negative)))))
+
+    (local.set $numbar (f64.convert_i64_u (local.get $digits)))
+    (if (i64.ne (local.get $digits) (i64.const 0))
+      (then
+        (block $scaled
+          (loop $large
+            (br_if $scaled (i32.le_s (local.get $exponent) (i32.const 22)))
+            (local.set $numbar (f64.mul (local.get $numbar) (f64.const 1e22)))
+            (local.set $exponent (i32.sub (local.get $exponent) (i32.const 22)))
+            (br $large)))
+        (block $scaled
+          (loop $small
+            (br_if $scaled (i32.ge_s (local.get $exponent) (i32.const -22)))
+            (local.set $numbar (f64.div (local.get $numbar) (f64.const 1e22)))
+            (local.set $exponent (i32.add (local.get $exponent) (i32.const 22)))
+            (br $small)))
+        (if (i32.ge_s (local.get $exponent) (i32.const 0))
+          (then
+            (local.set $numbar (f64.mul (local.get $numbar) (call $lol_pow10 (local.get $exponent)))))
+          (else
+            (local.set $numbar
+              (f64.div (local.get $numbar) (call $lol_pow10 (i32.sub (i32.const 0) (local.get $exponent)))))))))
+    (if (local.get $negative)
+      (then (local.set $numbar (f64.neg (local.get $numbar)))))
+    (call $lol_numbar (local.get $numbar)))
+
+  (func $lol_contains_dot (param $yarn i32) (result i32)
+    (local $at i32) (local $end i32)
+    (local.set $at (call $lol_start (local.get $yarn)))
+    (local.set $end (i32.add (local.get $at) (call $lol_len (local.get $yarn))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $at) (local.get $end)))
+        (if (i32.eq (i32.load8_u (local.get $at)) (i32.const 46))
+          (then (return (i32.const 1))))
+        (local.set $at (i32.add (local.get $at) (i32.const 1)))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Implicit cast for arithmetic. NOOB may only be cast explicitly.
+  (func $lol_to_number (param $value i32) (param $line i32) (param $column i32) (result i32)
+    (local $number i32)
+    (block $yarn
+      (block $troof
+        (block $noob
+          (br_table $noob $troof $yarn (i32.sub (call $lol_tag (local.get $value)) (i32.const 1))))
+        (call $lol_fail
+          (call $lol_yarn (i32.const 223) (i32.const 5)) (call $lol_yarn (i32.const 233) (i32.const 39)) (i32.const 0) (local.get $line) (local.get $column)))
+      (return (call $lol_numbr (i64.extend_i32_u (i32.load offset=8 (local.get $value))))))
+    (if (i32.ne (call $lol_tag (local.get $value)) (i32.const 5))
+      (then (return (local.get $value))))
+    (local.set $number
+      (if (result i32) (call $lol_contains_dot (local.get $value))
+        (then (call $lol_parse_numbar (local.get $value)))
+        (else (call $lol_parse_numbr (local.get $value)))))
+    (if (local.get $number)
+      (then (return (local.get $number))))
+    (call $lol_fail
+      (call $lol_yarn (i32.const 223) (i32.const 5))
+      (call $lol_concat
+        (call $lol_concat (call $lol_yarn (i32.const 272) (i32.const 17)) (call $lol_quote (local.get $value)))
+        (call $lol_yarn (i32.const 289) (i32.const 12)))
+      (i32.const 0)
+      (local.get $line)
+      (local.get $column))
+    (unreachable))
+
+  ;; Implicit cast for SMOOSH and interpolation.
+  (func $lol_to_yarn (param $value i32) (param $line i32) (param $column i32) (result i32)
+    (if (i32.eq (call $lol_tag (local.get $value)) (i32.const 1))
+      (then
+        (call $lol_fail
+          (call $lol_yarn (i32.const 223) (i32.const 5)) (call $lol_yarn (i32.const 301) (i32.const 37)) (i32.const 0) (local.get $line) (local.get $column))))
+    (call $lol_show (local.get $value)))
+
+  ;; Explicit cast with MAEK or IS NOW A, to the type with tag $to.
+  (func $lol_cast (param $value i32) (param $to i32) (param $line i32) (param $column i32)
+    (result i32)
+    (local $noob i32) (local $number i32)
+    (local.set $noob (i32.eq (call $lol_tag (local.get $value)) (i32.const 1)))
+    (block $yarn
+      (block $numbar
+        (block $numbr
+          (block $troof
+            (block $to_noob
+              (br_table $to_noob $troof $numbr $numbar $yarn (i32.sub (local.get $to) (i32.const 1))))
+            (return (call $lol_noob)))
+          (return (call $lol_troof (call $lol_to_troof (local.get $value)))))
+        (if (local.get $noob)
+          (then (return (call $lol_numbr (i64.const 0)))))
+        (local.set $number (call $lol_to_number (local.get $value) (local.get $line) (local.get $column)))
+        (if (i32.eq (call $lol_tag (local.get $number)) (i32.const 4))
+          (then
+            (return (call $lol_numbr (i64.trunc_sat_f64_s (f64.load offset=8 (local.get $number)))))))
+        (return (local.get $number)))
+      (if (local.get $noob)
+        (then (return (call $lol_numbar (f64.const 0)))))
+      (local.set $number (call $lol_to_number (local.get $value) (local.get $line) (local.get $column)))
+      (if (i32.eq (call $lol_tag (local.get $number)) (i32.const 3))
+        (then
+          (return (call $lol_numbar (f64.convert_i64_s (i64.load offset=8 (local.get $number)))))))
+      (return (local.get $number)))
+    (if (local.get $noob)
+      (then (return (call $lol_yarn (i32.const 0) (i32.const 0)))))
+    (call $lol_show (local.get $value)))
+
+  ;; Remainder of NUMBARs truncated towards zero, ported from musl.
+  (func $lol_fmod (param $x f64) (param $y f64) (result f64)
+    (local $ux i64) (local $uy i64) (local $ex i32) (local $ey i32) (local $sign i64) (local $i i64)
+    (local.set $ux (i64.reinterpret_f64 (local.get $x)))
+    (local.set $uy (i64.reinterpret_f64 (local.get $y)))
+    (local.set $ex (i32.wrap_i64 (i64.and (i64.shr_u (local.get $ux) (i64.const 52)) (i64.const 0x7ff))))
+    (local.set $ey (i32.wrap_i64 (i64.and (i64.shr_u (local.get $uy) (i64.const 52)) (i64.const 0x7ff))))
+    (local.set $sign (i64.and (local.get $ux) (i64.const 0x8000000000000000)))
+
+    (if (i32.or
+          (i32.or
+            (i64.eqz (i64.shl (local.get $uy) (i64.const 1)))
+            (f64.ne (local.get $y) (local.get $y)))
+          (i32.eq (local.get $ex) (i32.const 0x7ff)))
+      (then
+        (return
+          (f64.div
+            (f64.mul (local.get $x) (local.get $y))
+            (f64.mul (local.get $x) (local.get $y))))))
+    (if (i64.le_u (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+      (then
+        (if (i64.eq (i64.shl (local.get $ux) (i64.const 1)) (i64.shl (local.get $uy) (i64.const 1)))
+          (then (return (f64.mul (f64.const 0) (local.get $x)))))
+        (return (local.get $x))))
+
+    ;; Normalizes both mantissas
+    (if (i32.eqz (local.get $ex))
+      (then
+        (local.set $i (i64.shl (local.get $ux) (i64.const 12)))
+        (block $normal
+          (loop $shift
+            (br_if $normal (i64.lt_s (local.get $i) (i64.const 0)))
+            (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+            (local.set $i (i64.shl (local.get $i) (i64.const 1)))
+            (br $shift)))
+        (local.set $ux
+          (i64.shl (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex))))))
+      (else
+        (local.set $ux
+          (i64.or
+            (i64.and (local.get $ux) (i64.const 0xfffffffffffff))
+            (i64.const 0x10000000000000)))))
+    (if (i32.eqz (local.get $ey))
+      (then
+        (local.set $i (i64.shl (local.get $uy) (i64.const 12)))
+        (block $normal
+          (loop $shift
+            (br_if $normal (i64.lt_s (local.get $i) (i64.const 0)))
+            (local.set $ey (i32.sub (local.get $ey) (i32.const 1)))
+            (local.set $i (i64.shl (local.get $i) (i64.const 1)))
+            (br $shift)))
+        (local.set $uy
+          (i64.shl (local.get $uy) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ey))))))
+      (else
+        (local.set $uy
+          (i64.or
+            (i64.and (local.get $uy) (i64.const 0xfffffffffffff))
+            (i64.const 0x10000000000000)))))
+
+    (block $reduced
+      (loop $reduce
+        (br_if $reduced (i32.le_s (local.get $ex) (local.get $ey)))
+        (local.set $i (i64.sub (local.get $ux) (local.get $uy)))
+        (if (i64.ge_s (local.get $i) (i64.const 0))
+          (then
+            (if (i64.eqz (local.get $i))
+              (then (return (f64.mul (f64.const 0) (local.get $x)))))
+            (local.set $ux (local.get $i))))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $reduce)))
+    (local.set $i (i64.sub (local.get $ux) (local.get $uy)))
+    (if (i64.ge_s (local.get $i) (i64.const 0))
+      (then
+        (if (i64.eqz (local.get $i))
+          (then (return (f64.mul (f64.const 0) (local.get $x)))))
+        (local.set $ux (local.get $i))))
+    (block $normal
+      (loop $shift
+        (br_if $normal (i64.ne (i64.shr_u (local.get $ux) (i64.const 52)) (i64.const 0)))
+        (local.set $ux (i64.shl (local.get $ux) (i64.const 1)))
+        (local.set $ex (i32.sub (local.get $ex) (i32.const 1)))
+        (br $shift)))
+
+    (if (i32.gt_s (local.get $ex) (i32.const 0))
+      (then
+        (local.set $ux
+          (i64.or
+            (i64.sub (local.get $ux) (i64.const 0x10000000000000))
+            (i64.shl (i64.extend_i32_u (local.get $ex)) (i64.const 52)))))
+      (else
+        (local.set $ux
+          (i64.shr_u (local.get $ux) (i64.extend_i32_u (i32.sub (i32.const 1) (local.get $ex)))))))
+    (f64.reinterpret_i64 (i64.or (local.get $ux) (local.get $sign))))
+
+  ;; Larger or smaller of two NUMBARs, ignoring NaN as Rust's max and min do.
+  (func $lol_fmax (param $a f64) (param $b f64) (result f64)
+    (if (f64.ne (local.get $a) (local.get $a))
+      (then (return (local.get $b))))
+    (if (f64.ne (local.get $b) (local.get $b))
+      (then (return (local.get $a))))
+    (f64.max (local.get $a) (local.get $b)))
+
+  (func $lol_fmin (param $a f64) (param $b f64) (result f64)
+    (if (f64.ne (local.get $a) (local.get $a))
+      (then (return (local.get $b))))
+    (if (f64.ne (local.get $b) (local.get $b))
+      (then (return (local.get $a))))
+    (f64.min (local.get $a) (local.get $b)))
+
+  (func $lol_as_numbar (param $number i32) (result f64)
+    (if (result f64) (i32.eq (call $lol_tag (local.get $number)) (i32.const 3))
+      (then (f64.convert_i64_s (i64.load offset=8 (local.get $number))))
+      (else (f64.load offset=8 (local.get $number)))))
+
+  ;; SUM OF through SMALLR OF, numbered 0 to 6. NUMBRs stay NUMBRs unless
+  ;; either side is a NUMBAR.
+  (func $lol_arithmetic (param $left i32) (param $right i32) (param $op i32) (param $line i32)
+    (param $column i32) (result i32)
+    (local $x i64) (local $y i64) (local $a f64) (local $b f64)
+    (local.set $left (call $lol_to_number (local.get $left) (local.get $line) (local.get $column)))
+    (local.set $right (call $lol_to_number (local.get $right) (local.get $line) (local.get $column)))
+    (if (i32.and
+          (i32.eq (call $lol_tag (local.get $left)) (i32.const 3))
+          (i32.eq (call $lol_tag (local.get $right)) (i32.const 3)))
+      (then
+        (local.set $x (i64.load offset=8 (local.get $left)))
+        (local.set $y (i64.load offset=8 (local.get $right)))
+        (block $smallr
+          (block $biggr
+            (block $mod
+              (block $quoshunt
+                (block $produkt
+                  (block $diff
+                    (block $sum
+                      (br_table $sum $diff $produkt $quoshunt $mod $biggr $smallr (local.get $op)))
+                    (return (call $lol_numbr (i64.add (local.get $x) (local.get $y)))))
+                  (return (call $lol_numbr (i64.sub (local.get $x) (local.get $y)))))
+                (return (call $lol_numbr (i64.mul (local.get $x) (local.get $y)))))
+              (call $lol_check_division (local.get $x) (local.get $y) (local.get $line) (local.get $column))
+              (return (call $lol_numbr (i64.div_s (local.get $x) (local.get $y)))))
+            (call $lol_check_division (local.get $x) (local.get $y) (local.get $line) (local.get $column))
+            (return (call $lol_numbr (i64.rem_s (local.get $x) (local.get $y)))))
+          (return
+            (call $lol_numbr
+              (select (local.get $x) (local.get $y) (i64.gt_s (local.get $x) (local.get $y))))))
+        (return
+          (call $lol_numbr
+            (select (local.get $x) (local.get $y) (i64.lt_s (local.get $x) (local.get $y)))))))
+
+    (local.set $a (call $lol_as_numbar (local.get $left)))
+    (local.set $b (call $lol_as_numbar (local.get $right)))
+    (block $smallr
+      (block $biggr
+        (block $mod
+          (block $quoshunt
+            (block $produkt
+              (block $diff
+                (block $sum
+                  (br_table $sum $diff $produkt $quoshunt $mod $biggr $smallr (local.get $op)))
+                (return (call $lol_numbar (f64.add (local.get $a) (local.get $b)))))
+              (return (call $lol_numbar (f64.sub (local.get $a) (local.get $b)))))
+            (return (call $lol_numbar (f64.mul (local.get $a) (local.get $b)))))
+          (if (f64.eq (local.get $b) (f64.const 0))
+            (then (call $lol_division_by_zero (local.get $line) (local.get $column))))
+          (return (call $lol_numbar (f64.div (local.get $a) (local.get $b)))))
+        (if (f64.eq (local.get $b) (f64.const 0))
+          (then (call $lol_division_by_zero (local.get $line) (local.get $column))))
+        (return (call $lol_numbar (call $lol_fmod (local.get $a) (local.get $b)))))
+      (return (call $lol_numbar (call $lol_fmax (local.get $a) (local.get $b)))))
+    (call $lol_numbar (call $lol_fmin (local.get $a) (local.get $b))))
+
+  ;; Fails like Rust's checked division and remainder.
+  (func $lol_check_division (param $x i64) (param $y i64) (param $line i32) (param $column i32)
+    (if (i32.or
+          (i64.eqz (local.get $y))
+          (i32.and
+            (i64.eq (local.get $x) (i64.const 0x8000000000000000))
+            (i64.eq (local.get $y) (i64.const -1))))
+      (then (call $lol_division_by_zero (local.get $line) (local.get $column)))))
+
+  ;; BOTH SAEM. Only NUMBRs and NUMBARs are compared across types.
+  (func $lol_saem (param $left i32) (param $right i32) (result i32)
+    (local $tag i32) (local $len i32)
+    (local.set $tag (call $lol_tag (local.get $left)))
+    (if (i32.ne (local.get $tag) (call $lol_tag (local.get $right)))
+      (then
+        (if (i32.and
+              (i32.ge_u (local.get $tag) (i32.const 3))
+              (i32.and
+                (i32.le_u (local.get $tag) (i32.const 4))
+                (i32.ge_u (call $lol_tag (local.get $right)) (i32.const 3))))
+          (then
+            (if (i32.le_u (call $lol_tag (local.get $right)) (i32.const 4))
+              (then
+                (return
+                  (f64.eq
+                    (call $lol_as_numbar (local.get $left))
+                    (call $lol_as_numbar (local.get $right))))))))
+        (return (i32.const 0))))
+    (block $yarn
+      (block $numbar
+        (block $numbr
+          (block $troof
+            (block $noob
+              (br_table $noob $noob $troof $numbr $numbar $yarn (local.get $tag)))
+            (return (i32.const 1)))
+          (return
+            (i32.eq (i32.load offset=8 (local.get $left)) (i32.load offset=8 (local.get $right)))))
+        (return
+          (i64.eq (i64.load offset=8 (local.get $left)) (i64.load offset=8 (local.get $right)))))
+      (return
+        (f64.eq (f64.load offset=8 (local.get $left)) (f64.load offset=8 (local.get $right)))))
+    (local.set $len (call $lol_len (local.get $left)))
+    (if (i32.ne (local.get $len) (call $lol_len (local.get $right)))
+      (then (return (i32.const 0))))
+    (call $lol_bytes_equal
+      (call $lol_start (local.get $left)) (call $lol_start (local.get $right)) (local.get $len)))
+
+  (func $lol_bytes_equal (param $a i32) (param $b i32) (param $len i32) (result i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $len)))
+        (if (i32.ne (i32.load8_u (local.get $a)) (i32.load8_u (local.get $b)))
+          (then (return (i32.const 0))))
+        (local.set $a (i32.add (local.get $a) (i32.const 1)))
+        (local.set $b (i32.add (local.get $b) (i32.const 1)))
+        (local.set $len (i32.sub (local.get $len) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+
+  ;; Any operator with two operands, numbered as $lol_arithmetic then BOTH
+  ;; OF through DIFFRINT as 7 to 11. Both are evaluated whatever the first
+  ;; one is.
+  (func $lol_binary (param $left i32) (param $right i32) (param $op i32) (param $line i32)
+    (param $column i32) (result i32)
+    (block $arithmetic
+      (block $diffrint
+        (block $saem
+          (block $won
+            (block $either
+              (block $both
+                (br_table $both $either $won $saem $diffrint $arithmetic
+                  (i32.sub (local.get $op) (i32.const 7))))
+              (return
+                (call $lol_troof
+                  (i32.and
+                    (call $lol_to_troof (local.get $left))
+                    (call $lol_to_troof (local.get $right))))))
+            (return
+              (call $lol_troof
+                (i32.or
+                  (call $lol_to_troof (local.get $left))
+                  (call $lol_to_troof (local.get $right))))))
+          (return
+            (call $lol_troof
+              (i32.ne
+                (call $lol_to_troof (local.get $left))
+                (call $lol_to_troof (local.get $right))))))
+        (return (call $lol_troof (call $lol_saem (local.get $left) (local.get $right)))))
+      (return
+        (call $lol_troof (i32.eqz (call $lol_saem (local.get $left) (local.get $right))))))
+    (call $lol_arithmetic
+      (local.get $left) (local.get $right) (local.get $op) (local.get $line) (local.get $column)))
+
+  ;; Writes a YARN to stream 1 for VISIBLE or 2 for INVISIBLE.
+  ;; A line of input as a YARN. A line too long for the first buffer is read
+  ;; again into one of its length.
+  (func $lol_gimmeh (result i32)
+    (local $start i32) (local $len i32)
+    (local.set $start (call $lol_alloc (i32.const 64)))
+    (local.set $len (call $host_gimmeh (local.get $start) (i32.const 64)))
+    (if (i32.gt_u (local.get $len) (i32.const 64))
+      (then
+        (local.set $start (call $lol_alloc (local.get $len)))
+        (drop (call $host_gimmeh (local.get $start) (local.get $len)))))
+    (call $lol_yarn (local.get $start) (local.get $len)))
+
+  (func $lol_visible (param $yarn i32) (param $stream i32) (param $newline i32)
+    (if (local.get $newline)
+      (then (local.set $yarn (call $lol_concat (local.get $yarn) (call $lol_yarn (i32.const 203) (i32.const 1))))))
+    (call $host_visible
+      (local.get $stream) (call $lol_start (local.get $yarn)) (call $lol_len (local.get $yarn))))
@@ -0,0 +1,364 @@
+use std::collections::VecDeque;
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind,
+    },
+    diagnostic::Span,
+    framework::HandleTokenProcessingError,
+    interpreter::check_features,
+    value::Value,
+};
+
+use super::{Address, Bytecode, Constant, Function, Instruction, Name};
+
+pub trait Compile {
+    /// Compiles `prog` to [`Bytecode`], leaving its
+    /// [`source`](Bytecode::source) for the caller to name.
+    fn compile(&mut self, prog: &LolCodeProgram) -> anyhow::Result<Bytecode>;
+}
+
+impl<T> Compile for T
+where
+    T: HandleTokenProcessingError,
+{
+    fn compile(&mut self, prog: &LolCodeProgram) -> anyhow::Result<Bytecode> {
+        check_features(self, prog)?;
+
+        let mut compiler = Compiler {
+            bytecode: Bytecode {
+                version: prog.version.clone(),
+                source: String::new(),
+                code: Vec::new(),
+                spans: Vec::new(),
+                constants: Vec::new(),
+                names: Vec::new(),
+                functions: Vec::new(),
+            },
+            pending: VecDeque::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        compiler.block(&prog.body);
+        compiler.emit(Instruction::Halt, prog.span);
+
+        while let Some((index, func)) = compiler.pending.pop_front() {
+            compiler.function(index, func);
+        }
+        Ok(compiler.bytecode)
+    }
+}
+
+struct Compiler<'p> {
+    bytecode: Bytecode,
+    /// Functions whose bodies are still to be compiled, with their index in
+    /// [`Bytecode::functions`].
+    pending: VecDeque<(usize, &'p FuncDef)>,
+    /// Jumps to the end of each enclosing loop or `WTF?`, innermost last,
+    /// to be patched once the end is known.
+    breaks: Vec<Vec<usize>>,
+    in_function: bool,
+}
+
+impl<'p> Compiler<'p> {
+    /// Address of the next instruction.
+    fn here(&self) -> Address {
+        self.bytecode.code.len() as Address
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        self.bytecode.code.push(instruction);
+        self.bytecode.spans.push(span);
+        self.bytecode.code.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.bytecode.code[at] {
+            Instruction::Jump(address)
+            | Instruction::JumpIfFalse(address)
+            | Instruction::JumpIfTrue(address)
+            | Instruction::JumpUnlessIt(address)
+            | Instruction::JumpIfIt(address) => *address = target,
+            instruction => unreachable!("{instruction:?} is not a jump"),
+        }
+    }
+
+    fn constant(&mut self, value: Value) -> Constant {
+        self.bytecode.constants.push(value);
+        (self.bytecode.constants.len() - 1) as Constant
+    }
+
+    fn name(&mut self, ident: &Ident) -> Name {
+        self.bytecode.names.push(ident.clone());
+        (self.bytecode.names.len() - 1) as Name
+    }
+
+    fn push(&mut self, value: Value, span: Span) {
+        let constant = self.constant(value);
+        self.emit(Instruction::Push(constant), span);
+    }
+
+    fn function(&mut self, index: usize, func: &'p FuncDef) {
+        self.bytecode.functions[index].entry = self.here();
+        // Functions are only compiled after the main program, outside any loop
+        self.in_function = true;
+        self.block(&func.body);
+        self.emit(Instruction::ReturnIt, func.body.span);
+    }
+
+    fn block(&mut self, block: &'p Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// Compiles a loop or `WTF?`, pointing each `GTFO` in it just past it.
+    fn breakable<F>(&mut self, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.breaks.push(Vec::new());
+        body(self);
+        for at in self.breaks.pop().expect("pushed above") {
+            self.patch(at);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt) {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let visible = Instruction::Visible {
+                    args: args.len() as u32,
+                    invisible: *invisible,
+                    newline: *newline,
+                };
+                self.emit(visible, stmt.span);
+            }
+            StmtKind::CanHas { module } => {
+                let module_name = self.name(module);
+                self.emit(Instruction::Include(module_name), module.span);
+            }
+            StmtKind::Declare { name, init } => {
+                match init {
+                    Some(init) => self.expr(init),
+                    None => self.push(Value::Noob, name.span),
+                }
+                let var = self.name(name);
+                self.emit(Instruction::Declare(var), name.span);
+            }
+            StmtKind::Gimmeh(name) => {
+                self.emit(Instruction::Gimmeh, stmt.span);
+                let var = self.name(name);
+                self.emit(Instruction::Store(var), name.span);
+            }
+            StmtKind::Assign { name, value } => {
+                self.expr(value);
+                let var = self.name(name);
+                self.emit(Instruction::Store(var), name.span);
+            }
+            StmtKind::CastVar { name, to } => {
+                let var = self.name(name);
+                self.emit(Instruction::Load(var), name.span);
+                self.emit(Instruction::Cast(*to), stmt.span);
+                self.emit(Instruction::Store(var), name.span);
+            }
+            StmtKind::Expr(expr) => {
+                self.expr(expr);
+                self.emit(Instruction::SetIt, stmt.span);
+            }
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                let mut ends = Vec::new();
+                let mut next = self.emit(Instruction::JumpUnlessIt(0), stmt.span);
+                self.block(then);
+                for (condition, block) in elifs {
+                    ends.push(self.emit(Instruction::Jump(0), stmt.span));
+                    self.patch(next);
+                    self.expr(condition);
+                    next = self.emit(Instruction::JumpIfFalse(0), condition.span);
+                    self.block(block);
+                }
+                if let Some(otherwise) = otherwise {
+                    ends.push(self.emit(Instruction::Jump(0), stmt.span));
+                    self.patch(next);
+                    self.block(otherwise);
+                } else {
+                    ends.push(next);
+                }
+                for at in ends {
+                    self.patch(at);
+                }
+            }
+            StmtKind::Switch { cases, default } => {
+                let mut matches = Vec::with_capacity(cases.len());
+                for (literal, _) in cases {
+                    self.expr(literal);
+                    matches.push(self.emit(Instruction::JumpIfIt(0), literal.span));
+                }
+                let unmatched = self.emit(Instruction::Jump(0), stmt.span);
+
+                // Cases fall through into the ones after, OMGWTF included
+                self.breakable(|compiler| {
+                    for (at, (_, block)) in matches.into_iter().zip(cases) {
+                        compiler.patch(at);
+                        compiler.block(block);
+                    }
+                    compiler.patch(unmatched);
+                    if let Some(default) = default {
+                        compiler.block(default);
+                    }
+                });
+            }
+            StmtKind::Loop(lp) => {
+                if let Some(update) = &lp.update {
+                    let var = self.name(&update.var);
+                    self.emit(Instruction::EnterLoop(var), update.var.span);
+                }
+
+                let top = self.here();
+                let done = match &lp.condition {
+                    Some(LoopCondition::Til(condition)) => {
+                        self.expr(condition);
+                        Some(self.emit(Instruction::JumpIfTrue(0), condition.span))
+                    }
+                    Some(LoopCondition::Wile(condition)) => {
+                        self.expr(condition);
+                        Some(self.emit(Instruction::JumpIfFalse(0), condition.span))
+                    }
+                    None => None,
+                };
+                self.breakable(|compiler| {
+                    compiler.block(&lp.body);
+                    if let Some(update) = &lp.update {
+                        let op = match update.op {
+                            LoopOp::Uppin => BinaryOp::Sum,
+                            LoopOp::Nerfin => BinaryOp::Diff,
+                        };
+                        let (var, span) = (compiler.name(&update.var), update.var.span);
+                        compiler.emit(Instruction::Load(var), span);
+                        compiler.push(Value::Numbr(1), span);
+                        compiler.emit(Instruction::Binary(op), span);
+                        compiler.emit(Instruction::Store(var), span);
+                    }
+                    compiler.emit(Instruction::Jump(top), stmt.span);
+                    if let Some(done) = done {
+                        compiler.patch(done);
+                    }
+                });
+
+                if lp.update.is_some() {
+                    self.emit(Instruction::ExitLoop, stmt.span);
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let function = Function {
+                    name: self.name(&func.name),
+                    params: func.params.iter().map(|param| self.name(param)).collect(),
+                    entry: 0,
+                };
+                let index = self.bytecode.functions.len();
+                self.bytecode.functions.push(function);
+                self.pending.push_back((index, func));
+                self.emit(Instruction::Define(index as u32), stmt.span);
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.emit(Instruction::ReturnOutside, stmt.span);
+                    return;
+                }
+                self.expr(expr);
+                self.emit(Instruction::Return, stmt.span);
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(_) => {
+                    let at = self.emit(Instruction::Jump(0), stmt.span);
+                    self.breaks.last_mut().expect("matched above").push(at);
+                }
+                // GTFO in a function returns NOOB
+                None if self.in_function => {
+                    self.push(Value::Noob, stmt.span);
+                    self.emit(Instruction::Return, stmt.span);
+                }
+                None => {
+                    self.emit(Instruction::BreakOutside, stmt.span);
+                }
+            },
+        }
+    }
+
+    fn expr(&mut self, expr: &'p Expr) {
+        match &expr.kind {
+            ExprKind::Noob => self.push(Value::Noob, expr.span),
+            ExprKind::Troof(troof) => self.push(Value::Troof(*troof), expr.span),
+            ExprKind::Numbr(numbr) => self.push(Value::Numbr(*numbr), expr.span),
+            ExprKind::Numbar(numbar) => self.push(Value::Numbar(*numbar), expr.span),
+            ExprKind::Yarn(yarn) => self.push(Value::Yarn(yarn.clone()), expr.span),
+            ExprKind::Var(var) => {
+                let var_name = self.name(var);
+                self.emit(Instruction::Load(var_name), var.span);
+            }
+            ExprKind::Binary { op, left, right } => {
+                self.expr(left);
+                self.expr(right);
+                self.emit(Instruction::Binary(*op), expr.span);
+            }
+            ExprKind::Not(operand) => {
+                self.expr(operand);
+                self.emit(Instruction::Not, expr.span);
+            }
+            ExprKind::Nary { op, args } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let count = args.len() as u32;
+                match op {
+                    NaryOp::All => self.emit(Instruction::All(count), expr.span),
+                    NaryOp::Any => self.emit(Instruction::Any(count), expr.span),
+                    NaryOp::Smoosh => {
+                        // Every piece is evaluated before any is cast
+                        for (index, arg) in args.iter().enumerate() {
+                            let depth = count - 1 - index as u32;
+                            self.emit(Instruction::ToYarn(depth), arg.span);
+                        }
+                        self.emit(Instruction::Concat(count), expr.span)
+                    }
+                };
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                self.expr(operand);
+                self.emit(Instruction::Cast(*to), expr.span);
+            }
+            ExprKind::Call { module, name, args } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let args = args.len() as u32;
+                let call = match module {
+                    Some(module) => Instruction::CallNative {
+                        module: self.name(module),
+                        function: self.name(name),
+                        args,
+                    },
+                    None => Instruction::Call {
+                        name: self.name(name),
+                        args,
+                    },
+                };
+                self.emit(call, expr.span);
+            }
+        }
+    }
+}
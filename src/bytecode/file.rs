@@ -0,0 +1,564 @@
+//! The `.lolc` format, saving [`Bytecode`] so it can be run without reading
+//! the source again.
+//!
+//! Numbers are little-endian. The header is
+//!
+//! | Bytes | Contents                                          |
+//! |-------|---------------------------------------------------|
+//! | 4     | [`MAGIC`]                                         |
+//! | 2     | [`FORMAT_VERSION`]                                |
+//! | 4 + 4 | LOLCODE version of the source, major then minor   |
+//! | 4     | length of the body                                |
+//! | 4     | FNV-1a checksum of the body                       |
+//!
+//! and the body holds the source name, the constant pool, the names, the
+//! functions and the code section in turn. Counts and strings are prefixed
+//! with their length as a `u32`.
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    ast::{BinaryOp, Ident, Type},
+    diagnostic::{Position, Span},
+    parser::LolCodeVersion,
+    value::Value,
+};
+
+use super::{Bytecode, Function, Instruction};
+
+/// First bytes of every `.lolc` file.
+pub const MAGIC: [u8; 4] = *b"LOLC";
+
+/// Bumped whenever the layout changes. Files of any other version are
+/// rejected rather than misread.
+pub const FORMAT_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 22;
+
+/// Operators in the order of their encoding.
+const BINARY_OPS: [BinaryOp; 12] = [
+    BinaryOp::Sum,
+    BinaryOp::Diff,
+    BinaryOp::Produkt,
+    BinaryOp::Quoshunt,
+    BinaryOp::Mod,
+    BinaryOp::Biggr,
+    BinaryOp::Smallr,
+    BinaryOp::Both,
+    BinaryOp::Either,
+    BinaryOp::Won,
+    BinaryOp::Saem,
+    BinaryOp::Diffrint,
+];
+
+/// Types in the order of their encoding.
+const TYPES: [Type; 5] = [
+    Type::Noob,
+    Type::Troof,
+    Type::Numbr,
+    Type::Numbar,
+    Type::Yarn,
+];
+
+impl Bytecode {
+    /// Encodes the bytecode as the contents of a `.lolc` file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = Encoder::default();
+        body.str(&self.source);
+
+        body.len(self.constants.len());
+        for constant in &self.constants {
+            body.constant(constant);
+        }
+
+        body.len(self.names.len());
+        for name in &self.names {
+            body.str(&name.name);
+            body.span(&name.span);
+        }
+
+        body.len(self.functions.len());
+        for function in &self.functions {
+            body.u32(function.name);
+            body.len(function.params.len());
+            for param in &function.params {
+                body.u32(*param);
+            }
+            body.u32(function.entry);
+        }
+
+        body.len(self.code.len());
+        for (instruction, span) in self.code.iter().zip(&self.spans) {
+            body.instruction(instruction);
+            body.span(span);
+        }
+
+        let mut file = Encoder::default();
+        file.0.extend(MAGIC);
+        file.0.extend(FORMAT_VERSION.to_le_bytes());
+        file.i32(self.version.major);
+        file.i32(self.version.minor);
+        file.len(body.0.len());
+        file.u32(checksum(&body.0));
+        file.0.extend(body.0);
+        file.0
+    }
+
+    /// Decodes the contents of a `.lolc` file, failing if it is not one, was
+    /// written by an incompatible version of rlcc or has been corrupted.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Bytecode> {
+        ensure!(
+            bytes.starts_with(&MAGIC),
+            "not a compiled LOLCODE file (bad magic number)"
+        );
+        ensure!(bytes.len() >= HEADER_LEN, "file is truncated");
+
+        let mut header = Decoder::new(&bytes[MAGIC.len()..HEADER_LEN]);
+        let format = u16::from_le_bytes(header.array()?);
+        ensure!(
+            format == FORMAT_VERSION,
+            "format version {format} is not supported, expected {FORMAT_VERSION}"
+        );
+        let version = LolCodeVersion {
+            major: header.i32()?,
+            minor: header.i32()?,
+        };
+        ensure!(
+            version.is_supported(),
+            "compiled from LOLCODE {version}, which is not supported"
+        );
+        let len = header.len()?;
+        let expected = header.u32()?;
+
+        let body = &bytes[HEADER_LEN..];
+        ensure!(body.len() >= len, "file is truncated");
+        ensure!(body.len() == len, "unexpected data after the code section");
+        ensure!(
+            checksum(body) == expected,
+            "checksum does not match, the file is corrupted"
+        );
+
+        let mut body = Decoder::new(body);
+        let source = body.str()?;
+
+        let constants = (0..body.len()?)
+            .map(|_| body.constant())
+            .collect::<anyhow::Result<_>>()?;
+
+        let names = (0..body.len()?)
+            .map(|_| {
+                Ok(Ident {
+                    name: body.str()?,
+                    span: body.span()?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let functions = (0..body.len()?)
+            .map(|_| {
+                Ok(Function {
+                    name: body.u32()?,
+                    params: (0..body.len()?)
+                        .map(|_| body.u32())
+                        .collect::<anyhow::Result<_>>()?,
+                    entry: body.u32()?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut code = Vec::new();
+        let mut spans = Vec::new();
+        for _ in 0..body.len()? {
+            code.push(body.instruction()?);
+            spans.push(body.span()?);
+        }
+
+        let bytecode = Bytecode {
+            version,
+            source,
+            code,
+            spans,
+            constants,
+            names,
+            functions,
+        };
+        bytecode.validate()?;
+        Ok(bytecode)
+    }
+
+    /// Checks that every index refers to something that exists. What the
+    /// instructions do to the stack and frames is only known once they run,
+    /// so the VM reports popping more than was pushed or returning from the
+    /// main program as invalid bytecode.
+    fn validate(&self) -> anyhow::Result<()> {
+        let in_range = |kind: &str, index: u32, len: usize| {
+            ensure!((index as usize) < len, "{kind} {index} is out of range");
+            Ok(())
+        };
+        let name = |index| in_range("name", index, self.names.len());
+        let address = |index| in_range("address", index, self.code.len());
+
+        for function in &self.functions {
+            name(function.name)?;
+            function.params.iter().copied().try_for_each(name)?;
+            address(function.entry)?;
+        }
+
+        for instruction in &self.code {
+            match *instruction {
+                Instruction::Push(constant) => {
+                    in_range("constant", constant, self.constants.len())?
+                }
+                Instruction::Load(var)
+                | Instruction::Store(var)
+                | Instruction::Declare(var)
+                | Instruction::EnterLoop(var)
+                | Instruction::Include(var)
+                | Instruction::Call { name: var, .. } => name(var)?,
+                Instruction::CallNative {
+                    module, function, ..
+                } => {
+                    name(module)?;
+                    name(function)?;
+                }
+                Instruction::Jump(target)
+                | Instruction::JumpIfFalse(target)
+                | Instruction::JumpIfTrue(target)
+                | Instruction::JumpUnlessIt(target)
+                | Instruction::JumpIfIt(target) => address(target)?,
+                Instruction::Define(function) => {
+                    in_range("function", function, self.functions.len())?
+                }
+                _ => {}
+            }
+        }
+
+        // Execution must not run off the end
+        ensure!(
+            matches!(
+                self.code.last(),
+                Some(
+                    Instruction::Halt
+                        | Instruction::Jump(_)
+                        | Instruction::Return
+                        | Instruction::ReturnIt
+                        | Instruction::BreakOutside
+                        | Instruction::ReturnOutside
+                )
+            ),
+            "code section does not end the program"
+        );
+        Ok(())
+    }
+}
+
+/// 32-bit FNV-1a.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.0.extend(value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.0.extend(value.as_bytes());
+    }
+
+    fn span(&mut self, span: &Span) {
+        for value in [
+            span.start.line,
+            span.start.column,
+            span.end.line,
+            span.end.column,
+        ] {
+            self.len(value);
+        }
+    }
+
+    fn constant(&mut self, value: &Value) {
+        match value {
+            Value::Noob => self.u8(0),
+            Value::Troof(troof) => {
+                self.u8(1);
+                self.u8(u8::from(*troof));
+            }
+            Value::Numbr(numbr) => {
+                self.u8(2);
+                self.0.extend(numbr.to_le_bytes());
+            }
+            Value::Numbar(numbar) => {
+                self.u8(3);
+                self.0.extend(numbar.to_le_bytes());
+            }
+            Value::Yarn(yarn) => {
+                self.u8(4);
+                self.str(yarn);
+            }
+        }
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match *instruction {
+            Instruction::Push(constant) => {
+                self.u8(0);
+                self.u32(constant);
+            }
+            Instruction::Load(name) => {
+                self.u8(1);
+                self.u32(name);
+            }
+            Instruction::Store(name) => {
+                self.u8(2);
+                self.u32(name);
+            }
+            Instruction::Declare(name) => {
+                self.u8(3);
+                self.u32(name);
+            }
+            Instruction::SetIt => self.u8(4),
+            Instruction::Binary(op) => {
+                self.u8(5);
+                let index = BINARY_OPS.iter().position(|candidate| *candidate == op);
+                self.u8(index.expect("every operator is listed") as u8);
+            }
+            Instruction::Not => self.u8(6),
+            Instruction::All(count) => {
+                self.u8(7);
+                self.u32(count);
+            }
+            Instruction::Any(count) => {
+                self.u8(8);
+                self.u32(count);
+            }
+            Instruction::ToYarn(depth) => {
+                self.u8(9);
+                self.u32(depth);
+            }
+            Instruction::Concat(count) => {
+                self.u8(10);
+                self.u32(count);
+            }
+            Instruction::Cast(to) => {
+                self.u8(11);
+                let index = TYPES.iter().position(|candidate| *candidate == to);
+                self.u8(index.expect("every type is listed") as u8);
+            }
+            Instruction::Jump(address) => {
+                self.u8(12);
+                self.u32(address);
+            }
+            Instruction::JumpIfFalse(address) => {
+                self.u8(13);
+                self.u32(address);
+            }
+            Instruction::JumpIfTrue(address) => {
+                self.u8(14);
+                self.u32(address);
+            }
+            Instruction::JumpUnlessIt(address) => {
+                self.u8(15);
+                self.u32(address);
+            }
+            Instruction::JumpIfIt(address) => {
+                self.u8(16);
+                self.u32(address);
+            }
+            Instruction::EnterLoop(name) => {
+                self.u8(17);
+                self.u32(name);
+            }
+            Instruction::ExitLoop => self.u8(18),
+            Instruction::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                self.u8(19);
+                self.u32(args);
+                self.u8(u8::from(invisible) | u8::from(newline) << 1);
+            }
+            Instruction::Include(name) => {
+                self.u8(20);
+                self.u32(name);
+            }
+            Instruction::Define(function) => {
+                self.u8(21);
+                self.u32(function);
+            }
+            Instruction::Call { name, args } => {
+                self.u8(22);
+                self.u32(name);
+                self.u32(args);
+            }
+            Instruction::CallNative {
+                module,
+                function,
+                args,
+            } => {
+                self.u8(23);
+                self.u32(module);
+                self.u32(function);
+                self.u32(args);
+            }
+            Instruction::Return => self.u8(24),
+            Instruction::ReturnIt => self.u8(25),
+            Instruction::BreakOutside => self.u8(26),
+            Instruction::ReturnOutside => self.u8(27),
+            Instruction::Halt => self.u8(28),
+            Instruction::Gimmeh => self.u8(29),
+        }
+    }
+}
+
+struct Decoder<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Decoder<'b> {
+    fn new(bytes: &'b [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'b [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(len))
+            .context("file is truncated")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn len(&mut self) -> anyhow::Result<usize> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn str(&mut self) -> anyhow::Result<String> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        Ok(std::str::from_utf8(bytes)
+            .context("string is not valid UTF-8")?
+            .to_string())
+    }
+
+    fn span(&mut self) -> anyhow::Result<Span> {
+        Ok(Span {
+            start: Position {
+                line: self.len()?,
+                column: self.len()?,
+            },
+            end: Position {
+                line: self.len()?,
+                column: self.len()?,
+            },
+        })
+    }
+
+    fn constant(&mut self) -> anyhow::Result<Value> {
+        let value = match self.u8()? {
+            0 => Value::Noob,
+            1 => Value::Troof(self.u8()? != 0),
+            2 => Value::Numbr(i64::from_le_bytes(self.array()?)),
+            3 => Value::Numbar(f64::from_le_bytes(self.array()?)),
+            4 => Value::Yarn(self.str()?),
+            tag => bail!("unknown constant type {tag}"),
+        };
+        Ok(value)
+    }
+
+    fn instruction(&mut self) -> anyhow::Result<Instruction> {
+        let instruction = match self.u8()? {
+            0 => Instruction::Push(self.u32()?),
+            1 => Instruction::Load(self.u32()?),
+            2 => Instruction::Store(self.u32()?),
+            3 => Instruction::Declare(self.u32()?),
+            4 => Instruction::SetIt,
+            5 => {
+                let index = self.u8()?;
+                let op = BINARY_OPS.get(usize::from(index));
+                Instruction::Binary(*op.with_context(|| format!("unknown operator {index}"))?)
+            }
+            6 => Instruction::Not,
+            7 => Instruction::All(self.u32()?),
+            8 => Instruction::Any(self.u32()?),
+            9 => Instruction::ToYarn(self.u32()?),
+            10 => Instruction::Concat(self.u32()?),
+            11 => {
+                let index = self.u8()?;
+                let to = TYPES.get(usize::from(index));
+                Instruction::Cast(*to.with_context(|| format!("unknown type {index}"))?)
+            }
+            12 => Instruction::Jump(self.u32()?),
+            13 => Instruction::JumpIfFalse(self.u32()?),
+            14 => Instruction::JumpIfTrue(self.u32()?),
+            15 => Instruction::JumpUnlessIt(self.u32()?),
+            16 => Instruction::JumpIfIt(self.u32()?),
+            17 => Instruction::EnterLoop(self.u32()?),
+            18 => Instruction::ExitLoop,
+            19 => {
+                let args = self.u32()?;
+                let flags = self.u8()?;
+                Instruction::Visible {
+                    args,
+                    invisible: flags & 1 != 0,
+                    newline: flags & 2 != 0,
+                }
+            }
+            20 => Instruction::Include(self.u32()?),
+            21 => Instruction::Define(self.u32()?),
+            22 => Instruction::Call {
+                name: self.u32()?,
+                args: self.u32()?,
+            },
+            23 => Instruction::CallNative {
+                module: self.u32()?,
+                function: self.u32()?,
+                args: self.u32()?,
+            },
+            24 => Instruction::Return,
+            25 => Instruction::ReturnIt,
+            26 => Instruction::BreakOutside,
+            27 => Instruction::ReturnOutside,
+            28 => Instruction::Halt,
+            29 => Instruction::Gimmeh,
+            opcode => bail!("unknown instruction {opcode}"),
+        };
+        Ok(instruction)
+    }
+}
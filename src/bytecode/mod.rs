@@ -0,0 +1,129 @@
+//! Compact bytecode for a stack machine, as an alternative to walking the
+//! syntax tree. [`Compile`] produces it from a [`LolCodeProgram`] and
+//! [`RunBytecode`] executes it with the same observable behaviour as
+//! [`Interpret`](crate::Interpret).
+//!
+//! [`LolCodeProgram`]: crate::LolCodeProgram
+
+mod compiler;
+mod file;
+mod vm;
+
+pub use compiler::Compile;
+pub use file::{FORMAT_VERSION, MAGIC};
+pub use vm::RunBytecode;
+
+use crate::{
+    ast::{BinaryOp, Ident, Type},
+    diagnostic::Span,
+    parser::LolCodeVersion,
+    value::Value,
+};
+
+/// Index into [`Bytecode::constants`].
+pub type Constant = u32;
+/// Index into [`Bytecode::names`].
+pub type Name = u32;
+/// Index into [`Bytecode::code`].
+pub type Address = u32;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Instruction {
+    /// Pushes a constant.
+    Push(Constant),
+    /// Pushes the value of a variable, or `IT`.
+    Load(Name),
+    /// Pops a value into an existing variable, or `IT`.
+    Store(Name),
+    /// Pops a value into a new variable of the current frame.
+    Declare(Name),
+    /// Pops a value into `IT`.
+    SetIt,
+    /// Pops the right then the left operand and pushes the result.
+    Binary(BinaryOp),
+    Not,
+    /// Pops that many values and pushes whether all of them are WIN.
+    All(u32),
+    /// Pops that many values and pushes whether any of them is WIN.
+    Any(u32),
+    /// Casts the value that many places below the top of the stack to a YARN
+    /// in place, failing for NOOB.
+    ToYarn(u32),
+    /// Pops that many YARNs and pushes them joined together.
+    Concat(u32),
+    Cast(Type),
+    Jump(Address),
+    /// Pops a value and jumps if it is FAIL.
+    JumpIfFalse(Address),
+    /// Pops a value and jumps if it is WIN.
+    JumpIfTrue(Address),
+    /// Jumps if `IT` is FAIL, as at the start of `O RLY?`.
+    JumpUnlessIt(Address),
+    /// Pops a value and jumps if it is the same as `IT`, as for `OMG`.
+    JumpIfIt(Address),
+    /// Starts a loop over the variable, declaring it for the duration of the
+    /// loop if it does not exist yet.
+    EnterLoop(Name),
+    /// Removes the variable of the innermost loop if it was declared by it.
+    ExitLoop,
+    /// Pushes a line of input as a YARN.
+    Gimmeh,
+    /// Pops that many values and writes them on a line of output.
+    Visible {
+        args: u32,
+        invisible: bool,
+        newline: bool,
+    },
+    /// Loads a native module.
+    Include(Name),
+    /// Makes a function available to calls from here on.
+    Define(u32),
+    /// Pops the arguments and calls the function with that name.
+    Call {
+        name: Name,
+        args: u32,
+    },
+    /// Pops the arguments and calls a function of a native module.
+    CallNative {
+        module: Name,
+        function: Name,
+        args: u32,
+    },
+    /// Pops a value and returns it from the current function.
+    Return,
+    /// Returns `IT` from the current function.
+    ReturnIt,
+    /// `GTFO` with nothing to leave.
+    BreakOutside,
+    /// `FOUND YR` outside of a function.
+    ReturnOutside,
+    /// Ends the program.
+    Halt,
+}
+
+/// A function defined with `HOW IZ I`.
+#[derive(Debug, PartialEq, Clone)]
+#[jsm::public]
+pub struct Function {
+    name: Name,
+    params: Vec<Name>,
+    entry: Address,
+}
+
+/// A compiled program. The main program starts at the first instruction and
+/// function bodies follow it.
+#[derive(Debug, PartialEq, Clone)]
+#[jsm::public]
+pub struct Bytecode {
+    /// Version the source declared.
+    version: LolCodeVersion,
+    /// Name of the source it was compiled from, for diagnostics.
+    source: String,
+    code: Vec<Instruction>,
+    /// Source of each instruction, for diagnostics.
+    spans: Vec<Span>,
+    constants: Vec<Value>,
+    /// Variables, functions and modules, with where they were named.
+    names: Vec<Ident>,
+    functions: Vec<Function>,
+}
@@ -0,0 +1,354 @@
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Context;
+
+use crate::{
+    ast::Ident,
+    diagnostic::{Diagnostic, ErrorCode, RuntimeError, Span},
+    framework::{HandleTokenProcessingError, StdErr, StdIn, StdOut},
+    interpreter::{
+        argument_count, break_outside, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+    value::Value,
+};
+
+use super::{Bytecode, Function, Instruction, Name};
+
+pub trait RunBytecode {
+    fn run_bytecode(&mut self, bytecode: &Bytecode) -> anyhow::Result<()>;
+}
+
+impl<T> RunBytecode for T
+where
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
+{
+    fn run_bytecode(&mut self, bytecode: &Bytecode) -> anyhow::Result<()> {
+        Vm {
+            app: self,
+            bytecode,
+            stack: Vec::new(),
+            frames: vec![Frame::default()],
+            functions: HashMap::new(),
+        }
+        .run()
+    }
+}
+
+/// Variables of the main program or of a single function call.
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Value>,
+    it: Value,
+    /// For each loop being run, the variable it declared, if any.
+    loops: Vec<Option<String>>,
+    /// Where to carry on once the function returns.
+    ret: usize,
+}
+
+impl Frame {
+    fn get(&self, name: &str) -> Option<&Value> {
+        match name {
+            "IT" => Some(&self.it),
+            name => self.vars.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match name {
+            "IT" => Some(&mut self.it),
+            name => self.vars.get_mut(name),
+        }
+    }
+}
+
+struct Vm<'a, 'b, T> {
+    app: &'a mut T,
+    bytecode: &'b Bytecode,
+    stack: Vec<Value>,
+    /// The main program at the bottom, then a frame per call.
+    frames: Vec<Frame>,
+    /// Functions defined so far.
+    functions: HashMap<&'b str, &'b Function>,
+}
+
+impl<'b, T> Vm<'_, 'b, T>
+where
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
+{
+    /// Reports `diagnostic` and stops execution.
+    fn fail<R>(&mut self, diagnostic: Diagnostic) -> anyhow::Result<R> {
+        self.app.emit(diagnostic)?;
+        Err(RuntimeError.into())
+    }
+
+    /// Unwraps the result of a value operation, reporting errors at `span`.
+    fn check<R>(&mut self, result: Result<R, Diagnostic>, span: Span) -> anyhow::Result<R> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(diagnostic) => self.fail(diagnostic.with_span(span)),
+        }
+    }
+
+    fn name(&self, name: Name) -> &'b Ident {
+        &self.bytecode.names[name as usize]
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("the main program has a frame")
+    }
+
+    /// Reports bytecode the compiler would not have written, which the
+    /// loader cannot rule out without running it.
+    fn invalid<R>(&mut self, message: &str) -> anyhow::Result<R> {
+        self.fail(Diagnostic::error(
+            ErrorCode::InvalidBytecode,
+            format!("Invalid bytecode: {message}"),
+        ))
+    }
+
+    fn pop(&mut self) -> anyhow::Result<Value> {
+        match self.stack.pop() {
+            Some(value) => Ok(value),
+            None => self.invalid("pop from an empty stack"),
+        }
+    }
+
+    /// The top `count` values, in the order they were pushed.
+    fn pop_many(&mut self, count: u32) -> anyhow::Result<Vec<Value>> {
+        match self.stack.len().checked_sub(count as usize) {
+            Some(start) => Ok(self.stack.split_off(start)),
+            None => self.invalid(&format!(
+                "pop of {count} values from a stack of {}",
+                self.stack.len()
+            )),
+        }
+    }
+
+    /// Leaves the frame of the function being run.
+    fn pop_frame(&mut self) -> anyhow::Result<Frame> {
+        match self.frames.len() {
+            1 => self.invalid("return from outside of a function"),
+            _ => Ok(self.frames.pop().expect("a frame per call")),
+        }
+    }
+
+    fn run(&mut self) -> anyhow::Result<()> {
+        let mut pc = 0;
+        loop {
+            let instruction = self.bytecode.code[pc];
+            let span = self.bytecode.spans[pc];
+            pc += 1;
+
+            match instruction {
+                Instruction::Push(constant) => {
+                    let value = self.bytecode.constants[constant as usize].clone();
+                    self.stack.push(value);
+                }
+                Instruction::Load(name) => {
+                    let ident = self.name(name);
+                    match self.frame().get(&ident.name) {
+                        Some(value) => {
+                            let value = value.clone();
+                            self.stack.push(value);
+                        }
+                        None => return self.fail(undefined_variable(ident)),
+                    }
+                }
+                Instruction::Store(name) => {
+                    let ident = self.name(name);
+                    let value = self.pop()?;
+                    match self.frame().get_mut(&ident.name) {
+                        Some(var) => *var = value,
+                        None => return self.fail(undefined_variable(ident)),
+                    }
+                }
+                Instruction::Declare(name) => {
+                    let ident = self.name(name);
+                    let value = self.pop()?;
+                    self.frame().vars.insert(ident.name.clone(), value);
+                }
+                Instruction::SetIt => {
+                    let value = self.pop()?;
+                    self.frame().it = value;
+                }
+                Instruction::Binary(op) => {
+                    let right = self.pop()?;
+                    let left = self.pop()?;
+                    let value = self.check(left.binary(op, &right), span)?;
+                    self.stack.push(value);
+                }
+                Instruction::Not => {
+                    let value = self.pop()?;
+                    self.stack.push(Value::Troof(!value.to_troof()));
+                }
+                Instruction::All(count) => {
+                    let values = self.pop_many(count)?;
+                    self.stack
+                        .push(Value::Troof(values.iter().all(Value::to_troof)));
+                }
+                Instruction::Any(count) => {
+                    let values = self.pop_many(count)?;
+                    self.stack
+                        .push(Value::Troof(values.iter().any(Value::to_troof)));
+                }
+                Instruction::ToYarn(depth) => {
+                    let Some(index) = self.stack.len().checked_sub(1 + depth as usize) else {
+                        return self.invalid(&format!(
+                            "conversion {depth} deep in a stack of {}",
+                            self.stack.len()
+                        ));
+                    };
+                    let yarn = self.check(self.stack[index].to_yarn(), span)?;
+                    self.stack[index] = Value::Yarn(yarn);
+                }
+                Instruction::Concat(count) => {
+                    let yarn = self.pop_many(count)?.iter().map(Value::to_string).collect();
+                    self.stack.push(Value::Yarn(yarn));
+                }
+                Instruction::Cast(to) => {
+                    let value = self.pop()?;
+                    let value = self.check(value.cast(to), span)?;
+                    self.stack.push(value);
+                }
+                Instruction::Jump(address) => pc = address as usize,
+                Instruction::JumpIfFalse(address) => {
+                    if !self.pop()?.to_troof() {
+                        pc = address as usize;
+                    }
+                }
+                Instruction::JumpIfTrue(address) => {
+                    if self.pop()?.to_troof() {
+                        pc = address as usize;
+                    }
+                }
+                Instruction::JumpUnlessIt(address) => {
+                    if !self.frame().it.to_troof() {
+                        pc = address as usize;
+                    }
+                }
+                Instruction::JumpIfIt(address) => {
+                    let literal = self.pop()?;
+                    if literal.saem(&self.frame().it) {
+                        pc = address as usize;
+                    }
+                }
+                Instruction::EnterLoop(name) => {
+                    // A loop variable that does not exist yet only lives for the loop
+                    let var = &self.name(name).name;
+                    let frame = self.frame();
+                    let temporary = frame.get(var).is_none();
+                    if temporary {
+                        frame.vars.insert(var.clone(), Value::Numbr(0));
+                    }
+                    frame.loops.push(temporary.then(|| var.clone()));
+                }
+                Instruction::ExitLoop => {
+                    let frame = self.frame();
+                    if let Some(var) = frame.loops.pop().flatten() {
+                        frame.vars.remove(&var);
+                    }
+                }
+                Instruction::Gimmeh => {
+                    let line = self.app.read_line()?;
+                    self.stack.push(Value::Yarn(line));
+                }
+                Instruction::Visible {
+                    args,
+                    invisible,
+                    newline,
+                } => {
+                    let mut line: String =
+                        self.pop_many(args)?.iter().map(Value::to_string).collect();
+                    if newline {
+                        line.push('\n');
+                    }
+
+                    match invisible {
+                        true => {
+                            write!(self.app.err(), "{line}").context("write to error output")?
+                        }
+                        false => write!(self.app.out(), "{line}").context("write to output")?,
+                    }
+                }
+                Instruction::Include(name) => {
+                    let module = self.name(name);
+                    if let Err(err) = self.app.modules().load(&module.name) {
+                        return self.fail(
+                            Diagnostic::error(ErrorCode::UnknownModule, err.to_string())
+                                .with_span(module.span),
+                        );
+                    }
+                }
+                Instruction::Define(index) => {
+                    let function = &self.bytecode.functions[index as usize];
+                    let name = &self.name(function.name).name;
+                    self.functions.insert(name, function);
+                }
+                Instruction::Call { name, args } => {
+                    let ident = self.name(name);
+                    let Some(function) = self.functions.get(ident.name.as_str()).copied() else {
+                        return self.fail(undefined_function(ident));
+                    };
+                    if function.params.len() != args as usize {
+                        let name = self.name(function.name);
+                        let params = function.params.len();
+                        return self.fail(argument_count(span, name, params, args as usize));
+                    }
+
+                    // Functions only see their own arguments, not the caller's variables
+                    let values = self.pop_many(args)?;
+                    let vars = function
+                        .params
+                        .iter()
+                        .map(|param| self.name(*param).name.clone())
+                        .zip(values)
+                        .collect();
+                    self.frames.push(Frame {
+                        vars,
+                        ret: pc,
+                        ..Frame::default()
+                    });
+                    pc = function.entry as usize;
+                }
+                Instruction::CallNative {
+                    module,
+                    function,
+                    args,
+                } => {
+                    let (module, function) = (self.name(module), self.name(function));
+                    let values = self.pop_many(args)?;
+                    match self
+                        .app
+                        .modules()
+                        .call(&module.name, &function.name, values)
+                    {
+                        Ok(value) => self.stack.push(value),
+                        Err(err) => {
+                            return self.fail(
+                                Diagnostic::error(ErrorCode::NativeCall, format!("{err:#}"))
+                                    .with_span(span),
+                            )
+                        }
+                    }
+                }
+                Instruction::Return => {
+                    let value = self.pop()?;
+                    let frame = self.pop_frame()?;
+                    self.stack.push(value);
+                    pc = frame.ret;
+                }
+                Instruction::ReturnIt => {
+                    let frame = self.pop_frame()?;
+                    self.stack.push(frame.it);
+                    pc = frame.ret;
+                }
+                Instruction::BreakOutside => return self.fail(break_outside(span)),
+                Instruction::ReturnOutside => return self.fail(return_outside(span)),
+                Instruction::Halt => return Ok(()),
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Static checks on a parsed program, finding what would otherwise only fail
+//! once the interpreter reaches it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, Stmt, StmtKind},
+    diagnostic::{CompileError, Diagnostic, ErrorCode},
+    framework::HandleTokenProcessingError,
+    interpreter::{
+        argument_count, break_outside, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+};
+
+pub trait Check {
+    /// Reports every problem in `prog` that can be found without running it,
+    /// failing with [`CompileError`] if there were any.
+    fn check(&mut self, prog: &LolCodeProgram) -> anyhow::Result<()>;
+}
+
+impl<T> Check for T
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn check(&mut self, prog: &LolCodeProgram) -> anyhow::Result<()> {
+        let functions = prog
+            .body
+            .walk()
+            .into_iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::FuncDef(func) => Some((func.name.name.as_str(), func)),
+                _ => None,
+            })
+            .collect();
+
+        let mut checker = Checker {
+            app: self,
+            functions,
+            included: HashSet::new(),
+            failed: false,
+        };
+        checker.block(&prog.body, &mut Scope::default())?;
+
+        match checker.failed {
+            true => Err(CompileError.into()),
+            false => Ok(()),
+        }
+    }
+}
+
+/// What is known about the variables of the main program or a function.
+#[derive(Default)]
+struct Scope<'p> {
+    /// Variables declared by any statement so far. Declarations in branches
+    /// that may not run still count so only certain mistakes are reported.
+    vars: HashSet<&'p str>,
+    in_function: bool,
+    /// Loops and `WTF?` blocks that `GTFO` may leave.
+    breakable: usize,
+}
+
+struct Checker<'a, 'p, T> {
+    app: &'a mut T,
+    /// Every function in the program, wherever it is defined.
+    functions: HashMap<&'p str, &'p FuncDef>,
+    /// Modules included with `CAN HAS` so far.
+    included: HashSet<&'p str>,
+    failed: bool,
+}
+
+impl<'p, T> Checker<'_, 'p, T>
+where
+    T: Modules + HandleTokenProcessingError,
+{
+    fn report(&mut self, diagnostic: Diagnostic) -> anyhow::Result<()> {
+        self.failed = true;
+        self.app.emit(diagnostic)
+    }
+
+    fn block(&mut self, block: &'p Block, scope: &mut Scope<'p>) -> anyhow::Result<()> {
+        for stmt in &block.stmts {
+            self.stmt(stmt, scope)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt, scope: &mut Scope<'p>) -> anyhow::Result<()> {
+        match &stmt.kind {
+            StmtKind::Visible { args, .. } => {
+                for arg in args {
+                    self.expr(arg, scope)?;
+                }
+            }
+            StmtKind::CanHas { module } => match self.app.modules().functions(&module.name) {
+                Some(_) => {
+                    self.included.insert(&module.name);
+                }
+                None => self.report(
+                    Diagnostic::error(
+                        ErrorCode::UnknownModule,
+                        format!("Unknown module {}", module.name),
+                    )
+                    .with_span(module.span),
+                )?,
+            },
+            StmtKind::Declare { name, init } => {
+                if let Some(init) = init {
+                    self.expr(init, scope)?;
+                }
+                scope.vars.insert(&name.name);
+            }
+            StmtKind::Assign { name, value } => {
+                self.expr(value, scope)?;
+                self.var(name, scope)?;
+            }
+            StmtKind::Gimmeh(name) | StmtKind::CastVar { name, .. } => self.var(name, scope)?,
+            StmtKind::Expr(expr) => self.expr(expr, scope)?,
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                self.block(then, scope)?;
+                for (condition, block) in elifs {
+                    self.expr(condition, scope)?;
+                    self.block(block, scope)?;
+                }
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise, scope)?;
+                }
+            }
+            StmtKind::Switch { cases, default } => {
+                scope.breakable += 1;
+                for (literal, block) in cases {
+                    self.expr(literal, scope)?;
+                    self.block(block, scope)?;
+                }
+                if let Some(default) = default {
+                    self.block(default, scope)?;
+                }
+                scope.breakable -= 1;
+            }
+            StmtKind::Loop(lp) => {
+                // The loop declares its variable if it does not exist yet
+                let temporary = lp
+                    .update
+                    .as_ref()
+                    .map(|update| update.var.name.as_str())
+                    .filter(|var| *var != "IT" && scope.vars.insert(*var));
+
+                if let Some(LoopCondition::Til(condition) | LoopCondition::Wile(condition)) =
+                    &lp.condition
+                {
+                    self.expr(condition, scope)?;
+                }
+                scope.breakable += 1;
+                self.block(&lp.body, scope)?;
+                scope.breakable -= 1;
+
+                if let Some(var) = temporary {
+                    scope.vars.remove(var);
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let mut scope = Scope {
+                    vars: func
+                        .params
+                        .iter()
+                        .map(|param| param.name.as_str())
+                        .collect(),
+                    in_function: true,
+                    breakable: 0,
+                };
+                self.block(&func.body, &mut scope)?;
+            }
+            StmtKind::Return(expr) => {
+                if !scope.in_function {
+                    self.report(return_outside(stmt.span))?;
+                }
+                self.expr(expr, scope)?;
+            }
+            StmtKind::Break => {
+                if scope.breakable == 0 && !scope.in_function {
+                    self.report(break_outside(stmt.span))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn var(&mut self, name: &Ident, scope: &Scope) -> anyhow::Result<()> {
+        if name.name != "IT" && !scope.vars.contains(name.name.as_str()) {
+            self.report(undefined_variable(name))?;
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &'p Expr, scope: &Scope) -> anyhow::Result<()> {
+        match &expr.kind {
+            ExprKind::Noob
+            | ExprKind::Troof(_)
+            | ExprKind::Numbr(_)
+            | ExprKind::Numbar(_)
+            | ExprKind::Yarn(_) => {}
+            ExprKind::Var(var) => self.var(var, scope)?,
+            ExprKind::Binary { left, right, .. } => {
+                self.expr(left, scope)?;
+                self.expr(right, scope)?;
+            }
+            ExprKind::Not(operand) | ExprKind::Cast { expr: operand, .. } => {
+                self.expr(operand, scope)?
+            }
+            ExprKind::Nary { args, .. } => {
+                for arg in args {
+                    self.expr(arg, scope)?;
+                }
+            }
+            ExprKind::Call { module, name, args } => {
+                for arg in args {
+                    self.expr(arg, scope)?;
+                }
+                match module {
+                    Some(module) => self.native_call(expr, module, name)?,
+                    None => match self.functions.get(name.name.as_str()).copied() {
+                        None => self.report(undefined_function(name))?,
+                        Some(func) if func.params.len() != args.len() => self.report(
+                            argument_count(expr.span, &func.name, func.params.len(), args.len()),
+                        )?,
+                        Some(_) => {}
+                    },
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn native_call(&mut self, call: &Expr, module: &Ident, name: &Ident) -> anyhow::Result<()> {
+        if !self.included.contains(module.name.as_str()) {
+            return self.report(
+                Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has not been loaded", module.name),
+                )
+                .with_span(module.span)
+                .with_help(format!("Include it first with CAN HAS {}?", module.name)),
+            );
+        }
+        let functions = self.app.modules().functions(&module.name);
+        if !functions.is_some_and(|functions| functions.contains(&name.name.as_str())) {
+            return self.report(
+                Diagnostic::error(
+                    ErrorCode::NativeCall,
+                    format!("Module {} has no function {}", module.name, name.name),
+                )
+                .with_span(call.span),
+            );
+        }
+        Ok(())
+    }
+}
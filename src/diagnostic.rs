@@ -0,0 +1,620 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs,
+    io::{self, Write},
+    iter,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use derive_more::Display;
+use serde::Serialize;
+
+use crate::tokenizer::{Token, TokenLocation};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[display(fmt = "error")]
+    Error,
+    #[display(fmt = "warning")]
+    Warning,
+    #[display(fmt = "note")]
+    Note,
+}
+
+/// Stable identifier for each kind of diagnostic. The codes are part of the
+/// public interface so once assigned they must not be reused.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnexpectedToken,
+    MissingHai,
+    InvalidVersion,
+    UnterminatedString,
+    InvalidLineContinuation,
+    ExpectedNewline,
+    InvalidInclude,
+    UnknownModule,
+    UnexpectedEof,
+    UnsupportedVersion,
+    FeatureRequiresVersion,
+    UndefinedVariable,
+    UndefinedFunction,
+    ArgumentCount,
+    NativeCall,
+    ReturnOutsideFunction,
+    InvalidEscape,
+    InvalidCast,
+    DivisionByZero,
+    IncompleteKeyword,
+    UnreadableSource,
+    InvalidBytecode,
+    UnsupportedByBackend,
+    UnsupportedFeature,
+    BreakOutsideLoop,
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0001",
+            ErrorCode::UnexpectedToken => "E0002",
+            ErrorCode::MissingHai => "E0003",
+            ErrorCode::InvalidVersion => "E0004",
+            ErrorCode::UnterminatedString => "E0005",
+            ErrorCode::InvalidLineContinuation => "E0006",
+            ErrorCode::ExpectedNewline => "E0007",
+            ErrorCode::InvalidInclude => "E0008",
+            ErrorCode::UnknownModule => "E0009",
+            ErrorCode::UnexpectedEof => "E0010",
+            ErrorCode::UnsupportedVersion => "E0011",
+            ErrorCode::FeatureRequiresVersion => "E0012",
+            ErrorCode::UndefinedVariable => "E0013",
+            ErrorCode::UndefinedFunction => "E0014",
+            ErrorCode::ArgumentCount => "E0015",
+            ErrorCode::NativeCall => "E0016",
+            ErrorCode::ReturnOutsideFunction => "E0017",
+            ErrorCode::InvalidEscape => "E0018",
+            ErrorCode::InvalidCast => "E0019",
+            ErrorCode::DivisionByZero => "E0020",
+            ErrorCode::IncompleteKeyword => "E0021",
+            ErrorCode::UnreadableSource => "E0022",
+            ErrorCode::InvalidBytecode => "E0023",
+            ErrorCode::UnsupportedByBackend => "E0024",
+            ErrorCode::UnsupportedFeature => "E0025",
+            ErrorCode::BreakOutsideLoop => "E0026",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[jsm::public]
+pub struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl From<&TokenLocation> for Position {
+    fn from(value: &TokenLocation) -> Self {
+        Position {
+            line: value.line,
+            column: value.column,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[jsm::public]
+pub struct Span {
+    start: Position,
+    end: Position,
+}
+
+/// `line:column-line:column`, as in dumps of the program.
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}:{}",
+            self.start.line, self.start.column, self.end.line, self.end.column
+        )
+    }
+}
+
+impl Span {
+    /// Span covering `self` through the end of `end`.
+    pub fn to(self, end: Span) -> Span {
+        Span {
+            start: self.start,
+            end: end.end,
+        }
+    }
+}
+
+impl From<Position> for Span {
+    fn from(value: Position) -> Self {
+        Span {
+            start: value,
+            end: value,
+        }
+    }
+}
+
+impl From<&Token> for Span {
+    fn from(value: &Token) -> Self {
+        Span {
+            start: Position::from(&value.start),
+            end: Position::from(&value.end),
+        }
+    }
+}
+
+impl From<Token> for Span {
+    fn from(value: Token) -> Self {
+        Span::from(&value)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[jsm::public]
+pub struct Label {
+    span: Span,
+    message: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[jsm::public]
+pub struct Diagnostic {
+    severity: Severity,
+    code: ErrorCode,
+    message: String,
+    /// Primary location. Only absent for diagnostics raised after the source
+    /// locations have been discarded.
+    span: Option<Span>,
+    labels: Vec<Label>,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new<M>(severity: Severity, code: ErrorCode, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn error<M>(code: ErrorCode, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning<M>(code: ErrorCode, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn note<M>(code: ErrorCode, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        Self::new(Severity::Note, code, message)
+    }
+
+    pub fn with_span<S>(mut self, span: S) -> Self
+    where
+        S: Into<Span>,
+    {
+        self.span = Some(span.into());
+        self
+    }
+
+    pub fn with_label<S, M>(mut self, span: S, message: M) -> Self
+    where
+        S: Into<Span>,
+        M: Into<String>,
+    {
+        self.labels.push(Label {
+            span: span.into(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_help<M>(mut self, help: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+/// Source text, and the name diagnostics give its locations in.
+#[derive(Debug, Clone)]
+#[jsm::public]
+pub struct SourceFile {
+    name: String,
+    text: String,
+}
+
+impl SourceFile {
+    pub fn new<N, T>(name: N, text: T) -> Self
+    where
+        N: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            text: text.into(),
+        }
+    }
+
+    pub fn read<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        Ok(Self::new(
+            path.display().to_string(),
+            fs::read_to_string(path)?,
+        ))
+    }
+}
+
+/// Lines of `text`, counting them the way the tokenizer does: `\r\n`, `\r`
+/// and `\n` each end a line.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(end) = rest.find(['\r', '\n']) else {
+            return Some(std::mem::take(&mut rest));
+        };
+        let newline = match rest[end..].starts_with("\r\n") {
+            true => 2,
+            false => 1,
+        };
+        let line = &rest[..end];
+        rest = &rest[end + newline..];
+        Some(line)
+    })
+}
+
+impl From<String> for SourceFile {
+    fn from(value: String) -> Self {
+        SourceFile::new("<source>", value)
+    }
+}
+
+impl From<&str> for SourceFile {
+    fn from(value: &str) -> Self {
+        SourceFile::new("<source>", value)
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+const BOLD_BLUE: &str = "\x1b[1;34m";
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ErrorFormat {
+    /// Source snippets for people
+    #[default]
+    Human,
+    /// One JSON object per line for tools
+    Json,
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'a> {
+    line: usize,
+    column: usize,
+    end_line: usize,
+    end_column: usize,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    file: Option<&'a str>,
+    line: Option<usize>,
+    column: Option<usize>,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+    severity: Severity,
+    code: &'static str,
+    message: &'a str,
+    help: Option<&'a str>,
+    labels: Vec<JsonLabel<'a>>,
+}
+
+/// Recently read lines of a source kept by the [`Emitter`]. Diagnostics
+/// reported while parsing point at these, and the window keeps memory use
+/// bounded however long the source is.
+const SOURCE_WINDOW: usize = 64;
+
+/// Where the [`Emitter`] finds lines of the source outside of its window.
+#[derive(Debug, Default)]
+enum Origin {
+    /// Read once, e.g. from standard input, so older lines are gone.
+    #[default]
+    Stream,
+    /// Text given in full, which is kept anyway.
+    Text(Rc<str>),
+    /// A file, read again for the lines diagnostics quote.
+    File(PathBuf),
+}
+
+/// Renders diagnostics for the error writer. Every diagnostic reported by the
+/// tokenizer, parser and interpreter goes through here.
+#[derive(Debug, Default)]
+pub struct Emitter {
+    format: ErrorFormat,
+    color: bool,
+    /// Name of the source, for locations.
+    name: Option<String>,
+    origin: Origin,
+    /// The last [`SOURCE_WINDOW`] lines read, with their line numbers.
+    window: VecDeque<(usize, String)>,
+}
+
+impl Emitter {
+    pub fn set_format(&mut self, format: ErrorFormat) {
+        self.format = format;
+    }
+
+    pub fn set_color(&mut self, color: bool) {
+        self.color = color;
+    }
+
+    /// Quotes `source`, whose text is given in full.
+    pub fn set_source(&mut self, source: SourceFile) {
+        self.set_text(source.name, source.text.into());
+    }
+
+    /// Quotes `text`, shared with whoever is reading it.
+    pub(crate) fn set_text(&mut self, name: String, text: Rc<str>) {
+        self.name = Some(name);
+        self.origin = Origin::Text(text);
+        self.window.clear();
+    }
+
+    /// Quotes the file at `path`, reading it again for lines no longer in
+    /// the window.
+    pub fn set_file(&mut self, path: &Path) {
+        self.name = Some(path.display().to_string());
+        self.origin = Origin::File(path.to_path_buf());
+        self.window.clear();
+    }
+
+    /// Quotes a source called `name` that can only be read once, so only
+    /// the lines still in the window.
+    pub fn set_stream(&mut self, name: String) {
+        self.name = Some(name);
+        self.origin = Origin::Stream;
+        self.window.clear();
+    }
+
+    /// Adds source read as the lexer goes, `text` starting at line `line`.
+    pub fn extend_source(&mut self, line: usize, text: &str) {
+        for (line, text) in (line..).zip(lines(text)) {
+            if self.window.len() == SOURCE_WINDOW {
+                self.window.pop_front();
+            }
+            self.window.push_back((line, text.to_owned()));
+        }
+    }
+
+    /// Text of line `line` of the source, if it can still be found.
+    fn line(&self, line: usize) -> Option<String> {
+        if let Some((_, text)) = self.window.iter().find(|(number, _)| *number == line) {
+            return Some(text.clone());
+        }
+        let index = line.checked_sub(1)?;
+        match &self.origin {
+            Origin::Stream => None,
+            Origin::Text(text) => lines(text).nth(index).map(str::to_owned),
+            Origin::File(path) => {
+                let text = fs::read_to_string(path).ok()?;
+                let line = lines(&text).nth(index)?.to_owned();
+                Some(line)
+            }
+        }
+    }
+
+    fn paint(&self, style: &'static str, text: &str) -> String {
+        match self.color {
+            true => format!("{style}{text}{RESET}"),
+            false => text.to_string(),
+        }
+    }
+
+    pub fn emit<W>(&self, w: &mut W, diagnostic: &Diagnostic) -> io::Result<()>
+    where
+        W: Write,
+    {
+        match self.format {
+            ErrorFormat::Human => self.emit_human(w, diagnostic),
+            ErrorFormat::Json => self.emit_json(w, diagnostic),
+        }
+    }
+
+    fn emit_json<W>(&self, w: &mut W, diagnostic: &Diagnostic) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let span = diagnostic.span.as_ref();
+        let json = JsonDiagnostic {
+            file: self.name.as_deref(),
+            line: span.map(|span| span.start.line),
+            column: span.map(|span| span.start.column),
+            end_line: span.map(|span| span.end.line),
+            end_column: span.map(|span| span.end.column),
+            severity: diagnostic.severity,
+            code: diagnostic.code.code(),
+            message: &diagnostic.message,
+            help: diagnostic.help.as_deref(),
+            labels: diagnostic
+                .labels
+                .iter()
+                .map(|label| JsonLabel {
+                    line: label.span.start.line,
+                    column: label.span.start.column,
+                    end_line: label.span.end.line,
+                    end_column: label.span.end.column,
+                    message: &label.message,
+                })
+                .collect(),
+        };
+        serde_json::to_writer(&mut *w, &json)?;
+        writeln!(w)
+    }
+
+    fn emit_human<W>(&self, w: &mut W, diagnostic: &Diagnostic) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let style = match diagnostic.severity {
+            Severity::Error => BOLD_RED,
+            Severity::Warning => BOLD_YELLOW,
+            Severity::Note => BOLD_CYAN,
+        };
+        let header = format!("{}[{}]", diagnostic.severity, diagnostic.code);
+        writeln!(
+            w,
+            "{}{}",
+            self.paint(style, &header),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        )?;
+
+        let gutter_width = diagnostic
+            .span
+            .iter()
+            .chain(diagnostic.labels.iter().map(|label| &label.span))
+            .map(|span| span.start.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(gutter_width);
+
+        if let Some(span) = &diagnostic.span {
+            let name = self.name.as_deref().unwrap_or("<source>");
+            writeln!(
+                w,
+                "{gutter}{} {name}:{}:{}",
+                self.paint(BOLD_BLUE, "-->"),
+                span.start.line,
+                span.start.column
+            )?;
+            self.snippet(w, &gutter, span, '^', style, None)?;
+        }
+        for label in &diagnostic.labels {
+            self.snippet(
+                w,
+                &gutter,
+                &label.span,
+                '-',
+                BOLD_BLUE,
+                Some(&label.message),
+            )?;
+        }
+        if let Some(help) = &diagnostic.help {
+            writeln!(w, "{gutter} {} {help}", self.paint(BOLD_BLUE, "= help:"))?;
+        }
+        Ok(())
+    }
+
+    /// Quotes the first line of `span` and underlines the spanned columns.
+    fn snippet<W>(
+        &self,
+        w: &mut W,
+        gutter: &str,
+        span: &Span,
+        underline: char,
+        style: &'static str,
+        message: Option<&str>,
+    ) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let Some(line) = self.line(span.start.line) else {
+            return Ok(());
+        };
+        // Tokens count a tab as a single column so keep it that way here.
+        let line = line.replace('\t', " ");
+        let start = span.start.column.max(1);
+        let end = match span.end.line == span.start.line {
+            true => span.end.column.max(start),
+            false => line.chars().count().max(start),
+        };
+        let pipe = self.paint(BOLD_BLUE, "|");
+        let underline = format!(
+            "{}{}",
+            " ".repeat(start - 1),
+            underline.to_string().repeat(end - start + 1)
+        );
+        let underline = match message {
+            Some(message) => format!("{underline} {message}"),
+            None => underline,
+        };
+
+        writeln!(w, "{gutter} {pipe}")?;
+        writeln!(
+            w,
+            "{} {pipe} {line}",
+            self.paint(
+                BOLD_BLUE,
+                &format!("{:>width$}", span.start.line, width = gutter.len())
+            )
+        )?;
+        writeln!(w, "{gutter} {pipe} {}", self.paint(style, &underline))
+    }
+}
+
+/// Returned once the front end has reported errors through
+/// [`HandleTokenProcessingError`](crate::HandleTokenProcessingError).
+#[derive(Debug)]
+pub struct CompileError;
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Something went wrong when compiling.")
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Returned when execution stopped because of a reported error.
+#[derive(Debug)]
+pub struct RuntimeError;
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Something went wrong when running.")
+    }
+}
+
+impl std::error::Error for RuntimeError {}
@@ -0,0 +1,271 @@
+//! Printing the tokens or syntax tree of a program instead of running it, for
+//! debugging the front end and diffing in tests.
+
+use std::io::Write;
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{
+    ast::{Block, Expr, ExprKind, LolCodeProgram, LoopCondition, Stmt, StmtKind},
+    diagnostic::Span,
+    framework::StdOut,
+    tokenizer::{Token, TokenType},
+};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum DumpFormat {
+    /// One line per token or node, indented by depth
+    #[default]
+    Human,
+    /// JSON for tools
+    Json,
+}
+
+pub trait Dump {
+    /// Writes `token` to the output on a line of its own, with its location.
+    fn dump_token(&mut self, token: &Token, format: DumpFormat) -> anyhow::Result<()>;
+
+    /// Writes the tree of `prog` to the output.
+    fn dump_ast(&mut self, prog: &LolCodeProgram, format: DumpFormat) -> anyhow::Result<()>;
+}
+
+impl<T> Dump for T
+where
+    T: StdOut,
+{
+    fn dump_token(&mut self, token: &Token, format: DumpFormat) -> anyhow::Result<()> {
+        let out = self.out();
+        let (kind, text) = describe(&token.t_type);
+        match format {
+            DumpFormat::Human => {
+                let location = Span::from(token).to_string();
+                match text {
+                    Some(text) => writeln!(out, "{location:<16}{kind} {text}"),
+                    None => writeln!(out, "{location:<16}{kind}"),
+                }
+            }
+            // One object per line, like diagnostics
+            DumpFormat::Json => {
+                let json = JsonToken {
+                    line: token.start.line,
+                    column: token.start.column,
+                    offset: token.start.offset,
+                    end_line: token.end.line,
+                    end_column: token.end.column,
+                    end_offset: token.end.offset,
+                    kind,
+                    text,
+                };
+                serde_json::to_writer(&mut *out, &json)?;
+                writeln!(out)
+            }
+        }
+        .context("writing token")
+    }
+
+    fn dump_ast(&mut self, prog: &LolCodeProgram, format: DumpFormat) -> anyhow::Result<()> {
+        let out = self.out();
+        match format {
+            DumpFormat::Human => TreeWriter { out, depth: 0 }.program(prog),
+            DumpFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, prog)?;
+                writeln!(out)
+            }
+        }
+        .context("writing syntax tree")
+    }
+}
+
+/// Kind of a token and its text, if it has any beyond the kind.
+fn describe(t_type: &TokenType) -> (&'static str, Option<String>) {
+    match t_type {
+        TokenType::Keyword(keyword) => ("keyword", Some(keyword.to_string())),
+        TokenType::Word(word) => ("word", Some(word.clone())),
+        TokenType::Space(_) => ("space", None),
+        TokenType::NewLine => ("newline", None),
+        TokenType::Period => ("period", None),
+        TokenType::Comma => ("comma", None),
+        TokenType::Quote => ("quote", None),
+    }
+}
+
+#[derive(Serialize)]
+struct JsonToken {
+    line: usize,
+    column: usize,
+    offset: usize,
+    end_line: usize,
+    end_column: usize,
+    end_offset: usize,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+/// Writes a node per line as `<label> @<span>`, its children indented below.
+struct TreeWriter<'w, W> {
+    out: &'w mut W,
+    depth: usize,
+}
+
+impl<W> TreeWriter<'_, W>
+where
+    W: Write,
+{
+    fn line(&mut self, label: &str, span: Option<Span>) -> std::io::Result<()> {
+        let indent = "  ".repeat(self.depth);
+        match span {
+            Some(span) => writeln!(self.out, "{indent}{label} @{span}"),
+            None => writeln!(self.out, "{indent}{label}"),
+        }
+    }
+
+    /// Writes the node, then whatever `children` writes one level deeper.
+    fn node<F>(&mut self, label: &str, span: Option<Span>, children: F) -> std::io::Result<()>
+    where
+        F: FnOnce(&mut Self) -> std::io::Result<()>,
+    {
+        self.line(label, span)?;
+        self.depth += 1;
+        let result = children(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn program(&mut self, prog: &LolCodeProgram) -> std::io::Result<()> {
+        self.node(&format!("program {}", prog.version), Some(prog.span), |w| {
+            w.stmts(&prog.body)
+        })
+    }
+
+    fn stmts(&mut self, block: &Block) -> std::io::Result<()> {
+        block.stmts.iter().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn block(&mut self, label: &str, block: &Block) -> std::io::Result<()> {
+        self.node(label, Some(block.span), |w| w.stmts(block))
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) -> std::io::Result<()> {
+        exprs.iter().try_for_each(|expr| self.expr(expr))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> std::io::Result<()> {
+        let span = Some(stmt.span);
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                let label = match invisible {
+                    true => "invisible",
+                    false => "visible",
+                };
+                let label = match newline {
+                    true => label.to_string(),
+                    false => format!("{label} !"),
+                };
+                self.node(&label, span, |w| w.exprs(args))
+            }
+            StmtKind::CanHas { module } => self.line(&format!("can_has {}", module.name), span),
+            StmtKind::Declare { name, init } => {
+                self.node(&format!("declare {}", name.name), span, |w| {
+                    init.iter().try_for_each(|init| w.expr(init))
+                })
+            }
+            StmtKind::Assign { name, value } => {
+                self.node(&format!("assign {}", name.name), span, |w| w.expr(value))
+            }
+            StmtKind::Gimmeh(name) => self.line(&format!("gimmeh {}", name.name), span),
+            StmtKind::CastVar { name, to } => {
+                self.line(&format!("cast_var {} {to}", name.name), span)
+            }
+            StmtKind::Expr(expr) => self.node("expr", span, |w| w.expr(expr)),
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => self.node("if", span, |w| {
+                w.block("ya_rly", then)?;
+                for (condition, block) in elifs {
+                    w.node("mebbe", None, |w| {
+                        w.expr(condition)?;
+                        w.block("then", block)
+                    })?;
+                }
+                otherwise
+                    .iter()
+                    .try_for_each(|block| w.block("no_wai", block))
+            }),
+            StmtKind::Switch { cases, default } => self.node("switch", span, |w| {
+                for (literal, block) in cases {
+                    w.node("omg", None, |w| {
+                        w.expr(literal)?;
+                        w.block("then", block)
+                    })?;
+                }
+                default
+                    .iter()
+                    .try_for_each(|block| w.block("omgwtf", block))
+            }),
+            StmtKind::Loop(lp) => self.node(&format!("loop {}", lp.label.name), span, |w| {
+                if let Some(update) = &lp.update {
+                    let op = update.op.to_string().to_lowercase();
+                    w.line(&format!("{op} {}", update.var.name), Some(update.var.span))?;
+                }
+                match &lp.condition {
+                    Some(LoopCondition::Til(condition)) => {
+                        w.node("til", None, |w| w.expr(condition))?
+                    }
+                    Some(LoopCondition::Wile(condition)) => {
+                        w.node("wile", None, |w| w.expr(condition))?
+                    }
+                    None => {}
+                }
+                w.block("body", &lp.body)
+            }),
+            StmtKind::FuncDef(func) => {
+                let params: Vec<_> = func
+                    .params
+                    .iter()
+                    .map(|param| param.name.as_str())
+                    .collect();
+                let label = format!("func_def {}({})", func.name.name, params.join(", "));
+                self.node(&label, span, |w| w.block("body", &func.body))
+            }
+            StmtKind::Return(expr) => self.node("return", span, |w| w.expr(expr)),
+            StmtKind::Break => self.line("break", span),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> std::io::Result<()> {
+        let span = Some(expr.span);
+        match &expr.kind {
+            ExprKind::Noob => self.line("noob", span),
+            ExprKind::Troof(true) => self.line("troof WIN", span),
+            ExprKind::Troof(false) => self.line("troof FAIL", span),
+            ExprKind::Numbr(numbr) => self.line(&format!("numbr {numbr}"), span),
+            ExprKind::Numbar(numbar) => self.line(&format!("numbar {numbar}"), span),
+            ExprKind::Yarn(yarn) => self.line(&format!("yarn {yarn:?}"), span),
+            ExprKind::Var(var) => self.line(&format!("var {}", var.name), span),
+            ExprKind::Binary { op, left, right } => self.node(&format!("binary {op}"), span, |w| {
+                w.expr(left)?;
+                w.expr(right)
+            }),
+            ExprKind::Not(operand) => self.node("not", span, |w| w.expr(operand)),
+            ExprKind::Nary { op, args } => {
+                self.node(&format!("nary {op}"), span, |w| w.exprs(args))
+            }
+            ExprKind::Cast { expr, to } => self.node(&format!("cast {to}"), span, |w| w.expr(expr)),
+            ExprKind::Call { module, name, args } => {
+                let label = match module {
+                    Some(module) => format!("call {} IZ {}", module.name, name.name),
+                    None => format!("call {}", name.name),
+                };
+                self.node(&label, span, |w| w.exprs(args))
+            }
+        }
+    }
+}
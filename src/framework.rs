@@ -1,13 +1,30 @@
-use std::io::{BufWriter, Write};
+use std::{
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use anyhow::Context;
 
-use crate::tokenizer::Token;
+use crate::{
+    diagnostic::{Diagnostic, Emitter, ErrorCode, ErrorFormat, Severity},
+    dump::DumpFormat,
+    ir::OptLevel,
+    modules::{ModuleRegistry, Modules},
+    tokenizer::Token,
+    value::Value,
+};
 
 pub struct App<Out, Err> {
     out: Out,
     err: Err,
+    input: Box<dyn BufRead>,
     error_handled: bool,
+    emitter: Emitter,
+    modules: ModuleRegistry,
+    dump_format: DumpFormat,
+    output: Option<PathBuf>,
+    opt_level: OptLevel,
 }
 
 impl<O, E> App<BufWriter<O>, BufWriter<E>>
@@ -19,11 +36,108 @@ where
         Self {
             out: BufWriter::new(out),
             err: BufWriter::new(err),
+            input: Box::new(BufReader::new(io::stdin())),
             error_handled: false,
+            emitter: Emitter::default(),
+            modules: ModuleRegistry::default(),
+            dump_format: DumpFormat::default(),
+            output: None,
+            opt_level: OptLevel::O0,
         }
     }
 }
 
+impl<O, E> App<O, E> {
+    /// Makes `module` available to scripts through `CAN HAS <NAME>?`,
+    /// replacing any module already registered under the same name.
+    pub fn register_module<M>(&mut self, module: M) -> &mut Self
+    where
+        M: NativeModule + 'static,
+    {
+        self.modules.register(module);
+        self
+    }
+
+    /// Where `GIMMEH` reads from instead of standard input.
+    pub fn set_input<R>(&mut self, input: R) -> &mut Self
+    where
+        R: BufRead + 'static,
+    {
+        self.input = Box::new(input);
+        self
+    }
+
+    pub fn set_error_format(&mut self, format: ErrorFormat) -> &mut Self {
+        self.emitter.set_format(format);
+        self
+    }
+
+    /// Whether diagnostics are rendered with ANSI colours.
+    pub fn set_color(&mut self, color: bool) -> &mut Self {
+        self.emitter.set_color(color);
+        self
+    }
+
+    /// How [`Mode::Tokens`](crate::Mode::Tokens) and
+    /// [`Mode::Ast`](crate::Mode::Ast) write the program.
+    pub fn set_dump_format(&mut self, format: DumpFormat) -> &mut Self {
+        self.dump_format = format;
+        self
+    }
+
+    pub(crate) fn dump_format(&self) -> DumpFormat {
+        self.dump_format
+    }
+
+    /// Where [`Mode::Compile`](crate::Mode::Compile) writes the executable,
+    /// or the C source if it ends in `.c`.
+    pub fn set_output<P>(&mut self, output: P) -> &mut Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.output = Some(output.into());
+        self
+    }
+
+    pub(crate) fn output(&self) -> Option<&Path> {
+        self.output.as_deref()
+    }
+
+    /// How much [`Mode::Interpret`](crate::Mode::Interpret) optimizes
+    /// programs, which above [`OptLevel::O0`] are lowered to an IR first.
+    pub fn set_opt_level(&mut self, level: OptLevel) -> &mut Self {
+        self.opt_level = level;
+        self
+    }
+
+    pub(crate) fn opt_level(&self) -> OptLevel {
+        self.opt_level
+    }
+
+    /// Forgets errors reported for earlier programs, so that they do not fail
+    /// the next one run on the same [`App`].
+    pub(crate) fn start_run(&mut self) {
+        self.error_handled = false;
+    }
+
+    /// Lets diagnostics quote `text`, which is being read as the source
+    /// called `name`.
+    pub(crate) fn set_text(&mut self, name: String, text: Rc<str>) {
+        self.emitter.set_text(name, text);
+    }
+
+    /// Lets diagnostics quote the file at `path`.
+    pub(crate) fn set_file(&mut self, path: &Path) {
+        self.emitter.set_file(path);
+    }
+
+    /// Names the source being read as `name`, of which diagnostics can only
+    /// quote the lines read last.
+    pub(crate) fn set_stream(&mut self, name: String) {
+        self.emitter.set_stream(name);
+    }
+}
+
 pub trait StdOut {
     type Out: Write;
     fn out(&mut self) -> &mut Self::Out;
@@ -40,6 +154,68 @@ where
     }
 }
 
+/// Writer for program output meant for standard error, e.g. `INVISIBLE`.
+pub trait StdErr {
+    type Err: Write;
+    fn err(&mut self) -> &mut Self::Err;
+}
+
+impl<Out, E> StdErr for App<Out, E>
+where
+    E: Write,
+{
+    type Err = E;
+
+    fn err(&mut self) -> &mut Self::Err {
+        &mut self.err
+    }
+}
+
+/// Reader for program input, e.g. `GIMMEH`.
+pub trait StdIn {
+    /// The next line of input without its line ending. At the end of the
+    /// input this is whatever is left, so an empty YARN once nothing is, as
+    /// in lci.
+    fn read_line(&mut self) -> anyhow::Result<String>;
+}
+
+impl<O, E> StdIn for App<O, E>
+where
+    O: Write,
+{
+    fn read_line(&mut self) -> anyhow::Result<String> {
+        // Prompts written with VISIBLE ...! show before waiting on input
+        self.out.flush().context("flush output")?;
+        let mut line = String::new();
+        self.input.read_line(&mut line).context("read input")?;
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(line)
+    }
+}
+
+/// A module implemented in Rust whose functions can be called from scripts
+/// once it has been included with `CAN HAS <NAME>?`.
+pub trait NativeModule {
+    /// Name used to include the module, without the trailing `?`.
+    fn name(&self) -> &str;
+
+    /// Names of the functions `call` accepts.
+    fn functions(&self) -> &[&str];
+
+    fn call(&mut self, function: &str, args: Vec<Value>) -> anyhow::Result<Value>;
+}
+
+impl<O, E> Modules for App<O, E> {
+    fn modules(&mut self) -> &mut ModuleRegistry {
+        &mut self.modules
+    }
+}
+
 #[jsm::public]
 pub struct TokenProcessingError<'a> {
     token: &'a Token,
@@ -47,23 +223,38 @@ pub struct TokenProcessingError<'a> {
 }
 
 pub trait HandleTokenProcessingError {
-    fn handle_err(&mut self, err: TokenProcessingError) -> anyhow::Result<()>;
+    fn emit(&mut self, diagnostic: Diagnostic) -> anyhow::Result<()>;
+
+    /// Reports `err` as an unexpected token at the token's location.
+    fn handle_err(&mut self, err: TokenProcessingError) -> anyhow::Result<()> {
+        self.emit(Diagnostic::error(ErrorCode::UnexpectedToken, err.err).with_span(err.token))
+    }
 
     fn error_handled(&self) -> bool;
+
+    /// Called with each piece of source as the lexer reads it, starting at
+    /// line `line`, so that diagnostics can quote it.
+    fn source_read(&mut self, _line: usize, _text: &str) {}
 }
 
 impl<O, E> HandleTokenProcessingError for App<O, E>
 where
     E: Write,
 {
-    fn handle_err(&mut self, err: TokenProcessingError) -> anyhow::Result<()> {
-        self.error_handled = true;
-        let loc = &err.token.location;
-        write!(self.err, "@{}:{} -> {}\n", loc.line, loc.column, err.err)
-            .context("writing token error")
+    fn emit(&mut self, diagnostic: Diagnostic) -> anyhow::Result<()> {
+        if diagnostic.severity == Severity::Error {
+            self.error_handled = true;
+        }
+        self.emitter
+            .emit(&mut self.err, &diagnostic)
+            .context("writing diagnostic")
     }
 
     fn error_handled(&self) -> bool {
         self.error_handled
     }
+
+    fn source_read(&mut self, line: usize, text: &str) {
+        self.emitter.extend_source(line, text);
+    }
 }
@@ -1,10 +1,16 @@
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
 use anyhow::Context;
 
 use crate::{
-    framework::StdOut,
-    parser::{Instruction, LolCodeProgram},
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, Loop, LoopCondition,
+        LoopOp, NaryOp, Stmt, StmtKind,
+    },
+    diagnostic::{Diagnostic, ErrorCode, RuntimeError, Span},
+    framework::{HandleTokenProcessingError, StdErr, StdIn, StdOut},
+    modules::Modules,
+    value::Value,
 };
 
 pub trait Interpret {
@@ -13,22 +19,434 @@ pub trait Interpret {
 
 impl<T> Interpret for T
 where
-    T: StdOut,
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
 {
     fn execute(&mut self, prog: LolCodeProgram) -> anyhow::Result<()> {
-        for instr in prog.instrs {
-            match instr {
-                Instruction::Visible { args } => {
-                    for arg in args {
-                        write!(self.out(), "{arg}").context("write to output")?;
+        check_features(self, &prog)?;
+
+        let mut interpreter = Interpreter {
+            app: self,
+            functions: HashMap::new(),
+        };
+        match interpreter.block(&prog.body, &mut Frame::default())? {
+            Flow::Next => Ok(()),
+            Flow::Break | Flow::Return(_) => {
+                unreachable!("GTFO and FOUND YR are rejected outside functions")
+            }
+        }
+    }
+}
+
+/// Fails if `prog` uses a feature its version does not have. Programs need
+/// not come from the parser so backends check the gate again.
+pub(crate) fn check_features<T>(app: &mut T, prog: &LolCodeProgram) -> anyhow::Result<()>
+where
+    T: HandleTokenProcessingError,
+{
+    let mut gated = false;
+    for stmt in prog.body.walk() {
+        let mut features: Vec<_> = stmt.feature().map(|f| (f, stmt.span)).into_iter().collect();
+        for expr in stmt.exprs().into_iter().flat_map(Expr::walk) {
+            if let Some(feature) = expr.feature() {
+                features.push((feature, expr.span));
+            }
+        }
+        for (feature, span) in features {
+            if let Some(diagnostic) = prog.version.require(feature) {
+                app.emit(diagnostic.with_span(span))?;
+                gated = true;
+            }
+        }
+    }
+    match gated {
+        true => Err(RuntimeError.into()),
+        false => Ok(()),
+    }
+}
+
+/// Variables of the main program or of a single function call.
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Value>,
+    it: Value,
+    in_function: bool,
+    /// Loops and `WTF?` blocks that `GTFO` may leave.
+    breakable: usize,
+}
+
+impl Frame {
+    fn get(&self, name: &str) -> Option<&Value> {
+        match name {
+            "IT" => Some(&self.it),
+            name => self.vars.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match name {
+            "IT" => Some(&mut self.it),
+            name => self.vars.get_mut(name),
+        }
+    }
+}
+
+enum Flow {
+    Next,
+    Break,
+    Return(Value),
+}
+
+struct Interpreter<'a, 'p, T> {
+    app: &'a mut T,
+    functions: HashMap<&'p str, &'p FuncDef>,
+}
+
+impl<'p, T> Interpreter<'_, 'p, T>
+where
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
+{
+    /// Reports `diagnostic` and stops execution.
+    fn fail<R>(&mut self, diagnostic: Diagnostic) -> anyhow::Result<R> {
+        self.app.emit(diagnostic)?;
+        Err(RuntimeError.into())
+    }
+
+    /// Unwraps the result of a value operation, reporting errors at `span`.
+    fn check<R>(&mut self, result: Result<R, Diagnostic>, span: Span) -> anyhow::Result<R> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(diagnostic) => self.fail(diagnostic.with_span(span)),
+        }
+    }
+
+    fn block(&mut self, block: &'p Block, frame: &mut Frame) -> anyhow::Result<Flow> {
+        for stmt in &block.stmts {
+            match self.stmt(stmt, frame)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn stmt(&mut self, stmt: &'p Stmt, frame: &mut Frame) -> anyhow::Result<Flow> {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                let mut line = String::new();
+                for arg in args {
+                    line.push_str(&self.expr(arg, frame)?.to_string());
+                }
+                if *newline {
+                    line.push('\n');
+                }
+
+                match invisible {
+                    true => write!(self.app.err(), "{line}").context("write to error output")?,
+                    false => write!(self.app.out(), "{line}").context("write to output")?,
+                }
+            }
+            StmtKind::Gimmeh(name) => {
+                let line = self.app.read_line()?;
+                *self.var_mut(name, frame)? = Value::Yarn(line);
+            }
+            StmtKind::CanHas { module } => {
+                if let Err(err) = self.app.modules().load(&module.name) {
+                    return self.fail(
+                        Diagnostic::error(ErrorCode::UnknownModule, err.to_string())
+                            .with_span(module.span),
+                    );
+                }
+            }
+            StmtKind::Declare { name, init } => {
+                let value = match init {
+                    Some(init) => self.expr(init, frame)?,
+                    None => Value::Noob,
+                };
+                frame.vars.insert(name.name.clone(), value);
+            }
+            StmtKind::Assign { name, value } => {
+                let value = self.expr(value, frame)?;
+                *self.var_mut(name, frame)? = value;
+            }
+            StmtKind::CastVar { name, to } => {
+                let cast = self.var(name, frame)?.cast(*to);
+                let cast = self.check(cast, stmt.span)?;
+                *self.var_mut(name, frame)? = cast;
+            }
+            StmtKind::Expr(expr) => frame.it = self.expr(expr, frame)?,
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                if frame.it.to_troof() {
+                    return self.block(then, frame);
+                }
+                for (condition, block) in elifs {
+                    if self.expr(condition, frame)?.to_troof() {
+                        return self.block(block, frame);
+                    }
+                }
+                if let Some(otherwise) = otherwise {
+                    return self.block(otherwise, frame);
+                }
+            }
+            StmtKind::Switch { cases, default } => {
+                let mut start = cases.len();
+                for (index, (literal, _)) in cases.iter().enumerate() {
+                    if self.expr(literal, frame)?.saem(&frame.it) {
+                        start = index;
+                        break;
+                    }
+                }
+
+                // Cases fall through into the ones after, OMGWTF included
+                let blocks = cases.iter().map(|(_, block)| block).chain(default);
+                frame.breakable += 1;
+                let mut flow = Ok(Flow::Next);
+                for block in blocks.skip(start) {
+                    flow = self.block(block, frame);
+                    if !matches!(flow, Ok(Flow::Next)) {
+                        break;
+                    }
+                }
+                frame.breakable -= 1;
+
+                if let Flow::Return(value) = flow? {
+                    return Ok(Flow::Return(value));
+                }
+            }
+            StmtKind::Loop(lp) => return self.loop_stmt(lp, frame),
+            StmtKind::FuncDef(func) => {
+                self.functions.insert(&func.name.name, func);
+            }
+            StmtKind::Return(expr) => {
+                if !frame.in_function {
+                    return self.fail(return_outside(stmt.span));
+                }
+                return Ok(Flow::Return(self.expr(expr, frame)?));
+            }
+            StmtKind::Break => {
+                if frame.breakable == 0 && !frame.in_function {
+                    return self.fail(break_outside(stmt.span));
+                }
+                return Ok(Flow::Break);
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn loop_stmt(&mut self, lp: &'p Loop, frame: &mut Frame) -> anyhow::Result<Flow> {
+        // A loop variable that does not exist yet only lives for the loop
+        let temporary = lp
+            .update
+            .as_ref()
+            .filter(|update| frame.get(&update.var.name).is_none());
+        if let Some(update) = temporary {
+            frame.vars.insert(update.var.name.clone(), Value::Numbr(0));
+        }
+
+        frame.breakable += 1;
+        let flow = self.iterate(lp, frame);
+        frame.breakable -= 1;
+
+        if let Some(update) = temporary {
+            frame.vars.remove(&update.var.name);
+        }
+        match flow? {
+            Flow::Return(value) => Ok(Flow::Return(value)),
+            Flow::Next | Flow::Break => Ok(Flow::Next),
+        }
+    }
+
+    fn iterate(&mut self, lp: &'p Loop, frame: &mut Frame) -> anyhow::Result<Flow> {
+        loop {
+            let done = match &lp.condition {
+                Some(LoopCondition::Til(condition)) => self.expr(condition, frame)?.to_troof(),
+                Some(LoopCondition::Wile(condition)) => !self.expr(condition, frame)?.to_troof(),
+                None => false,
+            };
+            if done {
+                return Ok(Flow::Next);
+            }
+
+            match self.block(&lp.body, frame)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+
+            if let Some(update) = &lp.update {
+                let op = match update.op {
+                    LoopOp::Uppin => BinaryOp::Sum,
+                    LoopOp::Nerfin => BinaryOp::Diff,
+                };
+                let value = self
+                    .var(&update.var, frame)?
+                    .arithmetic(op, &Value::Numbr(1));
+                let value = self.check(value, update.var.span)?;
+                *self.var_mut(&update.var, frame)? = value;
+            }
+        }
+    }
+
+    fn var<'f>(&mut self, name: &Ident, frame: &'f Frame) -> anyhow::Result<&'f Value> {
+        match frame.get(&name.name) {
+            Some(value) => Ok(value),
+            None => self.fail(undefined_variable(name)),
+        }
+    }
+
+    fn var_mut<'f>(&mut self, name: &Ident, frame: &'f mut Frame) -> anyhow::Result<&'f mut Value> {
+        match frame.get_mut(&name.name) {
+            Some(value) => Ok(value),
+            None => self.fail(undefined_variable(name)),
+        }
+    }
+
+    fn expr(&mut self, expr: &'p Expr, frame: &mut Frame) -> anyhow::Result<Value> {
+        let value = match &expr.kind {
+            ExprKind::Noob => Value::Noob,
+            ExprKind::Troof(troof) => Value::Troof(*troof),
+            ExprKind::Numbr(numbr) => Value::Numbr(*numbr),
+            ExprKind::Numbar(numbar) => Value::Numbar(*numbar),
+            ExprKind::Yarn(yarn) => Value::Yarn(yarn.clone()),
+            ExprKind::Var(var) => self.var(var, frame)?.clone(),
+            ExprKind::Binary { op, left, right } => {
+                let left = self.expr(left, frame)?;
+                let right = self.expr(right, frame)?;
+                let value = left.binary(*op, &right);
+                self.check(value, expr.span)?
+            }
+            ExprKind::Not(operand) => Value::Troof(!self.expr(operand, frame)?.to_troof()),
+            ExprKind::Nary { op, args } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.expr(arg, frame)?);
+                }
+
+                match op {
+                    NaryOp::All => Value::Troof(values.iter().all(Value::to_troof)),
+                    NaryOp::Any => Value::Troof(values.iter().any(Value::to_troof)),
+                    NaryOp::Smoosh => {
+                        let mut yarn = String::new();
+                        for (value, arg) in values.iter().zip(args) {
+                            let piece = value.to_yarn();
+                            yarn.push_str(&self.check(piece, arg.span)?);
+                        }
+                        Value::Yarn(yarn)
                     }
+                }
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                let value = self.expr(operand, frame)?.cast(*to);
+                self.check(value, expr.span)?
+            }
+            ExprKind::Call { module, name, args } => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.expr(arg, frame)?);
+                }
 
-                    writeln!(self.out(), "").context("newline to output")?;
+                match module {
+                    Some(module) => {
+                        match self.app.modules().call(&module.name, &name.name, values) {
+                            Ok(value) => value,
+                            Err(err) => {
+                                return self.fail(
+                                    Diagnostic::error(ErrorCode::NativeCall, format!("{err:#}"))
+                                        .with_span(expr.span),
+                                )
+                            }
+                        }
+                    }
+                    None => self.call(expr, values)?,
                 }
-                Instruction::LoadModule { .. } => {}
             }
+        };
+        Ok(value)
+    }
+
+    fn call(&mut self, call: &'p Expr, args: Vec<Value>) -> anyhow::Result<Value> {
+        let ExprKind::Call { name, .. } = &call.kind else {
+            unreachable!("only called for function calls");
+        };
+        let Some(func) = self.functions.get(name.name.as_str()).copied() else {
+            return self.fail(undefined_function(name));
+        };
+        if func.params.len() != args.len() {
+            let diagnostic = argument_count(call.span, &func.name, func.params.len(), args.len());
+            return self.fail(diagnostic);
         }
 
-        Ok(())
+        // Functions only see their own arguments, not the caller's variables
+        let mut frame = Frame {
+            vars: func
+                .params
+                .iter()
+                .map(|param| param.name.clone())
+                .zip(args)
+                .collect(),
+            in_function: true,
+            ..Frame::default()
+        };
+        match self.block(&func.body, &mut frame)? {
+            Flow::Return(value) => Ok(value),
+            // GTFO in a function returns NOOB
+            Flow::Break => Ok(Value::Noob),
+            Flow::Next => Ok(frame.it),
+        }
     }
 }
+
+pub(crate) fn undefined_variable(name: &Ident) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::UndefinedVariable,
+        format!("Unknown variable {}", name.name),
+    )
+    .with_span(name.span)
+    .with_help(format!("Declare it first with I HAS A {}", name.name))
+}
+
+pub(crate) fn undefined_function(name: &Ident) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::UndefinedFunction,
+        format!("Unknown function {}", name.name),
+    )
+    .with_span(name.span)
+}
+
+pub(crate) fn argument_count(call: Span, func: &Ident, params: usize, given: usize) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::ArgumentCount,
+        format!(
+            "{} takes {} argument(s) but {} were given",
+            func.name, params, given
+        ),
+    )
+    .with_span(call)
+    .with_label(func.span, "defined here")
+}
+
+/// `GTFO` where there is nothing to leave.
+pub(crate) fn break_outside(span: Span) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::BreakOutsideLoop,
+        "GTFO outside of a loop, WTF? or function",
+    )
+    .with_span(span)
+}
+
+/// `FOUND YR` outside of a function.
+pub(crate) fn return_outside(span: Span) -> Diagnostic {
+    Diagnostic::error(
+        ErrorCode::ReturnOutsideFunction,
+        "FOUND YR outside of a function",
+    )
+    .with_span(span)
+}
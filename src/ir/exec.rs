@@ -0,0 +1,295 @@
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Context;
+
+use crate::{
+    diagnostic::{Diagnostic, ErrorCode, RuntimeError, Span},
+    framework::{HandleTokenProcessingError, StdErr, StdIn, StdOut},
+    interpreter::{
+        argument_count, break_outside, return_outside, undefined_function, undefined_variable,
+    },
+    modules::Modules,
+    value::Value,
+};
+
+use super::{Body, Ir, IrFunction, Op, Operand};
+
+pub trait RunIr {
+    fn run_ir(&mut self, ir: &Ir) -> anyhow::Result<()>;
+}
+
+impl<T> RunIr for T
+where
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
+{
+    fn run_ir(&mut self, ir: &Ir) -> anyhow::Result<()> {
+        let targets: Vec<_> = ir
+            .functions
+            .iter()
+            .map(|function| function.body.targets())
+            .collect();
+        let mut executor = Executor {
+            app: self,
+            ir,
+            targets: &targets,
+            functions: HashMap::new(),
+        };
+        let mut frame = Frame::new(&ir.main);
+        executor.body(&ir.main, &ir.main.targets(), &mut frame)?;
+        Ok(())
+    }
+}
+
+/// Variables and temporaries of the main program or of a single function
+/// call.
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Value>,
+    it: Value,
+    /// For each loop being run, the variable it declared, if any.
+    loops: Vec<Option<String>>,
+    temps: Vec<Value>,
+}
+
+impl Frame {
+    fn new(body: &Body) -> Self {
+        Self {
+            temps: vec![Value::Noob; body.temps as usize],
+            ..Frame::default()
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&Value> {
+        match name {
+            "IT" => Some(&self.it),
+            name => self.vars.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        match name {
+            "IT" => Some(&mut self.it),
+            name => self.vars.get_mut(name),
+        }
+    }
+
+    fn value(&self, operand: &Operand) -> Value {
+        match operand {
+            Operand::Const(value) => value.clone(),
+            Operand::Temp(temp) => self.temps[*temp as usize].clone(),
+        }
+    }
+
+    fn values(&self, operands: &[Operand]) -> Vec<Value> {
+        operands.iter().map(|operand| self.value(operand)).collect()
+    }
+}
+
+struct Executor<'a, 'b, T> {
+    app: &'a mut T,
+    ir: &'b Ir,
+    /// Where the labels of each function are.
+    targets: &'b [Vec<Option<usize>>],
+    /// Functions defined so far, by index in [`Ir::functions`].
+    functions: HashMap<&'b str, usize>,
+}
+
+impl<'b, T> Executor<'_, 'b, T>
+where
+    T: StdOut + StdErr + StdIn + Modules + HandleTokenProcessingError,
+{
+    /// Reports `diagnostic` and stops execution.
+    fn fail<R>(&mut self, diagnostic: Diagnostic) -> anyhow::Result<R> {
+        self.app.emit(diagnostic)?;
+        Err(RuntimeError.into())
+    }
+
+    /// Unwraps the result of a value operation, reporting errors at `span`.
+    fn check<R>(&mut self, result: Result<R, Diagnostic>, span: Span) -> anyhow::Result<R> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(diagnostic) => self.fail(diagnostic.with_span(span)),
+        }
+    }
+
+    /// Runs `body` until it returns, or to its end for the main program.
+    fn body(
+        &mut self,
+        body: &'b Body,
+        targets: &[Option<usize>],
+        frame: &mut Frame,
+    ) -> anyhow::Result<Value> {
+        let mut pc = 0;
+        while let Some(inst) = body.code.get(pc) {
+            pc += 1;
+
+            let value = match &inst.op {
+                Op::Load { var, .. } => match frame.get(&var.name) {
+                    Some(value) => value.clone(),
+                    None => return self.fail(undefined_variable(var)),
+                },
+                Op::Store { var, value } => {
+                    let value = frame.value(value);
+                    match frame.get_mut(&var.name) {
+                        Some(slot) => *slot = value,
+                        None => return self.fail(undefined_variable(var)),
+                    }
+                    continue;
+                }
+                Op::Declare { var, value } => {
+                    let value = frame.value(value);
+                    frame.vars.insert(var.name.clone(), value);
+                    continue;
+                }
+                Op::Binary {
+                    op, left, right, ..
+                } => {
+                    let (left, right) = (frame.value(left), frame.value(right));
+                    self.check(left.binary(*op, &right), inst.span)?
+                }
+                Op::Not { operand, .. } => Value::Troof(!frame.value(operand).to_troof()),
+                Op::All { operands, .. } => {
+                    Value::Troof(frame.values(operands).iter().all(Value::to_troof))
+                }
+                Op::Any { operands, .. } => {
+                    Value::Troof(frame.values(operands).iter().any(Value::to_troof))
+                }
+                Op::ToYarn { operand, .. } => {
+                    let yarn = self.check(frame.value(operand).to_yarn(), inst.span)?;
+                    Value::Yarn(yarn)
+                }
+                Op::Concat { operands, .. } => {
+                    let yarn = frame
+                        .values(operands)
+                        .iter()
+                        .map(Value::to_string)
+                        .collect();
+                    Value::Yarn(yarn)
+                }
+                Op::Cast { operand, to, .. } => {
+                    self.check(frame.value(operand).cast(*to), inst.span)?
+                }
+                Op::Label(_) => continue,
+                Op::Jump(label) => {
+                    pc = targets[*label as usize].expect("jumps go to labels that exist");
+                    continue;
+                }
+                Op::JumpIf {
+                    operand,
+                    win,
+                    target,
+                } => {
+                    if frame.value(operand).to_troof() == *win {
+                        pc = targets[*target as usize].expect("jumps go to labels that exist");
+                    }
+                    continue;
+                }
+                Op::EnterLoop(var) => {
+                    // A loop variable that does not exist yet only lives for the loop
+                    let temporary = frame.get(&var.name).is_none();
+                    if temporary {
+                        frame.vars.insert(var.name.clone(), Value::Numbr(0));
+                    }
+                    frame.loops.push(temporary.then(|| var.name.clone()));
+                    continue;
+                }
+                Op::ExitLoop => {
+                    if let Some(var) = frame.loops.pop().flatten() {
+                        frame.vars.remove(&var);
+                    }
+                    continue;
+                }
+                Op::Gimmeh { .. } => Value::Yarn(self.app.read_line()?),
+                Op::Visible {
+                    operands,
+                    invisible,
+                    newline,
+                } => {
+                    let mut line: String = frame
+                        .values(operands)
+                        .iter()
+                        .map(Value::to_string)
+                        .collect();
+                    if *newline {
+                        line.push('\n');
+                    }
+
+                    match invisible {
+                        true => {
+                            write!(self.app.err(), "{line}").context("write to error output")?
+                        }
+                        false => write!(self.app.out(), "{line}").context("write to output")?,
+                    }
+                    continue;
+                }
+                Op::Include(module) => {
+                    if let Err(err) = self.app.modules().load(&module.name) {
+                        return self.fail(
+                            Diagnostic::error(ErrorCode::UnknownModule, err.to_string())
+                                .with_span(module.span),
+                        );
+                    }
+                    continue;
+                }
+                Op::Define(index) => {
+                    let name = &self.ir.functions[*index].name.name;
+                    self.functions.insert(name, *index);
+                    continue;
+                }
+                Op::Call { name, args, .. } => {
+                    let Some(index) = self.functions.get(name.name.as_str()).copied() else {
+                        return self.fail(undefined_function(name));
+                    };
+                    let function: &'b IrFunction = &self.ir.functions[index];
+                    if function.params.len() != args.len() {
+                        let params = function.params.len();
+                        let diagnostic =
+                            argument_count(inst.span, &function.name, params, args.len());
+                        return self.fail(diagnostic);
+                    }
+
+                    // Functions only see their own arguments, not the caller's variables
+                    let mut callee = Frame::new(&function.body);
+                    callee.vars = function
+                        .params
+                        .iter()
+                        .map(|param| param.name.clone())
+                        .zip(frame.values(args))
+                        .collect();
+                    let targets = self.targets;
+                    self.body(&function.body, &targets[index], &mut callee)?
+                }
+                Op::CallNative {
+                    module,
+                    function,
+                    args,
+                    ..
+                } => {
+                    let values = frame.values(args);
+                    match self
+                        .app
+                        .modules()
+                        .call(&module.name, &function.name, values)
+                    {
+                        Ok(value) => value,
+                        Err(err) => {
+                            return self.fail(
+                                Diagnostic::error(ErrorCode::NativeCall, format!("{err:#}"))
+                                    .with_span(inst.span),
+                            )
+                        }
+                    }
+                }
+                Op::Return(value) => return Ok(frame.value(value)),
+                Op::BreakOutside => return self.fail(break_outside(inst.span)),
+                Op::ReturnOutside => return self.fail(return_outside(inst.span)),
+            };
+            let dest = inst
+                .op
+                .dest()
+                .expect("only instructions with a result get here");
+            frame.temps[dest as usize] = value;
+        }
+        Ok(Value::Noob)
+    }
+}
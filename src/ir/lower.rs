@@ -0,0 +1,422 @@
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, LoopCondition, LoopOp,
+        NaryOp, Stmt, StmtKind,
+    },
+    diagnostic::Span,
+    framework::HandleTokenProcessingError,
+    interpreter::check_features,
+    value::Value,
+};
+
+use super::{Body, Inst, Ir, IrFunction, Label, Op, Operand, Temp};
+
+pub trait Lower {
+    /// Lowers `prog` to [`Ir`] without optimizing it.
+    fn lower(&mut self, prog: &LolCodeProgram) -> anyhow::Result<Ir>;
+}
+
+impl<T> Lower for T
+where
+    T: HandleTokenProcessingError,
+{
+    fn lower(&mut self, prog: &LolCodeProgram) -> anyhow::Result<Ir> {
+        check_features(self, prog)?;
+
+        let mut lowerer = Lowerer {
+            body: Body::default(),
+            functions: Vec::new(),
+            breaks: Vec::new(),
+            in_function: false,
+        };
+        lowerer.block(&prog.body);
+        let main = std::mem::take(&mut lowerer.body);
+        Ok(Ir {
+            main,
+            functions: lowerer.functions,
+        })
+    }
+}
+
+/// `IT`, as a variable read or written at `span`.
+fn it(span: Span) -> Ident {
+    Ident {
+        name: String::from("IT"),
+        span,
+    }
+}
+
+struct Lowerer {
+    /// The body being lowered.
+    body: Body,
+    functions: Vec<IrFunction>,
+    /// Labels just past each enclosing loop or `WTF?`, innermost last.
+    breaks: Vec<Label>,
+    in_function: bool,
+}
+
+impl Lowerer {
+    fn emit(&mut self, op: Op, span: Span) {
+        self.body.code.push(Inst { op, span });
+    }
+
+    fn temp(&mut self) -> Temp {
+        self.body.temps += 1;
+        self.body.temps - 1
+    }
+
+    fn label(&mut self) -> Label {
+        self.body.labels += 1;
+        self.body.labels - 1
+    }
+
+    fn block(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    /// Lowers `func` into a body of its own, returning its index in
+    /// [`Ir::functions`].
+    fn function(&mut self, func: &FuncDef) -> usize {
+        // Function bodies are outside any loop of the code defining them
+        let outer = std::mem::take(&mut self.body);
+        let breaks = std::mem::take(&mut self.breaks);
+        let in_function = std::mem::replace(&mut self.in_function, true);
+
+        self.block(&func.body);
+        let value = self.load(&it(func.body.span));
+        self.emit(Op::Return(value), func.body.span);
+
+        let body = std::mem::replace(&mut self.body, outer);
+        self.breaks = breaks;
+        self.in_function = in_function;
+        self.functions.push(IrFunction {
+            name: func.name.clone(),
+            params: func.params.clone(),
+            body,
+        });
+        self.functions.len() - 1
+    }
+
+    fn load(&mut self, var: &Ident) -> Operand {
+        let dest = self.temp();
+        self.emit(
+            Op::Load {
+                dest,
+                var: var.clone(),
+            },
+            var.span,
+        );
+        Operand::Temp(dest)
+    }
+
+    /// Lowers a loop or `WTF?`, pointing each `GTFO` in it just past it.
+    fn breakable<F>(&mut self, span: Span, body: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let done = self.label();
+        self.breaks.push(done);
+        body(self);
+        self.breaks.pop();
+        self.emit(Op::Label(done), span);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Visible {
+                args,
+                invisible,
+                newline,
+            } => {
+                let operands = args.iter().map(|arg| self.expr(arg)).collect();
+                let visible = Op::Visible {
+                    operands,
+                    invisible: *invisible,
+                    newline: *newline,
+                };
+                self.emit(visible, stmt.span);
+            }
+            StmtKind::CanHas { module } => self.emit(Op::Include(module.clone()), module.span),
+            StmtKind::Declare { name, init } => {
+                let value = match init {
+                    Some(init) => self.expr(init),
+                    None => Operand::Const(Value::Noob),
+                };
+                let var = name.clone();
+                self.emit(Op::Declare { var, value }, name.span);
+            }
+            StmtKind::Gimmeh(name) => {
+                let dest = self.temp();
+                self.emit(Op::Gimmeh { dest }, stmt.span);
+                let var = name.clone();
+                self.emit(
+                    Op::Store {
+                        var,
+                        value: Operand::Temp(dest),
+                    },
+                    name.span,
+                );
+            }
+            StmtKind::Assign { name, value } => {
+                let value = self.expr(value);
+                let var = name.clone();
+                self.emit(Op::Store { var, value }, name.span);
+            }
+            StmtKind::CastVar { name, to } => {
+                let operand = self.load(name);
+                let dest = self.temp();
+                self.emit(
+                    Op::Cast {
+                        dest,
+                        operand,
+                        to: *to,
+                    },
+                    stmt.span,
+                );
+                let value = Operand::Temp(dest);
+                let var = name.clone();
+                self.emit(Op::Store { var, value }, name.span);
+            }
+            StmtKind::Expr(expr) => {
+                let value = self.expr(expr);
+                let var = it(stmt.span);
+                self.emit(Op::Store { var, value }, stmt.span);
+            }
+            StmtKind::If {
+                then,
+                elifs,
+                otherwise,
+            } => {
+                let done = self.label();
+                let mut next = self.label();
+                let value = self.load(&it(stmt.span));
+                self.jump_if(value, false, next, stmt.span);
+                self.block(then);
+                for (condition, block) in elifs {
+                    self.emit(Op::Jump(done), stmt.span);
+                    self.emit(Op::Label(next), stmt.span);
+                    next = self.label();
+                    let value = self.expr(condition);
+                    self.jump_if(value, false, next, condition.span);
+                    self.block(block);
+                }
+                self.emit(Op::Jump(done), stmt.span);
+                self.emit(Op::Label(next), stmt.span);
+                if let Some(otherwise) = otherwise {
+                    self.block(otherwise);
+                }
+                self.emit(Op::Label(done), stmt.span);
+            }
+            StmtKind::Switch { cases, default } => {
+                let mut matches = Vec::with_capacity(cases.len());
+                for (literal, _) in cases {
+                    let matched = self.label();
+                    let left = self.expr(literal);
+                    let right = self.load(&it(literal.span));
+                    let dest = self.temp();
+                    let op = BinaryOp::Saem;
+                    self.emit(
+                        Op::Binary {
+                            dest,
+                            op,
+                            left,
+                            right,
+                        },
+                        literal.span,
+                    );
+                    self.jump_if(Operand::Temp(dest), true, matched, literal.span);
+                    matches.push(matched);
+                }
+                let unmatched = self.label();
+                self.emit(Op::Jump(unmatched), stmt.span);
+
+                // Cases fall through into the ones after, OMGWTF included
+                self.breakable(stmt.span, |lowerer| {
+                    for (matched, (_, block)) in matches.into_iter().zip(cases) {
+                        lowerer.emit(Op::Label(matched), stmt.span);
+                        lowerer.block(block);
+                    }
+                    lowerer.emit(Op::Label(unmatched), stmt.span);
+                    if let Some(default) = default {
+                        lowerer.block(default);
+                    }
+                });
+            }
+            StmtKind::Loop(lp) => {
+                if let Some(update) = &lp.update {
+                    self.emit(Op::EnterLoop(update.var.clone()), update.var.span);
+                }
+
+                self.breakable(stmt.span, |lowerer| {
+                    let done = *lowerer.breaks.last().expect("pushed by breakable");
+                    let top = lowerer.label();
+                    lowerer.emit(Op::Label(top), stmt.span);
+                    match &lp.condition {
+                        Some(LoopCondition::Til(condition)) => {
+                            let value = lowerer.expr(condition);
+                            lowerer.jump_if(value, true, done, condition.span);
+                        }
+                        Some(LoopCondition::Wile(condition)) => {
+                            let value = lowerer.expr(condition);
+                            lowerer.jump_if(value, false, done, condition.span);
+                        }
+                        None => {}
+                    }
+                    lowerer.block(&lp.body);
+                    if let Some(update) = &lp.update {
+                        let op = match update.op {
+                            LoopOp::Uppin => BinaryOp::Sum,
+                            LoopOp::Nerfin => BinaryOp::Diff,
+                        };
+                        let span = update.var.span;
+                        let left = lowerer.load(&update.var);
+                        let right = Operand::Const(Value::Numbr(1));
+                        let dest = lowerer.temp();
+                        lowerer.emit(
+                            Op::Binary {
+                                dest,
+                                op,
+                                left,
+                                right,
+                            },
+                            span,
+                        );
+                        let var = update.var.clone();
+                        let value = Operand::Temp(dest);
+                        lowerer.emit(Op::Store { var, value }, span);
+                    }
+                    lowerer.emit(Op::Jump(top), stmt.span);
+                });
+
+                if lp.update.is_some() {
+                    self.emit(Op::ExitLoop, stmt.span);
+                }
+            }
+            StmtKind::FuncDef(func) => {
+                let index = self.function(func);
+                self.emit(Op::Define(index), stmt.span);
+            }
+            StmtKind::Return(expr) => {
+                if !self.in_function {
+                    self.emit(Op::ReturnOutside, stmt.span);
+                    return;
+                }
+                let value = self.expr(expr);
+                self.emit(Op::Return(value), stmt.span);
+            }
+            StmtKind::Break => match self.breaks.last() {
+                Some(done) => self.emit(Op::Jump(*done), stmt.span),
+                // GTFO in a function returns NOOB
+                None if self.in_function => {
+                    self.emit(Op::Return(Operand::Const(Value::Noob)), stmt.span)
+                }
+                None => self.emit(Op::BreakOutside, stmt.span),
+            },
+        }
+    }
+
+    fn jump_if(&mut self, operand: Operand, win: bool, target: Label, span: Span) {
+        self.emit(
+            Op::JumpIf {
+                operand,
+                win,
+                target,
+            },
+            span,
+        );
+    }
+
+    /// Lowers `expr`, returning the constant or temporary holding its value.
+    fn expr(&mut self, expr: &Expr) -> Operand {
+        let value = match &expr.kind {
+            ExprKind::Noob => Value::Noob,
+            ExprKind::Troof(troof) => Value::Troof(*troof),
+            ExprKind::Numbr(numbr) => Value::Numbr(*numbr),
+            ExprKind::Numbar(numbar) => Value::Numbar(*numbar),
+            ExprKind::Yarn(yarn) => Value::Yarn(yarn.clone()),
+            _ => return self.computed(expr),
+        };
+        Operand::Const(value)
+    }
+
+    /// Lowers an `expr` that is not a literal.
+    fn computed(&mut self, expr: &Expr) -> Operand {
+        let op = match &expr.kind {
+            ExprKind::Var(var) => return self.load(var),
+            ExprKind::Binary { op, left, right } => {
+                let (left, right) = (self.expr(left), self.expr(right));
+                let dest = self.temp();
+                Op::Binary {
+                    dest,
+                    op: *op,
+                    left,
+                    right,
+                }
+            }
+            ExprKind::Not(operand) => {
+                let operand = self.expr(operand);
+                let dest = self.temp();
+                Op::Not { dest, operand }
+            }
+            ExprKind::Nary { op, args } => {
+                let operands: Vec<Operand> = args.iter().map(|arg| self.expr(arg)).collect();
+                match op {
+                    NaryOp::All => {
+                        let dest = self.temp();
+                        Op::All { dest, operands }
+                    }
+                    NaryOp::Any => {
+                        let dest = self.temp();
+                        Op::Any { dest, operands }
+                    }
+                    // Every piece is evaluated before any is cast
+                    NaryOp::Smoosh => {
+                        let operands = operands
+                            .into_iter()
+                            .zip(args)
+                            .map(|(operand, arg)| {
+                                let dest = self.temp();
+                                self.emit(Op::ToYarn { dest, operand }, arg.span);
+                                Operand::Temp(dest)
+                            })
+                            .collect();
+                        let dest = self.temp();
+                        Op::Concat { dest, operands }
+                    }
+                }
+            }
+            ExprKind::Cast { expr: operand, to } => {
+                let operand = self.expr(operand);
+                let dest = self.temp();
+                Op::Cast {
+                    dest,
+                    operand,
+                    to: *to,
+                }
+            }
+            ExprKind::Call { module, name, args } => {
+                let args = args.iter().map(|arg| self.expr(arg)).collect();
+                let dest = self.temp();
+                match module {
+                    Some(module) => Op::CallNative {
+                        dest,
+                        module: module.clone(),
+                        function: name.clone(),
+                        args,
+                    },
+                    None => Op::Call {
+                        dest,
+                        name: name.clone(),
+                        args,
+                    },
+                }
+            }
+            _ => unreachable!("literals are constants"),
+        };
+        let dest = op.dest().expect("expressions assign a temporary");
+        self.emit(op, expr.span);
+        Operand::Temp(dest)
+    }
+}
@@ -0,0 +1,234 @@
+//! Three-address intermediate representation between the syntax tree and
+//! execution, for optimizing programs. [`Lower`] produces it from a
+//! [`LolCodeProgram`], a [`PassManager`] rewrites it and [`RunIr`] executes
+//! it with the same observable behaviour as [`Interpret`](crate::Interpret).
+//!
+//! Each instruction takes constants or temporaries as operands and assigns
+//! at most one temporary, which nothing assigns again. Variables are not
+//! in that form: they are read and written with [`Op::Load`] and
+//! [`Op::Store`], since they only exist once their declaration has run.
+//!
+//! [`LolCodeProgram`]: crate::LolCodeProgram
+
+mod exec;
+mod lower;
+mod passes;
+
+pub use exec::RunIr;
+pub use lower::Lower;
+pub use passes::{ConstantFolding, DeadCode, JoinVisible, OptLevel, Pass, PassManager};
+
+use crate::{
+    ast::{BinaryOp, Ident, Type},
+    diagnostic::Span,
+    value::Value,
+};
+
+/// A value computed by one instruction of a [`Body`].
+pub type Temp = u32;
+/// A place in a [`Body`] that jumps go to.
+pub type Label = u32;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+    Const(Value),
+    Temp(Temp),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Op {
+    /// Reads a variable, or `IT`.
+    Load {
+        dest: Temp,
+        var: Ident,
+    },
+    /// Writes an existing variable, or `IT`.
+    Store {
+        var: Ident,
+        value: Operand,
+    },
+    /// Creates a variable of the current frame.
+    Declare {
+        var: Ident,
+        value: Operand,
+    },
+    Binary {
+        dest: Temp,
+        op: BinaryOp,
+        left: Operand,
+        right: Operand,
+    },
+    Not {
+        dest: Temp,
+        operand: Operand,
+    },
+    /// Whether all of the operands are WIN.
+    All {
+        dest: Temp,
+        operands: Vec<Operand>,
+    },
+    /// Whether any of the operands is WIN.
+    Any {
+        dest: Temp,
+        operands: Vec<Operand>,
+    },
+    /// Implicit cast to a YARN, failing for NOOB.
+    ToYarn {
+        dest: Temp,
+        operand: Operand,
+    },
+    /// Joins YARNs together.
+    Concat {
+        dest: Temp,
+        operands: Vec<Operand>,
+    },
+    Cast {
+        dest: Temp,
+        operand: Operand,
+        to: Type,
+    },
+    Label(Label),
+    Jump(Label),
+    /// Jumps if the operand is WIN, or FAIL if `win` is false.
+    JumpIf {
+        operand: Operand,
+        win: bool,
+        target: Label,
+    },
+    /// Starts a loop over the variable, declaring it for the duration of the
+    /// loop if it does not exist yet.
+    EnterLoop(Ident),
+    /// Removes the variable of the innermost loop if it was declared by it.
+    ExitLoop,
+    /// Reads a line of input as a YARN.
+    Gimmeh {
+        dest: Temp,
+    },
+    /// Writes the operands on a line of output.
+    Visible {
+        operands: Vec<Operand>,
+        invisible: bool,
+        newline: bool,
+    },
+    /// Loads a native module.
+    Include(Ident),
+    /// Makes the function with that index in [`Ir::functions`] available to
+    /// calls from here on.
+    Define(usize),
+    Call {
+        dest: Temp,
+        name: Ident,
+        args: Vec<Operand>,
+    },
+    CallNative {
+        dest: Temp,
+        module: Ident,
+        function: Ident,
+        args: Vec<Operand>,
+    },
+    /// Returns from the current function.
+    Return(Operand),
+    /// `GTFO` with nothing to leave.
+    BreakOutside,
+    /// `FOUND YR` outside of a function.
+    ReturnOutside,
+}
+
+impl Op {
+    /// The temporary the instruction assigns, if any.
+    pub fn dest(&self) -> Option<Temp> {
+        match self {
+            Op::Load { dest, .. }
+            | Op::Binary { dest, .. }
+            | Op::Not { dest, .. }
+            | Op::All { dest, .. }
+            | Op::Any { dest, .. }
+            | Op::ToYarn { dest, .. }
+            | Op::Concat { dest, .. }
+            | Op::Cast { dest, .. }
+            | Op::Gimmeh { dest }
+            | Op::Call { dest, .. }
+            | Op::CallNative { dest, .. } => Some(*dest),
+            _ => None,
+        }
+    }
+
+    /// Every operand the instruction reads.
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Op::Store { value, .. } | Op::Declare { value, .. } => vec![value],
+            Op::Binary { left, right, .. } => vec![left, right],
+            Op::Not { operand, .. }
+            | Op::ToYarn { operand, .. }
+            | Op::Cast { operand, .. }
+            | Op::JumpIf { operand, .. }
+            | Op::Return(operand) => vec![operand],
+            Op::All { operands, .. }
+            | Op::Any { operands, .. }
+            | Op::Concat { operands, .. }
+            | Op::Visible { operands, .. }
+            | Op::Call { args: operands, .. }
+            | Op::CallNative { args: operands, .. } => operands.iter_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether execution never carries on with the next instruction.
+    pub fn ends_block(&self) -> bool {
+        matches!(
+            self,
+            Op::Jump(_) | Op::Return(_) | Op::BreakOutside | Op::ReturnOutside
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[jsm::public]
+pub struct Inst {
+    op: Op,
+    /// Source of the instruction, for diagnostics.
+    span: Span,
+}
+
+/// Instructions of the main program or of a function.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[jsm::public]
+pub struct Body {
+    code: Vec<Inst>,
+    /// Number of temporaries, which are numbered from 0.
+    temps: u32,
+    /// Number of labels, which are numbered from 0.
+    labels: u32,
+}
+
+impl Body {
+    /// Where each label is in [`code`](Self::code), or `None` for labels
+    /// that were optimized away.
+    pub fn targets(&self) -> Vec<Option<usize>> {
+        let mut targets = vec![None; self.labels as usize];
+        for (index, inst) in self.code.iter().enumerate() {
+            if let Op::Label(label) = inst.op {
+                targets[label as usize] = Some(index);
+            }
+        }
+        targets
+    }
+}
+
+/// A function defined with `HOW IZ I`.
+#[derive(Debug, PartialEq, Clone)]
+#[jsm::public]
+pub struct IrFunction {
+    name: Ident,
+    params: Vec<Ident>,
+    /// Ends by returning, with `IT` if nothing else.
+    body: Body,
+}
+
+/// A lowered program.
+#[derive(Debug, PartialEq, Clone)]
+#[jsm::public]
+pub struct Ir {
+    main: Body,
+    functions: Vec<IrFunction>,
+}
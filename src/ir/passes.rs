@@ -0,0 +1,282 @@
+use std::{collections::HashMap, iter};
+
+use mediator_tracing::tracing::debug;
+
+use super::{Body, Ir, Op, Operand, Temp};
+use crate::value::Value;
+
+/// How much to optimize programs before running them.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum OptLevel {
+    /// Interpret the syntax tree as parsed
+    #[default]
+    #[value(name = "0")]
+    O0,
+    /// Fold constants and remove code that can never run
+    #[value(name = "1")]
+    O1,
+    /// Also join constants written by the same VISIBLE
+    #[value(name = "2")]
+    O2,
+}
+
+/// A rewrite of a [`Body`] that keeps what the program does.
+pub trait Pass {
+    /// Name of the pass in logs.
+    fn name(&self) -> &'static str;
+
+    /// Rewrites `body`, returning whether anything changed.
+    fn run(&self, body: &mut Body) -> bool;
+}
+
+/// Passes run one after the other on every body of a program.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    /// The passes making up `level`.
+    pub fn for_level(level: OptLevel) -> Self {
+        let mut manager = Self::default();
+        if level != OptLevel::O0 {
+            manager.add(ConstantFolding).add(DeadCode);
+        }
+        if level == OptLevel::O2 {
+            manager.add(JoinVisible);
+        }
+        manager
+    }
+
+    pub fn add<P>(&mut self, pass: P) -> &mut Self
+    where
+        P: Pass + 'static,
+    {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn run(&self, ir: &mut Ir) {
+        let functions = ir.functions.iter_mut().map(|function| &mut function.body);
+        for body in iter::once(&mut ir.main).chain(functions) {
+            for pass in &self.passes {
+                let changed = pass.run(body);
+                debug!(pass = pass.name(), changed);
+            }
+        }
+    }
+}
+
+/// Computes instructions whose operands are all constants ahead of time,
+/// e.g. `SUM OF 2 AN 3`, using the results in place of their temporaries.
+/// Branches on constants become jumps, or go. Whatever would fail is left
+/// to fail when it runs.
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "constant-folding"
+    }
+
+    fn run(&self, body: &mut Body) -> bool {
+        // Temporaries are only used after the instruction assigning them
+        let mut known: HashMap<Temp, Value> = HashMap::new();
+        let before = body.code.clone();
+        body.code.retain_mut(|inst| {
+            for operand in inst.op.operands_mut() {
+                if let Operand::Temp(temp) = operand {
+                    if let Some(value) = known.get(temp) {
+                        *operand = Operand::Const(value.clone());
+                    }
+                }
+            }
+
+            if let Op::JumpIf {
+                operand: Operand::Const(value),
+                win,
+                target,
+            } = &inst.op
+            {
+                if value.to_troof() != *win {
+                    return false;
+                }
+                inst.op = Op::Jump(*target);
+            }
+            match (inst.op.dest(), fold(&inst.op)) {
+                (Some(dest), Some(value)) => {
+                    known.insert(dest, value);
+                    false
+                }
+                _ => true,
+            }
+        });
+        body.code != before
+    }
+}
+
+/// Values of `operands` if they are all constants.
+fn constants(operands: &[Operand]) -> Option<Vec<&Value>> {
+    operands
+        .iter()
+        .map(|operand| match operand {
+            Operand::Const(value) => Some(value),
+            Operand::Temp(_) => None,
+        })
+        .collect()
+}
+
+/// The value `op` computes if it can be known without running it.
+fn fold(op: &Op) -> Option<Value> {
+    match op {
+        Op::Binary {
+            op,
+            left: Operand::Const(left),
+            right: Operand::Const(right),
+            ..
+        } => left.binary(*op, right).ok(),
+        Op::Not {
+            operand: Operand::Const(value),
+            ..
+        } => Some(Value::Troof(!value.to_troof())),
+        Op::All { operands, .. } => {
+            let values = constants(operands)?;
+            Some(Value::Troof(values.into_iter().all(Value::to_troof)))
+        }
+        Op::Any { operands, .. } => {
+            let values = constants(operands)?;
+            Some(Value::Troof(values.into_iter().any(Value::to_troof)))
+        }
+        Op::ToYarn {
+            operand: Operand::Const(value),
+            ..
+        } => value.to_yarn().ok().map(Value::Yarn),
+        Op::Concat { operands, .. } => {
+            let values = constants(operands)?;
+            Some(Value::Yarn(
+                values.into_iter().map(Value::to_string).collect(),
+            ))
+        }
+        Op::Cast {
+            operand: Operand::Const(value),
+            to,
+            ..
+        } => value.cast(*to).ok(),
+        _ => None,
+    }
+}
+
+/// Removes instructions that can never run, such as those after `GTFO` or
+/// `FOUND YR`, along with jumps to the next instruction and labels that
+/// nothing jumps to any more.
+pub struct DeadCode;
+
+impl Pass for DeadCode {
+    fn name(&self) -> &'static str {
+        "dead-code"
+    }
+
+    fn run(&self, body: &mut Body) -> bool {
+        let before = body.code.len();
+        // Each removal can make more jumps and labels pointless
+        while remove_unreachable(body) || remove_needless_jumps(body) {}
+        body.code.len() != before
+    }
+}
+
+/// Removes instructions no path from the start of `body` reaches, and the
+/// labels no remaining jump goes to.
+fn remove_unreachable(body: &mut Body) -> bool {
+    let targets = body.targets();
+    let target = |label: u32| targets[label as usize].expect("jumps go to labels that exist");
+    let mut reachable = vec![false; body.code.len()];
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        if index >= body.code.len() || reachable[index] {
+            continue;
+        }
+        reachable[index] = true;
+        let op = &body.code[index].op;
+        match op {
+            Op::Jump(label) | Op::JumpIf { target: label, .. } => pending.push(target(*label)),
+            _ => {}
+        }
+        if !op.ends_block() {
+            pending.push(index + 1);
+        }
+    }
+
+    let mut used = vec![false; body.labels as usize];
+    for (inst, reachable) in body.code.iter().zip(&reachable) {
+        match inst.op {
+            Op::Jump(label) | Op::JumpIf { target: label, .. } if *reachable => {
+                used[label as usize] = true;
+            }
+            _ => {}
+        }
+    }
+
+    let before = body.code.len();
+    let mut reachable = reachable.into_iter();
+    body.code.retain(|inst| {
+        let reachable = reachable.next().expect("one per instruction");
+        match inst.op {
+            Op::Label(label) => reachable && used[label as usize],
+            _ => reachable,
+        }
+    });
+    body.code.len() != before
+}
+
+/// Removes jumps to labels that directly follow them.
+fn remove_needless_jumps(body: &mut Body) -> bool {
+    let needless: Vec<bool> = body
+        .code
+        .iter()
+        .enumerate()
+        .map(|(index, inst)| {
+            let next = body.code.get(index + 1).map(|next| &next.op);
+            match (&inst.op, next) {
+                (Op::Jump(label), Some(Op::Label(next))) => label == next,
+                _ => false,
+            }
+        })
+        .collect();
+
+    let before = body.code.len();
+    let mut needless = needless.into_iter();
+    body.code
+        .retain(|_| !needless.next().expect("one per instruction"));
+    body.code.len() != before
+}
+
+/// Joins adjacent constants written by the same `VISIBLE` into one YARN, as
+/// `VISIBLE "O " AN "HAI"` writes the same as `VISIBLE "O HAI"`. Run after
+/// [`ConstantFolding`], this also covers the results of expressions.
+pub struct JoinVisible;
+
+impl Pass for JoinVisible {
+    fn name(&self) -> &'static str {
+        "join-visible"
+    }
+
+    fn run(&self, body: &mut Body) -> bool {
+        let mut changed = false;
+        for inst in &mut body.code {
+            let Op::Visible { operands, .. } = &mut inst.op else {
+                continue;
+            };
+            let mut joined: Vec<Operand> = Vec::with_capacity(operands.len());
+            for operand in operands.drain(..) {
+                match (joined.last_mut(), operand) {
+                    (Some(Operand::Const(last)), Operand::Const(value)) => {
+                        *last = Value::Yarn(format!("{last}{value}"));
+                        changed = true;
+                    }
+                    (_, operand) => joined.push(operand),
+                }
+            }
+            *operands = joined;
+        }
+        changed
+    }
+}
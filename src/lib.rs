@@ -0,0 +1,372 @@
+//! Compiler for LOLCODE.
+//!
+//! [`App`] ties the pipeline together: source is split into [`Token`]s with
+//! [`Tokenize::tokenize`], turned into a [`LolCodeProgram`] by [`App::parse`]
+//! and run with [`Interpret::execute`], writing to the output and error
+//! writers the [`App`] was created with. Problems are reported as
+//! [`Diagnostic`]s on the error writer.
+
+mod ast;
+mod backend;
+mod bytecode;
+mod checker;
+mod diagnostic;
+mod dump;
+mod framework;
+mod interpreter;
+mod ir;
+mod modules;
+mod parser;
+mod tokenizer;
+mod value;
+
+#[cfg(test)]
+mod test;
+
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::Context;
+use mediator_tracing::tracing::debug;
+
+pub use ast::{Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, Stmt, StmtKind};
+pub use backend::{EmitAsm, EmitC, EmitJs, EmitLlvm, EmitWat};
+pub use bytecode::{Bytecode, Compile, Function, Instruction, RunBytecode, FORMAT_VERSION, MAGIC};
+pub use checker::Check;
+pub use diagnostic::{
+    CompileError, Diagnostic, Emitter, ErrorCode, ErrorFormat, Label, Position, RuntimeError,
+    Severity, SourceFile, Span,
+};
+pub use dump::{Dump, DumpFormat};
+pub use framework::{
+    App, HandleTokenProcessingError, NativeModule, StdErr, StdIn, StdOut, TokenProcessingError,
+};
+pub use interpreter::Interpret;
+pub use ir::{
+    ConstantFolding, DeadCode, Inst, Ir, IrFunction, JoinVisible, Lower, Op, Operand, OptLevel,
+    Pass, PassManager, RunIr,
+};
+#[cfg(feature = "socks")]
+pub use modules::socks::Socks;
+pub use modules::{ModuleRegistry, Modules};
+pub use parser::{Feature, LolCodeVersion, Parser, UnreadableSource};
+pub use tokenizer::{KeywordToken, Lexer, Token, TokenLocation, TokenType, Tokenize};
+pub use value::Value;
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum Mode {
+    /// Use the interpreter
+    Interpret,
+    /// Compile to bytecode and run it on the virtual machine
+    Vm,
+    /// Report every error that can be found without running the program
+    Check,
+    /// Print the tokens of the program
+    Tokens,
+    /// Print the syntax tree of the program
+    Ast,
+    /// Compile to a native executable with the system C compiler
+    Compile,
+    /// Print a WebAssembly text module importing its output function
+    Wat,
+    /// Print the program transpiled to JavaScript for Node.js
+    Js,
+    /// Compile to a native x86-64 Linux executable with the GNU assembler and
+    /// linker
+    Asm,
+    /// Write the program as LLVM IR to a .ll file, and the C runtime it calls
+    /// next to it
+    LlvmIr,
+}
+
+impl<StdOut, StdErr> App<StdOut, StdErr>
+where
+    StdOut: Write,
+    StdErr: Write,
+{
+    pub fn run<P>(&mut self, path: P, mode: Mode) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.start_run();
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => return Err(self.unreadable(&name, err.into())),
+        };
+        self.set_file(path);
+        self.run_lexed(name, BufReader::new(file), mode)
+    }
+
+    pub fn run_source<S>(&mut self, source: S, mode: Mode) -> anyhow::Result<()>
+    where
+        S: Into<SourceFile>,
+    {
+        self.start_run();
+        let source = source.into();
+        let text: Rc<str> = source.text.into();
+        self.set_text(source.name.clone(), Rc::clone(&text));
+        self.run_lexed(source.name, text.as_bytes(), mode)
+    }
+
+    /// Runs the program read from `reader`, naming it `name` in diagnostics.
+    pub fn run_reader<N, R>(&mut self, name: N, reader: R, mode: Mode) -> anyhow::Result<()>
+    where
+        N: Into<String>,
+        R: BufRead,
+    {
+        self.start_run();
+        let name = name.into();
+        self.set_stream(name.clone());
+        self.run_lexed(name, reader, mode)
+    }
+
+    /// Runs the program read from `reader` once the emitter knows where to
+    /// find its lines.
+    fn run_lexed<R>(&mut self, name: String, reader: R, mode: Mode) -> anyhow::Result<()>
+    where
+        R: BufRead,
+    {
+        if mode == Mode::Tokens {
+            self.dump_reader(&name, reader)?;
+            return match self.error_handled() {
+                true => Err(CompileError.into()),
+                false => Ok(()),
+            };
+        }
+
+        let prog = self.parse_lexed(&name, reader)?;
+
+        match mode {
+            Mode::Interpret if self.opt_level() == OptLevel::O0 => self.execute(prog),
+            Mode::Interpret => {
+                let mut ir = self.lower(&prog)?;
+                PassManager::for_level(self.opt_level()).run(&mut ir);
+                self.run_ir(&ir)
+            }
+            Mode::Vm => {
+                let bytecode = self.compile(&prog)?;
+                self.run_bytecode(&bytecode)
+            }
+            Mode::Check => self.check(&prog),
+            Mode::Tokens => unreachable!("tokens are dumped before parsing"),
+            Mode::Ast => self.dump_ast(&prog, self.dump_format()),
+            Mode::Compile => {
+                let c = self.emit_c(&prog, &name)?;
+                let output = self
+                    .output()
+                    .map_or_else(|| executable(&name), Path::to_path_buf);
+                // Writing to a .c file keeps the C for a build of one's own
+                match output.extension().is_some_and(|extension| extension == "c") {
+                    true => fs::write(&output, c)
+                        .with_context(|| format!("writing {}", output.display())),
+                    false => backend::build(&c, &output)
+                        .with_context(|| format!("building {}", output.display())),
+                }
+            }
+            Mode::Wat => {
+                let wat = self.emit_wat(&prog, &name)?;
+                self.write_program(&wat)
+            }
+            Mode::Js => {
+                let js = self.emit_js(&prog, &name)?;
+                self.write_program(&js)
+            }
+            Mode::Asm => {
+                let asm = self.emit_asm(&prog, &name)?;
+                let output = self
+                    .output()
+                    .map_or_else(|| executable(&name), Path::to_path_buf);
+                // Writing to a .s file keeps the assembly
+                match output.extension().is_some_and(|extension| extension == "s") {
+                    true => fs::write(&output, asm)
+                        .with_context(|| format!("writing {}", output.display())),
+                    false => backend::assemble(&asm, &output)
+                        .with_context(|| format!("building {}", output.display())),
+                }
+            }
+            Mode::LlvmIr => {
+                let ir = self.emit_llvm(&prog, &name)?;
+                let output = self
+                    .output()
+                    .map_or_else(|| llvm_ir(&name), Path::to_path_buf);
+                fs::write(&output, ir).with_context(|| format!("writing {}", output.display()))?;
+                // Built together with e.g. `clang prog.ll prog.runtime.c -lm`
+                let runtime = output.with_extension("runtime.c");
+                fs::write(&runtime, backend::llvm_runtime())
+                    .with_context(|| format!("writing {}", runtime.display()))
+            }
+        }
+    }
+
+    /// Writes the text of a program produced by a backend to the output
+    /// file if one was set, or to the output writer otherwise.
+    fn write_program(&mut self, program: &str) -> anyhow::Result<()> {
+        match self.output().map(Path::to_path_buf) {
+            Some(output) => {
+                fs::write(&output, program).with_context(|| format!("writing {}", output.display()))
+            }
+            None => Ok(self.out().write_all(program.as_bytes())?),
+        }
+    }
+
+    /// Compiles the program at `path` to a `.lolc` file at `output`, to be
+    /// run with [`run_compiled`](Self::run_compiled).
+    pub fn compile_file<P, Q>(&mut self, path: P, output: Q) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        self.start_run();
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) => return Err(self.unreadable(&name, err.into())),
+        };
+        self.set_file(path);
+        let prog = self.parse_lexed(&name, BufReader::new(file))?;
+        let bytecode = Bytecode {
+            source: name,
+            ..self.compile(&prog)?
+        };
+
+        let output = output.as_ref();
+        fs::write(output, bytecode.to_bytes())
+            .with_context(|| format!("writing {}", output.display()))
+    }
+
+    /// Runs a `.lolc` file written by [`compile_file`](Self::compile_file).
+    pub fn run_compiled<P>(&mut self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.start_run();
+        let name = path.as_ref().display().to_string();
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) => return Err(self.unreadable(&name, err.into())),
+        };
+        let bytecode = match Bytecode::from_bytes(&bytes) {
+            Ok(bytecode) => bytecode,
+            Err(err) => {
+                let diagnostic = Diagnostic::error(
+                    ErrorCode::InvalidBytecode,
+                    format!("Unable to load {name}: {err:#}"),
+                );
+                self.emit(diagnostic)?;
+                return Err(CompileError.into());
+            }
+        };
+
+        // The source is not kept, so diagnostics only give its locations
+        self.set_stream(bytecode.source.clone());
+        self.run_bytecode(&bytecode)
+    }
+
+    /// Tokenizes and parses `source`, failing with [`CompileError`] if any
+    /// errors were reported along the way. The text is kept so that later
+    /// diagnostics can quote it.
+    pub fn parse<S>(&mut self, source: S) -> anyhow::Result<LolCodeProgram>
+    where
+        S: Into<SourceFile>,
+    {
+        self.start_run();
+        let source = source.into();
+        let text: Rc<str> = source.text.into();
+        self.set_text(source.name.clone(), Rc::clone(&text));
+        self.parse_lexed(&source.name, text.as_bytes())
+    }
+
+    /// Like [`parse`](Self::parse), lexing the source as it is read from
+    /// `reader`. Only the last lines read are kept for diagnostics to quote.
+    pub fn parse_reader<N, R>(&mut self, name: N, reader: R) -> anyhow::Result<LolCodeProgram>
+    where
+        N: Into<String>,
+        R: BufRead,
+    {
+        self.start_run();
+        let name = name.into();
+        self.set_stream(name.clone());
+        self.parse_lexed(&name, reader)
+    }
+
+    fn parse_lexed<R>(&mut self, name: &str, reader: R) -> anyhow::Result<LolCodeProgram>
+    where
+        R: BufRead,
+    {
+        let prog = match Self::process_tokens(self.lex(reader)) {
+            Ok(prog) => prog,
+            Err(err) => {
+                return match err.downcast::<UnreadableSource>() {
+                    Ok(UnreadableSource(err)) => Err(self.unreadable(name, err)),
+                    Err(err) => Err(err),
+                }
+            }
+        };
+
+        debug!(?prog);
+
+        if self.error_handled() {
+            return Err(CompileError.into());
+        }
+
+        Ok(prog)
+    }
+
+    /// Writes the tokens of `reader` as they are lexed.
+    fn dump_reader<R>(&mut self, name: &str, reader: R) -> anyhow::Result<()>
+    where
+        R: BufRead,
+    {
+        let format = self.dump_format();
+        let mut tokens = self.lex(reader);
+        while let Some(token) = tokens.next() {
+            let app = tokens.handler();
+            let token = match token {
+                Ok(token) => token,
+                Err(err) => return Err(app.unreadable(name, err)),
+            };
+            app.dump_token(&token, format)?;
+        }
+        Ok(())
+    }
+
+    /// Reports that the source called `name` could not be read.
+    fn unreadable(&mut self, name: &str, err: anyhow::Error) -> anyhow::Error {
+        let diagnostic = Diagnostic::error(
+            ErrorCode::UnreadableSource,
+            format!("Unable to read {name}: {err:#}"),
+        );
+        match self.emit(diagnostic) {
+            Ok(()) => CompileError.into(),
+            Err(err) => err,
+        }
+    }
+}
+
+/// Where [`Mode::Compile`] and [`Mode::Asm`] put the executable for the source called `name`
+/// unless told otherwise: next to it, without the extension.
+fn executable(name: &str) -> PathBuf {
+    let path = Path::new(name);
+    match path.extension() {
+        // Sources without an extension, stdin and inline code
+        None => PathBuf::from("a.out"),
+        Some(_) => path.with_extension(""),
+    }
+}
+
+/// Where [`Mode::LlvmIr`] puts the IR for the source called `name` unless
+/// told otherwise: next to it, with the extension `.ll`.
+fn llvm_ir(name: &str) -> PathBuf {
+    let path = Path::new(name);
+    match path.extension() {
+        None => PathBuf::from("a.ll"),
+        Some(_) => path.with_extension("ll"),
+    }
+}
@@ -1,58 +1,23 @@
-mod framework;
-mod interpreter;
-mod parser;
-mod tokenizer;
-
-#[cfg(test)]
-mod test;
-
-use anyhow::bail;
 use clap::Parser as _;
-use framework::{App, HandleTokenProcessingError};
 use mediator::Module;
 use mediator_tracing::tracing::{info, Level};
-use mediator_tracing::{tracing::debug, TracingConfig};
+use mediator_tracing::TracingConfig;
 use mediator_tracing::{Targets, TracingModule};
-use parser::Parser;
-use std::io::{stderr, stdout, Write};
+use rlcc::{App, CompileError, DumpFormat, ErrorFormat, Mode, OptLevel, RuntimeError, SourceFile};
+use std::env;
+use std::io::{stderr, stdin, stdout, IsTerminal};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::str::FromStr;
-use std::{fs, path::Path, process::ExitCode};
-
-use crate::interpreter::Interpret;
-use crate::tokenizer::parse_tokens;
-
-impl<StdOut, StdErr> App<StdOut, StdErr>
-where
-    StdOut: Write,
-    StdErr: Write,
-{
-    fn run<P>(&mut self, path: P, mode: Mode) -> anyhow::Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        let file_contents = fs::read_to_string(path).expect("Unable to read provided file");
-        let tokens = parse_tokens(file_contents);
-
-        debug!(tokens = ?(tokens.iter().map(|token| &token.t_type).collect::<Vec<_>>()));
-
-        let prog = self.process_tokens(tokens)?;
-
-        debug!(?prog);
-
-        if self.error_handled() {
-            bail!("Something went wrong when compiling.");
-        }
-
-        if mode == Mode::Interpret {
-            self.execute(prog)?;
-        }
-
-        Ok(())
-    }
-}
 
 fn main() -> anyhow::Result<ExitCode> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Some(mode) = args.named_mode.take() {
+        args.mode = mode;
+    }
+    if let Some(Emit::LlvmIr) = args.emit {
+        args.mode = Mode::LlvmIr;
+    }
     TracingModule::new(Some(TracingConfig {
         base_targets: Some(
             Targets::default().with_default(Level::from_str(&args.log_level).unwrap()),
@@ -63,28 +28,127 @@ fn main() -> anyhow::Result<ExitCode> {
     .init();
     info!(?args);
 
-    App::new(stdout(), stderr())
-        .run(args.filename, args.mode)
-        .map(|_| {
-            println!("Compilation successful");
-            ExitCode::SUCCESS
-        })
+    let color = match args.color {
+        ColorChoice::Auto => stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+
+    // Dropping the app flushes the program's output
+    let result = {
+        let mut app = App::new(stdout(), stderr());
+        app.set_color(color)
+            .set_error_format(args.error_format)
+            .set_dump_format(args.format)
+            .set_opt_level(args.opt_level);
+        if let Some(output) = args.output {
+            app.set_output(output);
+        }
+        match (args.command, args.eval, args.filename.as_deref()) {
+            (Some(Command::Compile { filename, output }), _, _) => {
+                let output = output.unwrap_or_else(|| filename.with_extension("lolc"));
+                app.compile_file(filename, output)
+            }
+            (Some(Command::Run { filename }), _, _) => app.run_compiled(filename),
+            (None, Some(source), _) => {
+                app.run_source(SourceFile::new("<inline>", source), args.mode.clone())
+            }
+            (None, None, Some("-")) => app.run_reader("<stdin>", stdin().lock(), args.mode.clone()),
+            (None, None, Some(filename)) => app.run(filename, args.mode.clone()),
+            (None, None, None) => unreachable!("clap requires a filename without --eval"),
+        }
+    };
+
+    match result {
+        Ok(_) => {
+            // Anything else on stdout would mix with the program's output
+            if args.mode == Mode::Check {
+                println!("Compilation successful");
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        // Already reported as diagnostics
+        Err(err) if err.is::<CompileError>() || err.is::<RuntimeError>() => Ok(ExitCode::FAILURE),
+        Err(err) => Err(err),
+    }
 }
 
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    filename: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Program to run, or `-` to read it from stdin
+    #[arg(required_unless_present = "eval", conflicts_with = "eval")]
+    filename: Option<String>,
+    /// Run SOURCE instead of a file, e.g. `-e SOURCE --mode check`
+    #[arg(short, long, value_name = "SOURCE")]
+    eval: Option<String>,
     /// Log level
-    #[arg(long, default_value = "info")]
+    #[arg(long, default_value = "info", global = true)]
     log_level: String,
     /// Mode to execute
-    #[arg(value_enum, default_value_t = Mode::Interpret)]
+    #[arg(value_enum, default_value_t = Mode::Interpret, requires = "filename")]
     mode: Mode,
+    /// Mode to execute, given by name, as after --eval where there is no
+    /// filename for the mode to follow
+    #[arg(
+        short = 'm',
+        long = "mode",
+        value_enum,
+        value_name = "MODE",
+        conflicts_with = "mode"
+    )]
+    named_mode: Option<Mode>,
+    /// Optimization level of the interpret mode, which runs the program as
+    /// parsed at 0 and lowered to an optimized IR at 1 and 2
+    #[arg(short = 'O', value_enum, default_value_t = OptLevel::O0, value_name = "LEVEL")]
+    opt_level: OptLevel,
+    /// Write the program in another form instead of running it
+    #[arg(long, value_enum, value_name = "FORM", conflicts_with_all = ["mode", "named_mode"])]
+    emit: Option<Emit>,
+    /// Where the compile and asm modes write the executable, or the C or
+    /// assembly source if it ends in .c or .s, where the wat and js modes
+    /// write the program instead of stdout, and where --emit llvm-ir writes
+    /// the IR
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// How the tokens and ast modes write the program
+    #[arg(long, value_enum, default_value_t = DumpFormat::Human)]
+    format: DumpFormat,
+    /// How diagnostics are written to stderr
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human, global = true)]
+    error_format: ErrorFormat,
+    /// When to colour diagnostics
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Compile a program to bytecode saved in a .lolc file
+    Compile {
+        filename: PathBuf,
+        /// Where to write the bytecode, by default next to the program
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run a .lolc file written by compile
+    Run { filename: PathBuf },
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+enum Emit {
+    /// LLVM IR in a .ll file, with the C runtime it calls next to it
+    LlvmIr,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
-enum Mode {
-    /// Use the interpreter
-    Interpret,
+enum ColorChoice {
+    /// Colour when stderr is a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    /// Plain output, e.g. for CI logs
+    Never,
 }
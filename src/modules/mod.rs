@@ -0,0 +1,94 @@
+#[cfg(feature = "socks")]
+pub mod socks;
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context};
+use mediator_tracing::tracing::debug;
+
+use crate::{framework::NativeModule, value::Value};
+
+/// Module with no functions of its own. lci treats `STDIO` like this since
+/// `VISIBLE` and `GIMMEH` are statements, available without it.
+struct EmptyModule(&'static str);
+
+impl NativeModule for EmptyModule {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn functions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn call(&mut self, function: &str, _args: Vec<Value>) -> anyhow::Result<Value> {
+        bail!("Unknown function {function}")
+    }
+}
+
+pub struct ModuleRegistry {
+    available: HashMap<String, Box<dyn NativeModule>>,
+    loaded: HashSet<String>,
+}
+
+impl Default for ModuleRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            available: HashMap::new(),
+            loaded: HashSet::new(),
+        };
+        registry.register(EmptyModule("STDIO"));
+        #[cfg(feature = "socks")]
+        registry.register(socks::Socks::default());
+        registry
+    }
+}
+
+impl ModuleRegistry {
+    pub fn register<M>(&mut self, module: M)
+    where
+        M: NativeModule + 'static,
+    {
+        self.available
+            .insert(module.name().to_string(), Box::new(module));
+    }
+
+    /// Functions of the module registered as `name`, if there is one.
+    pub fn functions(&self, name: &str) -> Option<&[&str]> {
+        self.available.get(name).map(|module| module.functions())
+    }
+
+    pub fn load(&mut self, name: &str) -> anyhow::Result<()> {
+        if !self.available.contains_key(name) {
+            bail!("Unknown module {name}");
+        }
+        debug!(module = name, "loading module");
+        self.loaded.insert(name.to_string());
+        Ok(())
+    }
+
+    pub fn call(
+        &mut self,
+        module: &str,
+        function: &str,
+        args: Vec<Value>,
+    ) -> anyhow::Result<Value> {
+        if !self.loaded.contains(module) {
+            bail!("Module {module} has not been loaded. Are you missing CAN HAS {module}?");
+        }
+        let native = self
+            .available
+            .get_mut(module)
+            .context("loaded module is available")?;
+        if !native.functions().contains(&function) {
+            bail!("Module {module} has no function {function}");
+        }
+        native
+            .call(function, args)
+            .with_context(|| format!("calling {module}'Z {function}"))
+    }
+}
+
+pub trait Modules {
+    fn modules(&mut self) -> &mut ModuleRegistry;
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{framework::NativeModule, value::Value};
+
+/// Mirrors lci's `SOCKS` module. Sockets are referred to from scripts by the
+/// NUMBR handle returned from `BIND`, `LISTN` and `KONN`.
+#[derive(Default)]
+pub struct Socks {
+    next_handle: i64,
+    listeners: HashMap<i64, TcpListener>,
+    streams: HashMap<i64, TcpStream>,
+}
+
+impl NativeModule for Socks {
+    fn name(&self) -> &str {
+        "SOCKS"
+    }
+
+    fn functions(&self) -> &[&str] {
+        &["BIND", "LISTN", "KONN", "PUT", "GET", "CLOSE"]
+    }
+
+    fn call(&mut self, function: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+        match function {
+            "BIND" => {
+                let [addr, port] = expect_args::<2>(function, args)?;
+                let listener = TcpListener::bind((host(&expect_yarn(addr)?), expect_port(port)?))
+                    .context("binding socket")?;
+                Ok(self.insert_listener(listener))
+            }
+            "LISTN" => {
+                let [local] = expect_args::<1>(function, args)?;
+                let (stream, _) = self
+                    .listener(&local)?
+                    .accept()
+                    .context("accepting connection")?;
+                Ok(self.insert_stream(stream))
+            }
+            "KONN" => {
+                let [local, addr, port] = expect_args::<3>(function, args)?;
+                self.listener(&local)?;
+                let stream = TcpStream::connect((host(&expect_yarn(addr)?), expect_port(port)?))
+                    .context("connecting to remote")?;
+                Ok(self.insert_stream(stream))
+            }
+            "PUT" => {
+                let [local, remote, data] = expect_args::<3>(function, args)?;
+                self.listener(&local)?;
+                let data = expect_yarn(data)?;
+                self.stream(&remote)?
+                    .write_all(data.as_bytes())
+                    .context("writing to remote")?;
+                Ok(Value::Numbr(data.len() as i64))
+            }
+            "GET" => {
+                let [local, remote, amount] = expect_args::<3>(function, args)?;
+                self.listener(&local)?;
+                let amount = expect_numbr(amount)?;
+                let mut buf = Vec::new();
+                self.stream(&remote)?
+                    .take(amount.max(0) as u64)
+                    .read_to_end(&mut buf)
+                    .context("reading from remote")?;
+                Ok(Value::Yarn(String::from_utf8_lossy(&buf).into_owned()))
+            }
+            "CLOSE" => {
+                let [handle] = expect_args::<1>(function, args)?;
+                let handle = expect_numbr(handle)?;
+                if self.listeners.remove(&handle).is_none()
+                    && self.streams.remove(&handle).is_none()
+                {
+                    bail!("No socket with handle {handle}");
+                }
+                Ok(Value::Noob)
+            }
+            _ => bail!("Unknown function {function}"),
+        }
+    }
+}
+
+impl Socks {
+    /// Address a socket from `BIND` ended up on, e.g. after binding port 0.
+    pub fn local_addr(&self, handle: &Value) -> anyhow::Result<SocketAddr> {
+        self.listener(handle)?
+            .local_addr()
+            .context("reading bound address")
+    }
+
+    fn next_handle(&mut self) -> i64 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+
+    fn insert_listener(&mut self, listener: TcpListener) -> Value {
+        let handle = self.next_handle();
+        self.listeners.insert(handle, listener);
+        Value::Numbr(handle)
+    }
+
+    fn insert_stream(&mut self, stream: TcpStream) -> Value {
+        let handle = self.next_handle();
+        self.streams.insert(handle, stream);
+        Value::Numbr(handle)
+    }
+
+    fn listener(&self, handle: &Value) -> anyhow::Result<&TcpListener> {
+        let handle = expect_numbr(handle.clone())?;
+        self.listeners
+            .get(&handle)
+            .with_context(|| format!("No bound socket with handle {handle}"))
+    }
+
+    fn stream(&mut self, handle: &Value) -> anyhow::Result<&mut TcpStream> {
+        let handle = expect_numbr(handle.clone())?;
+        self.streams
+            .get_mut(&handle)
+            .with_context(|| format!("No connection with handle {handle}"))
+    }
+}
+
+/// lci uses `ANY` to bind on every interface.
+fn host(addr: &str) -> &str {
+    match addr {
+        "ANY" => "0.0.0.0",
+        addr => addr,
+    }
+}
+
+fn expect_args<const N: usize>(function: &str, args: Vec<Value>) -> anyhow::Result<[Value; N]> {
+    let len = args.len();
+    args.try_into()
+        .map_err(|_| anyhow::anyhow!("{function} expects {N} arguments but got {len}"))
+}
+
+fn expect_yarn(value: Value) -> anyhow::Result<String> {
+    match value {
+        Value::Yarn(yarn) => Ok(yarn),
+        value => bail!("Expected YARN but got {}", value.type_name()),
+    }
+}
+
+fn expect_numbr(value: Value) -> anyhow::Result<i64> {
+    match value {
+        Value::Numbr(numbr) => Ok(numbr),
+        value => bail!("Expected NUMBR but got {}", value.type_name()),
+    }
+}
+
+fn expect_port(value: Value) -> anyhow::Result<u16> {
+    let port = expect_numbr(value)?;
+    u16::try_from(port).with_context(|| format!("Invalid port {port}"))
+}
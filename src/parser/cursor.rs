@@ -0,0 +1,439 @@
+//! Lookahead over the token stream. Spaces, comments and line continuations
+//! are skipped here so the grammar only sees significant tokens.
+
+use std::io::BufRead;
+
+use crate::{
+    diagnostic::{Diagnostic, ErrorCode, Position, Span},
+    framework::{HandleTokenProcessingError, TokenProcessingError},
+    tokenizer::{candidates, KeywordToken, Token, TokenType},
+};
+
+use super::{grammar::is_ident, PResult, Parse, ParseError, UnreadableSource};
+
+/// Number of words in the longest multi-word keyword, `IF U SAY SO`.
+const LONGEST_PHRASE: usize = 4;
+
+/// Words starting a multi-word keyword without completing it.
+pub(super) struct IncompleteKeyword {
+    words: Vec<String>,
+    /// Keywords the words could have been the start of.
+    candidates: Vec<KeywordToken>,
+    span: Span,
+}
+
+impl IncompleteKeyword {
+    /// Whether the words are a single name, which means something without
+    /// the rest of a keyword.
+    pub(super) fn is_name(&self) -> bool {
+        matches!(self.words.as_slice(), [word] if is_ident(word))
+    }
+}
+
+impl<H, R> Parse<'_, H, R>
+where
+    H: HandleTokenProcessingError,
+    R: BufRead,
+{
+    pub(super) fn handler(&mut self) -> &mut H {
+        self.lexer.handler()
+    }
+
+    pub(super) fn emit(&mut self, diagnostic: Diagnostic) -> anyhow::Result<()> {
+        self.handler().emit(diagnostic)
+    }
+
+    /// Token `offset` places after the current one, without skipping
+    /// anything. Tokens are read from the lexer as they are looked at.
+    pub(super) fn raw(&mut self, offset: usize) -> Option<Token> {
+        while self.lookahead.len() <= offset && self.unreadable.is_none() {
+            match self.lexer.next() {
+                Some(Ok(token)) => {
+                    self.last = Some(Span::from(&token));
+                    self.lookahead.push_back(token);
+                }
+                Some(Err(err)) => self.unreadable = Some(err),
+                None => break,
+            }
+        }
+        self.lookahead.get(offset).cloned()
+    }
+
+    pub(super) fn bump(&mut self) -> Option<Token> {
+        let token = self.advance()?;
+        self.prev = Some(token.clone());
+        Some(token)
+    }
+
+    /// Consumes the next `n` tokens without looking at them.
+    pub(super) fn skip(&mut self, n: usize) {
+        for _ in 0..n {
+            self.advance();
+        }
+    }
+
+    /// Consumes the next token.
+    fn advance(&mut self) -> Option<Token> {
+        self.raw(0)?;
+        self.lookahead.pop_front()
+    }
+
+    /// Fails with the reason the lexer stopped, if it could not read the
+    /// whole source.
+    pub(super) fn check_readable(&mut self) -> anyhow::Result<()> {
+        match self.unreadable.take() {
+            Some(err) => Err(UnreadableSource(err).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Next significant token, or `None` at the end of the file.
+    pub(super) fn peek(&mut self) -> anyhow::Result<Option<Token>> {
+        loop {
+            let Some(token) = self.raw(0) else {
+                self.check_readable()?;
+                return Ok(None);
+            };
+            match &token.t_type {
+                TokenType::Space(_) => self.skip(1),
+                TokenType::Keyword(KeywordToken::Btw) => {
+                    while self
+                        .raw(0)
+                        .is_some_and(|token| token.t_type != TokenType::NewLine)
+                    {
+                        self.skip(1);
+                    }
+                }
+                TokenType::Keyword(KeywordToken::OBtw) => self.multiline_comment(&token)?,
+                TokenType::Period => self.line_continuation()?,
+                TokenType::Word(word)
+                    if word == "…"
+                        && self
+                            .raw(1)
+                            .is_some_and(|token| token.t_type == TokenType::NewLine) =>
+                {
+                    self.skip(2);
+                    self.empty_line_after_join()?;
+                }
+                _ => return Ok(Some(token)),
+            }
+        }
+    }
+
+    fn multiline_comment(&mut self, obtw: &Token) -> anyhow::Result<()> {
+        self.skip(1);
+        loop {
+            match self.raw(0) {
+                None => {
+                    self.check_readable()?;
+                    if !self.eof_reported {
+                        self.emit(
+                            Diagnostic::error(
+                                ErrorCode::UnexpectedEof,
+                                "Unterminated `OBTW` comment",
+                            )
+                            .with_span(obtw)
+                            .with_help("Close the comment with TLDR"),
+                        )?;
+                        self.eof_reported = true;
+                    }
+                    return Ok(());
+                }
+                Some(token) if token.t_type == TokenType::Keyword(KeywordToken::Tldr) => {
+                    self.skip(1);
+                    break;
+                }
+                Some(_) => self.skip(1),
+            }
+        }
+
+        while self.raw(0).is_some_and(|token| token.t_type.is_space()) {
+            self.skip(1);
+        }
+        match self.raw(0) {
+            None => {}
+            Some(token) if matches!(token.t_type, TokenType::NewLine | TokenType::Comma) => {}
+            Some(token) => {
+                self.emit(
+                    Diagnostic::error(ErrorCode::ExpectedNewline, "Expected newline after TLDR")
+                        .with_span(token),
+                )?;
+                while self
+                    .raw(0)
+                    .is_some_and(|token| token.t_type != TokenType::NewLine)
+                {
+                    self.skip(1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `...` joins the next line onto this one. Periods are not valid
+    /// anywhere else outside of numbers and YARNs.
+    fn line_continuation(&mut self) -> anyhow::Result<()> {
+        let periods = (0..3)
+            .take_while(|offset| {
+                self.raw(*offset)
+                    .is_some_and(|token| token.t_type == TokenType::Period)
+            })
+            .count();
+        if periods < 3 {
+            let next = self.raw(periods);
+            let message = format!("Unexpected {}. Expected `.`", self.describe(next.as_ref()));
+            let token = next.or(self.raw(periods - 1)).expect("at least one period");
+            self.emit(
+                Diagnostic::error(ErrorCode::InvalidLineContinuation, message).with_span(token),
+            )?;
+            self.skip(periods);
+            return Ok(());
+        }
+
+        self.skip(3);
+        while self.raw(0).is_some_and(|token| token.t_type.is_space()) {
+            self.skip(1);
+        }
+        match self.raw(0) {
+            Some(token) if token.t_type == TokenType::NewLine => {
+                self.skip(1);
+                self.empty_line_after_join()?;
+            }
+            Some(token) => {
+                self.emit(
+                    Diagnostic::error(
+                        ErrorCode::InvalidLineContinuation,
+                        format!("Unexpected {}. Expected newline", token.t_type.describe()),
+                    )
+                    .with_span(token),
+                )?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn empty_line_after_join(&mut self) -> anyhow::Result<()> {
+        while self.raw(0).is_some_and(|token| token.t_type.is_space()) {
+            self.skip(1);
+        }
+        if let Some(token) = self
+            .raw(0)
+            .filter(|token| token.t_type == TokenType::NewLine)
+        {
+            self.emit(
+                Diagnostic::error(
+                    ErrorCode::InvalidLineContinuation,
+                    "invalid newline after join".to_string(),
+                )
+                .with_span(token),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Up to `n` space separated words starting at the next significant
+    /// token. Keywords count as words, multi-word ones as a single word.
+    pub(super) fn peek_words(&mut self, n: usize) -> anyhow::Result<Vec<String>> {
+        let mut words = Vec::new();
+        if self.peek()?.is_none() {
+            return Ok(words);
+        }
+        let mut offset = 0;
+        while words.len() < n {
+            match self.raw(offset).and_then(|token| word(&token.t_type)) {
+                Some(word) => words.push(word),
+                None => break,
+            }
+            offset += 1;
+            if !self
+                .raw(offset)
+                .is_some_and(|token| token.t_type.is_space())
+            {
+                break;
+            }
+            while self
+                .raw(offset)
+                .is_some_and(|token| token.t_type.is_space())
+            {
+                offset += 1;
+            }
+        }
+        Ok(words)
+    }
+
+    /// Whether the next token is the keyword or word `phrase`, e.g.
+    /// `"IM IN YR"`.
+    pub(super) fn at(&mut self, phrase: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .peek()?
+            .and_then(|token| word(&token.t_type))
+            .as_deref()
+            == Some(phrase))
+    }
+
+    /// Consumes `phrase` if it comes next.
+    pub(super) fn eat(&mut self, phrase: &str) -> anyhow::Result<Option<Span>> {
+        if !self.at(phrase)? {
+            return Ok(None);
+        }
+        Ok(self.bump().map(Span::from))
+    }
+
+    pub(super) fn expect(&mut self, phrase: &str) -> PResult<Span> {
+        if let Some(span) = self.eat(phrase)? {
+            return Ok(span);
+        }
+        if let Some(incomplete) = self.incomplete_keyword()? {
+            if let Some(keyword) = incomplete
+                .candidates
+                .iter()
+                .find(|keyword| keyword.to_string() == phrase)
+            {
+                let candidates = vec![*keyword];
+                return self.report_incomplete(IncompleteKeyword {
+                    candidates,
+                    ..incomplete
+                });
+            }
+        }
+        self.unexpected(Some(&format!("`{phrase}`")))
+    }
+
+    /// The words from the next token on when they start a multi-word
+    /// keyword without completing it, e.g. `IM OUTTA` without `YR`.
+    pub(super) fn incomplete_keyword(&mut self) -> anyhow::Result<Option<IncompleteKeyword>> {
+        let words = self.peek_words(LONGEST_PHRASE)?;
+        let Some(len) = (1..=words.len())
+            .take_while(|len| !candidates(&words[..*len]).is_empty())
+            .last()
+        else {
+            return Ok(None);
+        };
+
+        // The tokens of the words, skipping the spaces between them
+        let start = self.peek_span()?;
+        let mut end = start;
+        let mut offset = 0;
+        for _ in 0..len {
+            while self
+                .raw(offset)
+                .is_some_and(|token| token.t_type.is_space())
+            {
+                offset += 1;
+            }
+            end = self.raw(offset).expect("peeked as a word").into();
+            offset += 1;
+        }
+        Ok(Some(IncompleteKeyword {
+            words: words[..len].to_vec(),
+            candidates: candidates(&words[..len]),
+            span: start.to(end),
+        }))
+    }
+
+    /// Reports `incomplete` where a keyword was expected, suggesting the
+    /// keywords it could have been.
+    pub(super) fn report_incomplete<T>(&mut self, incomplete: IncompleteKeyword) -> PResult<T> {
+        let candidates: Vec<_> = incomplete
+            .candidates
+            .iter()
+            .map(|keyword| format!("`{keyword}`"))
+            .collect();
+        self.emit(
+            Diagnostic::error(
+                ErrorCode::IncompleteKeyword,
+                format!("Incomplete keyword `{}`", incomplete.words.join(" ")),
+            )
+            .with_span(incomplete.span)
+            .with_help(format!("Did you mean {}?", candidates.join(" or "))),
+        )?;
+        Err(ParseError::Reported)
+    }
+
+    /// Reports the next token as unexpected.
+    pub(super) fn unexpected<T>(&mut self, expected: Option<&str>) -> PResult<T> {
+        let token = self.peek()?;
+        let mut err = format!("Unexpected {}", self.describe(token.as_ref()));
+        if let Some(expected) = expected {
+            err.push_str(&format!(". Expected {expected}"));
+        }
+        match token {
+            Some(token) => self
+                .handler()
+                .handle_err(TokenProcessingError { token: &token, err })?,
+            None => {
+                let diagnostic = Diagnostic::error(ErrorCode::UnexpectedEof, err);
+                let span = self.eof_span();
+                self.emit(diagnostic.with_span(span))?;
+                self.eof_reported = true;
+            }
+        }
+        Err(ParseError::Reported)
+    }
+
+    pub(super) fn describe(&self, token: Option<&Token>) -> String {
+        match token {
+            Some(token) => token.t_type.describe(),
+            None => "end of file".to_string(),
+        }
+    }
+
+    /// Whether the next token ends the current statement.
+    pub(super) fn at_stmt_end(&mut self) -> anyhow::Result<bool> {
+        Ok(match self.peek()? {
+            None => true,
+            Some(token) => matches!(token.t_type, TokenType::NewLine | TokenType::Comma),
+        })
+    }
+
+    pub(super) fn end_of_stmt(&mut self) -> PResult<()> {
+        match self.at_stmt_end()? {
+            true => Ok(()),
+            false => self.unexpected(None),
+        }
+    }
+
+    /// Skips the rest of a statement after an error has been reported.
+    pub(super) fn synchronize(&mut self) {
+        let mut in_yarn = false;
+        while let Some(token) = self.raw(0) {
+            match token.t_type {
+                TokenType::NewLine => break,
+                TokenType::Comma | TokenType::Keyword(KeywordToken::OBtw) if !in_yarn => break,
+                TokenType::Quote => in_yarn = !in_yarn,
+                _ => {}
+            }
+            self.skip(1);
+        }
+    }
+
+    /// Start of the next significant token.
+    pub(super) fn peek_span(&mut self) -> anyhow::Result<Span> {
+        Ok(match self.peek()? {
+            Some(token) => token.into(),
+            None => self.eof_span(),
+        })
+    }
+
+    /// The last token consumed.
+    pub(super) fn prev_span(&self) -> Span {
+        self.prev
+            .as_ref()
+            .map_or_else(|| self.eof_span(), Span::from)
+    }
+
+    /// The last token of the file, once it has all been read.
+    pub(super) fn eof_span(&self) -> Span {
+        self.last
+            .unwrap_or(Span::from(Position { line: 1, column: 1 }))
+    }
+}
+
+/// Text of a word or keyword token.
+pub(super) fn word(t_type: &TokenType) -> Option<String> {
+    match t_type {
+        TokenType::Word(word) => Some(word.clone()),
+        TokenType::Keyword(keyword) => Some(keyword.to_string()),
+        _ => None,
+    }
+}
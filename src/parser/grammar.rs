@@ -0,0 +1,1010 @@
+//! Recursive descent over statements and LOLCODE's prefix expressions.
+
+use std::io::BufRead;
+
+use crate::{
+    ast::{
+        BinaryOp, Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, Loop, LoopCondition,
+        LoopOp, LoopUpdate, NaryOp, Stmt, StmtKind, Type,
+    },
+    diagnostic::{Diagnostic, ErrorCode, Span},
+    framework::HandleTokenProcessingError,
+    tokenizer::{KeywordToken, Token, TokenType},
+};
+
+use super::{cursor::word, Feature, LolCodeVersion, PResult, Parse, ParseError};
+
+const BINARY_OPS: [(&str, BinaryOp); 12] = [
+    ("SUM OF", BinaryOp::Sum),
+    ("DIFF OF", BinaryOp::Diff),
+    ("PRODUKT OF", BinaryOp::Produkt),
+    ("QUOSHUNT OF", BinaryOp::Quoshunt),
+    ("MOD OF", BinaryOp::Mod),
+    ("BIGGR OF", BinaryOp::Biggr),
+    ("SMALLR OF", BinaryOp::Smallr),
+    ("BOTH OF", BinaryOp::Both),
+    ("EITHER OF", BinaryOp::Either),
+    ("WON OF", BinaryOp::Won),
+    ("BOTH SAEM", BinaryOp::Saem),
+    ("DIFFRINT", BinaryOp::Diffrint),
+];
+
+const NARY_OPS: [(&str, NaryOp); 3] = [
+    ("ALL OF", NaryOp::All),
+    ("ANY OF", NaryOp::Any),
+    ("SMOOSH", NaryOp::Smoosh),
+];
+
+const TYPES: [(&str, Type); 5] = [
+    ("NOOB", Type::Noob),
+    ("TROOF", Type::Troof),
+    ("NUMBR", Type::Numbr),
+    ("NUMBAR", Type::Numbar),
+    ("YARN", Type::Yarn),
+];
+
+/// Words which are part of the language and so cannot name variables.
+const RESERVED: [&str; 64] = [
+    "A",
+    "ALL",
+    "AN",
+    "ANY",
+    "BIGGR",
+    "BOTH",
+    "BTW",
+    "BUKKIT",
+    "CAN",
+    "DIFF",
+    "DIFFRINT",
+    "EITHER",
+    "FAIL",
+    "FOUND",
+    "GIMMEH",
+    "GTFO",
+    "HAI",
+    "HAS",
+    "HOW",
+    "IF",
+    "IM",
+    "IN",
+    "INVISIBLE",
+    "IS",
+    "ITZ",
+    "IZ",
+    "KTHXBYE",
+    "MAEK",
+    "MEBBE",
+    "MKAY",
+    "MOD",
+    "NERFIN",
+    "NO",
+    "NOOB",
+    "NOT",
+    "NOW",
+    "NUMBAR",
+    "NUMBR",
+    "O",
+    "OBTW",
+    "OF",
+    "OIC",
+    "OMG",
+    "OMGWTF",
+    "OUTTA",
+    "PRODUKT",
+    "QUOSHUNT",
+    "R",
+    "SAEM",
+    "SAY",
+    "SMALLR",
+    "SMOOSH",
+    "SO",
+    "SRS",
+    "SUM",
+    "TIL",
+    "TLDR",
+    "TROOF",
+    "U",
+    "UPPIN",
+    "VISIBLE",
+    "WILE",
+    "WIN",
+    "WON",
+];
+
+pub(super) fn is_ident(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RESERVED.contains(&word)
+        && !TYPES.iter().any(|(name, _)| *name == word)
+}
+
+/// `<bukkit>'Z`, accessing a slot of a BUKKIT.
+fn is_slot(word: &str) -> bool {
+    word.strip_suffix("'Z").is_some_and(is_ident)
+}
+
+fn is_numbr(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+impl<H, R> Parse<'_, H, R>
+where
+    H: HandleTokenProcessingError,
+    R: BufRead,
+{
+    pub(super) fn program(&mut self) -> PResult<LolCodeProgram> {
+        let hai = self.hai()?;
+        self.version = self.version_decl()?;
+        let body = self.block(&["KTHXBYE"])?;
+        let kthxbye = self.expect("KTHXBYE")?;
+
+        loop {
+            match self.peek()? {
+                None => break,
+                Some(token) if token.t_type == TokenType::NewLine => {
+                    self.bump();
+                }
+                Some(token) => {
+                    self.emit(
+                        Diagnostic::error(
+                            ErrorCode::UnexpectedToken,
+                            format!("Unexpected {}", token.t_type.describe()),
+                        )
+                        .with_span(token)
+                        .with_help("Nothing may follow KTHXBYE"),
+                    )?;
+                    self.synchronize();
+                    self.bump();
+                }
+            }
+        }
+
+        Ok(LolCodeProgram {
+            version: self.version.clone(),
+            body,
+            span: hai.to(kthxbye),
+        })
+    }
+
+    /// Skips to `HAI`, reporting anything else in the way.
+    fn hai(&mut self) -> PResult<Span> {
+        loop {
+            match self.peek()? {
+                None => {
+                    if !self.eof_reported {
+                        let diagnostic = Diagnostic::error(ErrorCode::MissingHai, "Expected `HAI`");
+                        let diagnostic = match self.last {
+                            Some(span) => diagnostic.with_span(span),
+                            None => diagnostic,
+                        };
+                        self.emit(diagnostic)?;
+                    }
+                    return Err(ParseError::Reported);
+                }
+                Some(token) => match &token.t_type {
+                    TokenType::Keyword(KeywordToken::Hai) => {
+                        self.bump();
+                        return Ok(token.into());
+                    }
+                    TokenType::NewLine => {
+                        self.bump();
+                    }
+                    t_type => {
+                        self.emit(
+                            Diagnostic::error(
+                                ErrorCode::MissingHai,
+                                format!(
+                                    "Unexpected {}. Expected `{}`",
+                                    t_type.describe(),
+                                    KeywordToken::Hai
+                                ),
+                            )
+                            .with_span(token)
+                            .with_help("Programs start with HAI followed by the version"),
+                        )?;
+                        self.synchronize();
+                        self.bump();
+                    }
+                },
+            }
+        }
+    }
+
+    /// The version following `HAI`, falling back to the default when it is
+    /// missing or invalid.
+    fn version_decl(&mut self) -> PResult<LolCodeVersion> {
+        if self.at_stmt_end()? {
+            return Ok(LolCodeVersion::DEFAULT);
+        }
+        let version = match self.version_number() {
+            Ok(version) => version,
+            Err(ParseError::Reported) => {
+                self.synchronize();
+                return Ok(LolCodeVersion::DEFAULT);
+            }
+            Err(err) => return Err(err),
+        };
+        match self.end_of_stmt() {
+            Err(ParseError::Reported) => self.synchronize(),
+            result => result?,
+        }
+        Ok(version)
+    }
+
+    fn version_number(&mut self) -> PResult<LolCodeVersion> {
+        let help = format!(
+            "Supported versions are {}",
+            LolCodeVersion::supported_list()
+        );
+        let invalid = |message: String, token: &Token| {
+            Diagnostic::error(ErrorCode::InvalidVersion, message)
+                .with_span(token)
+                .with_help(help.clone())
+        };
+
+        let token = self.peek()?.expect("not at end of statement");
+        let TokenType::Word(word) = &token.t_type else {
+            let message = format!("Unexpected {}. Expected version", token.t_type.describe());
+            self.emit(invalid(message, &token))?;
+            return Err(ParseError::Reported);
+        };
+        let Ok(major) = word.parse::<i32>() else {
+            let message = format!("Unable to parse major version from `{word}`");
+            self.emit(invalid(message, &token))?;
+            return Err(ParseError::Reported);
+        };
+        let start = self.bump().expect("peeked");
+
+        // The version is a single word, so no spaces around the period
+        match self.raw(0) {
+            Some(token) if token.t_type == TokenType::Period => self.skip(1),
+            token => {
+                let message = format!(
+                    "Unexpected {}. Expected `.` followed by the minor version",
+                    self.describe(token.as_ref())
+                );
+                self.emit(invalid(message, &token.unwrap_or(start)))?;
+                return Err(ParseError::Reported);
+            }
+        }
+        let minor = match &self.raw(0) {
+            Some(
+                token @ Token {
+                    t_type: TokenType::Word(word),
+                    ..
+                },
+            ) => match word.parse::<i32>() {
+                Ok(minor) => {
+                    self.bump();
+                    minor
+                }
+                Err(_) => {
+                    let message = format!("Unable to parse minor version from `{word}`");
+                    self.emit(invalid(message, token))?;
+                    return Err(ParseError::Reported);
+                }
+            },
+            token => {
+                let message = format!(
+                    "Unexpected {}. Expected minor version",
+                    self.describe(token.as_ref())
+                );
+                self.emit(invalid(message, token.as_ref().unwrap_or(&start)))?;
+                return Err(ParseError::Reported);
+            }
+        };
+
+        let version = LolCodeVersion::from((major, minor));
+        if !version.is_supported() {
+            self.emit(
+                Diagnostic::error(
+                    ErrorCode::UnsupportedVersion,
+                    format!("Unsupported version {version}"),
+                )
+                .with_span(Span::from(start).to(self.prev_span()))
+                .with_help(help),
+            )?;
+            return Ok(LolCodeVersion::DEFAULT);
+        }
+        Ok(version)
+    }
+
+    /// Statements up to, but not including, the first of `ends`.
+    fn block(&mut self, ends: &[&str]) -> PResult<Block> {
+        let start = self.peek_span()?;
+        let mut stmts: Vec<Stmt> = Vec::new();
+        loop {
+            let Some(token) = self.peek()? else {
+                if !self.eof_reported {
+                    let ends: Vec<_> = ends.iter().map(|end| format!("`{end}`")).collect();
+                    let mut diagnostic = Diagnostic::error(
+                        ErrorCode::UnexpectedEof,
+                        format!("Expected {}", ends.join(" or ")),
+                    )
+                    .with_span(self.eof_span());
+                    if ends == ["`KTHXBYE`"] {
+                        diagnostic = diagnostic.with_help("Programs end with KTHXBYE");
+                    }
+                    self.emit(diagnostic)?;
+                    self.eof_reported = true;
+                }
+                return Err(ParseError::Reported);
+            };
+            if matches!(token.t_type, TokenType::NewLine | TokenType::Comma) {
+                self.bump();
+                continue;
+            }
+            for end in ends {
+                if self.at(end)? {
+                    let span = match (stmts.first(), stmts.last()) {
+                        (Some(first), Some(last)) => first.span.to(last.span),
+                        _ => start,
+                    };
+                    return Ok(Block { stmts, span });
+                }
+            }
+
+            match self.stmt().and_then(|stmt| {
+                self.end_of_stmt()?;
+                Ok(stmt)
+            }) {
+                Ok(stmt) => stmts.push(stmt),
+                Err(ParseError::Reported) => self.synchronize(),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn stmt(&mut self) -> PResult<Stmt> {
+        let start = self.peek_span()?;
+        let words = self.peek_words(2)?;
+        let words: Vec<_> = words.iter().map(String::as_str).collect();
+
+        let kind = match words.as_slice() {
+            ["VISIBLE", ..] => self.visible(false)?,
+            ["INVISIBLE", ..] => {
+                // Parse the statement anyway so only the version is reported
+                self.require(Feature::Invisible, start)?;
+                self.visible(true)?
+            }
+            ["GIMMEH", ..] => {
+                self.expect("GIMMEH")?;
+                StmtKind::Gimmeh(self.ident()?)
+            }
+            ["CAN HAS", ..] => self.can_has()?,
+            ["HAS", ..] => {
+                self.emit(
+                    Diagnostic::error(ErrorCode::UnexpectedToken, "Unexpected `HAS`")
+                        .with_span(start)
+                        .with_help("Are you missing CAN?"),
+                )?;
+                return Err(ParseError::Reported);
+            }
+            ["I HAS A", ..] => {
+                self.expect("I HAS A")?;
+                let name = self.ident()?;
+                let init = match self.eat("ITZ")? {
+                    Some(_) if self.at_bukkit_init()? => {
+                        let span = self.peek_span()?;
+                        return self.unsupported("`BUKKIT`", span);
+                    }
+                    Some(_) => Some(self.expr()?),
+                    None => None,
+                };
+                StmtKind::Declare { name, init }
+            }
+            ["O", "HAI", ..] => return self.unsupported("`BUKKIT`", start),
+            ["O RLY?", ..] => self.if_stmt()?,
+            ["WTF?", ..] => self.switch()?,
+            ["IM IN YR", ..] => StmtKind::Loop(self.loop_stmt()?),
+            ["HOW IZ I", ..] => StmtKind::FuncDef(self.func_def()?),
+            ["FOUND YR", ..] => {
+                self.expect("FOUND YR")?;
+                StmtKind::Return(self.expr()?)
+            }
+            ["GTFO", ..] => {
+                self.expect("GTFO")?;
+                StmtKind::Break
+            }
+            [name, "R", ..] if is_ident(name) => {
+                let name = self.ident()?;
+                self.expect("R")?;
+                StmtKind::Assign {
+                    name,
+                    value: self.expr()?,
+                }
+            }
+            [name, "IS NOW A", ..] if is_ident(name) => {
+                let name = self.ident()?;
+                self.expect("IS NOW A")?;
+                StmtKind::CastVar {
+                    name,
+                    to: self.type_name()?,
+                }
+            }
+            _ => match self.incomplete_keyword()? {
+                // A lone name like YA needs no rest of a keyword
+                Some(incomplete) if !incomplete.is_name() => {
+                    return self.report_incomplete(incomplete)
+                }
+                _ if self.at_expr()? => StmtKind::Expr(self.expr()?),
+                _ => return self.unexpected(None),
+            },
+        };
+
+        Ok(Stmt {
+            kind,
+            span: start.to(self.prev_span()),
+        })
+    }
+
+    fn visible(&mut self, invisible: bool) -> PResult<StmtKind> {
+        self.bump();
+        let mut args = Vec::new();
+        let mut newline = true;
+        loop {
+            if self.eat("!")?.is_some() {
+                newline = false;
+                break;
+            }
+            if !self.at_expr()? {
+                break;
+            }
+            args.push(self.expr()?);
+        }
+        Ok(StmtKind::Visible {
+            args,
+            invisible,
+            newline,
+        })
+    }
+
+    fn can_has(&mut self) -> PResult<StmtKind> {
+        self.bump();
+        match &self.peek()? {
+            Some(
+                token @ Token {
+                    t_type: TokenType::Word(module),
+                    ..
+                },
+            ) => {
+                self.bump();
+                Ok(StmtKind::CanHas {
+                    module: Ident {
+                        name: module.trim_end_matches('?').to_owned(),
+                        span: token.into(),
+                    },
+                })
+            }
+            token => {
+                let token = token
+                    .clone()
+                    .or(self.prev.clone())
+                    .expect("CAN HAS was consumed");
+                self.emit(
+                    Diagnostic::error(ErrorCode::InvalidInclude, "Expected module to include")
+                        .with_span(token),
+                )?;
+                Err(ParseError::Reported)
+            }
+        }
+    }
+
+    fn if_stmt(&mut self) -> PResult<StmtKind> {
+        self.expect("O RLY?")?;
+        self.separators()?;
+        self.expect("YA RLY")?;
+        let then = self.block(&["MEBBE", "NO WAI", "OIC"])?;
+
+        let mut elifs = Vec::new();
+        while self.eat("MEBBE")?.is_some() {
+            let condition = self.expr()?;
+            elifs.push((condition, self.block(&["MEBBE", "NO WAI", "OIC"])?));
+        }
+        let otherwise = match self.eat("NO WAI")? {
+            Some(_) => Some(self.block(&["OIC"])?),
+            None => None,
+        };
+        self.expect("OIC")?;
+
+        Ok(StmtKind::If {
+            then,
+            elifs,
+            otherwise,
+        })
+    }
+
+    fn switch(&mut self) -> PResult<StmtKind> {
+        self.expect("WTF?")?;
+        self.separators()?;
+
+        let mut cases = Vec::new();
+        while self.eat("OMG")?.is_some() {
+            let literal = self.expr()?;
+            if !matches!(
+                literal.kind,
+                ExprKind::Noob
+                    | ExprKind::Troof(_)
+                    | ExprKind::Numbr(_)
+                    | ExprKind::Numbar(_)
+                    | ExprKind::Yarn(_)
+            ) {
+                self.emit(
+                    Diagnostic::error(ErrorCode::UnexpectedToken, "OMG takes a literal")
+                        .with_span(literal.span),
+                )?;
+                return Err(ParseError::Reported);
+            }
+            cases.push((literal, self.block(&["OMG", "OMGWTF", "OIC"])?));
+        }
+        let default = match self.eat("OMGWTF")? {
+            Some(_) => Some(self.block(&["OIC"])?),
+            None => None,
+        };
+        self.expect("OIC")?;
+
+        Ok(StmtKind::Switch { cases, default })
+    }
+
+    fn loop_stmt(&mut self) -> PResult<Loop> {
+        self.expect("IM IN YR")?;
+        let label = self.ident()?;
+
+        let op = match (self.eat("UPPIN")?, self.eat("NERFIN")?) {
+            (Some(_), _) => Some(LoopOp::Uppin),
+            (_, Some(_)) => Some(LoopOp::Nerfin),
+            _ => None,
+        };
+        let update = match op {
+            Some(op) => {
+                self.expect("YR")?;
+                Some(LoopUpdate {
+                    op,
+                    var: self.ident()?,
+                })
+            }
+            None => None,
+        };
+        let condition = match (self.eat("TIL")?, self.eat("WILE")?) {
+            (Some(_), _) => Some(LoopCondition::Til(self.expr()?)),
+            (_, Some(_)) => Some(LoopCondition::Wile(self.expr()?)),
+            _ => None,
+        };
+
+        let body = self.block(&["IM OUTTA YR"])?;
+        self.expect("IM OUTTA YR")?;
+        let end = self.ident()?;
+        if end.name != label.name {
+            self.emit(
+                Diagnostic::error(
+                    ErrorCode::UnexpectedToken,
+                    format!("Expected `IM OUTTA YR {}`", label.name),
+                )
+                .with_span(end.span)
+                .with_label(label.span, "loop opened here"),
+            )?;
+        }
+
+        Ok(Loop {
+            label,
+            update,
+            condition,
+            body,
+        })
+    }
+
+    fn func_def(&mut self) -> PResult<FuncDef> {
+        self.expect("HOW IZ I")?;
+        let name = self.ident()?;
+        let mut params = Vec::new();
+        if self.eat("YR")?.is_some() {
+            params.push(self.ident()?);
+            while self.eat("AN YR")?.is_some() {
+                params.push(self.ident()?);
+            }
+        }
+
+        let body = self.block(&["IF U SAY SO"])?;
+        self.expect("IF U SAY SO")?;
+        Ok(FuncDef { name, params, body })
+    }
+
+    /// Newlines and commas between the parts of a compound statement.
+    fn separators(&mut self) -> PResult<()> {
+        self.end_of_stmt()?;
+        while let Some(token) = self.peek()? {
+            if !matches!(token.t_type, TokenType::NewLine | TokenType::Comma) {
+                break;
+            }
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> PResult<Ident> {
+        match &self.peek()? {
+            Some(
+                token @ Token {
+                    t_type: TokenType::Word(name),
+                    ..
+                },
+            ) if is_ident(name) => {
+                self.bump();
+                Ok(Ident {
+                    name: name.clone(),
+                    span: token.into(),
+                })
+            }
+            _ => self.unexpected(Some("a name")),
+        }
+    }
+
+    /// `<module>'Z`, which lci writes after `I IZ` to call a function of a
+    /// module.
+    fn module_prefix(&mut self) -> PResult<Option<Ident>> {
+        let Some(
+            token @ Token {
+                t_type: TokenType::Word(word),
+                ..
+            },
+        ) = &self.peek()?
+        else {
+            return Ok(None);
+        };
+        let Some(name) = word.strip_suffix("'Z").filter(|name| is_ident(name)) else {
+            return Ok(None);
+        };
+        self.bump();
+        let mut prefix = token.clone();
+        prefix.end.column -= "'Z".len();
+        prefix.end.offset -= "'Z".len();
+        let span = Span::from(prefix);
+        Ok(Some(Ident {
+            name: name.to_owned(),
+            span,
+        }))
+    }
+
+    fn type_name(&mut self) -> PResult<Type> {
+        for (name, ty) in TYPES {
+            if self.eat(name)?.is_some() {
+                return Ok(ty);
+            }
+        }
+        if self.at("BUKKIT")? {
+            let span = self.peek_span()?;
+            return self.unsupported("`BUKKIT`", span);
+        }
+        self.unexpected(Some("a type"))
+    }
+
+    /// Whether `ITZ A BUKKIT` or `ITZ LIEK A <parent>` follows `ITZ`.
+    fn at_bukkit_init(&mut self) -> anyhow::Result<bool> {
+        let words = self.peek_words(2)?;
+        let words: Vec<_> = words.iter().map(String::as_str).collect();
+        Ok(matches!(words.as_slice(), ["A", "BUKKIT"] | ["LIEK", ..]))
+    }
+
+    /// Reports `feature` if the declared version does not have it. The
+    /// caller parses the construct anyway, so only the version is reported.
+    fn require(&mut self, feature: Feature, span: Span) -> PResult<()> {
+        if let Some(diagnostic) = self.version.require(feature) {
+            self.emit(diagnostic.with_span(span))?;
+        }
+        Ok(())
+    }
+
+    /// Reports the use of `feature`, an lci extension which is recognised
+    /// but not supported under any version.
+    fn unsupported<T>(&mut self, feature: &str, span: Span) -> PResult<T> {
+        self.emit(
+            Diagnostic::error(
+                ErrorCode::UnsupportedFeature,
+                format!("{feature} is not supported"),
+            )
+            .with_span(span),
+        )?;
+        Err(ParseError::Reported)
+    }
+
+    /// Whether an expression can start at the next token.
+    fn at_expr(&mut self) -> anyhow::Result<bool> {
+        let Some(token) = self.peek()? else {
+            return Ok(false);
+        };
+        if token.t_type == TokenType::Quote {
+            return Ok(true);
+        }
+        let Some(word) = word(&token.t_type) else {
+            return Ok(false);
+        };
+        if is_ident(&word)
+            || is_slot(&word)
+            || is_numbr(&word)
+            || ["WIN", "FAIL", "NOOB", "NOT", "MAEK", "SRS"].contains(&word.as_str())
+        {
+            return Ok(true);
+        }
+        for phrase in BINARY_OPS
+            .iter()
+            .map(|(phrase, _)| phrase)
+            .chain(NARY_OPS.iter().map(|(phrase, _)| phrase))
+            .chain(&["I IZ"])
+        {
+            if self.at(phrase)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn expr(&mut self) -> PResult<Expr> {
+        let start = self.peek_span()?;
+        let kind = self.expr_kind()?;
+        Ok(Expr {
+            kind,
+            span: start.to(self.prev_span()),
+        })
+    }
+
+    fn expr_kind(&mut self) -> PResult<ExprKind> {
+        let Some(token) = self.peek()? else {
+            return self.unexpected(Some("an expression"));
+        };
+        if token.t_type == TokenType::Quote {
+            return self.yarn();
+        }
+
+        for (phrase, op) in BINARY_OPS {
+            if self.eat(phrase)?.is_some() {
+                let left = Box::new(self.expr()?);
+                self.eat("AN")?;
+                let right = Box::new(self.expr()?);
+                return Ok(ExprKind::Binary { op, left, right });
+            }
+        }
+        for (phrase, op) in NARY_OPS {
+            if self.eat(phrase)?.is_some() {
+                let mut args = vec![self.expr()?];
+                loop {
+                    let an = self.eat("AN")?.is_some();
+                    if self.eat("MKAY")?.is_some() {
+                        break;
+                    }
+                    // MKAY may be left off at the end of the line
+                    if !an && !self.at_expr()? {
+                        break;
+                    }
+                    args.push(self.expr()?);
+                }
+                return Ok(ExprKind::Nary { op, args });
+            }
+        }
+        if self.eat("NOT")?.is_some() {
+            return Ok(ExprKind::Not(Box::new(self.expr()?)));
+        }
+        if self.eat("MAEK")?.is_some() {
+            let expr = Box::new(self.expr()?);
+            self.eat("A")?;
+            return Ok(ExprKind::Cast {
+                expr,
+                to: self.type_name()?,
+            });
+        }
+        if self.eat("I IZ")?.is_some() {
+            let module = self.module_prefix()?;
+            if let Some(module) = &module {
+                self.require(Feature::ModuleCall, module.span)?;
+            }
+            let name = self.ident()?;
+            let args = self.call_args()?;
+            return Ok(ExprKind::Call { module, name, args });
+        }
+
+        let word = word(&token.t_type);
+        match word.as_deref() {
+            Some("WIN") => {
+                self.bump();
+                Ok(ExprKind::Troof(true))
+            }
+            Some("FAIL") => {
+                self.bump();
+                Ok(ExprKind::Troof(false))
+            }
+            Some("NOOB") => {
+                self.bump();
+                Ok(ExprKind::Noob)
+            }
+            Some("SRS") => self.unsupported("`SRS`", token.into()),
+            Some(word) if is_slot(word) => self.unsupported("`BUKKIT`", token.into()),
+            Some(word) if is_numbr(word) => self.number(word),
+            Some(word) if is_ident(word) => {
+                let ident = self.ident()?;
+                if self.eat("IZ")?.is_none() {
+                    return Ok(ExprKind::Var(ident));
+                }
+                self.require(Feature::ModuleCall, ident.span)?;
+                let name = self.ident()?;
+                let args = self.call_args()?;
+                Ok(ExprKind::Call {
+                    module: Some(ident),
+                    name,
+                    args,
+                })
+            }
+            _ => self.unexpected(Some("an expression")),
+        }
+    }
+
+    /// `[YR <arg> [AN YR <arg>]...] MKAY`
+    fn call_args(&mut self) -> PResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        if self.eat("YR")?.is_some() {
+            args.push(self.expr()?);
+            while self.eat("AN YR")?.is_some() {
+                args.push(self.expr()?);
+            }
+        }
+        if self.eat("MKAY")?.is_none() && !self.at_stmt_end()? {
+            return self.unexpected(Some("`MKAY`"));
+        }
+        Ok(args)
+    }
+
+    /// NUMBR, or NUMBAR when directly followed by a period and digits.
+    fn number(&mut self, whole: &str) -> PResult<ExprKind> {
+        let token = self.bump().expect("peeked");
+        let fraction = match (self.raw(0), self.raw(1)) {
+            (
+                Some(Token {
+                    t_type: TokenType::Period,
+                    ..
+                }),
+                Some(Token {
+                    t_type: TokenType::Word(fraction),
+                    ..
+                }),
+            ) if fraction.chars().all(|c| c.is_ascii_digit()) => Some(fraction),
+            _ => None,
+        };
+
+        let kind = match fraction {
+            Some(fraction) => {
+                self.bump();
+                self.bump();
+                format!("{whole}.{fraction}")
+                    .parse()
+                    .map(ExprKind::Numbar)
+                    .ok()
+            }
+            None => whole.parse().map(ExprKind::Numbr).ok(),
+        };
+        match kind {
+            Some(kind) => Ok(kind),
+            None => {
+                self.emit(
+                    Diagnostic::error(
+                        ErrorCode::UnexpectedToken,
+                        format!("`{whole}` is out of range for a NUMBR"),
+                    )
+                    .with_span(Span::from(token).to(self.prev_span())),
+                )?;
+                Err(ParseError::Reported)
+            }
+        }
+    }
+
+    /// A quoted YARN. The tokenizer splits YARNs like any other text so the
+    /// tokens are joined back up here.
+    fn yarn(&mut self) -> PResult<ExprKind> {
+        let open = self.bump().expect("peeked");
+        let mut text = String::new();
+        loop {
+            let Some(token) = self.raw(0) else {
+                self.emit(
+                    Diagnostic::error(ErrorCode::UnterminatedString, "Unterminated YARN")
+                        .with_span(open)
+                        .with_help("Close the YARN with \""),
+                )?;
+                return Err(ParseError::Reported);
+            };
+            match &token.t_type {
+                TokenType::NewLine => {
+                    self.emit(
+                        Diagnostic::error(
+                            ErrorCode::UnterminatedString,
+                            "Unexpected newline".to_string(),
+                        )
+                        .with_span(token)
+                        .with_help("Close the YARN with \""),
+                    )?;
+                    return Err(ParseError::Reported);
+                }
+                TokenType::Quote => {
+                    self.skip(1);
+                    let colons = text.chars().rev().take_while(|c| *c == ':').count();
+                    if colons % 2 == 0 {
+                        self.prev = Some(token);
+                        break;
+                    }
+                    // :" is an escaped quote
+                    text.push('"');
+                }
+                t_type => {
+                    self.skip(1);
+                    text.push_str(&t_type.to_string());
+                }
+            }
+        }
+
+        let span = Span::from(open).to(self.prev_span());
+        self.unescape(&text, span)
+    }
+
+    /// Expands escapes, turning `:{var}` interpolation into a SMOOSH.
+    fn unescape(&mut self, text: &str, span: Span) -> PResult<ExprKind> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != ':' {
+                literal.push(c);
+                continue;
+            }
+            let escape = chars.next();
+            match escape {
+                Some(')') => literal.push('\n'),
+                Some('>') => literal.push('\t'),
+                Some('o') => literal.push('\u{7}'),
+                Some('"') => literal.push('"'),
+                Some(':') => literal.push(':'),
+                Some('(') => {
+                    let hex: String = chars.by_ref().take_while(|c| *c != ')').collect();
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(c) => literal.push(c),
+                        None => return self.invalid_escape(format!(":({hex})"), span),
+                    }
+                }
+                Some('{') => {
+                    let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    if !is_ident(&name) {
+                        return self.invalid_escape(format!(":{{{name}}}"), span);
+                    }
+                    if !literal.is_empty() {
+                        pieces.push(ExprKind::Yarn(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(ExprKind::Var(Ident { name, span }));
+                }
+                escape => {
+                    let escape = escape.map(String::from).unwrap_or_default();
+                    return self.invalid_escape(format!(":{escape}"), span);
+                }
+            }
+        }
+
+        if pieces.is_empty() {
+            return Ok(ExprKind::Yarn(literal));
+        }
+        if !literal.is_empty() {
+            pieces.push(ExprKind::Yarn(literal));
+        }
+        Ok(ExprKind::Nary {
+            op: NaryOp::Smoosh,
+            args: pieces.into_iter().map(|kind| Expr { kind, span }).collect(),
+        })
+    }
+
+    fn invalid_escape<T>(&mut self, escape: String, span: Span) -> PResult<T> {
+        self.emit(
+            Diagnostic::error(
+                ErrorCode::InvalidEscape,
+                format!("Unknown escape `{escape}` in YARN"),
+            )
+            .with_span(span)
+            .with_help("Write :: for a literal colon"),
+        )?;
+        Err(ParseError::Reported)
+    }
+}
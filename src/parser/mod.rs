@@ -1,148 +1,198 @@
-mod scope;
+mod cursor;
+mod grammar;
 
-use anyhow::{bail, Context};
-use mediator_tracing::tracing::{debug, debug_span};
-use scope::*;
+use std::{collections::VecDeque, fmt::Display, io::BufRead};
 
-pub use scope::ExprContext;
+use mediator_tracing::tracing::debug;
+use serde::Serialize;
 
 use crate::{
+    ast::LolCodeProgram,
+    diagnostic::{CompileError, Diagnostic, ErrorCode, Span},
     framework::HandleTokenProcessingError,
-    tokenizer::{KeywordToken, Token, TokenType},
+    tokenizer::{KeywordToken, Lexer, Token},
 };
 
-pub trait Parser {
-    fn process_tokens(&mut self, tokens: Vec<Token>) -> anyhow::Result<LolCodeProgram>;
+pub trait Parser: Sized {
+    /// Parses the tokens of `lexer` as it produces them, so the source is
+    /// only read as far as the parser has got.
+    fn process_tokens<R>(lexer: Lexer<'_, Self, R>) -> anyhow::Result<LolCodeProgram>
+    where
+        R: BufRead;
 }
 
 impl<T> Parser for T
 where
     T: HandleTokenProcessingError,
 {
-    fn process_tokens(&mut self, tokens: Vec<Token>) -> anyhow::Result<LolCodeProgram> {
-        let mut ctx_stack: Vec<ScopeContext> = vec![];
-        ctx_stack.push(MainContext::Pre.into());
-
-        for token in tokens {
-            let _ = debug_span!("Process token", ?ctx_stack).entered();
-            let mut context = ctx_stack.pop().context("non-empty ctx stack")?;
-            debug!(?context, token = ?token.t_type);
-
-            let op = match (&context, &token.t_type) {
-                (ScopeContext::Main(_), TokenType::Keyword(KeywordToken::Btw)) => {
-                    StackOp::Retain(Some(SingleComment::Started.into()))
-                }
-                (ScopeContext::Main(_), TokenType::Keyword(KeywordToken::OBtw)) => {
-                    StackOp::Retain(Some(MultilineComment::InProgress.into()))
-                }
-                _ => self.process_token(&mut context, &token)?,
-            };
-
-            execute_stack_op(op, &mut ctx_stack, context);
+    fn process_tokens<R>(lexer: Lexer<'_, Self, R>) -> anyhow::Result<LolCodeProgram>
+    where
+        R: BufRead,
+    {
+        let mut parse = Parse {
+            lexer,
+            lookahead: VecDeque::new(),
+            unreadable: None,
+            prev: None,
+            last: None,
+            version: LolCodeVersion::DEFAULT,
+            eof_reported: false,
+        };
+        let program = parse.program();
+        parse.check_readable()?;
+        match program {
+            Ok(program) => Ok(program),
+            Err(ParseError::Reported) => Err(CompileError.into()),
+            Err(ParseError::Fatal(err)) => Err(err),
         }
+    }
+}
 
-        debug!(?ctx_stack);
+/// Returned by [`Parser::process_tokens`] when the source could not be read
+/// to the end.
+#[derive(Debug)]
+pub struct UnreadableSource(pub anyhow::Error);
 
-        if ctx_stack.len() != 1 {
-            bail!("Stack is inappropriate length")
-        }
+impl Display for UnreadableSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
 
-        match ctx_stack.pop() {
-            Some(ScopeContext::Main(MainContext::Complete(program))) => Ok(program),
-            Some(ctx) => bail!("Unexpected ctx popped from stack {ctx:?}"),
-            None => unreachable!("stack should have length 1"),
-        }
+impl std::error::Error for UnreadableSource {}
+
+/// State of a single parse. The token-level helpers live in `cursor` and the
+/// grammar in `grammar`.
+struct Parse<'a, H, R> {
+    lexer: Lexer<'a, H, R>,
+    /// Tokens read from the lexer but not consumed yet.
+    lookahead: VecDeque<Token>,
+    /// Why the lexer stopped early. Reported once the parser gets there.
+    unreadable: Option<anyhow::Error>,
+    /// Last token consumed, where spans of finished nodes end.
+    prev: Option<Token>,
+    /// Last token read, where the end of the file is reported.
+    last: Option<Span>,
+    version: LolCodeVersion,
+    /// The end of the file is only reported once, however many constructs
+    /// it cut short.
+    eof_reported: bool,
+}
+
+enum ParseError {
+    /// A diagnostic has been emitted. The caller skips ahead and carries on.
+    Reported,
+    Fatal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ParseError {
+    fn from(value: anyhow::Error) -> Self {
+        ParseError::Fatal(value)
     }
 }
 
-fn execute_stack_op(
-    op: StackOp,
-    ctx_stack: &mut Vec<ScopeContext>,
-    context: ScopeContext,
-) -> Option<ScopeContext> {
-    match op {
-        StackOp::Unwind => {
-            let next = ctx_stack.last_mut().expect("popping main ctx");
-            debug!(?context, ?next, "performing unwind");
-            match (next, context) {
-                (_, ScopeContext::Main(MainContext::Root { .. })) => {}
-                (
-                    ScopeContext::Main(MainContext::Root {
-                        instrs: ref mut exprs,
-                        ..
-                    }),
-                    ScopeContext::Main(MainContext::Expr(ExprContext::Visible { args })),
-                ) => {
-                    exprs.push(Instruction::Visible { args });
-                }
-                (
-                    ScopeContext::Main(MainContext::Root {
-                        instrs: ref mut exprs,
-                        ..
-                    }),
-                    ScopeContext::Main(MainContext::Expr(ExprContext::Include(
-                        IncludesContext::Module(module),
-                    ))),
-                ) => {
-                    exprs.push(Instruction::LoadModule { module });
-                }
-                (
-                    ScopeContext::Main(MainContext::Expr(ExprContext::Visible { ref mut args })),
-                    ScopeContext::Main(MainContext::Expr(ExprContext::String(string))),
-                ) => args.push(string.0),
-                (
-                    ScopeContext::Main(MainContext::Expr(ExprContext::Visible { .. })),
-                    ScopeContext::Main(MainContext::Expr(ExprContext::Join(JoinContext::NewLine))),
-                ) => {}
-                (next, ScopeContext::SingleComment(SingleComment::InProgress(txt))) => {
-                    debug!(?txt, "Dropping comment text");
-                    match next {
-                        ScopeContext::Main(MainContext::Expr(_)) => {
-                            let n = ctx_stack.pop().expect("next exists from peek");
-                            let n = execute_stack_op(op, ctx_stack, n);
-                            if let Some(n) = n {
-                                ctx_stack.push(n);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                (_, ScopeContext::MultilineComment(MultilineComment::Completed)) => {}
-                _ => panic!("unexpected current and previous ctx"),
-            }
+type PResult<T> = Result<T, ParseError>;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
+#[jsm::public]
+pub struct LolCodeVersion {
+    major: i32,
+    minor: i32,
+}
+
+impl LolCodeVersion {
+    /// Versions rlcc knows how to compile.
+    pub const SUPPORTED: [LolCodeVersion; 2] = [
+        LolCodeVersion { major: 1, minor: 2 },
+        LolCodeVersion { major: 1, minor: 3 },
+    ];
+
+    /// Version assumed when `HAI` is not followed by one.
+    pub const DEFAULT: LolCodeVersion = LolCodeVersion { major: 1, minor: 2 };
+
+    pub fn is_supported(&self) -> bool {
+        Self::SUPPORTED.contains(self)
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        *self >= feature.min_version()
+    }
+
+    /// Error for using `feature` in a program declared as this version, or
+    /// `None` if the version supports it.
+    pub fn require(&self, feature: Feature) -> Option<Diagnostic> {
+        if self.supports(feature) {
+            return None;
         }
-        StackOp::Retain(next) => {
-            ctx_stack.push(context);
-            if let Some(next) = next {
-                ctx_stack.push(next);
+        let min_version = feature.min_version();
+        let message = match feature.is_lci_extension() {
+            true => {
+                format!("{feature} is an lci extension and requires HAI {min_version} or newer")
             }
-        }
-        StackOp::Replace(next) => {
-            ctx_stack.push(next);
+            false => format!("{feature} requires HAI {min_version} or newer"),
+        };
+        Some(
+            Diagnostic::error(ErrorCode::FeatureRequiresVersion, message).with_help(format!(
+                "The program declares HAI {self}. Declare `HAI {min_version}` to use {feature}"
+            )),
+        )
+    }
+
+    /// e.g. "1.2 and 1.3"
+    pub fn supported_list() -> String {
+        let versions: Vec<_> = Self::SUPPORTED.iter().map(ToString::to_string).collect();
+        match versions.split_last() {
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+            None => String::new(),
         }
     }
+}
 
-    None
+impl Display for LolCodeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-#[jsm::public]
-pub struct LolCodeVersion {
-    major: i32,
-    minor: i32,
+/// Language features which are not available in every supported version.
+/// Everything else is in LOLCODE 1.2 and so in every supported version.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Feature {
+    /// Calls to native module functions, with `<module> IZ <function>` or
+    /// `I IZ <module>'Z <function>`. This is the method call syntax of the
+    /// 1.3 spec, where modules stand in for BUKKITs.
+    ModuleCall,
+    /// lci's `INVISIBLE`, printing to standard error.
+    Invisible,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum Instruction {
-    Visible { args: Vec<String> },
-    LoadModule { module: String },
+impl Feature {
+    /// Oldest `HAI` version the feature may be used with. lci extensions are
+    /// only enabled from 1.3, as in lci itself.
+    pub fn min_version(&self) -> LolCodeVersion {
+        match self {
+            Feature::ModuleCall => LolCodeVersion { major: 1, minor: 3 },
+            Feature::Invisible => LolCodeVersion { major: 1, minor: 3 },
+        }
+    }
+
+    /// Whether the feature comes from lci rather than a LOLCODE spec.
+    pub fn is_lci_extension(&self) -> bool {
+        match self {
+            Feature::ModuleCall => false,
+            Feature::Invisible => true,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-#[jsm::public]
-pub struct LolCodeProgram {
-    version: LolCodeVersion,
-    instrs: Vec<Instruction>,
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Feature::ModuleCall => write!(f, "`<module> IZ <function>`"),
+            Feature::Invisible => write!(f, "`{}`", KeywordToken::Invisible),
+        }
+    }
 }
 
 impl TryFrom<String> for LolCodeVersion {
@@ -150,7 +200,7 @@ impl TryFrom<String> for LolCodeVersion {
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let parts: Vec<_> = value.split('.').collect();
-        let major = parts.get(0).ok_or("Version does not contain major part")?;
+        let major = parts.first().ok_or("Version does not contain major part")?;
         let major = major
             .parse::<_>()
             .map_err(|_| "Unable to parse major part")?;
@@ -173,14 +223,3 @@ impl From<(i32, i32)> for LolCodeVersion {
         version
     }
 }
-
-#[derive(Debug)]
-pub enum StackOp {
-    Unwind,
-    Retain(Option<ScopeContext>),
-    Replace(ScopeContext),
-}
-
-trait ParseScope<Scope> {
-    fn process_token(&mut self, scope: &mut Scope, token: &Token) -> anyhow::Result<StackOp>;
-}
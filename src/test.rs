@@ -1,8 +1,41 @@
-use std::{fs, io::stderr, path::Path};
+use std::{
+    env, fs,
+    io::{sink, stderr, Write},
+    path::Path,
+    process::{self, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
 
 use test_generator::test_resources;
 
-use crate::{framework::App, Mode};
+use crate::{
+    diagnostic::{Position, SourceFile, Span},
+    framework::App,
+    Mode, OptLevel,
+};
+
+/// Every way of running a program, which should all behave the same.
+const ENGINES: [Mode; 2] = [Mode::Interpret, Mode::Vm];
+
+/// Optimization levels going through the IR, which should behave like the
+/// interpreter.
+const OPT_LEVELS: [OptLevel; 2] = [OptLevel::O1, OptLevel::O2];
+
+/// Runs a program built by a backend, returning its output, its errors and
+/// whether building or running it failed.
+type RunBackend = fn(SourceFile) -> (String, String, bool);
+
+/// Every backend, which should behave like the [`ENGINES`].
+const BACKENDS: [(&str, RunBackend); 5] = [
+    ("native", run_native),
+    ("wasm", run_wasm),
+    ("js", run_js),
+    ("asm", run_asm),
+    ("llvm", run_llvm),
+];
 
 #[test_resources("tests/res/lci/test/1.3-Tests/1-Structure/**")]
 fn lci_structure_tests(resource: &str) {
@@ -25,6 +58,1906 @@ mod comments {
     }
 }
 
+mod diagnostics {
+    use std::{env, fs, io::sink, process};
+
+    use crate::{
+        diagnostic::{
+            CompileError, Diagnostic, Emitter, ErrorCode, ErrorFormat, Position, SourceFile, Span,
+        },
+        framework::{App, HandleTokenProcessingError},
+        Mode,
+    };
+
+    fn span(line: usize, start: usize, end: usize) -> Span {
+        Span {
+            start: Position {
+                line,
+                column: start,
+            },
+            end: Position { line, column: end },
+        }
+    }
+
+    #[test]
+    fn renders_source_snippet() {
+        let mut emitter = Emitter::default();
+        emitter.set_source(SourceFile::new(
+            "test.lol",
+            "HAI 1.2\nVISIBLE \"O HAI\" KTHX\nKTHXBYE\n",
+        ));
+        let diagnostic = Diagnostic::error(ErrorCode::UnexpectedToken, "Unexpected `KTHX`")
+            .with_span(span(2, 17, 20))
+            .with_label(span(2, 1, 7), "while parsing this VISIBLE")
+            .with_help("Did you mean KTHXBYE?");
+
+        let mut out = Vec::new();
+        emitter
+            .emit(&mut out, &diagnostic)
+            .expect("emit diagnostic");
+        let out = String::from_utf8(out).expect("convert output bytes to utf-8 string");
+        assert_eq!(
+            "error[E0002]: Unexpected `KTHX`\n \
+             --> test.lol:2:17\n  \
+             |\n\
+             2 | VISIBLE \"O HAI\" KTHX\n  \
+             |                 ^^^^\n  \
+             |\n\
+             2 | VISIBLE \"O HAI\" KTHX\n  \
+             | ------- while parsing this VISIBLE\n  \
+             = help: Did you mean KTHXBYE?\n",
+            out
+        );
+    }
+
+    #[test]
+    fn reports_code_and_help() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).parse("HAI 1.2\nHAS STDIO\nKTHXBYE\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.starts_with("error[E0002]: Unexpected `HAS`"), "{err}");
+        assert!(err.contains("= help: Are you missing CAN?"), "{err}");
+    }
+
+    #[test]
+    fn json_one_object_per_line() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err)
+            .set_error_format(ErrorFormat::Json)
+            .parse(SourceFile::new("test.lol", "HAI 1.2\nHAS\nHAS\nKTHXBYE\n"));
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        let lines: Vec<_> = err.lines().collect();
+        assert_eq!(2, lines.len(), "{err}");
+        for line in lines {
+            let json: serde_json::Value = serde_json::from_str(line).expect("parse json line");
+            assert_eq!("test.lol", json["file"]);
+            assert_eq!("error", json["severity"]);
+            assert_eq!("E0002", json["code"]);
+            assert_eq!("Unexpected `HAS`", json["message"]);
+            assert!(json["line"].is_u64());
+            assert!(json["column"].is_u64());
+        }
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run("tests/res/missing.lol", Mode::Interpret);
+        assert!(result.expect_err("missing file").is::<CompileError>());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0022]: Unable to read tests/res/missing.lol"),
+            "{err}"
+        );
+    }
+
+    /// A runtime error on line 2, followed by more lines than the emitter
+    /// keeps of a stream.
+    fn long_program() -> String {
+        format!(
+            "HAI 1.2\nVISIBLE NOPE\n{}KTHXBYE\n",
+            "VISIBLE 1\n".repeat(200)
+        )
+    }
+
+    #[test]
+    fn streams_keep_recent_lines() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run_reader(
+            "<stdin>",
+            long_program().as_bytes(),
+            Mode::Interpret,
+        );
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.contains("--> <stdin>:2:9"), "{err}");
+        assert!(!err.contains("VISIBLE NOPE"), "{err}");
+    }
+
+    #[test]
+    fn files_are_read_again() {
+        let dir = env::temp_dir().join(format!("rlcc-snippets-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create temporary directory");
+        let path = dir.join("long.lol");
+        fs::write(&path, long_program()).expect("write program");
+
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run(&path, Mode::Interpret);
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.contains("2 | VISIBLE NOPE"), "{err}");
+    }
+
+    #[test]
+    fn errors_do_not_outlive_their_run() {
+        let mut out = Vec::new();
+        let mut app = App::new(&mut out, sink());
+        for failing in [
+            "HAI 1.2\nVISIBLE \"oops\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE NOPE\nKTHXBYE\n",
+        ] {
+            assert!(
+                app.run_source(failing, Mode::Interpret).is_err(),
+                "{failing}"
+            );
+            app.run_source("HAI 1.2\nVISIBLE \"fine\"\nKTHXBYE\n", Mode::Interpret)
+                .expect("run passing program after a failing one");
+        }
+        drop(app);
+        assert_eq!(
+            "fine\nfine\n",
+            String::from_utf8(out).expect("utf-8 output")
+        );
+    }
+
+    #[test]
+    fn warnings_do_not_fail() {
+        let mut app = App::new(sink(), sink());
+        app.emit(Diagnostic::warning(
+            ErrorCode::UnexpectedToken,
+            "just a warning",
+        ))
+        .expect("emit warning");
+        assert!(!app.error_handled());
+
+        app.emit(Diagnostic::error(ErrorCode::UnexpectedToken, "an error"))
+            .expect("emit error");
+        assert!(app.error_handled());
+    }
+}
+
+mod recovery {
+    use std::io::sink;
+
+    use crate::{diagnostic::ErrorFormat, framework::App};
+
+    fn error_codes(source: &str) -> Vec<String> {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err)
+            .set_error_format(ErrorFormat::Json)
+            .parse(source);
+        assert!(result.is_err());
+
+        String::from_utf8(err)
+            .expect("convert err bytes to utf-8 string")
+            .lines()
+            .map(|line| {
+                let json: serde_json::Value = serde_json::from_str(line).expect("parse json line");
+                json["code"].as_str().expect("code is a string").to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_every_statement() {
+        let codes = error_codes(
+            "HAI 1.2\n\
+             VISIBLE \"a\" HAS \"b\"\n\
+             HAS STDIO\n\
+             CAN HAS\n\
+             VISIBLE \"unterminated\n\
+             VISIBLE \"ok\", VISIBLE \"two\" BTW fine\n\
+             KTHXBYE\n",
+        );
+        assert_eq!(vec!["E0002", "E0002", "E0008", "E0005"], codes);
+    }
+
+    #[test]
+    fn resyncs_at_comma() {
+        let codes = error_codes("HAI 1.2\nVISIBLE HAS junk, VISIBLE \"ok\", HAS\nKTHXBYE\n");
+        assert_eq!(vec!["E0002", "E0002"], codes);
+    }
+
+    #[test]
+    fn malformed_version_does_not_panic() {
+        assert_eq!(
+            vec!["E0004"],
+            error_codes("HAI abc\nVISIBLE \"x\"\nKTHXBYE\n")
+        );
+        assert_eq!(vec!["E0004"], error_codes("HAI 1.x\nKTHXBYE\n"));
+    }
+
+    #[test]
+    fn reports_unexpected_end_of_file() {
+        assert_eq!(vec!["E0003"], error_codes(""));
+        assert_eq!(vec!["E0010"], error_codes("HAI 1.2\nVISIBLE \"x\"\n"));
+        assert_eq!(vec!["E0010"], error_codes("HAI 1.2\nOBTW\nKTHXBYE\n"));
+        assert_eq!(vec!["E0005", "E0010"], error_codes("HAI 1.2\nVISIBLE \"x"));
+    }
+}
+
+mod ast {
+    use std::io::{sink, Cursor};
+
+    use crate::{
+        ast::{Block, Expr, ExprKind, FuncDef, Ident, LolCodeProgram, Stmt, StmtKind},
+        framework::App,
+        interpreter::Interpret,
+        test::span,
+    };
+
+    #[test]
+    fn statements_carry_spans() {
+        let prog = App::new(sink(), sink())
+            .parse("HAI 1.2\nVISIBLE \"a\" \"b\"\nCAN HAS STDIO?\nKTHXBYE\n")
+            .expect("parse program");
+
+        let [visible, can_has] = prog.body.stmts.as_slice() else {
+            panic!("expected two statements: {:?}", prog.body.stmts);
+        };
+        let StmtKind::Visible {
+            args,
+            invisible,
+            newline,
+        } = &visible.kind
+        else {
+            panic!("expected VISIBLE: {visible:?}");
+        };
+        assert!(!invisible);
+        assert!(newline);
+        assert_eq!(
+            vec![ExprKind::Yarn("a".into()), ExprKind::Yarn("b".into())],
+            args.iter().map(|arg| arg.kind.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(span(2, 1, 2, 15), visible.span);
+        assert_eq!(span(2, 9, 2, 11), args[0].span);
+        assert_eq!(span(2, 13, 2, 15), args[1].span);
+
+        let StmtKind::CanHas { module } = &can_has.kind else {
+            panic!("expected CAN HAS: {can_has:?}");
+        };
+        assert_eq!("STDIO", module.name);
+        assert_eq!(span(3, 1, 3, 14), can_has.span);
+        assert_eq!(span(1, 1, 4, 7), prog.span);
+    }
+
+    fn ident(name: &str) -> Ident {
+        Ident {
+            name: name.to_string(),
+            span: span(1, 1, 1, 1),
+        }
+    }
+
+    fn expr(kind: ExprKind) -> Expr {
+        Expr {
+            kind,
+            span: span(1, 1, 1, 1),
+        }
+    }
+
+    fn stmt(kind: StmtKind) -> Stmt {
+        Stmt {
+            kind,
+            span: span(1, 1, 1, 1),
+        }
+    }
+
+    fn block(stmts: Vec<Stmt>) -> Block {
+        Block {
+            stmts,
+            span: span(1, 1, 1, 1),
+        }
+    }
+
+    #[test]
+    fn functions_see_only_their_arguments() {
+        let echo = stmt(StmtKind::FuncDef(FuncDef {
+            name: ident("ECHO"),
+            params: vec![ident("WORD")],
+            body: block(vec![stmt(StmtKind::Return(expr(ExprKind::Var(ident(
+                "WORD",
+            )))))]),
+        }));
+        let call = |arg: &str| {
+            expr(ExprKind::Call {
+                module: None,
+                name: ident("ECHO"),
+                args: vec![expr(ExprKind::Yarn(arg.to_string()))],
+            })
+        };
+        let visible = stmt(StmtKind::Visible {
+            args: vec![call("O "), call("HAI")],
+            invisible: false,
+            newline: true,
+        });
+        let leak = stmt(StmtKind::Expr(expr(ExprKind::Var(ident("WORD")))));
+        let prog = LolCodeProgram {
+            version: (1, 2).into(),
+            body: block(vec![echo, visible, leak]),
+            span: span(1, 1, 1, 1),
+        };
+
+        let mut out = Cursor::new(Vec::new());
+        let mut err = Vec::new();
+        let result = App::new(&mut out, &mut err).execute(prog);
+        assert!(result.is_err());
+
+        assert_eq!(
+            "O HAI\n",
+            String::from_utf8(out.into_inner()).expect("utf-8 out")
+        );
+        let err = String::from_utf8(err).expect("utf-8 err");
+        assert!(
+            err.starts_with("error[E0013]: Unknown variable WORD"),
+            "{err}"
+        );
+    }
+}
+
+mod parser {
+    use std::io::sink;
+
+    use super::ENGINES;
+    use crate::{framework::App, Mode};
+
+    /// Output of `source`, which every engine must agree on.
+    fn run(source: &str) -> String {
+        let outputs: Vec<_> = ENGINES
+            .into_iter()
+            .map(|mode| {
+                let mut out = Vec::new();
+                App::new(&mut out, sink())
+                    .run_source(source, mode)
+                    .expect("run program");
+                String::from_utf8(out).expect("convert output bytes to utf-8 string")
+            })
+            .collect();
+        assert!(
+            outputs.windows(2).all(|pair| pair[0] == pair[1]),
+            "{outputs:?}"
+        );
+        outputs[0].clone()
+    }
+
+    #[test]
+    fn nested_prefix_expressions() {
+        assert_eq!(
+            "10\nWIN\na1WIN\n",
+            run("HAI 1.2\n\
+                 VISIBLE SUM OF PRODUKT OF 2 AN 3 AN 4\n\
+                 VISIBLE BOTH SAEM 10 AN SUM OF 4 6\n\
+                 VISIBLE SMOOSH \"a\" AN 1 AN WIN MKAY\n\
+                 KTHXBYE\n")
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(
+            "big\n0 1 2 \nten\nfall\n",
+            run("HAI 1.2\n\
+                 I HAS A X ITZ 10\n\
+                 BOTH SAEM X AN BIGGR OF X AN 5, O RLY?\n\
+                 YA RLY, VISIBLE \"big\"\n\
+                 NO WAI, VISIBLE \"small\"\n\
+                 OIC\n\
+                 IM IN YR LOOP UPPIN YR I TIL BOTH SAEM I AN 3\n\
+                 VISIBLE I \" \"!\n\
+                 IM OUTTA YR LOOP\n\
+                 VISIBLE \"\"\n\
+                 X, WTF?\n\
+                 OMG 10, VISIBLE \"ten\"\n\
+                 OMG 11, VISIBLE \"fall\", GTFO\n\
+                 OMGWTF, VISIBLE \"default\"\n\
+                 OIC\n\
+                 KTHXBYE\n")
+        );
+    }
+
+    #[test]
+    fn functions_and_escapes() {
+        assert_eq!(
+            "3\nsum 3:\n\n\"q\"\t\n",
+            run("HAI 1.2\n\
+                 HOW IZ I ADD YR P AN YR Q\n\
+                 FOUND YR SUM OF P AN Q\n\
+                 IF U SAY SO\n\
+                 I HAS A N ITZ I IZ ADD YR 1 AN YR 2 MKAY\n\
+                 VISIBLE N\n\
+                 VISIBLE \"sum :{N}:::)\"\n\
+                 VISIBLE \":\"q:\":>\"\n\
+                 KTHXBYE\n")
+        );
+    }
+
+    #[test]
+    fn yarns_keep_tabs_and_spaces() {
+        assert_eq!("a\tb  c\n", run("HAI 1.2\nVISIBLE \"a\tb  c\"\nKTHXBYE\n"));
+    }
+
+    #[test]
+    fn division_by_zero_is_reported() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run_source(
+            "HAI 1.2\nVISIBLE QUOSHUNT OF 1 AN 0\nKTHXBYE\n",
+            Mode::Interpret,
+        );
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.starts_with("error[E0020]: Division by zero"), "{err}");
+    }
+}
+
+mod keywords {
+    use std::io::sink;
+
+    use crate::{
+        framework::App,
+        tokenizer::{KeywordToken, TokenType, Tokenize},
+        Mode,
+    };
+
+    fn token_types(source: &str) -> Vec<TokenType> {
+        App::new(sink(), sink())
+            .tokenize(source)
+            .expect("tokenize")
+            .into_iter()
+            .map(|token| token.t_type)
+            .filter(|t_type| !t_type.is_space())
+            .collect()
+    }
+
+    #[test]
+    fn phrases_are_single_tokens() {
+        assert_eq!(
+            vec![
+                KeywordToken::IHasA.into(),
+                TokenType::Word("X".to_string()),
+                TokenType::NewLine,
+                KeywordToken::ImInYr.into(),
+                TokenType::Word("LOOP".to_string()),
+            ],
+            token_types("I HAS  A X\nIM IN YR LOOP")
+        );
+        // Only outside of YARNs
+        assert_eq!(
+            vec![
+                TokenType::Quote,
+                TokenType::Word("I".to_string()),
+                TokenType::Keyword(KeywordToken::Has),
+                TokenType::Word("A".to_string()),
+                TokenType::Quote,
+            ],
+            token_types("\"I HAS A\"")
+        );
+    }
+
+    #[test]
+    fn comments_are_not_joined() {
+        let types =
+            token_types("OBTW\nI HAS A X\nI HAS A Y\nTLDR\nI HAS A Z\nBTW I HAS A W\nI HAS A V\n");
+        let joined = types
+            .iter()
+            .filter(|t_type| **t_type == KeywordToken::IHasA.into())
+            .count();
+        assert_eq!(2, joined, "{types:?}");
+
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .run_source(
+                "HAI 1.2\nOBTW\nI HAS A X ITZ 1\nTLDR\nI HAS A X ITZ 2\nVISIBLE X\nKTHXBYE\n",
+                Mode::Interpret,
+            )
+            .expect("run program");
+        assert_eq!("2\n", String::from_utf8(out).expect("utf-8 output"));
+    }
+
+    #[test]
+    fn partial_phrase_suggests_keyword() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).parse("HAI 1.2\nBOTH 1 AN 2\nKTHXBYE\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0021]: Incomplete keyword `BOTH`"),
+            "{err}"
+        );
+        assert!(
+            err.contains("= help: Did you mean `BOTH OF` or `BOTH SAEM`?"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn unfinished_phrases_are_words() {
+        assert_eq!(
+            vec![
+                TokenType::Word("YA".to_string()),
+                TokenType::Word("R".to_string()),
+                TokenType::Word("SUM".to_string()),
+            ],
+            token_types("YA R SUM")
+        );
+
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .run_source(
+                "HAI 1.2\nI HAS A YA ITZ 3\nYA R SUM OF YA AN 1\nVISIBLE YA\nKTHXBYE\n",
+                Mode::Interpret,
+            )
+            .expect("YA is a name");
+        assert_eq!("4\n", String::from_utf8(out).expect("utf-8 output"));
+    }
+
+    #[test]
+    fn unfinished_statement_keyword_suggests_it() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err)
+            .parse("HAI 1.2\nIM IN YR LOOP\nGTFO\nIM OUTTA LOOP\nIM OUTTA YR LOOP\nKTHXBYE\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0021]: Incomplete keyword `IM OUTTA`"),
+            "{err}"
+        );
+        assert!(err.contains("= help: Did you mean `IM OUTTA YR`?"), "{err}");
+    }
+}
+
+mod lexer {
+    use std::io::{self, sink, BufReader, Read};
+
+    use crate::{
+        framework::App,
+        tokenizer::{TokenType, Tokenize},
+        Mode,
+    };
+
+    /// Fails every read, standing in for source that is not available yet.
+    struct Unavailable;
+
+    impl Read for Unavailable {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("not yet"))
+        }
+    }
+
+    #[test]
+    fn yields_tokens_before_reading_on() {
+        let mut app = App::new(sink(), sink());
+        let reader = BufReader::new("HAI 1.2\n".as_bytes().chain(Unavailable));
+        let mut tokens = app.lex(reader);
+
+        let first_line: Vec<_> = tokens
+            .by_ref()
+            .take(6)
+            .map(|token| token.expect("token of the first line").t_type)
+            .collect();
+        assert_eq!(Some(&TokenType::NewLine), first_line.last());
+        assert!(tokens.next().expect("read error").is_err());
+    }
+
+    #[test]
+    fn parses_up_to_unreadable_source() {
+        let mut err = Vec::new();
+        let reader = BufReader::new("HAI 1.2\nVISIBLE \"O HAI\"\n".as_bytes().chain(Unavailable));
+        let result = App::new(sink(), &mut err).parse_reader("<stdin>", reader);
+        assert!(result.is_err());
+
+        // The end of what could be read is not reported as the end of file
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert_eq!("error[E0022]: Unable to read <stdin>: not yet\n", err);
+    }
+
+    #[test]
+    fn runs_from_reader() {
+        let mut out = Vec::new();
+        let source = "HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n";
+        App::new(&mut out, sink())
+            .run_reader(
+                "<stdin>",
+                BufReader::new(source.as_bytes()),
+                Mode::Interpret,
+            )
+            .expect("run program");
+        assert_eq!("O HAI\n", String::from_utf8(out).expect("utf-8 out"));
+    }
+}
+
+mod spans {
+    use std::io::sink;
+
+    use crate::{
+        diagnostic::ErrorFormat,
+        framework::App,
+        tokenizer::{KeywordToken, TokenLocation, TokenType, Tokenize},
+    };
+
+    fn location(line: usize, column: usize, offset: usize) -> TokenLocation {
+        TokenLocation {
+            line,
+            column,
+            offset,
+        }
+    }
+
+    #[test]
+    fn tokens_know_start_and_end() {
+        let tokens = App::new(sink(), sink())
+            .tokenize("HAI\r\n\t\"é\" X\rKTHXBYE")
+            .expect("tokenize");
+        let tokens: Vec<_> = tokens
+            .iter()
+            .map(|token| (&token.t_type, token.start, token.end))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    &KeywordToken::Hai.into(),
+                    location(1, 1, 0),
+                    location(1, 3, 2)
+                ),
+                (&TokenType::NewLine, location(1, 4, 3), location(1, 5, 4)),
+                (
+                    &TokenType::Space('\t'),
+                    location(2, 1, 5),
+                    location(2, 1, 5)
+                ),
+                (&TokenType::Quote, location(2, 2, 6), location(2, 2, 6)),
+                (
+                    &TokenType::Word("é".to_string()),
+                    location(2, 3, 7),
+                    location(2, 3, 7)
+                ),
+                (&TokenType::Quote, location(2, 4, 9), location(2, 4, 9)),
+                (
+                    &TokenType::Space(' '),
+                    location(2, 5, 10),
+                    location(2, 5, 10)
+                ),
+                (
+                    &TokenType::Word("X".to_string()),
+                    location(2, 6, 11),
+                    location(2, 6, 11)
+                ),
+                (&TokenType::NewLine, location(2, 7, 12), location(2, 7, 12)),
+                (
+                    &KeywordToken::KThxBye.into(),
+                    location(3, 1, 13),
+                    location(3, 7, 19)
+                ),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn diagnostics_cover_the_token() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err)
+            .set_error_format(ErrorFormat::Json)
+            .parse("HAI 1.2\r\nVISIBLE \"é\"\tHAS\r\nKTHXBYE\r\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        let json: serde_json::Value = serde_json::from_str(&err).expect("parse json");
+        assert_eq!("E0002", json["code"]);
+        assert_eq!(2, json["line"]);
+        assert_eq!(13, json["column"]);
+        assert_eq!(2, json["end_line"]);
+        assert_eq!(15, json["end_column"]);
+    }
+}
+
+mod check {
+    use std::io::sink;
+
+    use crate::{diagnostic::ErrorFormat, framework::App, Mode};
+
+    #[test]
+    fn reports_all_errors_without_running() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let result = App::new(&mut out, &mut err)
+            .set_error_format(ErrorFormat::Json)
+            .run_source(
+                "HAI 1.2\n\
+                 VISIBLE \"not run\"\n\
+                 VISIBLE X\n\
+                 HOW IZ I ECHO YR WORD, FOUND YR WORD, IF U SAY SO\n\
+                 VISIBLE I IZ ECHO MKAY\n\
+                 VISIBLE I IZ NOPE MKAY\n\
+                 GTFO\n\
+                 KTHXBYE\n",
+                Mode::Check,
+            );
+        assert!(result.is_err());
+        assert!(out.is_empty());
+
+        let codes: Vec<_> = String::from_utf8(err)
+            .expect("convert err bytes to utf-8 string")
+            .lines()
+            .map(|line| {
+                let json: serde_json::Value = serde_json::from_str(line).expect("parse json line");
+                json["code"].as_str().expect("code is a string").to_string()
+            })
+            .collect();
+        assert_eq!(vec!["E0013", "E0015", "E0014", "E0026"], codes);
+    }
+
+    #[test]
+    fn accepts_loop_variables_and_modules() {
+        App::new(sink(), sink())
+            .run_source(
+                "HAI 1.2\n\
+                 CAN HAS STDIO?\n\
+                 IM IN YR LOOP UPPIN YR I TIL BOTH SAEM I AN 3\n\
+                 VISIBLE I\n\
+                 IM OUTTA YR LOOP\n\
+                 KTHXBYE\n",
+                Mode::Check,
+            )
+            .expect("check program");
+    }
+}
+
+mod dump {
+    use std::io::sink;
+
+    use crate::{dump::DumpFormat, framework::App, Mode};
+
+    fn dump(source: &str, mode: Mode, format: DumpFormat) -> String {
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .set_dump_format(format)
+            .run_source(source, mode)
+            .expect("dump program");
+        String::from_utf8(out).expect("convert output bytes to utf-8 string")
+    }
+
+    #[test]
+    fn tokens_with_locations() {
+        assert_eq!(
+            "1:1-1:3         keyword HAI\n\
+             1:4-1:4         newline\n\
+             2:1-2:7         keyword VISIBLE\n\
+             2:8-2:8         space\n\
+             2:9-2:14        keyword SUM OF\n\
+             2:15-2:15       space\n\
+             2:16-2:17       word hi\n\
+             2:18-2:18       newline\n\
+             3:1-3:7         keyword KTHXBYE\n",
+            dump(
+                "HAI\nVISIBLE SUM OF hi\nKTHXBYE",
+                Mode::Tokens,
+                DumpFormat::Human
+            )
+        );
+    }
+
+    #[test]
+    fn tokens_as_json_lines() {
+        let out = dump("HAI\nKTHXBYE\n", Mode::Tokens, DumpFormat::Json);
+        let tokens: Vec<serde_json::Value> = out
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("parse json line"))
+            .collect();
+        assert_eq!(4, tokens.len());
+        assert_eq!("keyword", tokens[2]["kind"]);
+        assert_eq!("KTHXBYE", tokens[2]["text"]);
+        assert_eq!(4, tokens[2]["offset"]);
+        assert_eq!(10, tokens[2]["end_offset"]);
+        assert_eq!(None, tokens[3].get("text"));
+    }
+
+    #[test]
+    fn ast_tree() {
+        assert_eq!(
+            "program 1.2 @1:1-6:7\n  \
+             declare X @2:1-2:15\n    \
+             numbr 1 @2:15-2:15\n  \
+             loop L @3:1-5:13\n    \
+             uppin X @3:21-3:21\n    \
+             wile\n      \
+             binary DIFFRINT @3:28-3:42\n        \
+             var X @3:37-3:37\n        \
+             numbr 3 @3:42-3:42\n    \
+             body @4:1-4:14\n      \
+             visible ! @4:1-4:14\n        \
+             var X @4:9-4:9\n        \
+             yarn \"x\" @4:11-4:13\n",
+            dump(
+                "HAI 1.2\n\
+                 I HAS A X ITZ 1\n\
+                 IM IN YR L UPPIN YR X WILE DIFFRINT X AN 3\n\
+                 VISIBLE X \"x\"!\n\
+                 IM OUTTA YR L\n\
+                 KTHXBYE",
+                Mode::Ast,
+                DumpFormat::Human
+            )
+        );
+    }
+
+    #[test]
+    fn ast_as_json() {
+        let out = dump("HAI 1.3\nGTFO\nKTHXBYE\n", Mode::Ast, DumpFormat::Json);
+        let json: serde_json::Value = serde_json::from_str(&out).expect("parse json");
+        assert_eq!(3, json["version"]["minor"]);
+        let stmt = &json["body"]["stmts"][0];
+        assert_eq!("break", stmt["kind"]);
+        assert_eq!(2, stmt["span"]["start"]["line"]);
+        assert_eq!(4, stmt["span"]["end"]["column"]);
+    }
+}
+
+mod vm {
+    use std::io::{sink, Cursor};
+
+    use super::{GIMMEH, OPT_LEVELS};
+    use crate::{diagnostic::ErrorFormat, framework::App, Mode, OptLevel};
+
+    /// Output, diagnostics and whether it failed.
+    fn run(source: &str, mode: Mode, level: OptLevel) -> (String, String, bool) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let failed = App::new(&mut out, &mut err)
+            .set_error_format(ErrorFormat::Json)
+            .set_opt_level(level)
+            .run_source(source, mode)
+            .is_err();
+        (
+            String::from_utf8(out).expect("convert output bytes to utf-8 string"),
+            String::from_utf8(err).expect("convert err bytes to utf-8 string"),
+            failed,
+        )
+    }
+
+    fn assert_same(source: &str) -> (String, String, bool) {
+        let interpreted = run(source, Mode::Interpret, OptLevel::O0);
+        assert_eq!(interpreted, run(source, Mode::Vm, OptLevel::O0));
+        for level in OPT_LEVELS {
+            assert_eq!(
+                interpreted,
+                run(source, Mode::Interpret, level),
+                "{level:?}"
+            );
+        }
+        interpreted
+    }
+
+    #[test]
+    fn functions_loops_and_switches() {
+        let (out, _, failed) = assert_same(
+            "HAI 1.2\n\
+             HOW IZ I FACT YR N\n\
+             BOTH SAEM N AN 0, O RLY?\n\
+             YA RLY, FOUND YR 1\n\
+             OIC\n\
+             FOUND YR PRODUKT OF N AN I IZ FACT YR DIFF OF N AN 1 MKAY\n\
+             IF U SAY SO\n\
+             HOW IZ I FIRST YR LIMIT\n\
+             IM IN YR LOOP UPPIN YR K\n\
+             BOTH SAEM K AN LIMIT, O RLY?\n\
+             YA RLY, GTFO\n\
+             OIC\n\
+             IM OUTTA YR LOOP\n\
+             GTFO\n\
+             IF U SAY SO\n\
+             VISIBLE I IZ FACT YR 5 MKAY\n\
+             VISIBLE I IZ FIRST YR 3 MKAY\n\
+             IM IN YR LOOP NERFIN YR I WILE DIFFRINT I AN -3\n\
+             I, WTF?\n\
+             OMG -1, VISIBLE \"one\"\n\
+             OMG -2, VISIBLE \"two\", GTFO\n\
+             OMGWTF, VISIBLE \"other\"\n\
+             OIC\n\
+             IM OUTTA YR LOOP\n\
+             SUM OF 1 AN 2\n\
+             VISIBLE SMOOSH IT AN \" \" AN MAEK IT A NUMBAR MKAY\n\
+             KTHXBYE\n",
+        );
+        assert!(!failed);
+        assert_eq!("120\nNOOB\nother\none\ntwo\ntwo\n3 3.00\n", out);
+    }
+
+    #[test]
+    fn loop_variables_are_temporary() {
+        let (_, err, failed) = assert_same(
+            "HAI 1.2\n\
+             IM IN YR LOOP UPPIN YR I TIL BOTH SAEM I AN 2\n\
+             IM OUTTA YR LOOP\n\
+             VISIBLE I\n\
+             KTHXBYE\n",
+        );
+        assert!(failed);
+        assert!(err.contains("\"code\":\"E0013\""), "{err}");
+    }
+
+    #[test]
+    fn runtime_errors_match() {
+        for source in [
+            "HAI 1.2\nVISIBLE \"before\"\nVISIBLE I IZ NOPE MKAY\nKTHXBYE\n",
+            "HAI 1.2\nHOW IZ I F YR X, FOUND YR X, IF U SAY SO\nVISIBLE I IZ F MKAY\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE SMOOSH 1 AN NOOB AN Y MKAY\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE \"before\"\nGTFO\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE MOD OF 1 AN 0\nKTHXBYE\n",
+            "HAI 1.3\nVISIBLE STDIO IZ NOPE MKAY\nKTHXBYE\n",
+        ] {
+            let (_, err, failed) = assert_same(source);
+            assert!(failed, "{source}");
+            assert_eq!(1, err.lines().count(), "{err}");
+        }
+    }
+
+    #[test]
+    fn gimmeh_reads_lines() {
+        let input = format!("KITTEH\r\n{}\n", "x".repeat(100));
+        let expected = format!("KITTEH\n[{}]\n[]\n", "x".repeat(100));
+        let mut runs = vec![(Mode::Interpret, OptLevel::O0), (Mode::Vm, OptLevel::O0)];
+        runs.extend(OPT_LEVELS.map(|level| (Mode::Interpret, level)));
+        for (mode, level) in runs {
+            let run = format!("{mode:?} {level:?}");
+            let mut out = Vec::new();
+            App::new(&mut out, sink())
+                .set_opt_level(level)
+                .set_input(Cursor::new(input.clone()))
+                .run_source(GIMMEH, mode)
+                .expect("run program");
+            let out = String::from_utf8(out).expect("convert output bytes to utf-8 string");
+            assert_eq!(expected, out, "{run}");
+        }
+    }
+}
+
+mod ir {
+    use std::io::sink;
+
+    use crate::{
+        framework::App,
+        ir::{Body, Ir, Lower, Op, Operand, OptLevel, PassManager},
+        value::Value,
+    };
+
+    fn optimized(source: &str, level: OptLevel) -> Ir {
+        let mut app = App::new(sink(), sink());
+        let prog = app.parse(source).expect("parse program");
+        let mut ir = app.lower(&prog).expect("lower program");
+        PassManager::for_level(level).run(&mut ir);
+        ir
+    }
+
+    fn ops(body: &Body) -> Vec<Op> {
+        body.code.iter().map(|inst| inst.op.clone()).collect()
+    }
+
+    /// Operands of every VISIBLE in `ops`.
+    fn visible(ops: &[Op]) -> Vec<Vec<Operand>> {
+        ops.iter()
+            .filter_map(|op| match op {
+                Op::Visible { operands, .. } => Some(operands.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn yarn(yarn: &str) -> Operand {
+        Operand::Const(Value::Yarn(yarn.to_string()))
+    }
+
+    #[test]
+    fn folds_constants() {
+        let ir = optimized(
+            "HAI 1.2\nVISIBLE SUM OF 2 AN PRODUKT OF 3 AN 4\nKTHXBYE\n",
+            OptLevel::O1,
+        );
+        let visible = Op::Visible {
+            operands: vec![Operand::Const(Value::Numbr(14))],
+            invisible: false,
+            newline: true,
+        };
+        assert_eq!(vec![visible], ops(&ir.main));
+
+        let unoptimized = optimized("HAI 1.2\nVISIBLE SUM OF 2 AN 3\nKTHXBYE\n", OptLevel::O0);
+        assert_eq!(2, unoptimized.main.code.len());
+    }
+
+    #[test]
+    fn keeps_operations_that_fail() {
+        let ir = optimized(
+            "HAI 1.2\nVISIBLE QUOSHUNT OF 1 AN 0\nVISIBLE SMOOSH 1 AN NOOB MKAY\nKTHXBYE\n",
+            OptLevel::O2,
+        );
+        let ops = ops(&ir.main);
+        assert!(
+            ops.iter().any(|op| matches!(op, Op::Binary { .. })),
+            "{ops:?}"
+        );
+        assert!(
+            ops.iter().any(|op| matches!(op, Op::ToYarn { .. })),
+            "{ops:?}"
+        );
+    }
+
+    #[test]
+    fn removes_code_that_never_runs() {
+        let ir = optimized(
+            "HAI 1.2\n\
+             HOW IZ I F YR X\n\
+             FOUND YR X\n\
+             VISIBLE \"after found\"\n\
+             IF U SAY SO\n\
+             IM IN YR LOOP\n\
+             GTFO\n\
+             VISIBLE \"after gtfo\"\n\
+             IM OUTTA YR LOOP\n\
+             IM IN YR NEVER WILE FAIL\n\
+             VISIBLE \"never\"\n\
+             IM OUTTA YR NEVER\n\
+             VISIBLE \"reached\"\n\
+             KTHXBYE\n",
+            OptLevel::O1,
+        );
+        let function = ops(&ir.functions[0].body);
+        assert!(visible(&function).is_empty(), "{function:?}");
+        assert!(
+            matches!(function.last(), Some(Op::Return(_))),
+            "{function:?}"
+        );
+        let main = ops(&ir.main);
+        assert_eq!(vec![vec![yarn("reached")]], visible(&main));
+        // Nothing is left of the loops
+        assert!(
+            main.iter()
+                .all(|op| !matches!(op, Op::Jump(_) | Op::Label(_))),
+            "{main:?}"
+        );
+    }
+
+    #[test]
+    fn joins_visible_constants() {
+        let source = "HAI 1.2\n\
+                      I HAS A X ITZ 1\n\
+                      VISIBLE \"a\" \"b\" X \"c\" SUM OF 1 AN 2 WIN\n\
+                      KTHXBYE\n";
+        let joined = vec![yarn("ab"), Operand::Temp(0), yarn("c3WIN")];
+        assert_eq!(
+            vec![joined],
+            visible(&ops(&optimized(source, OptLevel::O2).main))
+        );
+        assert_eq!(
+            6,
+            visible(&ops(&optimized(source, OptLevel::O1).main))[0].len()
+        );
+    }
+}
+
+/// Reads two lines and then the end of the input.
+const GIMMEH: &str = "HAI 1.2\n\
+                      I HAS A X\n\
+                      GIMMEH X\n\
+                      VISIBLE X\n\
+                      GIMMEH X\n\
+                      VISIBLE \"[\" X \"]\"\n\
+                      GIMMEH X\n\
+                      VISIBLE \"[\" X \"]\"\n\
+                      KTHXBYE\n";
+
+mod backends {
+    use std::{env, fs, io::sink, process};
+
+    use crate::{
+        diagnostic::{CompileError, SourceFile},
+        framework::App,
+        Mode,
+    };
+
+    use super::{run_wasm_with_input, BACKENDS, GIMMEH};
+
+    /// Runs `source` with every backend, checking each behaves like the
+    /// interpreter, and returns what the interpreter did. Diagnostics only
+    /// agree on their first two lines since built programs do not have the
+    /// source to quote.
+    fn assert_same(source: &str) -> (String, String, bool) {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let failed = App::new(&mut out, &mut err)
+            .run_source(SourceFile::new("test.lol", source), Mode::Interpret)
+            .is_err();
+        let out = String::from_utf8(out).expect("convert output bytes to utf-8 string");
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+
+        let location: Vec<_> = err.lines().take(2).collect();
+        for (backend, run) in BACKENDS {
+            let built = run(SourceFile::new("test.lol", source));
+            assert_eq!((&out, failed), (&built.0, built.2), "{backend}: {source}");
+            let built_location: Vec<_> = built.1.lines().take(2).collect();
+            assert_eq!(location, built_location, "{backend}: {source}");
+        }
+        (out, err, failed)
+    }
+
+    #[test]
+    fn functions_loops_and_switches() {
+        let (out, _, failed) = assert_same(
+            "HAI 1.2\n\
+             HOW IZ I FACT YR N\n\
+             BOTH SAEM N AN 0, O RLY?\n\
+             YA RLY, FOUND YR 1\n\
+             OIC\n\
+             FOUND YR PRODUKT OF N AN I IZ FACT YR DIFF OF N AN 1 MKAY\n\
+             IF U SAY SO\n\
+             HOW IZ I FIRST YR LIMIT\n\
+             IM IN YR LOOP UPPIN YR K\n\
+             BOTH SAEM K AN LIMIT, O RLY?\n\
+             YA RLY, GTFO\n\
+             OIC\n\
+             IM OUTTA YR LOOP\n\
+             GTFO\n\
+             IF U SAY SO\n\
+             VISIBLE I IZ FACT YR 20 MKAY\n\
+             VISIBLE I IZ FIRST YR 3 MKAY\n\
+             IM IN YR LOOP NERFIN YR I WILE DIFFRINT I AN -3\n\
+             I, WTF?\n\
+             OMG -1, VISIBLE \"one\"\n\
+             OMG -2, VISIBLE \"two\", GTFO\n\
+             OMGWTF, VISIBLE \"other\"\n\
+             OIC\n\
+             IM OUTTA YR LOOP\n\
+             SUM OF 1 AN 2\n\
+             VISIBLE SMOOSH IT AN \" \" AN MAEK IT A NUMBAR MKAY\n\
+             KTHXBYE\n",
+        );
+        assert!(!failed);
+        assert_eq!(
+            "2432902008176640000\nNOOB\nother\none\ntwo\ntwo\n3 3.00\n",
+            out
+        );
+    }
+
+    #[test]
+    fn wasm_gimmeh_reads_from_the_host() {
+        let input = format!("KITTEH\r\n{}\n", "x".repeat(100));
+        let (out, err, failed) = run_wasm_with_input(SourceFile::new("test.lol", GIMMEH), &input);
+        assert!(!failed, "{err}");
+        assert_eq!(format!("KITTEH\n[{}]\n[]\n", "x".repeat(100)), out);
+    }
+
+    #[test]
+    fn question_marks_are_not_trigraphs() {
+        let (out, _, failed) = assert_same("HAI 1.2\nVISIBLE \"WAT??!\"\nKTHXBYE\n");
+        assert!(!failed);
+        assert_eq!("WAT??!\n", out);
+    }
+
+    #[test]
+    fn values_and_casts() {
+        let (out, _, failed) = assert_same(
+            "HAI 1.3\n\
+             I HAS A X ITZ \"12\"\n\
+             X IS NOW A NUMBR\n\
+             VISIBLE SUM OF X AN \"0.5\" \" \" QUOSHUNT OF 7 AN -2 \" \" MOD OF 7.5 AN 2\n\
+             VISIBLE SUM OF 9223372036854775807 AN 1 \" \" MAEK \"1.0e300\" A NUMBR\n\
+             VISIBLE BOTH SAEM 1 AN 1.0 \" \" BOTH SAEM \"1\" AN 1 \" \" NOT \"\"\n\
+             VISIBLE ALL OF WIN AN 1 AN \"x\" MKAY \" \" ANY OF FAIL AN 0.0 MKAY\n\
+             VISIBLE MAEK NOOB A YARN \"::\" BIGGR OF 2 AN 3.5 \":>\" \"tab\"!\n\
+             VISIBLE \"\"\n\
+             INVISIBLE \"to stderr\"\n\
+             KTHXBYE\n",
+        );
+        assert!(!failed);
+        assert_eq!(
+            "12.50 -3 1.50\n-9223372036854775808 9223372036854775807\nWIN FAIL WIN\nWIN FAIL\n:3.50\ttab\n",
+            out
+        );
+    }
+
+    #[test]
+    fn runtime_errors_match() {
+        for source in [
+            "HAI 1.2\nVISIBLE \"before\"\nVISIBLE I IZ NOPE MKAY\nKTHXBYE\n",
+            "HAI 1.2\nHOW IZ I F YR X, FOUND YR X, IF U SAY SO\nVISIBLE I IZ F MKAY\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE SMOOSH 1 AN NOOB AN Y MKAY\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE \"before\"\nGTFO\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE MOD OF 1 AN 0\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE QUOSHUNT OF 1.0 AN 0\nKTHXBYE\n",
+            "HAI 1.2\nVISIBLE SUM OF \"1.5x\" AN 1\nKTHXBYE\n",
+            "HAI 1.3\nVISIBLE STDIO IZ NOPE MKAY\nKTHXBYE\n",
+            "HAI 1.3\nCAN HAS STDIO?\nVISIBLE STDIO IZ NOPE MKAY\nKTHXBYE\n",
+            "HAI 1.2\nCAN HAS NOPE?\nKTHXBYE\n",
+            "HAI 1.2\nIM IN YR L UPPIN YR I TIL BOTH SAEM I AN 2\nIM OUTTA YR L\nVISIBLE I\nKTHXBYE\n",
+        ] {
+            let (_, err, failed) = assert_same(source);
+            assert!(failed, "{source}");
+            assert!(err.starts_with("error[E00"), "{err}");
+        }
+    }
+
+    #[test]
+    fn writes_c_source() {
+        let dir = env::temp_dir().join(format!("rlcc-native-c-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create temporary directory");
+        let output = dir.join("hello.c");
+        App::new(sink(), sink())
+            .set_output(&output)
+            .run_source("HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n", Mode::Compile)
+            .expect("write C source");
+
+        let c = fs::read_to_string(&output).expect("read C source");
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        assert!(c.contains("int main(void)"), "{c}");
+        assert!(c.contains("lol_yarn(\"O HAI\")"), "{c}");
+    }
+
+    #[test]
+    fn writes_assembly() {
+        let dir = env::temp_dir().join(format!("rlcc-native-s-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create temporary directory");
+        let output = dir.join("hello.s");
+        App::new(sink(), sink())
+            .set_output(&output)
+            .run_source("HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n", Mode::Asm)
+            .expect("write assembly");
+
+        let asm = fs::read_to_string(&output).expect("read assembly");
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        assert!(asm.contains("\nlol_main:\n"), "{asm}");
+        assert!(asm.contains(".ascii \"O HAI\""), "{asm}");
+    }
+
+    #[test]
+    fn writes_llvm_ir_next_to_its_runtime() {
+        let dir = env::temp_dir().join(format!("rlcc-llvm-ll-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create temporary directory");
+        let output = dir.join("hello.ll");
+        App::new(sink(), sink())
+            .set_output(&output)
+            .run_source("HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n", Mode::LlvmIr)
+            .expect("write LLVM IR");
+
+        let ir = fs::read_to_string(&output).expect("read LLVM IR");
+        let runtime = fs::read_to_string(dir.join("hello.runtime.c")).expect("read runtime");
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        assert!(ir.contains("\ndefine i32 @main() {\n"), "{ir}");
+        assert!(ir.contains("c\"O HAI\\00\""), "{ir}");
+        assert!(runtime.contains("\nvoid lolrt_visible("), "{runtime}");
+    }
+
+    #[test]
+    fn rejects_modules_with_functions() {
+        for (mode, platform) in [
+            (Mode::Compile, "C"),
+            (Mode::Wat, "WebAssembly"),
+            (Mode::Js, "JavaScript"),
+            (Mode::Asm, "x86-64 assembly"),
+            (Mode::LlvmIr, "LLVM IR"),
+        ] {
+            let mut err = Vec::new();
+            let result = App::new(sink(), &mut err)
+                .register_module(super::native_modules::OurModule)
+                .set_output("unused")
+                .run_source("HAI 1.2\nCAN HAS OURMODULE?\nKTHXBYE\n", mode);
+            assert!(result.expect_err("unsupported module").is::<CompileError>());
+
+            let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+            let expected = format!("error[E0024]: Module OURMODULE is not available in {platform}");
+            assert!(err.starts_with(&expected), "{err}");
+        }
+    }
+
+    #[test]
+    fn numbars_print_exactly() {
+        let (out, _, failed) = assert_same(
+            "HAI 1.2\n\
+             VISIBLE 0.125 \" \" 0.375 \" \" -0.001 \" \" 123456.789\n\
+             VISIBLE PRODUKT OF \"1.0e20\" AN \"1.0e20\"\n\
+             VISIBLE PRODUKT OF \"1.0e200\" AN \"1.0e200\" \" \" QUOSHUNT OF 1 AN 3.0\n\
+             KTHXBYE\n",
+        );
+        assert!(!failed);
+        let mut lines = out.lines();
+        assert_eq!(Some("0.12 0.38 -0.00 123456.79"), lines.next());
+        assert_eq!(
+            Some("10000000000000000303786028427003666890752.00"),
+            lines.next()
+        );
+        assert_eq!(Some("inf 0.33"), lines.next());
+    }
+
+    #[test]
+    fn prints_wat_module() {
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .run_source("HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n", Mode::Wat)
+            .expect("print module");
+
+        let module = String::from_utf8(out).expect("convert output bytes to utf-8 string");
+        assert!(
+            module.contains("(import \"lolcode\" \"visible\""),
+            "{module}"
+        );
+        assert!(module.contains("O HAI"), "{module}");
+        wat::parse_str(&module).expect("valid module");
+    }
+
+    #[test]
+    fn prints_js_with_source_lines() {
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .run_source(
+                "HAI 1.2\nI HAS A X ITZ 1\n\nVISIBLE X \"!\"\nKTHXBYE\n",
+                Mode::Js,
+            )
+            .expect("print program");
+
+        let program = String::from_utf8(out).expect("convert output bytes to utf-8 string");
+        assert!(program.contains("// line 2\n  $X = 1n;\n"), "{program}");
+        assert!(program.contains("// line 4\n  console.log("), "{program}");
+    }
+}
+
+mod compiled {
+    use std::{env, fs, io::sink, process};
+
+    use crate::{
+        bytecode::{Bytecode, Compile, Instruction, FORMAT_VERSION},
+        diagnostic::{CompileError, RuntimeError},
+        framework::App,
+    };
+
+    const SOURCE: &str = "HAI 1.3\n\
+                          HOW IZ I TWICE YR N, FOUND YR PRODUKT OF N AN 2, IF U SAY SO\n\
+                          IM IN YR LOOP UPPIN YR I TIL BOTH SAEM I AN 3\n\
+                          VISIBLE SMOOSH \"x\" AN I IZ TWICE YR I MKAY AN 0.5 MKAY\n\
+                          IM OUTTA YR LOOP\n\
+                          KTHXBYE\n";
+
+    fn bytecode() -> Bytecode {
+        let mut app = App::new(sink(), sink());
+        let prog = app.parse(SOURCE).expect("parse program");
+        app.compile(&prog).expect("compile program")
+    }
+
+    fn load_error(bytes: &[u8]) -> String {
+        let err = Bytecode::from_bytes(bytes).expect_err("invalid file");
+        format!("{err:#}")
+    }
+
+    #[test]
+    fn round_trips() {
+        let bytecode = bytecode();
+        let bytes = bytecode.to_bytes();
+        assert!(bytes.starts_with(b"LOLC"));
+        assert_eq!(
+            bytecode,
+            Bytecode::from_bytes(&bytes).expect("load bytecode")
+        );
+    }
+
+    #[test]
+    fn compiles_and_runs_files() {
+        let dir = env::temp_dir().join(format!("rlcc-compiled-{}", process::id()));
+        fs::create_dir_all(&dir).expect("create temporary directory");
+        let (source, object) = (dir.join("loop.lol"), dir.join("loop.lolc"));
+        fs::write(&source, SOURCE).expect("write source");
+
+        App::new(sink(), sink())
+            .compile_file(&source, &object)
+            .expect("compile file");
+        // Running must not need the source
+        fs::remove_file(&source).expect("remove source");
+
+        let mut out = Vec::new();
+        App::new(&mut out, sink())
+            .run_compiled(&object)
+            .expect("run compiled file");
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        assert_eq!(
+            "x00.50\nx20.50\nx40.50\n",
+            String::from_utf8(out).expect("convert output bytes to utf-8 string")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let bytes = bytecode().to_bytes();
+
+        assert!(load_error(b"HAI 1.2\n").contains("not a compiled LOLCODE file"));
+        assert!(load_error(&bytes[..bytes.len() - 1]).contains("truncated"));
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().expect("file is not empty") ^= 0xff;
+        assert!(load_error(&corrupted).contains("corrupted"));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(load_error(&newer).contains("format version 2 is not supported"));
+
+        let mut unsupported = bytes;
+        unsupported[10..14].copy_from_slice(&7i32.to_le_bytes());
+        assert!(load_error(&unsupported).contains("LOLCODE 1.7, which is not supported"));
+    }
+
+    #[test]
+    fn stack_underflow_is_reported() {
+        let mut app = App::new(sink(), sink());
+        let prog = app
+            .parse("HAI 1.2\nVISIBLE \"O HAI\"\nKTHXBYE\n")
+            .expect("parse program");
+        let mut bytecode = app.compile(&prog).expect("compile program");
+        // More values than the program pushes
+        for instruction in &mut bytecode.code {
+            if let Instruction::Visible { args, .. } = instruction {
+                *args = 3;
+            }
+        }
+        let object = env::temp_dir().join(format!("rlcc-underflow-{}.lolc", process::id()));
+        fs::write(&object, bytecode.to_bytes()).expect("write bytecode");
+
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run_compiled(&object);
+        fs::remove_file(&object).expect("remove bytecode");
+        assert!(result.expect_err("invalid bytecode").is::<RuntimeError>());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0023]: Invalid bytecode: pop of 3 values from a stack of 1"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn invalid_file_is_reported() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).run_compiled("Cargo.toml");
+        assert!(result.expect_err("invalid file").is::<CompileError>());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0023]: Unable to load Cargo.toml: not a compiled"),
+            "{err}"
+        );
+    }
+}
+
+mod version {
+    use std::io::sink;
+
+    use crate::{framework::App, parser::LolCodeVersion};
+
+    fn parse_version(source: &str) -> LolCodeVersion {
+        App::new(sink(), sink())
+            .parse(source)
+            .expect("parse program")
+            .version
+    }
+
+    #[test]
+    fn declared() {
+        assert_eq!(
+            LolCodeVersion::from((1, 3)),
+            parse_version("HAI 1.3\nKTHXBYE\n")
+        );
+    }
+
+    #[test]
+    fn defaults_when_missing() {
+        assert_eq!(LolCodeVersion::DEFAULT, parse_version("HAI\nKTHXBYE\n"));
+        assert_eq!(
+            LolCodeVersion::DEFAULT,
+            parse_version("HAI BTW no version\nKTHXBYE\n")
+        );
+        assert_eq!(
+            LolCodeVersion::DEFAULT,
+            parse_version("HAI, VISIBLE \"x\"\nKTHXBYE\n")
+        );
+    }
+
+    #[test]
+    fn unsupported_names_supported_versions() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).parse("HAI 9.9\nKTHXBYE\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with("error[E0011]: Unsupported version 9.9"),
+            "{err}"
+        );
+        assert!(err.contains("Supported versions are 1.2 and 1.3"), "{err}");
+    }
+}
+
+mod features {
+    use std::io::sink;
+
+    use super::{native_modules::OurModule, ENGINES};
+    use crate::{
+        ast::{Block, Expr, ExprKind, LolCodeProgram, Stmt, StmtKind},
+        framework::App,
+        interpreter::Interpret,
+        parser::LolCodeVersion,
+        test::span,
+        Mode,
+    };
+
+    #[test]
+    fn invisible_writes_to_err() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        App::new(&mut out, &mut err)
+            .run_source(
+                "HAI 1.3\nVISIBLE \"out\"\nINVISIBLE \"err\"\nKTHXBYE\n",
+                Mode::Interpret,
+            )
+            .expect("run program");
+
+        assert_eq!("out\n", String::from_utf8(out).expect("utf-8 out"));
+        assert_eq!("err\n", String::from_utf8(err).expect("utf-8 err"));
+    }
+
+    #[test]
+    fn suggests_minimum_version() {
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err).parse("HAI 1.2\nINVISIBLE \"err\"\nKTHXBYE\n");
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(
+            err.starts_with(
+                "error[E0012]: `INVISIBLE` is an lci extension and requires HAI 1.3 or newer"
+            ),
+            "{err}"
+        );
+        assert!(err.contains("Declare `HAI 1.3`"), "{err}");
+        // Only the version is wrong, the statement itself is fine
+        assert_eq!(1, err.matches("error[").count(), "{err}");
+    }
+
+    #[test]
+    fn interpreter_checks_version() {
+        let mut err = Vec::new();
+        let invisible = Stmt {
+            kind: StmtKind::Visible {
+                args: vec![Expr {
+                    kind: ExprKind::Yarn("err".to_string()),
+                    span: span(1, 11, 1, 15),
+                }],
+                invisible: true,
+                newline: true,
+            },
+            span: span(1, 1, 1, 15),
+        };
+        let result = App::new(sink(), &mut err).execute(LolCodeProgram {
+            version: LolCodeVersion::from((1, 2)),
+            body: Block {
+                stmts: vec![invisible],
+                span: span(1, 1, 1, 15),
+            },
+            span: span(1, 1, 1, 15),
+        });
+        assert!(result.is_err());
+
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.starts_with("error[E0012]"), "{err}");
+    }
+
+    #[test]
+    fn spec_1_2_under_every_version() {
+        const BODY: &str = "HOW IZ I TWICE YR X, FOUND YR PRODUKT OF X AN 2, IF U SAY SO\n\
+             IM IN YR LOOP UPPIN YR I TIL BOTH SAEM I AN 3\n\
+             VISIBLE SMOOSH I AN \"-\" AN I IZ TWICE YR I MKAY MKAY\n\
+             IM OUTTA YR LOOP\n\
+             VISIBLE MAEK \"4\" A NUMBAR\n";
+        for version in ["1.2", "1.3"] {
+            for mode in ENGINES {
+                let mut out = Vec::new();
+                App::new(&mut out, sink())
+                    .run_source(
+                        format!("HAI {version}\n{BODY}KTHXBYE\n").as_str(),
+                        mode.clone(),
+                    )
+                    .unwrap_or_else(|err| panic!("HAI {version} with {mode:?}: {err:?}"));
+                assert_eq!(
+                    "0-0\n1-2\n2-4\n4.00\n",
+                    String::from_utf8(out).expect("utf-8 output")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spec_1_3_module_calls() {
+        for call in ["STDIO IZ NOPE MKAY", "I IZ STDIO'Z NOPE MKAY"] {
+            let mut err = Vec::new();
+            let source = format!("HAI 1.2\nVISIBLE {call}\nKTHXBYE\n");
+            let result = App::new(sink(), &mut err).parse(source.as_str());
+            assert!(result.is_err(), "{source}");
+
+            let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+            assert!(
+                err.starts_with("error[E0012]: `<module> IZ <function>` requires HAI 1.3 or newer"),
+                "{source}: {err}"
+            );
+            assert!(err.contains("Declare `HAI 1.3`"), "{err}");
+            assert_eq!(1, err.matches("error[").count(), "{err}");
+        }
+    }
+
+    #[test]
+    fn interpreter_checks_expression_version() {
+        let mut app = App::new(sink(), sink());
+        app.register_module(OurModule);
+        let mut prog = app
+            .parse(
+                "HAI 1.3\nCAN HAS OURMODULE?\nVISIBLE I IZ OURMODULE'Z ECHO YR 1 MKAY\nKTHXBYE\n",
+            )
+            .expect("parse program");
+        prog.version = LolCodeVersion::from((1, 2));
+
+        let mut err = Vec::new();
+        let result = App::new(sink(), &mut err)
+            .register_module(OurModule)
+            .execute(prog);
+        assert!(result.is_err());
+        let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+        assert!(err.starts_with("error[E0012]"), "{err}");
+    }
+
+    #[test]
+    fn reports_unsupported_lci_extensions() {
+        for (stmt, feature) in [
+            ("O HAI IM CAT", "`BUKKIT`"),
+            ("I HAS A CAT ITZ A BUKKIT", "`BUKKIT`"),
+            ("I HAS A KITTEN ITZ LIEK A CAT", "`BUKKIT`"),
+            ("VISIBLE CAT'Z NAME", "`BUKKIT`"),
+            ("VISIBLE SRS \"CAT\"", "`SRS`"),
+        ] {
+            // Not supported under any version, so the version is not blamed
+            for version in ["1.2", "1.3"] {
+                let mut err = Vec::new();
+                let source = format!("HAI {version}\n{stmt}\nKTHXBYE\n");
+                let result = App::new(sink(), &mut err).parse(source.as_str());
+                assert!(result.is_err(), "{source}");
+
+                let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+                let expected = format!("error[E0025]: {feature} is not supported");
+                assert!(err.starts_with(&expected), "{source}: {err}");
+            }
+        }
+    }
+}
+
+mod native_modules {
+    use std::io::sink;
+
+    use super::ENGINES;
+    use crate::{
+        ast::{Block, Ident, LolCodeProgram, Stmt, StmtKind},
+        framework::{App, NativeModule},
+        interpreter::Interpret,
+        modules::Modules,
+        test::span,
+        value::Value,
+    };
+
+    pub(super) struct OurModule;
+
+    impl NativeModule for OurModule {
+        fn name(&self) -> &str {
+            "OURMODULE"
+        }
+
+        fn functions(&self) -> &[&str] {
+            &["ECHO"]
+        }
+
+        fn call(&mut self, _function: &str, args: Vec<Value>) -> anyhow::Result<Value> {
+            Ok(args.into_iter().next().unwrap_or(Value::Noob))
+        }
+    }
+
+    fn include(module: &str) -> LolCodeProgram {
+        let can_has = Stmt {
+            kind: StmtKind::CanHas {
+                module: Ident {
+                    name: module.to_string(),
+                    span: span(1, 9, 1, 17),
+                },
+            },
+            span: span(1, 1, 1, 17),
+        };
+        LolCodeProgram {
+            version: (1, 2).into(),
+            body: Block {
+                stmts: vec![can_has],
+                span: span(1, 1, 1, 17),
+            },
+            span: span(1, 1, 1, 17),
+        }
+    }
+
+    #[test]
+    fn registered_module_resolves() {
+        let mut app = App::new(sink(), sink());
+        app.register_module(OurModule);
+        app.execute(include("OURMODULE"))
+            .expect("include OURMODULE");
+
+        let echoed = app
+            .modules()
+            .call("OURMODULE", "ECHO", vec!["KITTEH".into()])
+            .expect("call ECHO");
+        assert_eq!(Value::Yarn("KITTEH".to_string()), echoed);
+        assert!(app.modules().call("OURMODULE", "NOPE", vec![]).is_err());
+    }
+
+    #[test]
+    fn unregistered_module_errors() {
+        let mut app = App::new(sink(), sink());
+        assert!(app.execute(include("OURMODULE")).is_err());
+    }
+
+    #[test]
+    fn called_from_source() {
+        for mode in ENGINES {
+            let mut out = Vec::new();
+            App::new(&mut out, sink())
+                .register_module(OurModule)
+                .run_source(
+                    "HAI 1.3\n\
+                     CAN HAS OURMODULE?\n\
+                     VISIBLE I IZ OURMODULE'Z ECHO YR \"KITTEH\" MKAY\n\
+                     I HAS A ECHOED ITZ I IZ OURMODULE'Z ECHO YR SUM OF 1 AN 2 MKAY\n\
+                     VISIBLE ECHOED\n\
+                     KTHXBYE\n",
+                    mode.clone(),
+                )
+                .unwrap_or_else(|err| panic!("running with {mode:?}: {err:?}"));
+            assert_eq!("KITTEH\n3\n", String::from_utf8(out).expect("utf-8 output"));
+        }
+    }
+}
+
+#[cfg(feature = "socks")]
+mod socks {
+    use std::{
+        io::{sink, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::ENGINES;
+    use crate::{
+        framework::{App, NativeModule},
+        modules::{socks::Socks, ModuleRegistry},
+        value::Value,
+    };
+
+    fn call(socks: &mut Socks, function: &str, args: Vec<Value>) -> Value {
+        socks
+            .call(function, args)
+            .unwrap_or_else(|err| panic!("calling {function}: {err:?}"))
+    }
+
+    #[test]
+    fn requires_load() {
+        let mut registry = ModuleRegistry::default();
+        assert!(registry.call("SOCKS", "CLOSE", vec![1.into()]).is_err());
+    }
+
+    #[test]
+    fn loopback_round_trip() {
+        let mut socks = Socks::default();
+
+        let server = call(&mut socks, "BIND", vec!["127.0.0.1".into(), 0.into()]);
+        let port = socks.local_addr(&server).expect("bound address").port();
+        let client = call(&mut socks, "BIND", vec!["127.0.0.1".into(), 0.into()]);
+        let outgoing = call(
+            &mut socks,
+            "KONN",
+            vec![client.clone(), "127.0.0.1".into(), i64::from(port).into()],
+        );
+        let incoming = call(&mut socks, "LISTN", vec![server.clone()]);
+
+        let sent = call(
+            &mut socks,
+            "PUT",
+            vec![client.clone(), outgoing.clone(), "O HAI".into()],
+        );
+        assert_eq!(Value::Numbr(5), sent);
+
+        let received = call(
+            &mut socks,
+            "GET",
+            vec![server.clone(), incoming.clone(), 5.into()],
+        );
+        assert_eq!(Value::Yarn("O HAI".to_string()), received);
+
+        for handle in [incoming, outgoing, server, client] {
+            assert_eq!(Value::Noob, call(&mut socks, "CLOSE", vec![handle]));
+        }
+    }
+
+    /// Echoes one message of `len` bytes back to each of `connections`.
+    fn echo_server(connections: usize, len: usize) -> (u16, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind ephemeral port");
+        let port = listener.local_addr().expect("local addr").port();
+        let server = thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().expect("accept connection");
+                let mut buf = vec![0; len];
+                stream.read_exact(&mut buf).expect("read message");
+                stream.write_all(&buf).expect("echo message");
+            }
+        });
+        (port, server)
+    }
+
+    #[test]
+    fn lci_module_calls() {
+        let (port, server) = echo_server(ENGINES.len(), 5);
+        for mode in ENGINES {
+            let source = format!(
+                "HAI 1.3\n\
+                 CAN HAS SOCKS?\n\
+                 I HAS A CLIENT ITZ I IZ SOCKS'Z BIND YR \"127.0.0.1\" AN YR 0 MKAY\n\
+                 I HAS A OUTGOING ITZ I IZ SOCKS'Z KONN YR CLIENT AN YR \"127.0.0.1\" AN YR {port} MKAY\n\
+                 VISIBLE I IZ SOCKS'Z PUT YR CLIENT AN YR OUTGOING AN YR \"O HAI\" MKAY\n\
+                 VISIBLE I IZ SOCKS'Z GET YR CLIENT AN YR OUTGOING AN YR 5 MKAY\n\
+                 I IZ SOCKS'Z CLOSE YR OUTGOING MKAY\n\
+                 I IZ SOCKS'Z CLOSE YR CLIENT MKAY\n\
+                 KTHXBYE\n",
+            );
+            let mut out = Vec::new();
+            App::new(&mut out, sink())
+                .run_source(source.as_str(), mode.clone())
+                .unwrap_or_else(|err| panic!("running with {mode:?}: {err:?}"));
+            assert_eq!("5\nO HAI\n", String::from_utf8(out).expect("utf-8 output"));
+        }
+        server.join().expect("echo server");
+    }
+
+    #[test]
+    fn rejects_unknown_handle() {
+        let mut registry = ModuleRegistry::default();
+        registry.load("SOCKS").expect("load SOCKS");
+        assert!(registry.call("SOCKS", "LISTN", vec![42.into()]).is_err());
+    }
+}
+
+fn span(line: usize, column: usize, end_line: usize, end_column: usize) -> Span {
+    Span {
+        start: Position { line, column },
+        end: Position {
+            line: end_line,
+            column: end_column,
+        },
+    }
+}
+
 fn run_dir(resource: &str) {
     let test_dir = Path::new(resource);
 
@@ -37,25 +1970,301 @@ fn run_dir(resource: &str) {
     let mut input_file = test_dir.to_path_buf();
     input_file.push("test.lol");
 
-    let mut output = Vec::new();
-    let result = App::new(&mut output, stderr()).run(input_file, Mode::Interpret);
-    let out_str = String::from_utf8(output).expect("convert output bytes to utf-8 string");
-
     let out_file = {
         let mut out_file = test_dir.to_path_buf();
         out_file.push("test.out");
         out_file
     };
+    let out_content = out_file
+        .is_file()
+        .then(|| fs::read_to_string(out_file).expect("Unable to read provided file"));
 
-    if out_file.is_file() {
-        let out_content = fs::read_to_string(out_file).expect("Unable to read provided file");
-        println!("Testing output");
-        assert_eq!(
-            out_content, out_str,
-            "prog output does not match test output"
-        );
+    let optimized = OPT_LEVELS.map(|level| (Mode::Interpret, level));
+    for (mode, level) in ENGINES
+        .map(|mode| (mode, OptLevel::O0))
+        .into_iter()
+        .chain(optimized)
+    {
+        let mut output = Vec::new();
+        let result = App::new(&mut output, stderr())
+            .set_opt_level(level)
+            .run(&input_file, mode.clone());
+        let out_str = String::from_utf8(output).expect("convert output bytes to utf-8 string");
+
+        if let Some(out_content) = &out_content {
+            println!("Testing output of {mode:?} at {level:?}");
+            assert_eq!(
+                out_content, &out_str,
+                "prog output of {mode:?} at {level:?} does not match test output"
+            );
+        }
+
+        println!("Output: {out_str}");
+        assert_eq!(contains_err_file, result.is_err(), "{mode:?} at {level:?}")
+    }
+
+    let source = fs::read_to_string(&input_file).expect("Unable to read provided file");
+    for (backend, run) in BACKENDS {
+        let (out_str, _, failed) = run(SourceFile::new("test.lol", source.clone()));
+        if let Some(out_content) = &out_content {
+            assert_eq!(
+                out_content, &out_str,
+                "{backend} output does not match test output"
+            );
+        }
+        assert_eq!(contains_err_file, failed, "{backend}");
+    }
+}
+
+/// Builds `source` into an executable with [`Mode::Compile`] and runs it,
+/// returning its output, its errors and whether building or running failed.
+fn run_native(source: SourceFile) -> (String, String, bool) {
+    run_executable(source, Mode::Compile)
+}
+
+/// Like [`run_native`] with [`Mode::Asm`].
+fn run_asm(source: SourceFile) -> (String, String, bool) {
+    run_executable(source, Mode::Asm)
+}
+
+/// Why running `llc` failed. It is not optional: CI installs it, and the
+/// LLVM backend is untested without it.
+const LLC: &str = "run llc, which the LLVM backend is tested with (install LLVM)";
+
+/// Lowers `source` to LLVM IR with [`Mode::LlvmIr`], builds it together with
+/// its runtime using `llc` and the C compiler, and runs it.
+fn run_llvm(source: SourceFile) -> (String, String, bool) {
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+    let build = BUILDS.fetch_add(1, Ordering::Relaxed);
+    let dir = env::temp_dir().join(format!("rlcc-llvm-{}-{build}", process::id()));
+    fs::create_dir_all(&dir).expect("create temporary directory");
+    let ir = dir.join("test.ll");
+
+    let mut err = Vec::new();
+    let result = App::new(sink(), &mut err)
+        .set_output(&ir)
+        .run_source(source, Mode::LlvmIr);
+    let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+    if let Err(lower_err) = result {
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+        return (String::new(), format!("{err}{lower_err:#}"), true);
+    }
+
+    let object = dir.join("test.o");
+    let status = Command::new("llc")
+        .args(opaque_pointers())
+        .args(["-filetype=obj", "-relocation-model=pic", "-o"])
+        .arg(&object)
+        .arg(&ir)
+        .status()
+        .expect(LLC);
+    assert!(status.success(), "llc failed on {}", ir.display());
+    let executable = dir.join("test");
+    let status = Command::new(env::var_os("CC").unwrap_or_else(|| "cc".into()))
+        .arg("-o")
+        .arg(&executable)
+        .arg(&object)
+        .arg(dir.join("test.runtime.c"))
+        .arg("-lm")
+        .status()
+        .expect("run the C compiler");
+    assert!(status.success(), "linking {} failed", object.display());
+
+    let output = Command::new(&executable)
+        .output()
+        .expect("run native executable");
+    fs::remove_dir_all(&dir).expect("remove temporary directory");
+    (
+        String::from_utf8(output.stdout).expect("convert output bytes to utf-8 string"),
+        String::from_utf8(output.stderr).expect("convert err bytes to utf-8 string"),
+        !output.status.success(),
+    )
+}
+
+/// The option `llc` needs to read opaque pointers, which LLVM 15 turned on
+/// by default and LLVM 17 no longer accepts.
+fn opaque_pointers() -> Option<&'static str> {
+    static OPTION: OnceLock<Option<&str>> = OnceLock::new();
+    *OPTION.get_or_init(|| {
+        let output = Command::new("llc").arg("--version").output().expect(LLC);
+        let version = String::from_utf8(output.stdout).expect("convert version to utf-8");
+        let major: u32 = version
+            .split("LLVM version ")
+            .nth(1)
+            .and_then(|version| version.split('.').next())
+            .and_then(|major| major.parse().ok())
+            .expect("llc prints its version");
+        (major < 15).then_some("-opaque-pointers")
+    })
+}
+
+/// Builds `source` into an executable with `mode` and runs it.
+fn run_executable(source: SourceFile, mode: Mode) -> (String, String, bool) {
+    static BUILDS: AtomicUsize = AtomicUsize::new(0);
+    let build = BUILDS.fetch_add(1, Ordering::Relaxed);
+    let executable = env::temp_dir().join(format!("rlcc-native-{}-{build}", process::id()));
+
+    let mut err = Vec::new();
+    let result = App::new(sink(), &mut err)
+        .set_output(&executable)
+        .run_source(source, mode);
+    let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+    if let Err(build_err) = result {
+        return (String::new(), format!("{err}{build_err:#}"), true);
+    }
+
+    let output = Command::new(&executable)
+        .output()
+        .expect("run native executable");
+    fs::remove_file(&executable).expect("remove native executable");
+    (
+        String::from_utf8(output.stdout).expect("convert output bytes to utf-8 string"),
+        String::from_utf8(output.stderr).expect("convert err bytes to utf-8 string"),
+        !output.status.success(),
+    )
+}
+
+/// Lowers `source` to a module with [`Mode::Wat`], assembles it and runs it
+/// in a WebAssembly interpreter, returning its output, its errors and
+/// whether lowering or running failed.
+fn run_wasm(source: SourceFile) -> (String, String, bool) {
+    run_wasm_with_input(source, "")
+}
+
+/// What a module compiled with [`Mode::Wat`] sees of its host.
+#[derive(Default)]
+struct WasmHost {
+    /// What the program wrote to streams 1 and 2
+    out: Vec<u8>,
+    err: Vec<u8>,
+    /// Input not yet read, and a line too long for the last buffer
+    input: Vec<u8>,
+    pending: Option<Vec<u8>>,
+}
+
+impl WasmHost {
+    fn next_line(&mut self) -> Vec<u8> {
+        if let Some(line) = self.pending.take() {
+            return line;
+        }
+        match self.input.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                let mut line: Vec<u8> = self.input.drain(..=end).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                line
+            }
+            None => std::mem::take(&mut self.input),
+        }
+    }
+}
+
+/// [`run_wasm`] with `input` behind the `gimmeh` import.
+fn run_wasm_with_input(source: SourceFile, input: &str) -> (String, String, bool) {
+    let mut module = Vec::new();
+    let mut err = Vec::new();
+    let result = App::new(&mut module, &mut err).run_source(source, Mode::Wat);
+    let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+    if let Err(lower_err) = result {
+        return (String::new(), format!("{err}{lower_err:#}"), true);
+    }
+
+    let module = String::from_utf8(module).expect("convert module bytes to utf-8 string");
+    let wasm = wat::parse_str(&module).unwrap_or_else(|err| panic!("{err}\n{module}"));
+    let engine = wasmi::Engine::default();
+    let module = wasmi::Module::new(&engine, &wasm[..]).expect("validate module");
+    let host = WasmHost {
+        input: input.as_bytes().to_vec(),
+        ..WasmHost::default()
+    };
+    let mut store = wasmi::Store::new(&engine, host);
+    let mut linker = wasmi::Linker::new(&engine);
+    linker
+        .func_wrap(
+            "lolcode",
+            "visible",
+            |mut caller: wasmi::Caller<'_, WasmHost>, stream: i32, start: i32, len: i32| {
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(wasmi::Extern::into_memory)
+                    .expect("memory is exported");
+                let (memory, host) = memory.data_and_store_mut(&mut caller);
+                let text = &memory[start as usize..][..len as usize];
+                match stream {
+                    1 => host.out.extend_from_slice(text),
+                    _ => host.err.extend_from_slice(text),
+                }
+            },
+        )
+        .expect("define visible");
+    linker
+        .func_wrap(
+            "lolcode",
+            "gimmeh",
+            |mut caller: wasmi::Caller<'_, WasmHost>, start: i32, capacity: i32| {
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(wasmi::Extern::into_memory)
+                    .expect("memory is exported");
+                let (memory, host) = memory.data_and_store_mut(&mut caller);
+                let line = host.next_line();
+                let len = i32::try_from(line.len()).expect("line fits in memory");
+                if len > capacity {
+                    host.pending = Some(line);
+                } else {
+                    memory[start as usize..][..line.len()].copy_from_slice(&line);
+                }
+                len
+            },
+        )
+        .expect("define gimmeh");
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .expect("instantiate module");
+    let run = instance
+        .get_typed_func::<(), ()>(&store, "run")
+        .expect("run is exported");
+    // Runtime errors trap once reported
+    let failed = run.call(&mut store, ()).is_err();
+
+    let host = store.into_data();
+    (
+        String::from_utf8(host.out).expect("convert output bytes to utf-8 string"),
+        String::from_utf8(host.err).expect("convert err bytes to utf-8 string"),
+        failed,
+    )
+}
+
+/// Transpiles `source` with [`Mode::Js`] and runs it with Node.js, returning
+/// its output, its errors and whether transpiling or running failed.
+fn run_js(source: SourceFile) -> (String, String, bool) {
+    let mut program = Vec::new();
+    let mut err = Vec::new();
+    let result = App::new(&mut program, &mut err).run_source(source, Mode::Js);
+    let err = String::from_utf8(err).expect("convert err bytes to utf-8 string");
+    if let Err(transpile_err) = result {
+        return (String::new(), format!("{err}{transpile_err:#}"), true);
     }
 
-    println!("Output: {out_str}");
-    assert_eq!(contains_err_file, result.is_err())
+    let mut node = Command::new("node")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run node");
+    node.stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(&program)
+        .expect("write program to node");
+    let output = node.wait_with_output().expect("wait for node");
+    (
+        String::from_utf8(output.stdout).expect("convert output bytes to utf-8 string"),
+        String::from_utf8(output.stderr).expect("convert err bytes to utf-8 string"),
+        !output.status.success(),
+    )
 }
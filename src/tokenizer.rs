@@ -1,31 +1,129 @@
+use std::{collections::VecDeque, io::BufRead};
+
 use derive_more::Display;
 use mediator_tracing::tracing::trace;
 
-#[derive(Debug, PartialEq, Eq, Display)]
+use crate::{
+    diagnostic::{Diagnostic, ErrorCode, Position},
+    framework::HandleTokenProcessingError,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display)]
 pub enum KeywordToken {
+    #[display(fmt = "HAI")]
     Hai,
+    #[display(fmt = "KTHXBYE")]
     KThxBye,
+    #[display(fmt = "VISIBLE")]
     Visible,
+    #[display(fmt = "INVISIBLE")]
+    Invisible,
+    #[display(fmt = "BTW")]
     Btw,
-    Can,
+    #[display(fmt = "HAS")]
     Has,
+    #[display(fmt = "OBTW")]
     OBtw,
+    #[display(fmt = "TLDR")]
     Tldr,
+    #[display(fmt = "I HAS A")]
+    IHasA,
+    #[display(fmt = "IS NOW A")]
+    IsNowA,
+    #[display(fmt = "CAN HAS")]
+    CanHas,
+    #[display(fmt = "O RLY?")]
+    ORly,
+    #[display(fmt = "YA RLY")]
+    YaRly,
+    #[display(fmt = "NO WAI")]
+    NoWai,
+    #[display(fmt = "IM IN YR")]
+    ImInYr,
+    #[display(fmt = "IM OUTTA YR")]
+    ImOuttaYr,
+    #[display(fmt = "HOW IZ I")]
+    HowIzI,
+    #[display(fmt = "IF U SAY SO")]
+    IfUSaySo,
+    #[display(fmt = "FOUND YR")]
+    FoundYr,
+    #[display(fmt = "I IZ")]
+    IIz,
+    #[display(fmt = "AN YR")]
+    AnYr,
+    #[display(fmt = "SUM OF")]
+    SumOf,
+    #[display(fmt = "DIFF OF")]
+    DiffOf,
+    #[display(fmt = "PRODUKT OF")]
+    ProduktOf,
+    #[display(fmt = "QUOSHUNT OF")]
+    QuoshuntOf,
+    #[display(fmt = "MOD OF")]
+    ModOf,
+    #[display(fmt = "BIGGR OF")]
+    BiggrOf,
+    #[display(fmt = "SMALLR OF")]
+    SmallrOf,
+    #[display(fmt = "BOTH OF")]
+    BothOf,
+    #[display(fmt = "EITHER OF")]
+    EitherOf,
+    #[display(fmt = "WON OF")]
+    WonOf,
+    #[display(fmt = "BOTH SAEM")]
+    BothSaem,
+    #[display(fmt = "ALL OF")]
+    AllOf,
+    #[display(fmt = "ANY OF")]
+    AnyOf,
 }
 
+/// Keywords spelled with several words. Each is lexed as a single token.
+const PHRASES: [KeywordToken; 26] = [
+    KeywordToken::IHasA,
+    KeywordToken::IsNowA,
+    KeywordToken::CanHas,
+    KeywordToken::ORly,
+    KeywordToken::YaRly,
+    KeywordToken::NoWai,
+    KeywordToken::ImInYr,
+    KeywordToken::ImOuttaYr,
+    KeywordToken::HowIzI,
+    KeywordToken::IfUSaySo,
+    KeywordToken::FoundYr,
+    KeywordToken::IIz,
+    KeywordToken::AnYr,
+    KeywordToken::SumOf,
+    KeywordToken::DiffOf,
+    KeywordToken::ProduktOf,
+    KeywordToken::QuoshuntOf,
+    KeywordToken::ModOf,
+    KeywordToken::BiggrOf,
+    KeywordToken::SmallrOf,
+    KeywordToken::BothOf,
+    KeywordToken::EitherOf,
+    KeywordToken::WonOf,
+    KeywordToken::BothSaem,
+    KeywordToken::AllOf,
+    KeywordToken::AnyOf,
+];
+
 impl From<KeywordToken> for TokenType {
     fn from(value: KeywordToken) -> Self {
         TokenType::Keyword(value)
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Display)]
+#[derive(Debug, PartialEq, Eq, Clone, Display)]
 pub enum TokenType {
     Keyword(KeywordToken),
     #[display(fmt = "{_0}")]
     Word(String),
-    #[display(fmt = " ")]
-    Space,
+    /// A space or a tab, kept as written for YARNs.
+    #[display(fmt = "{_0}")]
+    Space(char),
     #[display(fmt = "\n")]
     NewLine,
     #[display(fmt = ".")]
@@ -36,85 +134,360 @@ pub enum TokenType {
     Quote,
 }
 
-#[derive(Debug)]
+impl TokenType {
+    pub fn is_space(&self) -> bool {
+        matches!(self, TokenType::Space(_))
+    }
+
+    /// Human readable name for use in diagnostics.
+    pub fn describe(&self) -> String {
+        match self {
+            TokenType::Space(_) => "space".to_string(),
+            TokenType::NewLine => "newline".to_string(),
+            t_type => format!("`{t_type}`"),
+        }
+    }
+}
+
+/// A character in the source. Columns count characters, so a tab or a
+/// multi-byte character is a single column, while `offset` counts bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[jsm::public]
 pub struct TokenLocation {
     line: usize,
     column: usize,
+    offset: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[jsm::public]
 pub struct Token {
-    location: TokenLocation,
+    /// First character of the token.
+    start: TokenLocation,
+    /// Last character of the token. For a `\r\n` newline that is the `\n`.
+    end: TokenLocation,
     t_type: TokenType,
 }
 
-pub fn parse_tokens(content_string: String) -> Vec<Token> {
-    let mut parsed_tokens = Vec::new();
-    let mut buffer: String = String::new();
-    let mut skip_nl = false;
-    let mut current_line = 1;
-    let mut current_col = 1;
-    for c in content_string.chars() {
-        let mut consume_buffer_and_append = |token| {
-            let mut tokens = vec![];
-            if let Some(token) = parse_word(&mut buffer) {
-                tokens.push(token);
+pub trait Tokenize: Sized {
+    /// Tokens of the LOLCODE read from `reader`, produced as the source is
+    /// read rather than all at once.
+    fn lex<R>(&mut self, reader: R) -> Lexer<'_, Self, R>
+    where
+        R: BufRead;
+
+    fn tokenize(&mut self, content_string: &str) -> anyhow::Result<Vec<Token>>;
+}
+
+impl<T> Tokenize for T
+where
+    T: HandleTokenProcessingError,
+{
+    fn lex<R>(&mut self, reader: R) -> Lexer<'_, Self, R>
+    where
+        R: BufRead,
+    {
+        Lexer {
+            handler: self,
+            reader,
+            line: 1,
+            column: 1,
+            offset: 0,
+            buffer: String::new(),
+            word: None,
+            tokens: PhraseJoiner::default(),
+            done: false,
+        }
+    }
+
+    fn tokenize(&mut self, content_string: &str) -> anyhow::Result<Vec<Token>> {
+        self.lex(content_string.as_bytes()).collect()
+    }
+}
+
+/// Iterator over the tokens of a [`BufRead`], reading a line at a time.
+/// Unexpected characters are reported to the handler as they are found.
+pub struct Lexer<'h, H, R> {
+    handler: &'h mut H,
+    reader: R,
+    line: usize,
+    column: usize,
+    /// Bytes read so far.
+    offset: usize,
+    buffer: String,
+    /// First and last character of the word in `buffer`.
+    word: Option<(TokenLocation, TokenLocation)>,
+    tokens: PhraseJoiner,
+    done: bool,
+}
+
+impl<H, R> Iterator for Lexer<'_, H, R>
+where
+    H: HandleTokenProcessingError,
+    R: BufRead,
+{
+    type Item = anyhow::Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.tokens.pop() {
+                return Some(Ok(token));
             }
-            tokens.push(token);
-            tokens
-        };
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.read_line() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
 
-        let tokens;
-        (tokens, current_line, current_col) = match c {
-            '\r' => {
-                skip_nl = true;
-                let tokens = consume_buffer_and_append(TokenType::NewLine);
-                (Some(tokens), current_line + 1, 1)
+impl<H, R> Lexer<'_, H, R>
+where
+    H: HandleTokenProcessingError,
+    R: BufRead,
+{
+    /// The handler the lexer reports to, for whatever consumes the tokens
+    /// to report to as well.
+    pub fn handler(&mut self) -> &mut H {
+        self.handler
+    }
+
+    fn read_line(&mut self) -> anyhow::Result<()> {
+        let mut text = String::new();
+        if self.reader.read_line(&mut text)? == 0 {
+            self.consume_word();
+            self.tokens.finish();
+            self.done = true;
+            return Ok(());
+        }
+        self.handler.source_read(self.line, &text);
+
+        // Lines end at \n, so a \r\n is never split between two reads
+        let mut chars = text.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            let location = TokenLocation {
+                line: self.line,
+                column: self.column,
+                offset: self.offset + index,
+            };
+            let t_type = match c {
+                '\r' | '\n' => {
+                    self.consume_word();
+                    let mut end = location;
+                    // \r\n is a single newline, as is a lone \r
+                    if c == '\r' {
+                        if let Some((index, _)) = chars.next_if(|(_, next)| *next == '\n') {
+                            end = TokenLocation {
+                                column: self.column + 1,
+                                offset: self.offset + index,
+                                ..location
+                            };
+                        }
+                    }
+                    self.tokens.push(Token {
+                        start: location,
+                        end,
+                        t_type: TokenType::NewLine,
+                    });
+                    self.line += 1;
+                    self.column = 1;
+                    continue;
+                }
+                ' ' | '\t' => Some(TokenType::Space(c)),
+                '.' => Some(TokenType::Period),
+                ',' => Some(TokenType::Comma),
+                '"' => Some(TokenType::Quote),
+                c if c.is_control() => {
+                    self.consume_word();
+                    self.handler.emit(
+                        Diagnostic::error(
+                            ErrorCode::UnexpectedCharacter,
+                            format!("Unexpected character {c:?}"),
+                        )
+                        .with_span(Position::from(&location)),
+                    )?;
+                    None
+                }
+                c => {
+                    self.buffer.push(c);
+                    let start = self.word.map_or(location, |(start, _)| start);
+                    self.word = Some((start, location));
+                    None
+                }
+            };
+            if let Some(t_type) = t_type {
+                self.consume_word();
+                self.tokens.push(Token {
+                    start: location,
+                    end: location,
+                    t_type,
+                });
             }
-            '\n' => match skip_nl {
-                true => (None, current_line, current_col),
-                false => {
-                    let tokens = consume_buffer_and_append(TokenType::NewLine);
-                    (Some(tokens), current_line + 1, 1)
+            self.column += 1;
+        }
+        self.offset += text.len();
+        Ok(())
+    }
+
+    fn consume_word(&mut self) {
+        if let (Some(t_type), Some((start, end))) = (parse_word(&mut self.buffer), self.word.take())
+        {
+            self.tokens.push(Token { start, end, t_type });
+        }
+    }
+}
+
+/// Joins the words of multi-word keywords into single tokens as they are
+/// pushed. Words inside YARNs and comments are left alone.
+#[derive(Default)]
+struct PhraseJoiner {
+    tokens: VecDeque<Token>,
+    /// Words, and the spaces between them, that start some phrase.
+    pending: Vec<Token>,
+    in_yarn: bool,
+    comment: Option<Comment>,
+    /// Whether the last token ends in an odd number of colons, escaping a
+    /// quote after it in a YARN.
+    escapes_quote: bool,
+}
+
+/// Kind of comment the [`PhraseJoiner`] is in.
+#[derive(PartialEq, Eq)]
+enum Comment {
+    /// `BTW`, up to the end of the line.
+    Line,
+    /// `OBTW`, up to `TLDR`.
+    Block,
+}
+
+impl PhraseJoiner {
+    /// The next token no longer waiting on the words after it.
+    fn pop(&mut self) -> Option<Token> {
+        self.tokens.pop_front()
+    }
+
+    fn push(&mut self, token: Token) {
+        if !self.pending.is_empty() {
+            if token.t_type.is_space() {
+                self.pending.push(token);
+                return;
+            }
+            if let Some(word) = phrase_word(&token.t_type) {
+                let mut words = self.pending_words();
+                words.push(word);
+                if let Some(keyword) = phrase(&words) {
+                    let start = self.pending[0].start;
+                    self.pending.clear();
+                    self.emit(Token {
+                        start,
+                        end: token.end,
+                        t_type: keyword.into(),
+                    });
+                    return;
+                }
+                if !candidates(&words).is_empty() {
+                    self.pending.push(token);
+                    return;
                 }
-            },
-            ' ' | '\t' => {
-                let tokens = consume_buffer_and_append(TokenType::Space);
-                (Some(tokens), current_line, current_col + 1)
             }
-            '.' => {
-                let tokens = consume_buffer_and_append(TokenType::Period);
-                (Some(tokens), current_line, current_col + 1)
+            self.flush();
+        }
+
+        match &token.t_type {
+            TokenType::NewLine => {
+                self.in_yarn = false;
+                if self.comment == Some(Comment::Line) {
+                    self.comment = None;
+                }
             }
-            ',' => {
-                let tokens = consume_buffer_and_append(TokenType::Comma);
-                (Some(tokens), current_line, current_col + 1)
+            // :" is an escaped quote
+            TokenType::Quote
+                if self.comment.is_none() && (!self.in_yarn || !self.escapes_quote) =>
+            {
+                self.in_yarn = !self.in_yarn;
             }
-            '"' => {
-                let tokens = consume_buffer_and_append(TokenType::Quote);
-                (Some(tokens), current_line, current_col + 1)
+            TokenType::Keyword(KeywordToken::Btw) if !self.in_yarn && self.comment.is_none() => {
+                self.comment = Some(Comment::Line);
             }
-            _ => {
-                buffer.push(c);
-                (None, current_line, current_col + 1)
+            TokenType::Keyword(KeywordToken::OBtw) if !self.in_yarn && self.comment.is_none() => {
+                self.comment = Some(Comment::Block);
             }
-        };
-        if let Some(tokens) = tokens {
-            for token in tokens {
-                parsed_tokens.push(Token {
-                    location: TokenLocation {
-                        line: current_line,
-                        column: current_col,
-                    },
-                    t_type: token,
-                });
+            TokenType::Keyword(KeywordToken::Tldr) if self.comment == Some(Comment::Block) => {
+                self.comment = None;
             }
+            t_type if !self.in_yarn && self.comment.is_none() => {
+                if let Some(word) = phrase_word(t_type) {
+                    if !candidates(&[word]).is_empty() {
+                        self.pending.push(token);
+                        return;
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.emit(token);
+    }
+
+    fn emit(&mut self, token: Token) {
+        self.escapes_quote = match &token.t_type {
+            TokenType::Word(word) => word.chars().rev().take_while(|c| *c == ':').count() % 2 == 1,
+            _ => false,
+        };
+        self.tokens.push_back(token);
+    }
+
+    fn pending_words(&self) -> Vec<String> {
+        self.pending
+            .iter()
+            .filter_map(|token| phrase_word(&token.t_type))
+            .collect()
+    }
+
+    /// Gives up on the pending words ever forming a phrase. They are plain
+    /// words then, which the parser may still report as an incomplete
+    /// keyword where it expected one.
+    fn flush(&mut self) {
+        for token in std::mem::take(&mut self.pending) {
+            self.emit(token);
         }
     }
 
-    parsed_tokens
+    /// Flushes anything still pending at the end of the source.
+    fn finish(&mut self) {
+        if !self.pending.is_empty() {
+            self.flush();
+        }
+    }
+}
+
+fn phrase_word(t_type: &TokenType) -> Option<String> {
+    match t_type {
+        TokenType::Word(word) => Some(word.clone()),
+        TokenType::Keyword(KeywordToken::Has) => Some(KeywordToken::Has.to_string()),
+        _ => None,
+    }
+}
+
+/// The phrase spelled exactly by `words`.
+fn phrase(words: &[String]) -> Option<KeywordToken> {
+    PHRASES
+        .into_iter()
+        .find(|phrase| phrase.to_string() == words.join(" "))
+}
+
+/// Phrases that start with, but are longer than, `words`.
+pub(crate) fn candidates(words: &[String]) -> Vec<KeywordToken> {
+    PHRASES
+        .into_iter()
+        .filter(|phrase| {
+            let phrase = phrase.to_string();
+            let phrase: Vec<_> = phrase.split(' ').collect();
+            phrase.len() > words.len() && phrase.iter().zip(words).all(|(a, b)| a == b)
+        })
+        .collect()
 }
 
 fn parse_word(buffer: &mut String) -> Option<TokenType> {
@@ -122,8 +495,8 @@ fn parse_word(buffer: &mut String) -> Option<TokenType> {
         "HAI" => Some(KeywordToken::Hai.into()),
         "KTHXBYE" => Some(KeywordToken::KThxBye.into()),
         "VISIBLE" => Some(KeywordToken::Visible.into()),
+        "INVISIBLE" => Some(KeywordToken::Invisible.into()),
         "BTW" => Some(KeywordToken::Btw.into()),
-        "CAN" => Some(KeywordToken::Can.into()),
         "HAS" => Some(KeywordToken::Has.into()),
         "OBTW" => Some(KeywordToken::OBtw.into()),
         "TLDR" => Some(KeywordToken::Tldr.into()),
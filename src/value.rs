@@ -0,0 +1,210 @@
+use std::fmt::Display;
+
+use crate::{
+    ast::{BinaryOp, Type},
+    diagnostic::{Diagnostic, ErrorCode},
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Value {
+    #[default]
+    Noob,
+    Troof(bool),
+    Numbr(i64),
+    Numbar(f64),
+    Yarn(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Noob => write!(f, "NOOB"),
+            Value::Troof(true) => write!(f, "WIN"),
+            Value::Troof(false) => write!(f, "FAIL"),
+            Value::Numbr(numbr) => write!(f, "{numbr}"),
+            Value::Numbar(numbar) => write!(f, "{numbar:.2}"),
+            Value::Yarn(yarn) => write!(f, "{yarn}"),
+        }
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Noob => "NOOB",
+            Value::Troof(_) => "TROOF",
+            Value::Numbr(_) => "NUMBR",
+            Value::Numbar(_) => "NUMBAR",
+            Value::Yarn(_) => "YARN",
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Yarn(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Yarn(value.to_string())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Numbr(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Numbar(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Troof(value)
+    }
+}
+
+/// NUMBR or NUMBAR operands of an arithmetic operator.
+enum Numbers {
+    Numbrs(i64, i64),
+    Numbars(f64, f64),
+}
+
+impl Value {
+    /// Truthiness, used wherever a TROOF is expected.
+    pub fn to_troof(&self) -> bool {
+        match self {
+            Value::Noob => false,
+            Value::Troof(troof) => *troof,
+            Value::Numbr(numbr) => *numbr != 0,
+            Value::Numbar(numbar) => *numbar != 0.0,
+            Value::Yarn(yarn) => !yarn.is_empty(),
+        }
+    }
+
+    /// Implicit cast for arithmetic. NOOB may only be cast explicitly.
+    fn to_number(&self) -> Result<Value, Diagnostic> {
+        match self {
+            Value::Noob => Err(Diagnostic::error(
+                ErrorCode::InvalidCast,
+                "Cannot implicitly cast NOOB to a number",
+            )),
+            Value::Troof(troof) => Ok(Value::Numbr(i64::from(*troof))),
+            Value::Numbr(_) | Value::Numbar(_) => Ok(self.clone()),
+            Value::Yarn(yarn) => {
+                let number = match yarn.contains('.') {
+                    true => yarn.parse().map(Value::Numbar).ok(),
+                    false => yarn.parse().map(Value::Numbr).ok(),
+                };
+                number.ok_or_else(|| {
+                    Diagnostic::error(
+                        ErrorCode::InvalidCast,
+                        format!("Cannot cast YARN {yarn:?} to a number"),
+                    )
+                })
+            }
+        }
+    }
+
+    /// Implicit cast for SMOOSH and interpolation.
+    pub fn to_yarn(&self) -> Result<String, Diagnostic> {
+        match self {
+            Value::Noob => Err(Diagnostic::error(
+                ErrorCode::InvalidCast,
+                "Cannot implicitly cast NOOB to a YARN",
+            )),
+            value => Ok(value.to_string()),
+        }
+    }
+
+    /// Explicit cast with `MAEK` or `IS NOW A`.
+    pub fn cast(&self, to: Type) -> Result<Value, Diagnostic> {
+        let value = match (to, self) {
+            (Type::Noob, _) => Value::Noob,
+            (Type::Troof, value) => Value::Troof(value.to_troof()),
+            (Type::Numbr | Type::Numbar, Value::Noob) => Value::Numbr(0).cast(to)?,
+            (Type::Numbr, value) => match value.to_number()? {
+                Value::Numbar(numbar) => Value::Numbr(numbar as i64),
+                numbr => numbr,
+            },
+            (Type::Numbar, value) => match value.to_number()? {
+                Value::Numbr(numbr) => Value::Numbar(numbr as f64),
+                numbar => numbar,
+            },
+            (Type::Yarn, Value::Noob) => Value::Yarn(String::new()),
+            (Type::Yarn, value) => Value::Yarn(value.to_string()),
+        };
+        Ok(value)
+    }
+
+    fn numbers(&self, other: &Value) -> Result<Numbers, Diagnostic> {
+        Ok(match (self.to_number()?, other.to_number()?) {
+            (Value::Numbr(left), Value::Numbr(right)) => Numbers::Numbrs(left, right),
+            (Value::Numbr(left), Value::Numbar(right)) => Numbers::Numbars(left as f64, right),
+            (Value::Numbar(left), Value::Numbr(right)) => Numbers::Numbars(left, right as f64),
+            (Value::Numbar(left), Value::Numbar(right)) => Numbers::Numbars(left, right),
+            _ => unreachable!("to_number only returns NUMBRs and NUMBARs"),
+        })
+    }
+
+    /// `SUM OF` through `SMALLR OF`. NUMBRs stay NUMBRs unless either side is
+    /// a NUMBAR.
+    pub fn arithmetic(&self, op: BinaryOp, other: &Value) -> Result<Value, Diagnostic> {
+        let division_by_zero = || Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero");
+        let value = match self.numbers(other)? {
+            Numbers::Numbrs(left, right) => Value::Numbr(match op {
+                BinaryOp::Sum => left.wrapping_add(right),
+                BinaryOp::Diff => left.wrapping_sub(right),
+                BinaryOp::Produkt => left.wrapping_mul(right),
+                BinaryOp::Quoshunt => left.checked_div(right).ok_or_else(division_by_zero)?,
+                BinaryOp::Mod => left.checked_rem(right).ok_or_else(division_by_zero)?,
+                BinaryOp::Biggr => left.max(right),
+                BinaryOp::Smallr => left.min(right),
+                op => unreachable!("{op} is not arithmetic"),
+            }),
+            Numbers::Numbars(left, right) => Value::Numbar(match op {
+                BinaryOp::Sum => left + right,
+                BinaryOp::Diff => left - right,
+                BinaryOp::Produkt => left * right,
+                BinaryOp::Quoshunt | BinaryOp::Mod if right == 0.0 => {
+                    return Err(division_by_zero())
+                }
+                BinaryOp::Quoshunt => left / right,
+                BinaryOp::Mod => left % right,
+                BinaryOp::Biggr => left.max(right),
+                BinaryOp::Smallr => left.min(right),
+                op => unreachable!("{op} is not arithmetic"),
+            }),
+        };
+        Ok(value)
+    }
+
+    /// Any operator with two operands. Both are evaluated whatever the first
+    /// one is.
+    pub fn binary(&self, op: BinaryOp, other: &Value) -> Result<Value, Diagnostic> {
+        let value = match op {
+            BinaryOp::Both => Value::Troof(self.to_troof() && other.to_troof()),
+            BinaryOp::Either => Value::Troof(self.to_troof() || other.to_troof()),
+            BinaryOp::Won => Value::Troof(self.to_troof() != other.to_troof()),
+            BinaryOp::Saem => Value::Troof(self.saem(other)),
+            BinaryOp::Diffrint => Value::Troof(!self.saem(other)),
+            op => self.arithmetic(op, other)?,
+        };
+        Ok(value)
+    }
+
+    /// `BOTH SAEM`. Only NUMBRs and NUMBARs are compared across types.
+    pub fn saem(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Numbr(left), Value::Numbar(right))
+            | (Value::Numbar(right), Value::Numbr(left)) => *left as f64 == *right,
+            (left, right) => left == right,
+        }
+    }
+}